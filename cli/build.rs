@@ -0,0 +1,38 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+	println!("cargo:rerun-if-env-changed=VERSION");
+	println!("cargo:rerun-if-changed=../.git/HEAD");
+
+	let label = std::env::var("VERSION").unwrap_or_else(|_| String::from("dev"));
+	let git_hash = Command::new("git")
+		.args(["rev-parse", "--short", "HEAD"])
+		.output()
+		.ok()
+		.filter(|output| output.status.success())
+		.and_then(|output| String::from_utf8(output.stdout).ok())
+		.map(|hash| hash.trim().to_string())
+		.filter(|hash| !hash.is_empty())
+		.unwrap_or_else(|| String::from("unknown"));
+	let build_date = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|elapsed| format_date(elapsed.as_secs() / 86_400))
+		.unwrap_or_else(|_| String::from("unknown"));
+
+	println!("cargo:rustc-env=PUPPYPEER_VERSION_DISPLAY={label} ({git_hash}, built {build_date})");
+}
+
+// Civil date from days since the unix epoch (Howard Hinnant's algorithm).
+fn format_date(days: u64) -> String {
+	let z = days as i64 + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+	format!("{year:04}-{month:02}-{day:02}")
+}
@@ -1,6 +1,16 @@
 use clap::Parser;
+
+/// Parse an octal Unix mode such as `644` or `0o755`.
+fn parse_mode(value: &str) -> Result<u32, String> {
+	let digits = value.strip_prefix("0o").unwrap_or(value);
+	match u32::from_str_radix(digits, 8) {
+		Ok(mode) if mode <= 0o7777 => Ok(mode),
+		_ => Err(format!("{value} is not an octal mode like 644")),
+	}
+}
+
 #[derive(Debug, Parser)]
-#[clap(name = "puppyagent")]
+#[clap(name = "puppypeer", version = crate::utility::VERSION_DISPLAY)]
 pub struct Args {
 	#[clap(long)]
 	pub peer: Vec<String>,
@@ -10,20 +20,163 @@ pub struct Args {
 	pub read: Vec<String>,
 	#[clap(long = "write", value_name = "PATH")]
 	pub write: Vec<String>,
+	/// Maximum bytes peers may store in each --write folder
+	#[clap(long = "quota", value_name = "BYTES")]
+	pub quota: Option<u64>,
+	/// Octal Unix mode of files peers create in each --write folder, e.g. 600
+	#[clap(long, value_name = "MODE", value_parser = parse_mode)]
+	pub file_mode: Option<u32>,
+	/// Octal Unix mode of directories peers create in each --write folder, e.g. 700
+	#[clap(long, value_name = "MODE", value_parser = parse_mode)]
+	pub dir_mode: Option<u32>,
 	#[clap(long, default_value = "127.0.0.1:8832")]
 	pub ui_bind: String,
+	/// Serve the web UI on this address, e.g. 127.0.0.1:8833; needs the `web` feature
+	#[clap(long, value_name = "ADDR")]
+	pub web_addr: Option<String>,
+	/// Accept uploads into this content store with `PUT /api/store/<hash>` on the web UI
+	#[clap(long, value_name = "DIR", requires = "web_addr")]
+	pub store_dir: Option<String>,
+	/// Let pages from this origin call the web API, e.g. http://localhost:5173; `*` allows any
+	#[clap(long = "cors-origin", value_name = "ORIGIN", requires = "web_addr")]
+	pub cors_origins: Vec<String>,
+	/// Largest upload accepted into --store-dir; bigger ones get 413
+	#[clap(long, value_name = "BYTES", default_value_t = 1 << 30)]
+	pub max_upload: u64,
+	/// Check the binary's signature on start, skipping it while the binary is unchanged
+	#[clap(long)]
+	pub verify_on_start: bool,
+	/// Print command results as JSON on stdout; logs go to stderr
+	#[clap(long, global = true)]
+	pub json: bool,
+	/// Use the config, identity and database in ~/.puppypeer/profiles/NAME
+	#[clap(long, global = true, value_name = "NAME")]
+	pub profile: Option<String>,
 	#[clap(subcommand)]
 	pub command: Option<Command>,
 }
 
 #[derive(Debug, Parser)]
 pub enum Command {
-	Copy { src: String, dest: String },
-	Scan { path: String },
+	Copy {
+		src: String,
+		dest: String,
+	},
+	Scan {
+		path: String,
+		/// Only index files matching this glob; may be repeated
+		#[clap(long, value_name = "GLOB")]
+		include: Vec<String>,
+		/// Skip files and directories matching this glob, e.g. '**/node_modules/**'; may be
+		/// repeated
+		#[clap(long, value_name = "GLOB")]
+		exclude: Vec<String>,
+		/// Follow symlinked files and directories, visiting each target once
+		#[clap(long)]
+		follow_symlinks: bool,
+	},
 	Install,
 	Uninstall,
-	Update { version: Option<String> },
+	Update {
+		version: Option<String>,
+		/// Release source: github:<owner>/<repo>, a mirror URL or a local directory
+		#[clap(long, value_name = "SOURCE")]
+		source: Option<String>,
+	},
 	Tui,
 	Gui,
 	Daemon,
+	Doctor,
+	Peers {
+		/// Seconds to wait for mDNS discovery before printing
+		#[clap(long, value_name = "SECS", default_value_t = 3)]
+		wait: u64,
+	},
+	Config {
+		#[clap(subcommand)]
+		command: ConfigCommand,
+	},
+	Identity {
+		#[clap(subcommand)]
+		command: IdentityCommand,
+	},
+	/// Write a file from a peer to stdout
+	Cat {
+		/// Peer id or nickname
+		peer: String,
+		path: String,
+		/// Seconds to wait for the peer to connect
+		#[clap(long, value_name = "SECS", default_value_t = 10)]
+		wait: u64,
+	},
+	/// Write stdin to a file on a peer
+	Put {
+		/// Peer id or nickname
+		peer: String,
+		path: String,
+		/// Seconds to wait for the peer to connect
+		#[clap(long, value_name = "SECS", default_value_t = 10)]
+		wait: u64,
+	},
+	/// Mount a folder of a peer as a local filesystem until Ctrl-C; needs the `fuse`
+	/// feature
+	Mount {
+		/// Peer id or nickname
+		peer: String,
+		remote_path: String,
+		mountpoint: String,
+		/// Seconds to wait for the peer to connect
+		#[clap(long, value_name = "SECS", default_value_t = 10)]
+		wait: u64,
+	},
+	/// Measure request latency and throughput against a peer
+	Bench {
+		/// Peer id or nickname; benchmarks this node without the network when omitted
+		peer: Option<String>,
+		/// Requests sent in each phase
+		#[clap(long, value_name = "N", default_value_t = 100)]
+		requests: usize,
+		/// Requests in flight at once during the concurrent phases
+		#[clap(long, value_name = "N", default_value_t = 8)]
+		concurrency: usize,
+		/// Directory listed by the ListDir phases
+		#[clap(long, value_name = "PATH", default_value = "/")]
+		dir: String,
+		/// File read by the ReadFile phases, which are skipped without one
+		#[clap(long, value_name = "PATH")]
+		file: Option<String>,
+		/// Bytes asked for by each ReadFile
+		#[clap(long, value_name = "BYTES", default_value_t = 1024 * 1024)]
+		chunk_size: u64,
+		/// Seconds to wait for the peer to connect
+		#[clap(long, value_name = "SECS", default_value_t = 10)]
+		wait: u64,
+	},
+	/// Verify the content store written by `copy`: re-hash every blob and pair blobs
+	/// with their metadata sidecars
+	Fsck {
+		/// Folder holding the `epic-shelter-data-*` blobs
+		#[clap(long, value_name = "DIR")]
+		repo: String,
+		/// Remove corrupt blobs and sidecars whose blob is gone
+		#[clap(long)]
+		delete: bool,
+	},
+	/// Print a shell completion script, e.g. `source <(puppypeer completions bash)`
+	Completions {
+		#[clap(value_enum)]
+		shell: clap_complete::Shell,
+	},
+}
+
+#[derive(Debug, Parser)]
+pub enum ConfigCommand {
+	/// Print the effective settings and where each one came from
+	Show,
+}
+
+#[derive(Debug, Parser)]
+pub enum IdentityCommand {
+	/// Generate a new peer id, backing up the old keypair; stop the node first
+	Rotate,
 }
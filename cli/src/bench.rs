@@ -0,0 +1,236 @@
+use anyhow::{Result, bail};
+use futures::StreamExt;
+use libp2p::PeerId;
+use puppypeer_core::PuppyPeer;
+use serde::Serialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::pipe::connect;
+use crate::utility::print_json;
+
+/// What `puppypeer bench` runs and against which paths.
+pub struct BenchOptions {
+	/// Requests sent in every phase.
+	pub requests: usize,
+	/// Requests in flight at once during the concurrent phases.
+	pub concurrency: usize,
+	/// Directory listed by the `ListDir` phases.
+	pub dir: String,
+	/// File read by the `ReadFile` phases; those phases are skipped without one.
+	pub file: Option<String>,
+	/// Bytes asked for by each `ReadFile`.
+	pub chunk_size: u64,
+}
+
+/// Latency and throughput of one phase, e.g. concurrent `ReadFile`.
+#[derive(Debug, Serialize)]
+struct PhaseReport {
+	name: String,
+	requests: usize,
+	errors: usize,
+	p50_ms: f64,
+	p95_ms: f64,
+	p99_ms: f64,
+	elapsed_secs: f64,
+	requests_per_sec: f64,
+	bytes: u64,
+	mb_per_sec: f64,
+}
+
+/// Output of `puppypeer bench --json`.
+#[derive(Debug, Serialize)]
+struct BenchOutput {
+	peer: String,
+	/// The peer benchmarked itself, so requests never touched the network.
+	local: bool,
+	phases: Vec<PhaseReport>,
+}
+
+/// The sample at percentile `p` (0-100) of `sorted`, by nearest rank.
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+	if sorted.is_empty() {
+		return Duration::ZERO;
+	}
+	let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+	sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Send `requests` requests built by `request`, `concurrency` at a time. Each request
+/// resolves to the bytes it transferred.
+async fn run_phase<F, Fut>(
+	name: &str,
+	requests: usize,
+	concurrency: usize,
+	request: F,
+) -> PhaseReport
+where
+	F: Fn(usize) -> Fut,
+	Fut: Future<Output = Result<u64>>,
+{
+	let started = Instant::now();
+	let results: Vec<(Duration, Result<u64>)> = futures::stream::iter(0..requests)
+		.map(|idx| {
+			let sent = request(idx);
+			async move {
+				let at = Instant::now();
+				let result = sent.await;
+				(at.elapsed(), result)
+			}
+		})
+		.buffer_unordered(concurrency.max(1))
+		.collect()
+		.await;
+	let elapsed = started.elapsed();
+	let mut latencies = Vec::with_capacity(results.len());
+	let mut errors = 0;
+	let mut bytes = 0;
+	for (latency, result) in results {
+		match result {
+			Ok(transferred) => {
+				latencies.push(latency);
+				bytes += transferred;
+			}
+			Err(err) => {
+				log::debug!("{name} request failed: {err:#}");
+				errors += 1;
+			}
+		}
+	}
+	latencies.sort();
+	let ms = |p| percentile(&latencies, p).as_secs_f64() * 1000.0;
+	PhaseReport {
+		name: name.to_string(),
+		requests,
+		errors,
+		p50_ms: ms(50.0),
+		p95_ms: ms(95.0),
+		p99_ms: ms(99.0),
+		elapsed_secs: elapsed.as_secs_f64(),
+		requests_per_sec: requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+		bytes,
+		mb_per_sec: bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(f64::EPSILON),
+	}
+}
+
+async fn run_phases(
+	peer: &PuppyPeer,
+	id: PeerId,
+	options: &BenchOptions,
+) -> Result<Vec<PhaseReport>> {
+	// fail early with the real error instead of a report full of failed requests
+	peer.list_dir(id, options.dir.clone()).await?;
+	let file = match &options.file {
+		Some(path) => {
+			let size = peer.stat_file(id, path.clone()).await?.size;
+			if size == 0 {
+				bail!("{} is empty; pick a file with data to read", path);
+			}
+			Some((path.clone(), size))
+		}
+		None => None,
+	};
+	let list_dir = move |_| async move {
+		// listings carry no file data, so they only count towards requests per second
+		peer.list_dir(id, options.dir.clone()).await?;
+		Ok(0)
+	};
+	let mut phases = vec![
+		run_phase("ListDir sequential", options.requests, 1, list_dir).await,
+		run_phase(
+			"ListDir concurrent",
+			options.requests,
+			options.concurrency,
+			list_dir,
+		)
+		.await,
+	];
+	if let Some((path, size)) = &file {
+		// walk through the file so every request reads a different range
+		let chunks = size.div_ceil(options.chunk_size);
+		let read_file = move |idx: usize| async move {
+			let offset = (idx as u64 % chunks) * options.chunk_size;
+			let chunk = peer
+				.read_file(id, path.clone(), offset, Some(options.chunk_size))
+				.await?;
+			Ok(chunk.data.len() as u64)
+		};
+		phases.push(run_phase("ReadFile sequential", options.requests, 1, read_file).await);
+		phases.push(
+			run_phase(
+				"ReadFile concurrent",
+				options.requests,
+				options.concurrency,
+				read_file,
+			)
+			.await,
+		);
+	}
+	Ok(phases)
+}
+
+/// Benchmark requests against `target`, a peer id or nickname, or against this node when
+/// `target` is `None`.
+pub async fn run(
+	target: Option<&str>,
+	options: BenchOptions,
+	wait: Duration,
+	json: bool,
+) -> Result<()> {
+	let (peer, id) = match target {
+		Some(target) => connect(target, wait).await?,
+		None => {
+			let peer = PuppyPeer::new();
+			peer.await_ready().await;
+			let me = peer.state().lock().unwrap().me;
+			(peer, me)
+		}
+	};
+	let local = peer.state().lock().unwrap().me == id;
+	let phases = run_phases(&peer, id, &options).await?;
+	if json {
+		return print_json(&BenchOutput {
+			peer: id.to_string(),
+			local,
+			phases,
+		});
+	}
+	println!(
+		"Benchmarking {}{}: {} requests per phase, {} concurrent",
+		id,
+		if local { " (local)" } else { "" },
+		options.requests,
+		options.concurrency
+	);
+	println!(
+		"{:<20} {:>8} {:>9} {:>9} {:>9} {:>10} {:>10}",
+		"PHASE", "ERRORS", "P50 MS", "P95 MS", "P99 MS", "REQ/S", "MB/S"
+	);
+	for phase in phases {
+		println!(
+			"{:<20} {:>8} {:>9.2} {:>9.2} {:>9.2} {:>10.1} {:>10.2}",
+			phase.name,
+			phase.errors,
+			phase.p50_ms,
+			phase.p95_ms,
+			phase.p99_ms,
+			phase.requests_per_sec,
+			phase.mb_per_sec
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn percentile_uses_nearest_rank() {
+		let samples: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+		assert_eq!(percentile(&samples, 50.0), Duration::from_millis(50));
+		assert_eq!(percentile(&samples, 99.0), Duration::from_millis(99));
+		assert_eq!(percentile(&samples[..1], 95.0), Duration::from_millis(1));
+		assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+	}
+}
@@ -0,0 +1,230 @@
+use puppypeer_core::{PuppyConfig, config_path};
+use serde_json::{Map, Value, json};
+
+use crate::args::Args;
+use crate::updater::{DEFAULT_UPDATE_SOURCE, UPDATE_SOURCE_ENV};
+use crate::utility::print_json;
+
+/// Where an effective setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+	Default,
+	File,
+	Env(&'static str),
+	Flag(&'static str),
+}
+
+impl std::fmt::Display for Source {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Source::Default => write!(f, "default"),
+			Source::File => write!(f, "config file"),
+			Source::Env(var) => write!(f, "env {}", var),
+			Source::Flag(flag) => write!(f, "flag {}", flag),
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Setting {
+	key: &'static str,
+	/// `Null` when the setting is unset.
+	value: Value,
+	source: Source,
+}
+
+/// Resolve every setting the way the rest of the CLI does, noting where each value came from.
+///
+/// `file` holds the keys present in the config file and `config` the loaded config, so a
+/// key missing from the file is reported as a default.
+fn resolve(
+	args: &Args,
+	file: &Map<String, Value>,
+	config: &PuppyConfig,
+	env: impl Fn(&str) -> Option<String>,
+) -> Vec<Setting> {
+	let from_file = |key: &'static str, value: Value| Setting {
+		key,
+		value,
+		source: if file.contains_key(key) {
+			Source::File
+		} else {
+			Source::Default
+		},
+	};
+	// the env var wins over the file, as `PuppyConfig::load` applies it last
+	let from_env_or_file = |key, var: &'static str, value: Value| match env(var) {
+		Some(value) => Setting {
+			key,
+			value: json!(value),
+			source: Source::Env(var),
+		},
+		None => from_file(key, value),
+	};
+	let from_flag = |key, flag, value: Value, set: bool| Setting {
+		key,
+		value,
+		source: if set {
+			Source::Flag(flag)
+		} else {
+			Source::Default
+		},
+	};
+	let update_source = match env(UPDATE_SOURCE_ENV) {
+		Some(source) => Setting {
+			key: "update_source",
+			value: json!(source),
+			source: Source::Env(UPDATE_SOURCE_ENV),
+		},
+		None => from_file(
+			"update_source",
+			json!(
+				config
+					.update_source
+					.as_deref()
+					.unwrap_or(DEFAULT_UPDATE_SOURCE)
+			),
+		),
+	};
+	vec![
+		Setting {
+			key: "config_file",
+			value: json!(config_path()),
+			source: if env("CONFIG").is_some() {
+				Source::Env("CONFIG")
+			} else if args.profile.is_some() {
+				Source::Flag("--profile")
+			} else {
+				Source::Default
+			},
+		},
+		from_env_or_file("keypair_path", "KEYPAIR", json!(config.keypair_path())),
+		from_env_or_file("db_path", "DB", json!(config.db_path())),
+		from_file("listen_addrs", json!(config.listen_addrs)),
+		from_file("download_dir", json!(config.download_dir)),
+		from_file("min_free_space", json!(config.min_free_space)),
+		update_source,
+		from_file("scan_exclude", json!(config.scan_exclude)),
+		from_file("request_retries", json!(config.request_retries)),
+		from_file("max_reads_per_peer", json!(config.max_reads_per_peer)),
+		from_file("max_tree_entries", json!(config.max_tree_entries)),
+		from_file("bootstrap_peers", json!(config.bootstrap_peers)),
+		from_file("reconnect", json!(config.reconnect)),
+		from_file("show_local_peer", json!(config.show_local_peer)),
+		from_flag(
+			"read_shares",
+			"--read",
+			json!(args.read),
+			!args.read.is_empty(),
+		),
+		from_flag(
+			"write_shares",
+			"--write",
+			json!(args.write),
+			!args.write.is_empty(),
+		),
+		from_flag("quota", "--quota", json!(args.quota), args.quota.is_some()),
+		from_flag(
+			"file_mode",
+			"--file-mode",
+			json!(args.file_mode.map(|mode| format!("{mode:o}"))),
+			args.file_mode.is_some(),
+		),
+		from_flag(
+			"dir_mode",
+			"--dir-mode",
+			json!(args.dir_mode.map(|mode| format!("{mode:o}"))),
+			args.dir_mode.is_some(),
+		),
+		from_flag(
+			"verify_on_start",
+			"--verify-on-start",
+			json!(args.verify_on_start),
+			args.verify_on_start,
+		),
+	]
+}
+
+/// Print the effective configuration, as JSON or as TOML with the source of each value.
+pub fn show(args: &Args, json: bool) -> anyhow::Result<()> {
+	let path = config_path();
+	let (config, file) = match PuppyConfig::load_from(&path) {
+		Ok(Some(config)) => {
+			let raw = std::fs::read_to_string(&path)?;
+			let file = match serde_json::from_str(&raw)? {
+				Value::Object(file) => file,
+				_ => Map::new(),
+			};
+			(config, file)
+		}
+		Ok(None) => (PuppyConfig::default(), Map::new()),
+		Err(err) => {
+			eprintln!("warning: {err:#}; showing defaults");
+			(PuppyConfig::default(), Map::new())
+		}
+	};
+	let settings = resolve(args, &file, &config, |var| std::env::var(var).ok());
+	if json {
+		let out: Map<String, Value> = settings
+			.into_iter()
+			.map(|setting| {
+				let entry = json!({
+					"value": setting.value,
+					"source": setting.source.to_string(),
+				});
+				(setting.key.to_string(), entry)
+			})
+			.collect();
+		return print_json(&out);
+	}
+	for setting in settings {
+		// TOML has no null, so unset values are commented out
+		let line = match setting.value {
+			Value::Null => format!("# {} is unset", setting.key),
+			value => format!("{} = {}", setting.key, value),
+		};
+		println!("{:<48} # {}", line, setting.source);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use clap::Parser;
+
+	#[test]
+	fn sources_follow_precedence() {
+		let args = Args::parse_from(["puppypeer", "--read", "/srv/photos"]);
+		let mut file = Map::new();
+		file.insert(String::from("min_free_space"), json!(1024));
+		let config = PuppyConfig {
+			min_free_space: 1024,
+			..PuppyConfig::default()
+		};
+		let settings = resolve(&args, &file, &config, |var| {
+			(var == "DB").then(|| String::from("/tmp/test.db"))
+		});
+		let find = |key| settings.iter().find(|s| s.key == key).unwrap();
+
+		assert_eq!(find("db_path").source, Source::Env("DB"));
+		assert_eq!(find("db_path").value, json!("/tmp/test.db"));
+		assert_eq!(find("keypair_path").source, Source::Default);
+		assert_eq!(find("keypair_path").value, json!("peer_keypair.bin"));
+		assert_eq!(find("min_free_space").source, Source::File);
+		assert_eq!(find("download_dir").source, Source::Default);
+		assert_eq!(find("read_shares").source, Source::Flag("--read"));
+		assert_eq!(find("quota").value, Value::Null);
+	}
+
+	#[test]
+	fn json_flag_is_global() {
+		for argv in [
+			["puppypeer", "--json", "config", "show"],
+			["puppypeer", "config", "show", "--json"],
+		] {
+			assert!(Args::parse_from(argv).json);
+		}
+		assert!(!Args::parse_from(["puppypeer", "config", "show"]).json);
+	}
+}
@@ -0,0 +1,137 @@
+use puppypeer_core::p2p::BuildInfo;
+use puppypeer_core::{PuppyConfig, PuppyPeer, SchemaStatus, config_path, schema_status};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::utility;
+
+#[derive(Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "lowercase")]
+enum Check {
+	Ok(String),
+	Warn(String),
+	Fail(String),
+}
+
+#[derive(Serialize)]
+struct NamedCheck {
+	name: &'static str,
+	#[serde(flatten)]
+	check: Check,
+}
+
+/// Output of `puppypeer doctor --json`.
+#[derive(Serialize)]
+struct DoctorOutput {
+	version: &'static str,
+	build: BuildInfo,
+	checks: Vec<NamedCheck>,
+}
+
+fn report(named: &NamedCheck) {
+	let (tag, detail) = match &named.check {
+		Check::Ok(detail) => ("ok", detail),
+		Check::Warn(detail) => ("warn", detail),
+		Check::Fail(detail) => ("FAIL", detail),
+	};
+	println!("[{:>4}] {:<12} {}", tag, named.name, detail);
+}
+
+fn check_config() -> (PuppyConfig, Check) {
+	let path = config_path();
+	match PuppyConfig::load_from(&path) {
+		Ok(Some(config)) => (config, Check::Ok(format!("loaded {}", path.display()))),
+		Ok(None) => (
+			PuppyConfig::default(),
+			Check::Ok(format!("{} not found, using defaults", path.display())),
+		),
+		Err(err) => (
+			PuppyConfig::default(),
+			Check::Fail(format!("{err:#}; falling back to defaults")),
+		),
+	}
+}
+
+fn check_file(path: &Path) -> Check {
+	if path.exists() {
+		Check::Ok(path.display().to_string())
+	} else {
+		Check::Warn(format!(
+			"{} does not exist yet and will be created",
+			path.display()
+		))
+	}
+}
+
+fn check_schema(path: &Path) -> Check {
+	match schema_status(path) {
+		Ok(SchemaStatus {
+			version: None,
+			latest,
+		}) => Check::Ok(format!("not created yet, starts at version {}", latest)),
+		Ok(SchemaStatus {
+			version: Some(version),
+			latest,
+		}) if version > latest => Check::Fail(format!(
+			"version {} is newer than version {} known to this build; upgrade puppypeer",
+			version, latest
+		)),
+		Ok(SchemaStatus {
+			version: Some(version),
+			latest,
+		}) if version < latest => Check::Warn(format!(
+			"version {}, migrated to {} on next start",
+			version, latest
+		)),
+		Ok(SchemaStatus {
+			version: Some(version),
+			..
+		}) => Check::Ok(format!("version {}", version)),
+		Err(err) => Check::Fail(format!("{err:#}")),
+	}
+}
+
+/// Print build information and sanity check the local setup.
+pub fn run(json: bool) -> anyhow::Result<()> {
+	let (mut config, config_check) = check_config();
+	config.apply_env_overrides();
+	let checks = vec![
+		NamedCheck {
+			name: "config",
+			check: config_check,
+		},
+		NamedCheck {
+			name: "downloads",
+			check: match config.ensure_download_dir() {
+				Ok(dir) => Check::Ok(dir.display().to_string()),
+				Err(err) => Check::Fail(format!("{err:#}")),
+			},
+		},
+		NamedCheck {
+			name: "keypair",
+			check: check_file(&config.keypair_path()),
+		},
+		NamedCheck {
+			name: "database",
+			check: check_file(&config.db_path()),
+		},
+		NamedCheck {
+			name: "schema",
+			check: check_schema(&config.db_path()),
+		},
+	];
+	if json {
+		return utility::print_json(&DoctorOutput {
+			version: utility::VERSION_DISPLAY,
+			build: PuppyPeer::build_info(),
+			checks,
+		});
+	}
+	println!("puppypeer {}", utility::VERSION_DISPLAY);
+	println!("{}", PuppyPeer::build_info());
+	println!();
+	for check in &checks {
+		report(check);
+	}
+	Ok(())
+}
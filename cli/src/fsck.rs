@@ -0,0 +1,203 @@
+//! `puppypeer fsck`: check the content store written by the legacy `copy` command.
+//!
+//! The store is a flat folder. Every file is kept once as `epic-shelter-data-<hash>`, named
+//! after the SHA-256 of its content, next to an `epic-shelter-metadata-<hash>.json` sidecar
+//! recording where it was copied from.
+
+use anyhow::{Context, Result, bail};
+use puppypeer_core::scan::sha256_hash;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::utility::print_json;
+
+pub(crate) const DATA_PREFIX: &str = "epic-shelter-data-";
+const METADATA_PREFIX: &str = "epic-shelter-metadata-";
+const METADATA_SUFFIX: &str = ".json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ProblemKind {
+	/// The blob's content no longer hashes to its name, e.g. bit rot or a partial write.
+	Corrupt,
+	/// A sidecar whose blob is gone.
+	MissingBlob,
+	/// A blob without a sidecar. Its content is intact, so `--delete` keeps it.
+	MissingMetadata,
+}
+
+#[derive(Debug, Serialize)]
+struct Problem {
+	kind: ProblemKind,
+	hash: String,
+	path: PathBuf,
+	/// Removed by `--delete`.
+	deleted: bool,
+}
+
+/// Output of `puppypeer fsck --json`.
+#[derive(Debug, Serialize)]
+struct FsckOutput {
+	repo: PathBuf,
+	blobs_checked: usize,
+	problems: Vec<Problem>,
+}
+
+/// Hex digest the way the legacy `copy` command names files: each byte without zero
+/// padding, so `0x0a` becomes `a`. Names are compared in this form, not as plain hex.
+pub(crate) fn store_name(hash: &[u8]) -> String {
+	hash.iter().map(|byte| format!("{:x}", byte)).collect()
+}
+
+#[derive(Default)]
+struct StoreEntry {
+	blob: Option<PathBuf>,
+	metadata: Option<PathBuf>,
+}
+
+/// Re-hash every blob in `repo` and pair blobs with sidecars. Other files are ignored.
+fn check_repo(repo: &Path) -> Result<(usize, Vec<Problem>)> {
+	let mut entries: BTreeMap<String, StoreEntry> = BTreeMap::new();
+	let dir = std::fs::read_dir(repo).with_context(|| format!("cannot read {}", repo.display()))?;
+	for entry in dir {
+		let path = entry?.path();
+		let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+			continue;
+		};
+		if let Some(hash) = name.strip_prefix(DATA_PREFIX) {
+			entries.entry(hash.to_string()).or_default().blob = Some(path.clone());
+		} else if let Some(hash) = name
+			.strip_prefix(METADATA_PREFIX)
+			.and_then(|rest| rest.strip_suffix(METADATA_SUFFIX))
+		{
+			entries.entry(hash.to_string()).or_default().metadata = Some(path.clone());
+		}
+	}
+	let mut blobs_checked = 0;
+	let mut problems = Vec::new();
+	for (hash, entry) in entries {
+		let problem = |kind, path| Problem {
+			kind,
+			hash: hash.clone(),
+			path,
+			deleted: false,
+		};
+		match (entry.blob, entry.metadata) {
+			(Some(blob), metadata) => {
+				let file = std::fs::File::open(&blob)
+					.with_context(|| format!("cannot open {}", blob.display()))?;
+				let actual = sha256_hash(std::io::BufReader::new(file))
+					.with_context(|| format!("cannot read {}", blob.display()))?;
+				blobs_checked += 1;
+				if store_name(&actual) != hash {
+					problems.push(problem(ProblemKind::Corrupt, blob));
+				} else if metadata.is_none() {
+					problems.push(problem(ProblemKind::MissingMetadata, blob));
+				}
+			}
+			(None, Some(metadata)) => problems.push(problem(ProblemKind::MissingBlob, metadata)),
+			(None, None) => {}
+		}
+	}
+	Ok((blobs_checked, problems))
+}
+
+/// Remove corrupt blobs and sidecars without a blob. The sidecar of a corrupt blob stays,
+/// so copying the source folder again restores the blob with its history.
+fn delete_problems(problems: &mut [Problem]) -> Result<()> {
+	for problem in problems
+		.iter_mut()
+		.filter(|problem| problem.kind != ProblemKind::MissingMetadata)
+	{
+		std::fs::remove_file(&problem.path)
+			.with_context(|| format!("cannot delete {}", problem.path.display()))?;
+		problem.deleted = true;
+	}
+	Ok(())
+}
+
+/// Verify the store in `repo`, deleting what cannot be trusted when `delete` is set.
+/// Fails when problems are left, so scripts can tell a clean store from a damaged one.
+pub fn run(repo: &Path, delete: bool, json: bool) -> Result<()> {
+	let (blobs_checked, mut problems) = check_repo(repo)?;
+	if delete {
+		delete_problems(&mut problems)?;
+	}
+	let left = problems.iter().filter(|problem| !problem.deleted).count();
+	if json {
+		print_json(&FsckOutput {
+			repo: repo.to_path_buf(),
+			blobs_checked,
+			problems,
+		})?;
+	} else {
+		for problem in &problems {
+			let kind = match problem.kind {
+				ProblemKind::Corrupt => "corrupt",
+				ProblemKind::MissingBlob => "missing blob",
+				ProblemKind::MissingMetadata => "missing metadata",
+			};
+			let deleted = if problem.deleted { " (deleted)" } else { "" };
+			println!("{:<17} {}{}", kind, problem.path.display(), deleted);
+		}
+		println!(
+			"checked {} blobs in {}, {} problems, {} deleted",
+			blobs_checked,
+			repo.display(),
+			problems.len(),
+			problems.len() - left
+		);
+	}
+	if left > 0 {
+		bail!("{} problems left in {}", left, repo.display());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn corrupt_and_orphaned_entries_are_found_and_deleted() {
+		let repo = std::env::temp_dir().join(format!("puppypeer-fsck-{}", std::process::id()));
+		std::fs::create_dir_all(&repo).unwrap();
+		let name = |data: &[u8]| store_name(&sha256_hash(data).unwrap());
+		let blob = |hash: &str| repo.join(format!("{DATA_PREFIX}{hash}"));
+		let metadata = |hash: &str| repo.join(format!("{METADATA_PREFIX}{hash}{METADATA_SUFFIX}"));
+		let (good, rotten, gone, bare) =
+			(name(b"good"), name(b"rotten"), name(b"gone"), name(b"bare"));
+		std::fs::write(blob(&good), b"good").unwrap();
+		std::fs::write(metadata(&good), b"{}").unwrap();
+		std::fs::write(blob(&rotten), b"rottem").unwrap();
+		std::fs::write(metadata(&rotten), b"{}").unwrap();
+		std::fs::write(metadata(&gone), b"{}").unwrap();
+		std::fs::write(blob(&bare), b"bare").unwrap();
+
+		let (checked, mut problems) = check_repo(&repo).unwrap();
+		assert_eq!(checked, 3);
+		let kind_of = |hash: &str, problems: &[Problem]| {
+			problems.iter().find(|p| p.hash == hash).map(|p| p.kind)
+		};
+		assert_eq!(kind_of(&good, &problems), None);
+		assert_eq!(kind_of(&rotten, &problems), Some(ProblemKind::Corrupt));
+		assert_eq!(kind_of(&gone, &problems), Some(ProblemKind::MissingBlob));
+		assert_eq!(
+			kind_of(&bare, &problems),
+			Some(ProblemKind::MissingMetadata)
+		);
+
+		delete_problems(&mut problems).unwrap();
+		assert!(!blob(&rotten).exists() && metadata(&rotten).exists());
+		assert!(!metadata(&gone).exists());
+		assert!(blob(&bare).exists() && blob(&good).exists());
+
+		std::fs::remove_dir_all(repo).unwrap();
+	}
+
+	#[test]
+	fn store_names_drop_leading_zeros_of_each_byte() {
+		assert_eq!(store_name(&[0x0a, 0x00, 0xff, 0x10]), "a0ff10");
+	}
+}
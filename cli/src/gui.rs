@@ -3,41 +3,71 @@ use std::mem;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
+use futures::StreamExt;
 use iced::alignment::{Horizontal, Vertical};
 use iced::executor;
 use iced::theme;
 use iced::time;
 use iced::widget::image::Handle as ImageHandle;
 use iced::widget::{
-	Image, button, checkbox, container, pick_list, scrollable, text, text_input, tooltip,
+	Image, button, checkbox, container, pick_list, progress_bar, scrollable, text, text_editor,
+	text_input, tooltip,
 };
-use iced::{Application, Command, Element, Length, Settings, Subscription, Theme};
+use iced::{Application, Color, Command, Element, Length, Settings, Subscription, Theme};
 use libp2p::PeerId;
-use puppypeer_core::p2p::{CpuInfo, DirEntry};
+use puppypeer_core::p2p::{
+	CpuInfo, DirEntry, ErrorCode, InterfaceInfo, SUBTREE_DEPTH, SystemLoad, SystemOverview,
+	WriteMode,
+};
+use puppypeer_core::scan::{ScanOptions, ScanProgress};
 use puppypeer_core::{
-	FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FileChunk, FolderRule, Permission, PuppyPeer, Rule, State,
+	CatalogPage, ConflictPolicy, DirSize, DirTree, FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FileChunk,
+	FileEntry, FolderRule, Permission, PuppyConfig, PuppyPeer, RecentFile, Rule, State, SyncMode,
+	SyncOperation, SyncPlan, unique_path,
 };
+use tokio::io::AsyncWriteExt;
+
+use crate::utility::{abbreviate_peer_id, format_load, format_uptime};
 
 const LOCAL_LISTEN_MULTIADDR: &str = "/ip4/0.0.0.0:8336";
+/// Text color of this node's own row, so it stands apart from remote peers.
+const LOCAL_PEER_COLOR: Color = Color::from_rgb(0.95, 0.8, 0.3);
+/// Text color of the identity conflict banner.
+const WARNING_COLOR: Color = Color::from_rgb(0.95, 0.3, 0.3);
 const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(200);
 const FILE_VIEW_CHUNK_SIZE: u64 = 64 * 1024;
+/// Bytes of a viewed file kept in memory; data further away is dropped and fetched again.
+const FILE_VIEW_WINDOW: u64 = 16 * FILE_VIEW_CHUNK_SIZE;
+const FILE_OPEN_CHUNK_SIZE: u64 = 1024 * 1024;
+/// Catalog entries fetched per page.
+const CATALOG_PAGE_SIZE: u64 = 200;
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+	"exe", "msi", "bat", "cmd", "com", "ps1", "vbs", "scr", "sh", "command", "app", "jar",
+	"appimage", "run", "deb", "rpm", "pkg", "dmg",
+];
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum MenuItem {
 	Peers,
 	PeersGraph,
+	SharedFolders,
 	CreateUser,
 	FileSearch,
+	ScanFolder,
 	Quit,
 }
 
-const MENU_ITEMS: [MenuItem; 5] = [
+const MENU_ITEMS: [MenuItem; 7] = [
 	MenuItem::Peers,
 	MenuItem::PeersGraph,
+	MenuItem::SharedFolders,
 	MenuItem::CreateUser,
 	MenuItem::FileSearch,
+	MenuItem::ScanFolder,
 	MenuItem::Quit,
 ];
 
@@ -46,8 +76,10 @@ impl MenuItem {
 		match self {
 			MenuItem::Peers => "Peers",
 			MenuItem::PeersGraph => "Peers Graph",
+			MenuItem::SharedFolders => "Shared Folders",
 			MenuItem::CreateUser => "Create User",
 			MenuItem::FileSearch => "File Search",
+			MenuItem::ScanFolder => "Scan Folder",
 			MenuItem::Quit => "Quit",
 		}
 	}
@@ -56,16 +88,35 @@ impl MenuItem {
 #[derive(Debug, Clone)]
 struct PeerRow {
 	id: String,
+	name: Option<String>,
 	address: String,
 	status: String,
 }
 
+impl PeerRow {
+	fn is_local(&self) -> bool {
+		self.status == "local"
+	}
+}
+
+#[derive(Debug, Clone)]
+struct PeerOverviewState {
+	peer_id: String,
+	overview: SystemOverview,
+}
+
 #[derive(Debug, Clone)]
 struct PeerCpuState {
 	peer_id: String,
 	cpus: Vec<CpuInfo>,
 }
 
+#[derive(Debug, Clone)]
+struct PeerInterfacesState {
+	peer_id: String,
+	interfaces: Vec<InterfaceInfo>,
+}
+
 #[derive(Debug, Clone)]
 struct PeerPermissionsState {
 	peer_id: String,
@@ -160,11 +211,17 @@ impl EditableFolderPermission {
 #[derive(Debug, Clone)]
 struct FileBrowserState {
 	peer_id: String,
+	/// Empty while showing the list of `available_roots` rather than a directory
 	path: String,
 	entries: Vec<DirEntry>,
 	loading: bool,
 	error: Option<String>,
 	available_roots: Vec<String>,
+	/// Single files shared on their own, listed next to `available_roots`
+	shared_files: Vec<String>,
+	/// Set when `path` turned out to be a file, which is offered to open instead
+	listed_file: Option<String>,
+	show_hidden: bool,
 }
 
 impl FileBrowserState {
@@ -176,8 +233,21 @@ impl FileBrowserState {
 			loading: true,
 			error: None,
 			available_roots: Vec::new(),
+			shared_files: Vec::new(),
+			listed_file: None,
+			show_hidden: false,
 		}
 	}
+
+	fn at_shares(&self) -> bool {
+		self.path.is_empty()
+	}
+
+	fn visible_entries(&self) -> impl Iterator<Item = &DirEntry> {
+		self.entries
+			.iter()
+			.filter(|entry| self.show_hidden || !entry.is_hidden())
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -186,11 +256,44 @@ struct FileViewerState {
 	peer_id: String,
 	path: String,
 	mime: Option<String>,
+	/// Resident bytes, starting at `start` in the file
 	data: Vec<u8>,
+	start: u64,
+	/// Where the next forward read starts, the end of the resident range
 	offset: u64,
+	/// The resident range reaches the end of the file
 	eof: bool,
 	loading: bool,
 	error: Option<String>,
+	// Set after warning that the file looks executable; the next Open goes ahead
+	confirm_open: bool,
+	/// Unsaved edits, while the file is open for editing
+	editor: Option<FileEditor>,
+}
+
+#[derive(Debug)]
+struct FileEditor {
+	content: text_editor::Content,
+	saving: bool,
+}
+
+impl FileEditor {
+	fn new(text: &str) -> Self {
+		Self {
+			content: text_editor::Content::with_text(text),
+			saving: false,
+		}
+	}
+}
+
+// the editor content has no Clone, so copy it through its text
+impl Clone for FileEditor {
+	fn clone(&self) -> Self {
+		Self {
+			content: text_editor::Content::with_text(&self.content.text()),
+			saving: self.saving,
+		}
+	}
 }
 
 impl FileViewerState {
@@ -201,27 +304,65 @@ impl FileViewerState {
 			mime,
 			browser,
 			data: Vec::new(),
+			start: 0,
 			offset: 0,
 			eof: false,
 			loading: true,
 			error: None,
+			confirm_open: false,
+			editor: None,
 		}
 	}
 
+	fn resident(&self) -> std::ops::Range<u64> {
+		self.start..self.start + self.data.len() as u64
+	}
+
+	/// Merge a chunk into the resident range, then trim it to `FILE_VIEW_WINDOW` by dropping
+	/// the side away from the chunk. A chunk that neither overlaps nor touches the range
+	/// replaces it.
 	fn apply_chunk(&mut self, chunk: FileChunk) {
-		let offset = chunk.offset;
-		let eof = chunk.eof;
-		let data = chunk.data;
-		if offset != self.offset {
-			self.offset = offset;
-		}
-		if !data.is_empty() {
-			self.offset = offset.saturating_add(data.len() as u64);
-			self.data.extend_from_slice(&data);
+		let resident = self.resident();
+		let start = chunk.offset;
+		let end = start + chunk.data.len() as u64;
+		let backwards = start < resident.start;
+		if self.data.is_empty() || end < resident.start || start > resident.end {
+			self.start = start;
+			self.data = chunk.data;
+			self.eof = chunk.eof;
 		} else {
-			self.offset = offset;
+			if backwards {
+				let head = (resident.start - start) as usize;
+				self.data.splice(0..0, chunk.data[..head].iter().copied());
+				self.start = start;
+			}
+			if end >= resident.end {
+				let skip = (resident.end - start) as usize;
+				self.data.extend_from_slice(&chunk.data[skip..]);
+				self.eof = chunk.eof;
+			}
+		}
+		let excess = self.data.len().saturating_sub(FILE_VIEW_WINDOW as usize);
+		if excess > 0 {
+			if backwards {
+				self.data.truncate(FILE_VIEW_WINDOW as usize);
+				self.eof = false;
+			} else {
+				self.data.drain(..excess);
+				self.start += excess as u64;
+			}
 		}
-		self.eof = eof;
+		self.offset = self.resident().end;
+	}
+
+	/// The whole file is resident.
+	fn is_complete(&self) -> bool {
+		self.start == 0 && self.eof
+	}
+
+	/// Offset of the chunk just before the resident range, if any was dropped.
+	fn previous_offset(&self) -> Option<u64> {
+		(self.start > 0).then(|| self.start.saturating_sub(FILE_VIEW_CHUNK_SIZE))
 	}
 
 	fn is_image(&self) -> bool {
@@ -230,6 +371,14 @@ impl FileViewerState {
 			.map(|value| value.starts_with("image/"))
 			.unwrap_or(false)
 	}
+
+	/// The file as text when it can be edited: wholly loaded, not an image and valid UTF-8.
+	fn editable_text(&self) -> Option<&str> {
+		if !self.is_complete() || self.is_image() {
+			return None;
+		}
+		std::str::from_utf8(&self.data).ok()
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -305,6 +454,118 @@ impl CreateUserForm {
 	}
 }
 
+#[derive(Debug, Clone)]
+struct SharedFoldersState {
+	folders: Vec<(FolderRule, DirSize)>,
+	loading: bool,
+	error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ScanState {
+	path: String,
+	running: bool,
+	progress: Option<ScanProgress>,
+	outcome: Option<Result<String, String>>,
+}
+
+/// Sync modes offered on the Sync Folder page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncChoice {
+	OneWay,
+	NewestWins,
+	PreferLocal,
+	PreferRemote,
+}
+
+const SYNC_CHOICES: [SyncChoice; 4] = [
+	SyncChoice::OneWay,
+	SyncChoice::NewestWins,
+	SyncChoice::PreferLocal,
+	SyncChoice::PreferRemote,
+];
+
+impl SyncChoice {
+	fn mode(self) -> SyncMode {
+		match self {
+			SyncChoice::OneWay => SyncMode::OneWay,
+			SyncChoice::NewestWins => SyncMode::TwoWay(ConflictPolicy::NewestWins),
+			SyncChoice::PreferLocal => SyncMode::TwoWay(ConflictPolicy::PreferLocal),
+			SyncChoice::PreferRemote => SyncMode::TwoWay(ConflictPolicy::PreferRemote),
+		}
+	}
+}
+
+impl std::fmt::Display for SyncChoice {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			SyncChoice::OneWay => "One-way (push only)",
+			SyncChoice::NewestWins => "Two-way, newest wins",
+			SyncChoice::PreferLocal => "Two-way, prefer local",
+			SyncChoice::PreferRemote => "Two-way, prefer remote",
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+struct SyncState {
+	peer_id: String,
+	local_path: String,
+	remote_path: String,
+	choice: SyncChoice,
+	/// Planning or syncing is in progress.
+	busy: bool,
+	plan: Option<SyncPlan>,
+	outcome: Option<Result<String, String>>,
+}
+
+impl SyncState {
+	fn new(peer_id: String) -> Self {
+		Self {
+			peer_id,
+			local_path: String::new(),
+			remote_path: String::new(),
+			choice: SyncChoice::OneWay,
+			busy: false,
+			plan: None,
+			outcome: None,
+		}
+	}
+}
+
+/// Pages of the files a peer has indexed, served from its database.
+#[derive(Debug, Clone)]
+struct CatalogState {
+	peer_id: String,
+	prefix: String,
+	/// Entries before the page on screen.
+	offset: u64,
+	page: Option<CatalogPage>,
+	loading: bool,
+	error: Option<String>,
+}
+
+impl CatalogState {
+	fn new(peer_id: String) -> Self {
+		Self {
+			peer_id,
+			prefix: String::from("/"),
+			offset: 0,
+			page: None,
+			loading: false,
+			error: None,
+		}
+	}
+}
+
+/// Files recently opened on one peer, newest first.
+#[derive(Debug, Clone)]
+struct RecentFilesState {
+	peer_id: String,
+	files: Vec<RecentFile>,
+	error: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 struct FileSearchState {
 	query: String,
@@ -326,6 +587,22 @@ pub struct FileSearchEntry {
 	latest: String,
 }
 
+impl From<FileEntry> for FileSearchEntry {
+	fn from(entry: FileEntry) -> Self {
+		Self {
+			hash: entry
+				.hash
+				.iter()
+				.map(|byte| format!("{:02x}", byte))
+				.collect(),
+			size: entry.size.max(0) as u64,
+			mime_type: entry.mime_type,
+			first: entry.first_datetime,
+			latest: entry.latest_datetime,
+		}
+	}
+}
+
 impl FileSearchState {
 	fn new() -> Self {
 		Self {
@@ -345,14 +622,45 @@ fn map_result<T>(result: anyhow::Result<T, anyhow::Error>) -> Result<T, String>
 	result.map_err(|err| format!("{err}"))
 }
 
-async fn list_dir(
-	peer: Arc<PuppyPeer>,
-	peer_id: String,
-	path: String,
-) -> (String, String, Result<Vec<DirEntry>, String>) {
+async fn list_dir(peer: Arc<PuppyPeer>, peer_id: String, path: String) -> GuiMessage {
+	let target = PeerId::from_str(&peer_id).unwrap();
+	match peer.list_dir_nearest(target, path.clone()).await {
+		Ok((listed, entries)) => GuiMessage::FileBrowserLoaded {
+			peer_id,
+			path: listed,
+			entries: Ok(entries),
+		},
+		Err(err) if ErrorCode::of(&err) == Some(ErrorCode::NotADirectory) => {
+			GuiMessage::FileBrowserFoundFile { peer_id, path }
+		}
+		Err(err) => GuiMessage::FileBrowserLoaded {
+			peer_id,
+			path,
+			entries: Err(format!("{err}")),
+		},
+	}
+}
+
+async fn list_subtree(peer: Arc<PuppyPeer>, peer_id: String, path: String) -> GuiMessage {
 	let target = PeerId::from_str(&peer_id).unwrap();
-	let result = peer.list_dir(target, path.clone()).await;
-	(peer_id, path, map_result(result))
+	let result = peer
+		.list_dir_recursive(target, path.clone(), SUBTREE_DEPTH)
+		.await;
+	GuiMessage::FileSubtreeLoaded {
+		peer_id,
+		path,
+		result: map_result(result),
+	}
+}
+
+/// Remember a file the user opened; failing to is only worth a log line.
+fn record_recent_file(peer: &PuppyPeer, peer_id: &str, path: &str) {
+	let result = PeerId::from_str(peer_id)
+		.map_err(anyhow::Error::from)
+		.and_then(|target| peer.record_recent_file(target, path));
+	if let Err(err) = result {
+		log::warn!("failed to add {} to recent files: {err:#}", path);
+	}
 }
 
 async fn list_permissions(
@@ -373,6 +681,29 @@ async fn list_granted_permissions(
 	(peer_id, result.map_err(|err| format!("{err}")))
 }
 
+async fn disconnect_peer(peer: Arc<PuppyPeer>, peer_id: String) -> (String, Result<(), String>) {
+	let target = PeerId::from_str(&peer_id).unwrap();
+	let result = peer.disconnect(target).await;
+	(peer_id, map_result(result))
+}
+
+async fn browse_catalog(
+	peer: Arc<PuppyPeer>,
+	peer_id: String,
+	prefix: String,
+	offset: u64,
+) -> GuiMessage {
+	let target = PeerId::from_str(&peer_id).unwrap();
+	let result = peer
+		.browse_catalog(target, prefix, offset, CATALOG_PAGE_SIZE)
+		.await;
+	GuiMessage::CatalogLoaded {
+		peer_id,
+		offset,
+		result: map_result(result),
+	}
+}
+
 async fn set_permissions(
 	peer: Arc<PuppyPeer>,
 	peer_id: String,
@@ -383,6 +714,36 @@ async fn set_permissions(
 	(peer_id, map_result(result.map(|_| permissions)))
 }
 
+/// Replace `path` on `peer` with `data`. The write is atomic, so a failed save leaves the
+/// old file in place. Returns `data` for the viewer to show.
+async fn save_file(
+	peer: Arc<PuppyPeer>,
+	peer_id: String,
+	path: String,
+	data: Vec<u8>,
+) -> (String, String, Result<Vec<u8>, String>) {
+	let result = async {
+		let target = PeerId::from_str(&peer_id).map_err(|err| err.to_string())?;
+		let chunks: Vec<&[u8]> = if data.is_empty() {
+			vec![&[]]
+		} else {
+			data.chunks(FILE_VIEW_CHUNK_SIZE as usize).collect()
+		};
+		let last = chunks.len() - 1;
+		for (idx, chunk) in chunks.into_iter().enumerate() {
+			let offset = idx as u64 * FILE_VIEW_CHUNK_SIZE;
+			let mode = WriteMode::Atomic { last: idx == last };
+			let written = peer
+				.write_file(target, path.clone(), offset, chunk.to_vec(), mode, None)
+				.await;
+			map_result(written)?;
+		}
+		Ok(())
+	}
+	.await;
+	(peer_id, path, result.map(|_| data))
+}
+
 async fn read_file(
 	peer: Arc<PuppyPeer>,
 	peer_id: String,
@@ -396,6 +757,72 @@ async fn read_file(
 	(peer_id, path, offset, map_result(result))
 }
 
+/// Per-process directory under the download dir that opened files land in; removed when
+/// the GUI exits.
+fn open_temp_dir(download_dir: &std::path::Path) -> PathBuf {
+	download_dir.join(format!(".open-{}", std::process::id()))
+}
+
+fn looks_executable(path: &str, mime: Option<&str>) -> bool {
+	let by_mime = mime
+		.map(|value| {
+			matches!(
+				value,
+				"application/x-executable"
+					| "application/x-msdownload"
+					| "application/x-sh"
+					| "application/x-mach-binary"
+					| "application/vnd.microsoft.portable-executable"
+			)
+		})
+		.unwrap_or(false);
+	let by_extension = std::path::Path::new(path)
+		.extension()
+		.and_then(|ext| ext.to_str())
+		.map(|ext| {
+			EXECUTABLE_EXTENSIONS
+				.iter()
+				.any(|candidate| candidate.eq_ignore_ascii_case(ext))
+		})
+		.unwrap_or(false);
+	by_mime || by_extension
+}
+
+async fn download_and_open(
+	peer: Arc<PuppyPeer>,
+	peer_id: String,
+	path: String,
+) -> (String, Result<PathBuf, String>) {
+	let result = async {
+		let target = PeerId::from_str(&peer_id).map_err(|err| format!("{err}"))?;
+		let name = std::path::Path::new(&path)
+			.file_name()
+			.ok_or_else(|| String::from("path has no file name"))?;
+		let dir = open_temp_dir(&peer.config().download_dir);
+		tokio::fs::create_dir_all(&dir)
+			.await
+			.map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+		let local = unique_path(&dir, &name.to_string_lossy());
+		let mut file = tokio::fs::File::create(&local)
+			.await
+			.map_err(|err| format!("failed to create {}: {err}", local.display()))?;
+		let mut chunks =
+			std::pin::pin!(peer.read_file_stream(target, path.clone(), FILE_OPEN_CHUNK_SIZE));
+		while let Some(chunk) = chunks.next().await {
+			let chunk = map_result(chunk)?;
+			file.write_all(&chunk.data)
+				.await
+				.map_err(|err| format!("failed to write {}: {err}", local.display()))?;
+		}
+		file.flush().await.map_err(|err| format!("{err}"))?;
+		open::that_detached(&local)
+			.map_err(|err| format!("failed to open {}: {err}", local.display()))?;
+		Ok(local)
+	}
+	.await;
+	(path, result)
+}
+
 pub struct GuiApp {
 	peer: Arc<PuppyPeer>,
 	latest_state: Option<State>,
@@ -405,29 +832,63 @@ pub struct GuiApp {
 	peers: Vec<PeerRow>,
 	selected_peer_id: Option<String>,
 	graph: GraphView,
+	/// Whether `peers` and the graph include this node.
+	show_local_peer: bool,
 	status: String,
 	app_title: String,
+	/// Latest report from a running scan, written by the scan thread and polled by the view.
+	scan_progress: Arc<std::sync::Mutex<Option<ScanProgress>>>,
+	/// Set by the Cancel button to stop the running scan.
+	scan_cancel: Arc<AtomicBool>,
+	/// Uptime and load of the peer on the actions page, refreshed every tick.
+	peer_load: Option<(String, SystemLoad)>,
 }
 
 #[derive(Debug, Clone)]
 enum Mode {
 	Peers,
-	PeerActions { peer_id: String },
+	PeerActions { peer_id: String, name: String },
 	PeerPermissions(PeerPermissionsState),
+	PeerOverview(PeerOverviewState),
 	PeerCpus(PeerCpuState),
+	PeerInterfaces(PeerInterfacesState),
 	FileBrowser(FileBrowserState),
 	FileViewer(FileViewerState),
 	PeersGraph,
 	CreateUser(CreateUserForm),
 	FileSearch(FileSearchState),
+	SharedFolders(SharedFoldersState),
+	ScanFolder(ScanState),
+	SyncFolder(SyncState),
+	Catalog(CatalogState),
+	RecentFiles(RecentFilesState),
+	Confirm(ConfirmState),
+}
+
+/// A destructive action waiting for the user to confirm it.
+#[derive(Debug, Clone)]
+struct ConfirmState {
+	prompt: String,
+	action: Box<GuiMessage>,
+	/// Restored on cancel, and before `action` runs on confirm.
+	previous: Box<Mode>,
 }
 
 #[derive(Debug, Clone)]
 pub enum GuiMessage {
 	Tick,
+	PeerReady,
 	MenuSelected(MenuItem),
 	BackToPeers,
 	PeerActionsRequested(String),
+	PeerLoadLoaded(String, Result<SystemLoad, String>),
+	PeerNameChanged(String),
+	PeerNameSaved,
+	PeerDisconnectRequested(String),
+	PeerDisconnected {
+		peer_id: String,
+		result: Result<(), String>,
+	},
 	PeerPermissionsRequested(String),
 	PeerPermissionsLoaded {
 		peer_id: String,
@@ -453,8 +914,12 @@ pub enum GuiMessage {
 		peer_id: String,
 		result: Result<Vec<Permission>, String>,
 	},
+	OverviewRequested(String),
+	OverviewLoaded(String, Result<SystemOverview, String>),
 	CpuRequested(String),
 	CpuLoaded(String, Result<Vec<CpuInfo>, String>),
+	InterfacesRequested(String),
+	InterfacesLoaded(String, Result<Vec<InterfaceInfo>, String>),
 	FileBrowserRequested {
 		peer_id: String,
 	},
@@ -467,8 +932,29 @@ pub enum GuiMessage {
 		path: String,
 		entries: Result<Vec<DirEntry>, String>,
 	},
+	/// The peer answered that the path to list is a file.
+	FileBrowserFoundFile {
+		peer_id: String,
+		path: String,
+	},
 	FileEntryActivated(DirEntry),
+	/// Replace the listing with everything below the current folder.
+	FileSubtreeRequested,
+	FileSubtreeLoaded {
+		peer_id: String,
+		path: String,
+		result: Result<DirTree, String>,
+	},
 	FileNavigateUp,
+	FileShareSelected(String),
+	SharedFileSelected(String),
+	FileShowHiddenToggled(bool),
+	SharedFoldersLoaded(Result<Vec<(FolderRule, DirSize)>, String>),
+	FileOpenRequested,
+	FileOpened {
+		path: String,
+		result: Result<PathBuf, String>,
+	},
 	FileReadLoaded {
 		peer_id: String,
 		path: String,
@@ -476,9 +962,20 @@ pub enum GuiMessage {
 		result: Result<FileChunk, String>,
 	},
 	FileReadMore,
+	FileReadPrevious,
 	FileViewerBack,
+	FileEditRequested,
+	FileEditAction(text_editor::Action),
+	FileEditCancelled,
+	FileSaveRequested,
+	FileSaved {
+		peer_id: String,
+		path: String,
+		result: Result<Vec<u8>, String>,
+	},
 	GraphNext,
 	GraphPrev,
+	ShowLocalPeerToggled(bool),
 	UsernameChanged(String),
 	PasswordChanged(String),
 	CreateUserSubmit,
@@ -487,6 +984,35 @@ pub enum GuiMessage {
 	FileSearchToggleSort,
 	FileSearchExecute,
 	FileSearchLoaded(Result<(Vec<FileSearchEntry>, Vec<String>), String>),
+	ScanPathChanged(String),
+	ScanStart,
+	ScanCancel,
+	ScanPoll,
+	ScanFinished(Result<String, String>),
+	SyncFolderRequested(String),
+	SyncLocalPathChanged(String),
+	SyncRemotePathChanged(String),
+	SyncChoiceSelected(SyncChoice),
+	SyncPlanRequested,
+	SyncPlanLoaded(Result<SyncPlan, String>),
+	SyncConfirmed,
+	SyncFinished(Result<String, String>),
+	CatalogRequested(String),
+	CatalogPrefixChanged(String),
+	/// Fetch the page starting at this offset.
+	CatalogPageRequested(u64),
+	CatalogLoaded {
+		peer_id: String,
+		offset: u64,
+		result: Result<CatalogPage, String>,
+	},
+	RecentFilesRequested(String),
+	/// Open this path from the recent files of the peer on screen.
+	RecentFileSelected(String),
+	ConfirmAccepted,
+	ConfirmCancelled,
+	/// An action the user already confirmed, run without asking again.
+	Confirmed(Box<GuiMessage>),
 }
 
 impl Application for GuiApp {
@@ -496,27 +1022,7 @@ impl Application for GuiApp {
 	type Flags = String;
 
 	fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
-		let peer = Arc::new(PuppyPeer::new());
-		let latest_state = peer.state().lock().ok().map(|state| state.clone());
-		let peers = latest_state
-			.as_ref()
-			.map(aggregate_peers)
-			.unwrap_or_default();
-		let mut graph = GraphView::new();
-		graph.set_peers(&peers);
-		let app = GuiApp {
-			peer,
-			latest_state: latest_state.clone(),
-			local_peer_id: latest_state.as_ref().map(|state| state.me.to_string()),
-			menu: MenuItem::Peers,
-			mode: Mode::Peers,
-			peers,
-			selected_peer_id: None,
-			graph,
-			status: String::from("Ready"),
-			app_title: flags,
-		};
-		(app, Command::none())
+		Self::with_peer(Arc::new(PuppyPeer::new()), flags)
 	}
 
 	fn title(&self) -> String {
@@ -528,13 +1034,62 @@ impl Application for GuiApp {
 	}
 
 	fn subscription(&self) -> Subscription<Self::Message> {
-		time::every(REFRESH_INTERVAL).map(|_| GuiMessage::Tick)
+		let refresh = time::every(REFRESH_INTERVAL).map(|_| GuiMessage::Tick);
+		match &self.mode {
+			Mode::ScanFolder(state) if state.running => Subscription::batch([
+				refresh,
+				time::every(SCAN_POLL_INTERVAL).map(|_| GuiMessage::ScanPoll),
+			]),
+			_ => refresh,
+		}
 	}
 
 	fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+		let message = match message {
+			GuiMessage::Confirmed(action) => *action,
+			message => match self.confirmation_prompt(&message) {
+				Some(prompt) => {
+					let previous = mem::replace(&mut self.mode, Mode::Peers);
+					self.status = String::from("Waiting for confirmation");
+					self.mode = Mode::Confirm(ConfirmState {
+						prompt,
+						action: Box::new(message),
+						previous: Box::new(previous),
+					});
+					return Command::none();
+				}
+				None => message,
+			},
+		};
 		match message {
+			GuiMessage::ConfirmAccepted => {
+				if let Mode::Confirm(confirm) = mem::replace(&mut self.mode, Mode::Peers) {
+					self.mode = *confirm.previous;
+					return self.update(GuiMessage::Confirmed(confirm.action));
+				}
+				Command::none()
+			}
+			GuiMessage::ConfirmCancelled => {
+				if let Mode::Confirm(confirm) = mem::replace(&mut self.mode, Mode::Peers) {
+					self.mode = *confirm.previous;
+					self.status = String::from("Cancelled");
+				}
+				Command::none()
+			}
+			// unwrapped above
+			GuiMessage::Confirmed(_) => Command::none(),
 			GuiMessage::Tick => {
 				self.refresh_from_state();
+				match &self.mode {
+					Mode::PeerActions { peer_id, .. } => self.fetch_load(peer_id.clone()),
+					_ => Command::none(),
+				}
+			}
+			GuiMessage::PeerReady => {
+				self.refresh_from_state();
+				if self.status == "Starting..." {
+					self.status = String::from("Ready");
+				}
 				Command::none()
 			}
 			GuiMessage::MenuSelected(item) => {
@@ -562,6 +1117,20 @@ impl Application for GuiApp {
 							None => String::from("Graph overview — no peers"),
 						};
 					}
+					MenuItem::SharedFolders => {
+						self.menu = item;
+						self.mode = Mode::SharedFolders(SharedFoldersState {
+							folders: Vec::new(),
+							loading: true,
+							error: None,
+						});
+						self.status = String::from("Measuring shared folders...");
+						let peer = self.peer.clone();
+						return Command::perform(
+							shared_folder_usage(peer),
+							GuiMessage::SharedFoldersLoaded,
+						);
+					}
 					MenuItem::CreateUser => {
 						self.menu = item;
 						self.mode = Mode::CreateUser(CreateUserForm::new());
@@ -572,6 +1141,11 @@ impl Application for GuiApp {
 						self.mode = Mode::FileSearch(FileSearchState::new());
 						self.status = String::from("File search");
 					}
+					MenuItem::ScanFolder => {
+						self.menu = item;
+						self.mode = Mode::ScanFolder(ScanState::default());
+						self.status = String::from("Scan a local folder into the database");
+					}
 				}
 				Command::none()
 			}
@@ -581,11 +1155,60 @@ impl Application for GuiApp {
 				Command::none()
 			}
 			GuiMessage::PeerActionsRequested(peer_id) => {
+				let name = self
+					.peers
+					.iter()
+					.find(|row| row.id == peer_id)
+					.and_then(|row| row.name.clone())
+					.unwrap_or_default();
 				self.mode = Mode::PeerActions {
 					peer_id: peer_id.clone(),
+					name,
 				};
 				self.selected_peer_id = Some(peer_id.clone());
 				self.status = format!("Peer actions for {}", peer_id);
+				self.fetch_load(peer_id)
+			}
+			GuiMessage::PeerLoadLoaded(peer_id, result) => {
+				// a failed refresh hides the figures rather than showing stale ones
+				self.peer_load = result.ok().map(|load| (peer_id, load));
+				Command::none()
+			}
+			GuiMessage::PeerNameChanged(value) => {
+				if let Mode::PeerActions { name, .. } = &mut self.mode {
+					*name = value;
+				}
+				Command::none()
+			}
+			GuiMessage::PeerNameSaved => {
+				if let Mode::PeerActions { peer_id, name } = &self.mode {
+					let result = PeerId::from_str(peer_id)
+						.map_err(anyhow::Error::from)
+						.and_then(|target| self.peer.set_peer_name(target, Some(name)));
+					self.status = match result {
+						Ok(()) if name.trim().is_empty() => {
+							format!("Cleared nickname of {}", peer_id)
+						}
+						Ok(()) => format!("Renamed {} to {}", peer_id, name.trim()),
+						Err(err) => format!("Failed to rename {}: {}", peer_id, err),
+					};
+					self.refresh_from_state();
+				}
+				Command::none()
+			}
+			GuiMessage::PeerDisconnectRequested(peer_id) => {
+				self.status = format!("Disconnecting from {}...", peer_id);
+				let peer = self.peer.clone();
+				Command::perform(disconnect_peer(peer, peer_id), |(peer_id, result)| {
+					GuiMessage::PeerDisconnected { peer_id, result }
+				})
+			}
+			GuiMessage::PeerDisconnected { peer_id, result } => {
+				self.status = match result {
+					Ok(()) => format!("Disconnected from {}", peer_id),
+					Err(err) => format!("Failed to disconnect {}: {}", peer_id, err),
+				};
+				self.refresh_from_state();
 				Command::none()
 			}
 			GuiMessage::PeerPermissionsRequested(peer_id) => {
@@ -723,6 +1346,26 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::OverviewRequested(peer_id) => {
+				self.status = format!("Loading overview of {}...", peer_id);
+				let peer = self.peer.clone();
+				Command::perform(
+					fetch_overview(peer, peer_id.clone()),
+					move |(id, result)| GuiMessage::OverviewLoaded(id, result),
+				)
+			}
+			GuiMessage::OverviewLoaded(peer_id, result) => {
+				match result {
+					Ok(overview) => {
+						self.status = format!("Overview of {}", peer_id);
+						self.mode = Mode::PeerOverview(PeerOverviewState { peer_id, overview });
+					}
+					Err(err) => {
+						self.status = format!("Failed to load the overview: {}", err);
+					}
+				}
+				Command::none()
+			}
 			GuiMessage::CpuRequested(peer_id) => {
 				self.status = format!("Loading CPU info for {}...", peer_id);
 				let peer = self.peer.clone();
@@ -743,6 +1386,29 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::InterfacesRequested(peer_id) => {
+				self.status = format!("Loading interfaces for {}...", peer_id);
+				let peer = self.peer.clone();
+				Command::perform(
+					fetch_interfaces(peer, peer_id.clone()),
+					move |(id, result)| GuiMessage::InterfacesLoaded(id, result),
+				)
+			}
+			GuiMessage::InterfacesLoaded(peer_id, result) => {
+				match result {
+					Ok(interfaces) => {
+						self.status = format!("Interfaces: {}", interfaces.len());
+						self.mode = Mode::PeerInterfaces(PeerInterfacesState {
+							peer_id,
+							interfaces,
+						});
+					}
+					Err(err) => {
+						self.status = format!("Failed to load interfaces: {}", err);
+					}
+				}
+				Command::none()
+			}
 			GuiMessage::FileBrowserRequested { peer_id } => {
 				self.status = format!("Fetching shared folders for {}...", peer_id);
 				self.mode =
@@ -753,6 +1419,7 @@ impl Application for GuiApp {
 					state.loading = true;
 					state.error = None;
 					state.available_roots.clear();
+					state.shared_files.clear();
 				}
 				let peer = self.peer.clone();
 				Command::perform(
@@ -768,35 +1435,44 @@ impl Application for GuiApp {
 				permissions,
 			} => match permissions {
 				Ok(perms) => {
-					let default_path =
-						default_browser_path(&perms).unwrap_or_else(|| String::from("/"));
+					let roots = permissions_roots(&perms);
+					let files = shared_files(&perms);
+					let default_path = match default_browser_path(&perms) {
+						Some(path) => path,
+						// only single files are shared, so start at the list of shares
+						None if !files.is_empty() => String::new(),
+						None => String::from("/"),
+					};
 					let list_path = default_path.clone();
 					let status_path = default_path.clone();
-					let roots = permissions_roots(&perms);
-					self.status = format!("Listing {} on {}...", status_path, peer_id);
+					let at_shares = default_path.is_empty();
 					match &mut self.mode {
 						Mode::FileBrowser(state) if state.peer_id == peer_id => {
 							state.path = default_path.clone();
 							state.available_roots = roots.clone();
+							state.shared_files = files.clone();
 							state.entries.clear();
-							state.loading = true;
+							state.loading = !at_shares;
 							state.error = None;
 						}
 						_ => {
 							let mut state =
 								FileBrowserState::new(peer_id.clone(), default_path.clone());
 							state.available_roots = roots.clone();
+							state.shared_files = files.clone();
+							state.loading = !at_shares;
 							self.mode = Mode::FileBrowser(state);
 						}
 					}
+					if at_shares {
+						self.status = format!("{} shared files", files.len());
+						return Command::none();
+					}
+					self.status = format!("Listing {} on {}...", status_path, peer_id);
 					let peer = self.peer.clone();
 					return Command::perform(
 						list_dir(peer, peer_id.clone(), list_path),
-						|(peer_id, path, entries)| GuiMessage::FileBrowserLoaded {
-							peer_id,
-							path,
-							entries,
-						},
+						std::convert::identity,
 					);
 				}
 				Err(err) => {
@@ -816,14 +1492,23 @@ impl Application for GuiApp {
 				entries,
 			} => {
 				match &mut self.mode {
-					Mode::FileBrowser(state) if state.peer_id == peer_id => {
-						state.path = path.clone();
+					// a listing that lands after going up to the shares list is stale
+					Mode::FileBrowser(state) if state.peer_id == peer_id && !state.at_shares() => {
+						let requested = std::mem::replace(&mut state.path, path.clone());
 						state.loading = false;
+						state.listed_file = None;
 						match entries {
 							Ok(entries) => {
 								state.entries = entries;
 								state.error = None;
-								self.status = format!("Loaded {} entries", state.entries.len());
+								self.status = if requested == path {
+									format!("Loaded {} entries", state.entries.len())
+								} else {
+									format!(
+										"Folder {} no longer exists, showing {}",
+										requested, path
+									)
+								};
 							}
 							Err(err) => {
 								state.entries.clear();
@@ -836,9 +1521,24 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::FileBrowserFoundFile { peer_id, path } => {
+				match &mut self.mode {
+					Mode::FileBrowser(state)
+						if state.peer_id == peer_id && state.path == path && !state.at_shares() =>
+					{
+						state.loading = false;
+						state.entries.clear();
+						state.error = Some(format!("{} is a file, not a folder", path));
+						state.listed_file = Some(path.clone());
+						self.status = format!("{} is a file", path);
+					}
+					_ => {}
+				}
+				Command::none()
+			}
 			GuiMessage::FileEntryActivated(entry) => {
 				if let Mode::FileBrowser(state) = &mut self.mode {
-					if entry.is_dir {
+					if entry.is_navigable() {
 						let target = join_child_path(&state.path, &entry.name);
 						let peer_id = state.peer_id.clone();
 						state.path = target.clone();
@@ -850,11 +1550,7 @@ impl Application for GuiApp {
 						let local = self.local_peer_id.clone();
 						return Command::perform(
 							list_dir(peer, peer_id.parse().unwrap(), target),
-							|(peer_id, path, entries)| GuiMessage::FileBrowserLoaded {
-								peer_id,
-								path,
-								entries,
-							},
+							std::convert::identity,
 						);
 					}
 					let target = join_child_path(&state.path, &entry.name);
@@ -888,11 +1584,91 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::SharedFoldersLoaded(result) => {
+				if let Mode::SharedFolders(state) = &mut self.mode {
+					state.loading = false;
+					match result {
+						Ok(folders) => {
+							self.status = format!("{} shared folders", folders.len());
+							state.folders = folders;
+							state.error = None;
+						}
+						Err(err) => {
+							self.status = format!("Failed to load shared folders: {}", err);
+							state.error = Some(err);
+						}
+					}
+				}
+				Command::none()
+			}
+			GuiMessage::FileSubtreeRequested => {
+				if let Mode::FileBrowser(state) = &mut self.mode {
+					if state.at_shares() || state.loading {
+						return Command::none();
+					}
+					state.loading = true;
+					state.error = None;
+					self.status = format!("Loading everything below {}...", state.path);
+					return Command::perform(
+						list_subtree(self.peer.clone(), state.peer_id.clone(), state.path.clone()),
+						std::convert::identity,
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::FileSubtreeLoaded {
+				peer_id,
+				path,
+				result,
+			} => {
+				match &mut self.mode {
+					Mode::FileBrowser(state) if state.peer_id == peer_id && state.path == path => {
+						state.loading = false;
+						match result {
+							Ok(tree) => {
+								state.entries = tree.entries;
+								state.error = None;
+								self.status = format!(
+									"Loaded {} entries below {}{}",
+									state.entries.len(),
+									path,
+									if tree.truncated {
+										" (truncated by the peer)"
+									} else {
+										""
+									}
+								);
+							}
+							Err(err) => {
+								state.error = Some(err.clone());
+								self.status = format!("Failed to load the subtree: {}", err);
+							}
+						}
+					}
+					_ => {}
+				}
+				Command::none()
+			}
+			GuiMessage::FileShowHiddenToggled(value) => {
+				if let Mode::FileBrowser(state) = &mut self.mode {
+					state.show_hidden = value;
+				}
+				Command::none()
+			}
 			GuiMessage::FileNavigateUp => {
 				if let Mode::FileBrowser(state) = &mut self.mode {
+					if state.at_shares() {
+						self.status = String::from("Already at shared folders");
+						return Command::none();
+					}
 					let current = normalize_path(&state.path);
 					if state.available_roots.iter().any(|root| root == &current) {
-						self.status = String::from("Already at shared root");
+						// up from a share's top directory goes back to the list of shares
+						state.path.clear();
+						state.entries.clear();
+						state.loading = false;
+						state.error = None;
+						self.status = format!("{} shared folders", state.available_roots.len());
 						return Command::none();
 					}
 					let target = parent_path(&state.path);
@@ -910,15 +1686,24 @@ impl Application for GuiApp {
 					let local = self.local_peer_id.clone();
 					return Command::perform(
 						list_dir(peer, peer_id.parse().unwrap(), target),
-						|(peer_id, path, entries)| GuiMessage::FileBrowserLoaded {
-							peer_id,
-							path,
-							entries,
-						},
+						std::convert::identity,
 					);
 				}
 				Command::none()
 			}
+			GuiMessage::FileShareSelected(root) => {
+				if let Mode::FileBrowser(state) = &mut self.mode {
+					let peer_id = state.peer_id.clone();
+					state.path = root.clone();
+					state.entries.clear();
+					state.loading = true;
+					state.error = None;
+					self.status = format!("Opening {}...", root);
+					let peer = self.peer.clone();
+					return Command::perform(list_dir(peer, peer_id, root), std::convert::identity);
+				}
+				Command::none()
+			}
 			GuiMessage::FileReadLoaded {
 				peer_id,
 				path,
@@ -931,6 +1716,9 @@ impl Application for GuiApp {
 						state.loading = false;
 						match result {
 							Ok(chunk) => {
+								if state.data.is_empty() {
+									record_recent_file(&self.peer, &peer_id, &path);
+								}
 								let prev_offset = state.offset;
 								let chunk_len = chunk.data.len();
 								state.error = None;
@@ -938,7 +1726,9 @@ impl Application for GuiApp {
 								let mime_label =
 									state.mime.clone().unwrap_or_else(|| String::from("?"));
 								let base_status = if state.is_image()
-									&& state.eof && !state.data.is_empty()
+									&& state.is_complete() && !state
+									.data
+									.is_empty()
 								{
 									format!(
 										"Image loaded: {} bytes | {}",
@@ -947,16 +1737,20 @@ impl Application for GuiApp {
 									)
 								} else {
 									let eof_note = if state.eof { " (end of file)" } else { "" };
+									let resident = state.resident();
 									format!(
-										"Loaded {} bytes{} | {}",
-										state.data.len(),
-										eof_note,
-										mime_label
+										"Showing bytes {}-{}{} | {}",
+										resident.start, resident.end, eof_note, mime_label
 									)
 								};
 								let progressed = state.offset > prev_offset;
 								if state.eof {
 									self.status = base_status;
+								} else if progressed
+									&& (state.data.len() as u64) >= FILE_VIEW_WINDOW
+								{
+									// keep memory bounded; further data is fetched on request
+									self.status = base_status;
 								} else if progressed {
 									self.status = format!("{}; fetching more...", base_status);
 									state.loading = true;
@@ -1021,11 +1815,200 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::FileReadPrevious => {
+				if let Mode::FileViewer(state) = &mut self.mode {
+					if state.loading {
+						return Command::none();
+					}
+					let Some(offset) = state.previous_offset() else {
+						self.status = String::from("Already at start of file");
+						return Command::none();
+					};
+					state.loading = true;
+					let peer_id = state.peer_id.clone();
+					let path = state.path.clone();
+					self.status = format!("Loading bytes starting at {}...", offset);
+					let peer = self.peer.clone();
+					return Command::perform(
+						read_file(peer, peer_id, path, offset),
+						|(peer_id, path, offset, result)| GuiMessage::FileReadLoaded {
+							peer_id,
+							path,
+							offset,
+							result,
+						},
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::FileOpenRequested => {
+				if let Mode::FileViewer(state) = &mut self.mode {
+					if !state.confirm_open && looks_executable(&state.path, state.mime.as_deref()) {
+						state.confirm_open = true;
+						self.status = format!(
+							"Warning: {} looks like an executable. Click Open again to run it.",
+							state.path
+						);
+						return Command::none();
+					}
+					state.confirm_open = false;
+					self.status = format!("Downloading {} to open locally...", state.path);
+					let peer = self.peer.clone();
+					return Command::perform(
+						download_and_open(peer, state.peer_id.clone(), state.path.clone()),
+						|(path, result)| GuiMessage::FileOpened { path, result },
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::FileEditRequested => {
+				if let Mode::FileViewer(state) = &mut self.mode {
+					match state.editable_text() {
+						Some(text) => {
+							state.editor = Some(FileEditor::new(text));
+							self.status = format!("Editing {}", state.path);
+						}
+						None => {
+							self.status = String::from(
+								"Only text files that are loaded completely can be edited",
+							);
+						}
+					}
+				}
+				Command::none()
+			}
+			GuiMessage::FileEditAction(action) => {
+				if let Mode::FileViewer(FileViewerState {
+					editor: Some(editor),
+					..
+				}) = &mut self.mode
+					&& !editor.saving
+				{
+					editor.content.perform(action);
+				}
+				Command::none()
+			}
+			GuiMessage::FileEditCancelled => {
+				if let Mode::FileViewer(state) = &mut self.mode {
+					state.editor = None;
+					self.status = format!("Discarded edits to {}", state.path);
+				}
+				Command::none()
+			}
+			GuiMessage::FileSaveRequested => {
+				if let Mode::FileViewer(state) = &mut self.mode {
+					let Some(editor) = &mut state.editor else {
+						return Command::none();
+					};
+					if editor.saving {
+						return Command::none();
+					}
+					editor.saving = true;
+					self.status = format!("Saving {}...", state.path);
+					return Command::perform(
+						save_file(
+							self.peer.clone(),
+							state.peer_id.clone(),
+							state.path.clone(),
+							editor.content.text().into_bytes(),
+						),
+						|(peer_id, path, result)| GuiMessage::FileSaved {
+							peer_id,
+							path,
+							result,
+						},
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::FileSaved {
+				peer_id,
+				path,
+				result,
+			} => {
+				match &mut self.mode {
+					Mode::FileViewer(state) if state.peer_id == peer_id && state.path == path => {
+						match result {
+							Ok(data) => {
+								self.status = format!("Saved {} bytes to {}", data.len(), path);
+								state.editor = None;
+								state.error = None;
+								// the saved text is the whole file now
+								state.start = 0;
+								state.offset = data.len() as u64;
+								state.data = data;
+								state.eof = true;
+							}
+							Err(err) => {
+								// keep the edits so the user can retry or copy them out
+								if let Some(editor) = &mut state.editor {
+									editor.saving = false;
+								}
+								self.status = format!("Failed to save {}: {}", path, err);
+								state.error = Some(err);
+							}
+						}
+					}
+					_ => {
+						self.status = match result {
+							Ok(data) => format!("Saved {} bytes to {}", data.len(), path),
+							Err(err) => format!("Failed to save {}: {}", path, err),
+						};
+					}
+				}
+				Command::none()
+			}
+			GuiMessage::FileOpened { path, result } => {
+				self.status = match result {
+					Ok(local) => format!("Opened {} from {}", path, local.display()),
+					Err(err) => format!("Failed to open {}: {}", path, err),
+				};
+				Command::none()
+			}
+			GuiMessage::SharedFileSelected(path) => {
+				if let Mode::FileBrowser(state) = &self.mode {
+					let peer_id = state.peer_id.clone();
+					let browser_snapshot = state.clone();
+					self.status = format!("Reading {}", path);
+					let peer = self.peer.clone();
+					let command = Command::perform(
+						read_file(peer, peer_id.clone(), path.clone(), 0),
+						|(peer_id, path, offset, result)| GuiMessage::FileReadLoaded {
+							peer_id,
+							path,
+							offset,
+							result,
+						},
+					);
+					self.mode = Mode::FileViewer(FileViewerState::new(
+						browser_snapshot,
+						peer_id,
+						path,
+						None,
+					));
+					return command;
+				}
+				Command::none()
+			}
 			GuiMessage::FileViewerBack => {
 				if let Mode::FileViewer(state) = mem::replace(&mut self.mode, Mode::Peers) {
 					let browser = state.browser;
 					self.status = format!("Browsing {} on {}", browser.path, browser.peer_id);
+					// a file reopened from the recent list has a folder that was never listed
+					let command = if browser.loading {
+						Command::perform(
+							list_dir(
+								self.peer.clone(),
+								browser.peer_id.clone(),
+								browser.path.clone(),
+							),
+							std::convert::identity,
+						)
+					} else {
+						Command::none()
+					};
 					self.mode = Mode::FileBrowser(browser);
+					return command;
 				}
 				Command::none()
 			}
@@ -1037,6 +2020,11 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::ShowLocalPeerToggled(show) => {
+				self.show_local_peer = show;
+				self.refresh_from_state();
+				Command::none()
+			}
 			GuiMessage::GraphPrev => {
 				self.graph.previous();
 				if let Some(id) = self.graph.selected_id() {
@@ -1124,6 +2112,264 @@ impl Application for GuiApp {
 				}
 				Command::none()
 			}
+			GuiMessage::ScanPathChanged(path) => {
+				if let Mode::ScanFolder(state) = &mut self.mode {
+					state.path = path;
+				}
+				Command::none()
+			}
+			GuiMessage::ScanStart => {
+				if let Mode::ScanFolder(state) = &mut self.mode {
+					if state.running || state.path.trim().is_empty() {
+						return Command::none();
+					}
+					state.running = true;
+					state.progress = None;
+					state.outcome = None;
+					if let Ok(mut progress) = self.scan_progress.lock() {
+						*progress = None;
+					}
+					// a fresh flag, so a Cancel meant for an earlier scan cannot stop this one
+					self.scan_cancel = Arc::new(AtomicBool::new(false));
+					self.status = format!("Scanning {}...", state.path.trim());
+					return Command::perform(
+						scan_folder(
+							self.peer.clone(),
+							state.path.trim().to_string(),
+							self.scan_progress.clone(),
+							self.scan_cancel.clone(),
+						),
+						GuiMessage::ScanFinished,
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::ScanCancel => {
+				if matches!(&self.mode, Mode::ScanFolder(state) if state.running) {
+					self.scan_cancel.store(true, Ordering::Relaxed);
+					self.status = String::from("Cancelling scan...");
+				}
+				Command::none()
+			}
+			GuiMessage::ScanPoll => {
+				if let Mode::ScanFolder(state) = &mut self.mode
+					&& let Ok(progress) = self.scan_progress.lock()
+				{
+					state.progress = progress.clone();
+				}
+				Command::none()
+			}
+			GuiMessage::RecentFilesRequested(peer_id) => {
+				self.selected_peer_id = Some(peer_id.clone());
+				let result = PeerId::from_str(&peer_id)
+					.map_err(anyhow::Error::from)
+					.and_then(|target| self.peer.recent_files(Some(target)));
+				let (files, error) = match result {
+					Ok(files) => {
+						self.status = format!("{} recent files on {}", files.len(), peer_id);
+						(files, None)
+					}
+					Err(err) => {
+						self.status = format!("Failed to load recent files: {}", err);
+						(Vec::new(), Some(err.to_string()))
+					}
+				};
+				self.mode = Mode::RecentFiles(RecentFilesState {
+					peer_id,
+					files,
+					error,
+				});
+				Command::none()
+			}
+			GuiMessage::RecentFileSelected(path) => {
+				if let Mode::RecentFiles(state) = &self.mode {
+					let peer_id = state.peer_id.clone();
+					let browser = FileBrowserState::new(peer_id.clone(), parent_path(&path));
+					self.status = format!("Reading {}", path);
+					let command = Command::perform(
+						read_file(self.peer.clone(), peer_id.clone(), path.clone(), 0),
+						|(peer_id, path, offset, result)| GuiMessage::FileReadLoaded {
+							peer_id,
+							path,
+							offset,
+							result,
+						},
+					);
+					self.mode =
+						Mode::FileViewer(FileViewerState::new(browser, peer_id, path, None));
+					return command;
+				}
+				Command::none()
+			}
+			GuiMessage::CatalogRequested(peer_id) => {
+				self.selected_peer_id = Some(peer_id.clone());
+				self.mode = Mode::Catalog(CatalogState::new(peer_id));
+				self.update(GuiMessage::CatalogPageRequested(0))
+			}
+			GuiMessage::CatalogPrefixChanged(prefix) => {
+				if let Mode::Catalog(state) = &mut self.mode {
+					state.prefix = prefix;
+				}
+				Command::none()
+			}
+			GuiMessage::CatalogPageRequested(offset) => {
+				if let Mode::Catalog(state) = &mut self.mode {
+					state.loading = true;
+					state.error = None;
+					self.status = format!("Loading catalog of {}...", state.peer_id);
+					return Command::perform(
+						browse_catalog(
+							self.peer.clone(),
+							state.peer_id.clone(),
+							state.prefix.clone(),
+							offset,
+						),
+						std::convert::identity,
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::CatalogLoaded {
+				peer_id,
+				offset,
+				result,
+			} => {
+				if let Mode::Catalog(state) = &mut self.mode {
+					if state.peer_id != peer_id {
+						return Command::none();
+					}
+					state.loading = false;
+					match result {
+						Ok(page) => {
+							self.status = format!(
+								"{} indexed files on {} from entry {}",
+								page.entries.len(),
+								peer_id,
+								offset + 1
+							);
+							state.offset = offset;
+							state.page = Some(page);
+						}
+						Err(err) => {
+							self.status = format!("Failed to load catalog: {}", err);
+							state.error = Some(err);
+						}
+					}
+				}
+				Command::none()
+			}
+			GuiMessage::SyncFolderRequested(peer_id) => {
+				self.selected_peer_id = Some(peer_id.clone());
+				self.status = format!("Sync a folder with {}", peer_id);
+				self.mode = Mode::SyncFolder(SyncState::new(peer_id));
+				Command::none()
+			}
+			GuiMessage::SyncLocalPathChanged(path) => {
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					state.local_path = path;
+					state.plan = None;
+				}
+				Command::none()
+			}
+			GuiMessage::SyncRemotePathChanged(path) => {
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					state.remote_path = path;
+					state.plan = None;
+				}
+				Command::none()
+			}
+			GuiMessage::SyncChoiceSelected(choice) => {
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					state.choice = choice;
+					state.plan = None;
+				}
+				Command::none()
+			}
+			GuiMessage::SyncPlanRequested => {
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					if state.busy
+						|| state.local_path.trim().is_empty()
+						|| state.remote_path.trim().is_empty()
+					{
+						return Command::none();
+					}
+					state.busy = true;
+					state.plan = None;
+					state.outcome = None;
+					self.status = String::from("Comparing folders...");
+					return Command::perform(
+						sync_plan(
+							self.peer.clone(),
+							state.peer_id.clone(),
+							state.local_path.trim().to_string(),
+							state.remote_path.trim().to_string(),
+							state.choice.mode(),
+						),
+						GuiMessage::SyncPlanLoaded,
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::SyncPlanLoaded(result) => {
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					state.busy = false;
+					match result {
+						Ok(plan) => {
+							self.status = format!(
+								"Plan ready: {} to upload, {} to download, {} skipped",
+								plan.count(SyncOperation::Upload),
+								plan.count(SyncOperation::Download),
+								plan.count(SyncOperation::Skip)
+							);
+							state.plan = Some(plan);
+						}
+						Err(err) => {
+							self.status = format!("Failed to compare folders: {}", err);
+							state.outcome = Some(Err(err));
+						}
+					}
+				}
+				Command::none()
+			}
+			GuiMessage::SyncConfirmed => {
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					let Some(plan) = state.plan.take().filter(|_| !state.busy) else {
+						return Command::none();
+					};
+					state.busy = true;
+					self.status = String::from("Syncing...");
+					return Command::perform(
+						apply_sync_plan(self.peer.clone(), plan),
+						GuiMessage::SyncFinished,
+					);
+				}
+				Command::none()
+			}
+			GuiMessage::SyncFinished(result) => {
+				self.status = match &result {
+					Ok(summary) => format!("Sync finished: {}", summary),
+					Err(err) => format!("Sync failed: {}", err),
+				};
+				if let Mode::SyncFolder(state) = &mut self.mode {
+					state.busy = false;
+					state.outcome = Some(result);
+				}
+				Command::none()
+			}
+			GuiMessage::ScanFinished(result) => {
+				self.status = match &result {
+					Ok(summary) => format!("Scan finished: {}", summary),
+					Err(err) => format!("Scan failed: {}", err),
+				};
+				if let Mode::ScanFolder(state) = &mut self.mode {
+					state.running = false;
+					if let Ok(progress) = self.scan_progress.lock() {
+						state.progress = progress.clone();
+					}
+					state.outcome = Some(result);
+				}
+				Command::none()
+			}
 		}
 	}
 
@@ -1145,14 +2391,22 @@ impl Application for GuiApp {
 			.style(theme::Container::Box);
 		let content: Element<_> = match &self.mode {
 			Mode::Peers => self.view_peers(),
-			Mode::PeerActions { peer_id } => self.view_peer_actions(peer_id),
+			Mode::PeerActions { peer_id, name } => self.view_peer_actions(peer_id, name),
 			Mode::PeerPermissions(state) => self.view_peer_permissions(state),
+			Mode::PeerOverview(state) => self.view_peer_overview(state),
 			Mode::PeerCpus(state) => self.view_peer_cpus(state),
+			Mode::PeerInterfaces(state) => self.view_peer_interfaces(state),
 			Mode::FileBrowser(state) => self.view_file_browser(state),
 			Mode::FileViewer(state) => self.view_file_viewer(state),
 			Mode::PeersGraph => self.view_graph(),
 			Mode::CreateUser(form) => self.view_create_user(form),
 			Mode::FileSearch(state) => self.view_file_search(state),
+			Mode::SharedFolders(state) => self.view_shared_folders(state),
+			Mode::ScanFolder(state) => self.view_scan_folder(state),
+			Mode::SyncFolder(state) => self.view_sync_folder(state),
+			Mode::Catalog(state) => self.view_catalog(state),
+			Mode::RecentFiles(state) => self.view_recent_files(state),
+			Mode::Confirm(state) => self.view_confirm(state),
 		};
 		let content_container = container(content)
 			.width(Length::Fill)
@@ -1168,21 +2422,104 @@ impl Application for GuiApp {
 			.width(Length::Fill)
 			.padding(12)
 			.style(theme::Container::Box);
+		let mut layout = iced::widget::Column::new().spacing(12).padding(12);
+		if let Some(warning) = self
+			.latest_state
+			.as_ref()
+			.and_then(|state| state.identity_warning.as_ref())
+		{
+			layout = layout.push(
+				container(text(format!("Identity conflict: {}", warning)).style(WARNING_COLOR))
+					.width(Length::Fill)
+					.padding(12)
+					.style(theme::Container::Box),
+			);
+		}
+		layout.push(main).push(status).into()
+	}
+}
+
+impl GuiApp {
+	/// The app on top of an already started `peer`.
+	fn with_peer(peer: Arc<PuppyPeer>, title: String) -> (Self, Command<GuiMessage>) {
+		let show_local_peer = peer.config().show_local_peer;
+		let latest_state = peer.state().lock().ok().map(|state| state.clone());
+		let peers = latest_state
+			.as_ref()
+			.map(|state| aggregate_peers(state, show_local_peer))
+			.unwrap_or_default();
+		let mut graph = GraphView::new();
+		graph.set_peers(&peers);
+		let app = GuiApp {
+			peer,
+			latest_state: latest_state.clone(),
+			local_peer_id: latest_state.as_ref().map(|state| state.me.to_string()),
+			menu: MenuItem::Peers,
+			mode: Mode::Peers,
+			peers,
+			selected_peer_id: None,
+			graph,
+			show_local_peer,
+			status: String::from("Starting..."),
+			app_title: title,
+			scan_progress: Arc::new(std::sync::Mutex::new(None)),
+			scan_cancel: Arc::new(AtomicBool::new(false)),
+			peer_load: None,
+		};
+		let peer = app.peer.clone();
+		let ready = Command::perform(async move { peer.await_ready().await }, |_| {
+			GuiMessage::PeerReady
+		});
+		(app, ready)
+	}
+
+	/// What to ask before running `message`, for messages that overwrite or delete data.
+	/// Add new destructive actions here so they go through `Mode::Confirm`.
+	fn confirmation_prompt(&self, message: &GuiMessage) -> Option<String> {
+		match (message, &self.mode) {
+			(GuiMessage::SyncConfirmed, Mode::SyncFolder(state)) if !state.busy => {
+				let overwrites = state
+					.plan
+					.as_ref()?
+					.files
+					.iter()
+					.filter(|file| match file.operation {
+						SyncOperation::Upload => file.remote.is_some(),
+						SyncOperation::Download => file.local.is_some(),
+						SyncOperation::Skip => false,
+					})
+					.count();
+				(overwrites > 0).then(|| {
+					format!(
+						"Syncing will overwrite {} existing file{}. Continue?",
+						overwrites,
+						if overwrites == 1 { "" } else { "s" }
+					)
+				})
+			}
+			_ => None,
+		}
+	}
+
+	fn view_confirm(&self, state: &ConfirmState) -> Element<'_, GuiMessage> {
 		iced::widget::Column::new()
 			.spacing(12)
-			.padding(12)
-			.push(main)
-			.push(status)
+			.push(text("Are you sure?").size(24))
+			.push(text(&state.prompt).size(16))
+			.push(
+				iced::widget::Row::new()
+					.spacing(12)
+					.push(button(text("Confirm")).on_press(GuiMessage::ConfirmAccepted))
+					.push(button(text("Cancel")).on_press(GuiMessage::ConfirmCancelled)),
+			)
 			.into()
 	}
-}
 
-impl GuiApp {
 	fn refresh_from_state(&mut self) {
 		if let Ok(state_guard) = self.peer.state().lock() {
 			let snapshot = state_guard.clone();
 			self.local_peer_id = Some(snapshot.me.to_string());
-			self.peers = aggregate_peers(&snapshot);
+			self.peers = aggregate_peers(&snapshot, self.show_local_peer);
 			if self
 				.selected_peer_id
 				.clone()
@@ -1192,7 +2529,7 @@ impl GuiApp {
 				self.selected_peer_id = None;
 			}
 			let missing_peer = match &self.mode {
-				Mode::PeerActions { peer_id } => {
+				Mode::PeerActions { peer_id, .. } => {
 					if !self.peers.iter().any(|p| p.id == *peer_id) {
 						Some(peer_id.clone())
 					} else {
@@ -1230,6 +2567,10 @@ impl GuiApp {
 	fn view_peers(&self) -> Element<'_, GuiMessage> {
 		let mut layout = iced::widget::Column::new().spacing(12);
 		layout = layout.push(text("Discovered Peers").size(24));
+		layout = layout.push(
+			checkbox("Show this node", self.show_local_peer)
+				.on_toggle(GuiMessage::ShowLocalPeerToggled),
+		);
 		if self.peers.is_empty() {
 			layout = layout.push(text("No peers discovered yet.").size(16));
 		} else {
@@ -1240,10 +2581,24 @@ impl GuiApp {
 				} else {
 					""
 				};
-				let id_label = format!("{} {}", indicator, abbreviate_peer_id(&peer.id));
+				let label = match &peer.name {
+					Some(name) => name.clone(),
+					None => abbreviate_peer_id(&peer.id),
+				};
+				let mut title = text(format!("{} {}", indicator, label)).size(16);
+				if peer.is_local() {
+					title = title.style(LOCAL_PEER_COLOR);
+				}
+				let id_text: Element<'_, GuiMessage> = match &peer.name {
+					Some(_) => iced::widget::Column::new()
+						.push(title)
+						.push(text(abbreviate_peer_id(&peer.id)).size(12))
+						.into(),
+					None => title.into(),
+				};
 				let id_cell = container(
 					tooltip(
-						text(id_label).size(16),
+						id_text,
 						text(peer.id.clone()),
 						tooltip::Position::FollowCursor,
 					)
@@ -1275,11 +2630,45 @@ impl GuiApp {
 		layout.into()
 	}
 
-	fn view_peer_actions(&self, peer_id: &str) -> Element<'_, GuiMessage> {
+	fn view_peer_actions<'a>(&'a self, peer_id: &str, name: &str) -> Element<'a, GuiMessage> {
 		if let Some(peer) = self.peers.iter().find(|row| row.id == peer_id) {
 			let mut layout = iced::widget::Column::new().spacing(12);
-			layout = layout.push(text(format!("Peer {}", peer.id)).size(24));
+			match &peer.name {
+				Some(nickname) => {
+					layout = layout.push(text(nickname.clone()).size(24));
+					layout = layout.push(text(format!("Peer {}", peer.id)).size(14));
+				}
+				None => {
+					layout = layout.push(text(format!("Peer {}", peer.id)).size(24));
+				}
+			}
+			let rename = iced::widget::Row::new()
+				.spacing(8)
+				.push(
+					text_input("Nickname (stored locally)", name)
+						.padding(8)
+						.size(16)
+						.on_input(GuiMessage::PeerNameChanged)
+						.on_submit(GuiMessage::PeerNameSaved),
+				)
+				.push(button(text("Save name")).on_press(GuiMessage::PeerNameSaved));
+			layout = layout.push(rename);
 			layout = layout.push(text(format!("Status: {}", peer.status)).size(16));
+			let directions = self.connection_summaries(peer_id);
+			if !directions.is_empty() {
+				layout =
+					layout.push(text(format!("Connections: {}", directions.join(", "))).size(16));
+			}
+			if let Some((_, load)) = self.peer_load.as_ref().filter(|(id, _)| id == peer_id) {
+				layout = layout.push(
+					text(format!(
+						"Uptime: {}   Load: {}",
+						format_uptime(load.uptime),
+						format_load(load.load.as_ref())
+					))
+					.size(16),
+				);
+			}
 			if !peer.address.is_empty() {
 				layout = layout.push(text(format!("Dial address: {}", peer.address)).size(16));
 			}
@@ -1291,9 +2680,18 @@ impl GuiApp {
 				}
 				layout = layout.push(container(addr_box).padding(8).style(theme::Container::Box));
 			}
+			let connected = !directions.is_empty();
 			let controls = iced::widget::Row::new()
 				.spacing(12)
+				.push(
+					button(text("Overview"))
+						.on_press(GuiMessage::OverviewRequested(peer.id.clone())),
+				)
 				.push(button(text("CPU info")).on_press(GuiMessage::CpuRequested(peer.id.clone())))
+				.push(
+					button(text("Interfaces"))
+						.on_press(GuiMessage::InterfacesRequested(peer.id.clone())),
+				)
 				.push(
 					button(text("File browser")).on_press(GuiMessage::FileBrowserRequested {
 						peer_id: peer.id.clone(),
@@ -1303,6 +2701,20 @@ impl GuiApp {
 					button(text("Permissions"))
 						.on_press(GuiMessage::PeerPermissionsRequested(peer.id.clone())),
 				)
+				.push(
+					button(text("Sync folder"))
+						.on_press(GuiMessage::SyncFolderRequested(peer.id.clone())),
+				)
+				.push(
+					button(text("Catalog")).on_press(GuiMessage::CatalogRequested(peer.id.clone())),
+				)
+				.push(
+					button(text("Recent files"))
+						.on_press(GuiMessage::RecentFilesRequested(peer.id.clone())),
+				)
+				.push(button(text("Disconnect")).on_press_maybe(
+					connected.then(|| GuiMessage::PeerDisconnectRequested(peer.id.clone())),
+				))
 				.push(button(text("Back")).on_press(GuiMessage::BackToPeers));
 			layout = layout.push(controls);
 			layout.into()
@@ -1395,6 +2807,73 @@ impl GuiApp {
 		layout.into()
 	}
 
+	fn view_peer_overview(&self, state: &PeerOverviewState) -> Element<'_, GuiMessage> {
+		let o = &state.overview;
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout.push(text(format!("Overview of {}", state.peer_id)).size(24));
+		let used_of = |used: u64, total: u64| {
+			let percent = if total == 0 {
+				0.0
+			} else {
+				used as f64 / total as f64 * 100.0
+			};
+			format!(
+				"{} of {} ({:.0}%)",
+				format_size(used),
+				format_size(total),
+				percent
+			)
+		};
+		let rows = [
+			(
+				"CPU",
+				format!("{:.1}% average over {} CPUs", o.cpu_avg, o.cpu_count),
+			),
+			("Load (1, 5, 15 min)", format_load(o.load.as_ref())),
+			("Memory", used_of(o.mem.used, o.mem.total)),
+			("Swap", used_of(o.mem.swap_used, o.mem.swap_total)),
+			(
+				"Disks",
+				format!(
+					"{} free on {} disks",
+					used_of(o.disks_summary.available_space, o.disks_summary.total_space),
+					o.disks_summary.count
+				),
+			),
+			(
+				"Network",
+				format!(
+					"{} in, {} out on {} interfaces",
+					format_size(o.net_summary.total_received),
+					format_size(o.net_summary.total_transmitted),
+					o.net_summary.interfaces
+				),
+			),
+			("Uptime", format_uptime(o.uptime)),
+		];
+		let mut list = iced::widget::Column::new().spacing(4);
+		for (label, value) in rows {
+			let row = iced::widget::Row::new()
+				.spacing(12)
+				.push(text(label).size(14).width(Length::FillPortion(1)))
+				.push(text(value).size(14).width(Length::FillPortion(3)));
+			list = list.push(container(row).padding(8).style(theme::Container::Box));
+		}
+		layout = layout.push(list);
+		let controls = iced::widget::Row::new()
+			.spacing(12)
+			.push(
+				button(text("Refresh"))
+					.on_press(GuiMessage::OverviewRequested(state.peer_id.clone())),
+			)
+			.push(
+				button(text("Back to actions"))
+					.on_press(GuiMessage::PeerActionsRequested(state.peer_id.clone())),
+			);
+		layout = layout.push(controls);
+		layout.into()
+	}
+
 	fn view_peer_cpus(&self, state: &PeerCpuState) -> Element<'_, GuiMessage> {
 		let mut layout = iced::widget::Column::new().spacing(12);
 		layout = layout.push(text(format!("CPU inventory for {}", state.peer_id)).size(24));
@@ -1437,28 +2916,120 @@ impl GuiApp {
 		layout.into()
 	}
 
+	fn view_peer_interfaces(&self, state: &PeerInterfacesState) -> Element<'_, GuiMessage> {
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout.push(text(format!("Network interfaces of {}", state.peer_id)).size(24));
+		if state.interfaces.is_empty() {
+			layout = layout.push(text("No interfaces reported.").size(16));
+		} else {
+			let cell =
+				|value: String, portion| text(value).size(14).width(Length::FillPortion(portion));
+			let header = iced::widget::Row::new()
+				.spacing(12)
+				.push(cell(String::from("Name"), 2))
+				.push(cell(String::from("MAC"), 2))
+				.push(cell(String::from("Addresses"), 4))
+				.push(cell(String::from("Received"), 2))
+				.push(cell(String::from("Sent"), 2))
+				.push(cell(String::from("Errors in/out"), 2));
+			let mut list = iced::widget::Column::new().spacing(4);
+			for interface in &state.interfaces {
+				let ips = if interface.ips.is_empty() {
+					String::from("-")
+				} else {
+					interface.ips.join(", ")
+				};
+				let row = iced::widget::Row::new()
+					.spacing(12)
+					.push(cell(interface.name.clone(), 2))
+					.push(cell(interface.mac.clone(), 2))
+					.push(cell(ips, 4))
+					.push(cell(format_size(interface.total_received), 2))
+					.push(cell(format_size(interface.total_transmitted), 2))
+					.push(cell(
+						format!(
+							"{} / {}",
+							interface.errors_on_received, interface.errors_on_transmitted
+						),
+						2,
+					));
+				list = list.push(container(row).padding(8).style(theme::Container::Box));
+			}
+			layout = layout.push(container(header).padding([0, 8]));
+			layout = layout.push(scrollable(list).height(Length::Fill));
+		}
+		let controls = iced::widget::Row::new()
+			.spacing(12)
+			.push(
+				button(text("Refresh"))
+					.on_press(GuiMessage::InterfacesRequested(state.peer_id.clone())),
+			)
+			.push(
+				button(text("Back to actions"))
+					.on_press(GuiMessage::PeerActionsRequested(state.peer_id.clone())),
+			);
+		layout = layout.push(controls);
+		layout.into()
+	}
+
 	fn view_file_browser(&self, state: &FileBrowserState) -> Element<'_, GuiMessage> {
 		let mut layout = iced::widget::Column::new().spacing(12);
-		layout =
-			layout.push(text(format!("Browsing {} on {}", state.path, state.peer_id)).size(24));
+		let title = if state.at_shares() {
+			format!("Shared folders on {}", state.peer_id)
+		} else {
+			format!("Browsing {} on {}", state.path, state.peer_id)
+		};
+		layout = layout.push(text(title).size(24));
 		let controls = iced::widget::Row::new()
 			.spacing(12)
 			.push(button(text("Up")).on_press(GuiMessage::FileNavigateUp))
+			.push(button(text("Load subtree")).on_press_maybe(
+				(!state.at_shares() && !state.loading).then_some(GuiMessage::FileSubtreeRequested),
+			))
 			.push(
 				button(text("Back to actions"))
 					.on_press(GuiMessage::PeerActionsRequested(state.peer_id.clone())),
+			)
+			.push(
+				checkbox("Show hidden files", state.show_hidden)
+					.on_toggle(GuiMessage::FileShowHiddenToggled),
 			);
 		layout = layout.push(controls);
-		if state.loading {
+		if state.at_shares() {
+			let mut list = iced::widget::Column::new().spacing(4);
+			for root in &state.available_roots {
+				list = list.push(
+					button(text(format!("[SHARE] {}", root)))
+						.width(Length::Fill)
+						.on_press(GuiMessage::FileShareSelected(root.clone())),
+				);
+			}
+			for file in &state.shared_files {
+				list = list.push(
+					button(text(format!("[FILE] {}", file)))
+						.width(Length::Fill)
+						.on_press(GuiMessage::SharedFileSelected(file.clone())),
+				);
+			}
+			layout = layout.push(scrollable(list).height(Length::Fill));
+		} else if state.loading {
 			layout = layout.push(text("Loading directory...").size(16));
 		} else if let Some(err) = &state.error {
 			layout = layout.push(text(format!("Error: {}", err)).size(16));
-		} else if state.entries.is_empty() {
+			if let Some(file) = &state.listed_file {
+				layout = layout.push(
+					button(text("Open as file"))
+						.on_press(GuiMessage::SharedFileSelected(file.clone())),
+				);
+			}
+		} else if state.visible_entries().next().is_none() {
 			layout = layout.push(text("Directory is empty").size(16));
 		} else {
 			let mut list = iced::widget::Column::new().spacing(4);
-			for entry in &state.entries {
-				let label = if entry.is_dir {
+			for entry in state.visible_entries() {
+				let label = if entry.is_symlink && entry.is_navigable() {
+					format!("[LINK] {}", entry.name)
+				} else if entry.is_dir {
 					format!("[DIR] {}", entry.name)
 				} else {
 					format!("{} ({})", entry.name, format_size(entry.size))
@@ -1473,10 +3044,11 @@ impl GuiApp {
 		layout.into()
 	}
 
-	fn view_file_viewer(&self, state: &FileViewerState) -> Element<'_, GuiMessage> {
+	fn view_file_viewer<'a>(&'a self, state: &'a FileViewerState) -> Element<'a, GuiMessage> {
 		let mut layout = iced::widget::Column::new().spacing(12);
 		layout = layout.push(text(format!("Viewing {} on {}", state.path, state.peer_id)).size(24));
-		let mut summary = format!("Loaded {} bytes", state.data.len());
+		let resident = state.resident();
+		let mut summary = format!("Showing bytes {}-{}", resident.start, resident.end);
 		if let Some(mime) = &state.mime {
 			summary.push_str(&format!(" | {}", mime));
 		}
@@ -1487,6 +3059,26 @@ impl GuiApp {
 		if let Some(err) = &state.error {
 			layout = layout.push(text(format!("Error: {}", err)).size(14));
 		}
+		if let Some(editor) = &state.editor {
+			let mut editor_view = text_editor(&editor.content).height(Length::Fill);
+			if !editor.saving {
+				editor_view = editor_view.on_action(GuiMessage::FileEditAction);
+			}
+			layout = layout.push(editor_view);
+			let save_label = if editor.saving { "Saving..." } else { "Save" };
+			let mut save_btn = button(text(save_label));
+			let mut cancel_btn = button(text("Cancel"));
+			if !editor.saving {
+				save_btn = save_btn.on_press(GuiMessage::FileSaveRequested);
+				cancel_btn = cancel_btn.on_press(GuiMessage::FileEditCancelled);
+			}
+			let controls = iced::widget::Row::new()
+				.spacing(12)
+				.push(save_btn)
+				.push(cancel_btn)
+				.push(button(text("Back to browser")).on_press(GuiMessage::FileViewerBack));
+			return layout.push(controls).into();
+		}
 		if state.is_image() {
 			if state.data.is_empty() {
 				if state.loading {
@@ -1494,7 +3086,15 @@ impl GuiApp {
 				} else {
 					layout = layout.push(text("Image data not yet loaded").size(14));
 				}
-			} else if !state.eof {
+			} else if !state.is_complete() && state.data.len() as u64 >= FILE_VIEW_WINDOW {
+				layout = layout.push(
+					text(format!(
+						"Image is larger than the {} byte preview window",
+						FILE_VIEW_WINDOW
+					))
+					.size(14),
+				);
+			} else if !state.is_complete() {
 				layout = layout.push(
 					text("Partial image data loaded — load remaining bytes to render").size(14),
 				);
@@ -1535,6 +3135,13 @@ impl GuiApp {
 			layout = layout.push(text("No data loaded yet").size(14));
 		}
 		let mut controls = iced::widget::Row::new().spacing(12);
+		if state.previous_offset().is_some() {
+			let mut previous_btn = button(text("Load previous"));
+			if !state.loading {
+				previous_btn = previous_btn.on_press(GuiMessage::FileReadPrevious);
+			}
+			controls = controls.push(previous_btn);
+		}
 		if !state.eof {
 			let label = if state.loading {
 				"Loading..."
@@ -1547,6 +3154,15 @@ impl GuiApp {
 			}
 			controls = controls.push(load_btn);
 		}
+		let open_label = if state.confirm_open {
+			"Open anyway"
+		} else {
+			"Open"
+		};
+		controls = controls.push(button(text(open_label)).on_press(GuiMessage::FileOpenRequested));
+		if !state.loading && state.editable_text().is_some() {
+			controls = controls.push(button(text("Edit")).on_press(GuiMessage::FileEditRequested));
+		}
 		controls =
 			controls.push(button(text("Back to browser")).on_press(GuiMessage::FileViewerBack));
 		layout = layout.push(controls);
@@ -1567,15 +3183,17 @@ impl GuiApp {
 				let marker = if Some(node.id.as_str()) == self.graph.selected_id() {
 					"▶"
 				} else {
-					""
-				};
-				list = list.push(
-					text(format!(
-						"{} {} (angle {:.2} rad)",
-						marker, node.id, node.angle
-					))
-					.size(14),
-				);
+					""
+				};
+				let mut label = text(format!(
+					"{} {} (angle {:.2} rad)",
+					marker, node.id, node.angle
+				))
+				.size(14);
+				if self.local_peer_id.as_deref() == Some(node.id.as_str()) {
+					label = label.style(LOCAL_PEER_COLOR);
+				}
+				list = list.push(label);
 			}
 			layout = layout.push(scrollable(list).height(Length::Fill));
 			let action_message = self
@@ -1610,6 +3228,301 @@ impl GuiApp {
 		layout.into()
 	}
 
+	fn view_shared_folders(&self, state: &SharedFoldersState) -> Element<'_, GuiMessage> {
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout.push(text("Shared Folders").size(24));
+		if state.loading {
+			return layout.push(text("Measuring usage...").size(16)).into();
+		}
+		if let Some(err) = &state.error {
+			return layout.push(text(format!("Error: {}", err)).size(16)).into();
+		}
+		if state.folders.is_empty() {
+			return layout
+				.push(text("No folders are shared. Use --read or --write to share one.").size(16))
+				.into();
+		}
+		let mut list = iced::widget::Column::new().spacing(4);
+		for (folder, size) in &state.folders {
+			let access = if folder.can_write() {
+				"read/write"
+			} else {
+				"read only"
+			};
+			let used = format!(
+				"{}{}",
+				format_size(size.total_bytes),
+				if size.partial { "+" } else { "" }
+			);
+			let usage = match folder.max_bytes() {
+				Some(max) => format!(
+					"{} of {} ({:.0}%)",
+					used,
+					format_size(max),
+					size.total_bytes as f64 * 100.0 / max.max(1) as f64
+				),
+				None => format!("{} (no quota)", used),
+			};
+			let row = iced::widget::Row::new()
+				.spacing(12)
+				.push(
+					text(folder.path().display().to_string())
+						.size(14)
+						.width(Length::FillPortion(3)),
+				)
+				.push(text(access).size(14).width(Length::FillPortion(1)))
+				.push(text(usage).size(14).width(Length::FillPortion(2)));
+			list = list.push(container(row).padding(8).style(theme::Container::Box));
+		}
+		layout = layout.push(scrollable(list).height(Length::Fill));
+		layout.into()
+	}
+
+	fn view_scan_folder(&self, state: &ScanState) -> Element<'_, GuiMessage> {
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout.push(text("Scan Folder").size(24));
+		let mut path_input = text_input("Folder to scan", &state.path);
+		let start = if state.running {
+			button(text("Cancel")).on_press(GuiMessage::ScanCancel)
+		} else {
+			path_input = path_input
+				.on_input(GuiMessage::ScanPathChanged)
+				.on_submit(GuiMessage::ScanStart);
+			button(text("Scan")).on_press(GuiMessage::ScanStart)
+		};
+		layout = layout.push(
+			iced::widget::Row::new()
+				.spacing(12)
+				.push(path_input)
+				.push(start),
+		);
+		if let Some(progress) = &state.progress {
+			let total = progress.total_files.max(1) as f32;
+			layout = layout
+				.push(progress_bar(0.0..=total, progress.files_scanned as f32))
+				.push(
+					text(format!(
+						"{} of {} files",
+						progress.files_scanned, progress.total_files
+					))
+					.size(16),
+				)
+				.push(text(progress.current_path.display().to_string()).size(14));
+		} else if state.running {
+			layout = layout.push(text("Walking folder...").size(16));
+		}
+		match &state.outcome {
+			Some(Ok(summary)) => layout.push(text(summary).size(16)).into(),
+			Some(Err(err)) => layout.push(text(format!("Error: {}", err)).size(16)).into(),
+			None => layout.into(),
+		}
+	}
+
+	fn view_recent_files(&self, state: &RecentFilesState) -> Element<'_, GuiMessage> {
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout
+			.push(text(format!("Recent files on {}", state.peer_id)).size(24))
+			.push(
+				button(text("Back"))
+					.on_press(GuiMessage::PeerActionsRequested(state.peer_id.clone())),
+			);
+		if let Some(err) = &state.error {
+			return layout.push(text(format!("Error: {}", err))).into();
+		}
+		if state.files.is_empty() {
+			return layout
+				.push(text("No files opened on this peer yet."))
+				.into();
+		}
+		let mut list = iced::widget::Column::new().spacing(4);
+		for file in &state.files {
+			let opened = file.opened_at.with_timezone(&chrono::Local);
+			list = list.push(
+				button(text(format!(
+					"{}  {}",
+					opened.format("%Y-%m-%d %H:%M"),
+					file.path
+				)))
+				.width(Length::Fill)
+				.on_press(GuiMessage::RecentFileSelected(file.path.clone())),
+			);
+		}
+		layout.push(scrollable(list).height(Length::Fill)).into()
+	}
+
+	fn view_catalog(&self, state: &CatalogState) -> Element<'_, GuiMessage> {
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout.push(text(format!("Catalog of {}", state.peer_id)).size(24));
+		let has_more = state.page.as_ref().is_some_and(|page| page.has_more);
+		let shown = state
+			.page
+			.as_ref()
+			.map_or(0, |page| page.entries.len() as u64);
+		let idle = |message: GuiMessage| (!state.loading).then_some(message);
+		layout = layout
+			.push(
+				text_input("Folder, e.g. / or a shared folder", &state.prefix)
+					.padding(8)
+					.on_input(GuiMessage::CatalogPrefixChanged)
+					.on_submit(GuiMessage::CatalogPageRequested(0)),
+			)
+			.push(
+				iced::widget::Row::new()
+					.spacing(12)
+					.push(
+						button(text("Browse"))
+							.on_press_maybe(idle(GuiMessage::CatalogPageRequested(0))),
+					)
+					.push(
+						button(text("Previous")).on_press_maybe(
+							idle(GuiMessage::CatalogPageRequested(
+								state.offset.saturating_sub(CATALOG_PAGE_SIZE),
+							))
+							.filter(|_| state.offset > 0),
+						),
+					)
+					.push(
+						button(text("Next")).on_press_maybe(
+							idle(GuiMessage::CatalogPageRequested(state.offset + shown))
+								.filter(|_| has_more),
+						),
+					)
+					.push(
+						button(text("Back"))
+							.on_press(GuiMessage::PeerActionsRequested(state.peer_id.clone())),
+					),
+			);
+		if state.loading {
+			return layout.push(text("Loading...")).into();
+		}
+		if let Some(err) = &state.error {
+			return layout.push(text(format!("Error: {}", err))).into();
+		}
+		let Some(page) = &state.page else {
+			return layout.into();
+		};
+		if page.entries.is_empty() {
+			return layout
+				.push(text(
+					"Nothing indexed here. The peer has to scan the folder first.",
+				))
+				.into();
+		}
+		layout = layout.push(
+			text(format!(
+				"Entries {}-{}{}",
+				state.offset + 1,
+				state.offset + shown,
+				if has_more { ", more follow" } else { "" }
+			))
+			.size(14),
+		);
+		let mut list = iced::widget::Column::new().spacing(4);
+		for entry in &page.entries {
+			let modified = entry
+				.modified_at
+				.map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+				.unwrap_or_default();
+			let row = iced::widget::Row::new()
+				.spacing(8)
+				.push(text(&entry.path).size(14).width(Length::FillPortion(5)))
+				.push(
+					text(entry.mime_type.clone().unwrap_or_else(|| "?".into()))
+						.size(14)
+						.width(Length::FillPortion(2)),
+				)
+				.push(
+					text(format_size(entry.size))
+						.size(14)
+						.width(Length::FillPortion(1)),
+				)
+				.push(text(modified).size(14).width(Length::FillPortion(2)));
+			list = list.push(container(row).padding(4).style(theme::Container::Box));
+		}
+		layout.push(scrollable(list).height(Length::Fill)).into()
+	}
+
+	fn view_sync_folder(&self, state: &SyncState) -> Element<'_, GuiMessage> {
+		let mut layout = iced::widget::Column::new().spacing(12);
+		layout = layout.push(text(format!("Sync folder with {}", state.peer_id)).size(24));
+		let mut local_input = text_input("Local folder", &state.local_path).padding(8);
+		let mut remote_input = text_input("Folder on the peer", &state.remote_path).padding(8);
+		let mut compare = button(text("Compare"));
+		if !state.busy {
+			local_input = local_input
+				.on_input(GuiMessage::SyncLocalPathChanged)
+				.on_submit(GuiMessage::SyncPlanRequested);
+			remote_input = remote_input
+				.on_input(GuiMessage::SyncRemotePathChanged)
+				.on_submit(GuiMessage::SyncPlanRequested);
+			compare = compare.on_press(GuiMessage::SyncPlanRequested);
+		}
+		layout = layout.push(local_input).push(remote_input).push(
+			iced::widget::Row::new()
+				.spacing(12)
+				.push(pick_list(
+					&SYNC_CHOICES[..],
+					Some(state.choice),
+					GuiMessage::SyncChoiceSelected,
+				))
+				.push(compare)
+				.push(
+					button(text("Back"))
+						.on_press(GuiMessage::PeerActionsRequested(state.peer_id.clone())),
+				),
+		);
+		if state.busy {
+			layout = layout.push(text("Working...").size(16));
+		}
+		if let Some(plan) = &state.plan {
+			let changes: Vec<_> = plan
+				.files
+				.iter()
+				.filter(|file| file.operation != SyncOperation::Skip)
+				.collect();
+			layout = layout.push(
+				text(format!(
+					"{} to upload, {} to download, {} skipped",
+					plan.count(SyncOperation::Upload),
+					plan.count(SyncOperation::Download),
+					plan.count(SyncOperation::Skip)
+				))
+				.size(16),
+			);
+			let mut list = iced::widget::Column::new().spacing(4);
+			if changes.is_empty() {
+				list = list.push(text("Both folders are already in sync.").size(14));
+			}
+			for file in changes {
+				let operation = match file.operation {
+					SyncOperation::Upload => "upload",
+					SyncOperation::Download => "download",
+					SyncOperation::Skip => "skip",
+				};
+				let mut line =
+					format!("{:<8} {} ({})", operation, file.path.display(), file.reason);
+				if file.conflict {
+					line.push_str(", conflict");
+				}
+				if file.verify {
+					line.push_str(", skipped if contents match");
+				}
+				list = list.push(text(line).size(14));
+			}
+			layout = layout.push(scrollable(list).height(Length::Fill));
+			let mut confirm = button(text("Sync now"));
+			if !state.busy {
+				confirm = confirm.on_press(GuiMessage::SyncConfirmed);
+			}
+			layout = layout.push(confirm);
+		}
+		match &state.outcome {
+			Some(Ok(summary)) => layout.push(text(summary).size(16)).into(),
+			Some(Err(err)) => layout.push(text(format!("Error: {}", err)).size(16)).into(),
+			None => layout.into(),
+		}
+	}
+
 	fn view_file_search(&self, state: &FileSearchState) -> Element<'_, GuiMessage> {
 		let mut layout = iced::widget::Column::new().spacing(12);
 		layout = layout.push(text("File Search").size(24));
@@ -1682,6 +3595,38 @@ impl GuiApp {
 		layout.push(scrollable(list).height(Length::Fill)).into()
 	}
 
+	/// Direction and route of each open connection to the peer.
+	/// Ask `peer_id` for its uptime and load, unless we are not connected to it, in which
+	/// case every tick would dial it.
+	fn fetch_load(&self, peer_id: String) -> Command<GuiMessage> {
+		let local = self.local_peer_id.as_deref() == Some(peer_id.as_str());
+		if !local && self.connection_summaries(&peer_id).is_empty() {
+			return Command::none();
+		}
+		let peer = self.peer.clone();
+		Command::perform(
+			async move {
+				let result = match PeerId::from_str(&peer_id) {
+					Ok(id) => peer.system_load(id).await.map_err(|err| err.to_string()),
+					Err(err) => Err(err.to_string()),
+				};
+				(peer_id, result)
+			},
+			|(id, result)| GuiMessage::PeerLoadLoaded(id, result),
+		)
+	}
+
+	fn connection_summaries(&self, peer_id: &str) -> Vec<String> {
+		match (&self.latest_state, PeerId::from_str(peer_id)) {
+			(Some(state), Ok(target)) => state
+				.connection_routes(target)
+				.iter()
+				.map(|(direction, route)| format!("{} {}", direction, route))
+				.collect(),
+			_ => Vec::new(),
+		}
+	}
+
 	fn gather_known_addresses(&self, peer_id: &str) -> Vec<String> {
 		if let Some(state) = &self.latest_state {
 			if let Ok(target) = PeerId::from_str(peer_id) {
@@ -1689,7 +3634,7 @@ impl GuiApp {
 					.discovered_peers
 					.iter()
 					.filter(|p| p.peer_id == target)
-					.map(|p| p.multiaddr.to_string())
+					.flat_map(|p| p.addresses.iter().map(|addr| addr.to_string()))
 					.collect()
 			} else {
 				Vec::new()
@@ -1700,19 +3645,25 @@ impl GuiApp {
 	}
 }
 
-fn aggregate_peers(state: &State) -> Vec<PeerRow> {
+fn aggregate_peers(state: &State, show_local: bool) -> Vec<PeerRow> {
 	let mut rows: HashMap<String, PeerRow> = HashMap::new();
 	for discovered in &state.discovered_peers {
 		let id = format!("{}", discovered.peer_id);
+		let address = discovered
+			.addresses
+			.first()
+			.map(|addr| addr.to_string())
+			.unwrap_or_default();
 		rows.entry(id.clone())
 			.and_modify(|row| {
 				if row.address.is_empty() {
-					row.address = discovered.multiaddr.to_string();
+					row.address = address.clone();
 				}
 			})
 			.or_insert(PeerRow {
 				id,
-				address: discovered.multiaddr.to_string(),
+				name: None,
+				address,
 				status: String::from("discovered"),
 			});
 	}
@@ -1724,31 +3675,40 @@ fn aggregate_peers(state: &State) -> Vec<PeerRow> {
 			})
 			.or_insert(PeerRow {
 				id,
+				name: None,
 				address: String::new(),
 				status: String::from("connected"),
 			});
 	}
 	for peer in &state.peers {
 		let id = format!("{}", peer.id);
-		rows.entry(id.clone()).or_insert(PeerRow {
-			id,
-			address: String::new(),
-			status: String::new(),
-		});
+		rows.entry(id.clone())
+			.or_insert(PeerRow {
+				id,
+				name: None,
+				address: String::new(),
+				status: String::new(),
+			})
+			.name = peer.name.clone();
 	}
 	let me_id = format!("{}", state.me);
-	rows.entry(me_id.clone())
-		.and_modify(|row| {
-			row.status = String::from("local");
-			if row.address.is_empty() {
-				row.address = LOCAL_LISTEN_MULTIADDR.into();
-			}
-		})
-		.or_insert(PeerRow {
-			id: me_id,
-			address: LOCAL_LISTEN_MULTIADDR.into(),
-			status: String::from("local"),
-		});
+	if show_local {
+		rows.entry(me_id.clone())
+			.and_modify(|row| {
+				row.status = String::from("local");
+				if row.address.is_empty() {
+					row.address = LOCAL_LISTEN_MULTIADDR.into();
+				}
+			})
+			.or_insert(PeerRow {
+				id: me_id,
+				name: None,
+				address: LOCAL_LISTEN_MULTIADDR.into(),
+				status: String::from("local"),
+			});
+	} else {
+		rows.remove(&me_id);
+	}
 	let mut vec: Vec<PeerRow> = rows.into_iter().map(|(_, row)| row).collect();
 	vec.sort_by(|a, b| a.id.cmp(&b.id));
 	vec
@@ -1795,16 +3755,6 @@ fn file_preview_text(data: &[u8]) -> (String, bool) {
 	}
 }
 
-fn abbreviate_peer_id(id: &str) -> String {
-	const PREFIX: usize = 8;
-	const SUFFIX: usize = 6;
-	if id.len() <= PREFIX + SUFFIX + 1 {
-		id.to_string()
-	} else {
-		format!("{}…{}", &id[..PREFIX], &id[id.len() - SUFFIX..])
-	}
-}
-
 fn abbreviate_hash(hash_hex: &str) -> String {
 	const PREFIX: usize = 8;
 	const SUFFIX: usize = 8;
@@ -1836,6 +3786,9 @@ fn permissions_roots(permissions: &[Permission]) -> Vec<String> {
 	let mut roots: BTreeSet<String> = BTreeSet::new();
 	for permission in permissions {
 		if let Rule::Folder(rule) = permission.rule() {
+			if rule.is_file() {
+				continue;
+			}
 			let path = rule.path().to_string_lossy().to_string();
 			roots.insert(normalize_path(&path));
 		}
@@ -1843,9 +3796,24 @@ fn permissions_roots(permissions: &[Permission]) -> Vec<String> {
 	roots.into_iter().collect()
 }
 
+fn shared_files(permissions: &[Permission]) -> Vec<String> {
+	let mut files: BTreeSet<String> = BTreeSet::new();
+	for permission in permissions {
+		if let Rule::Folder(rule) = permission.rule()
+			&& rule.is_file()
+		{
+			files.insert(rule.path().to_string_lossy().to_string());
+		}
+	}
+	files.into_iter().collect()
+}
+
 fn default_browser_path(permissions: &[Permission]) -> Option<String> {
 	permissions.iter().find_map(|permission| {
 		if let Rule::Folder(rule) = permission.rule() {
+			if rule.is_file() {
+				return None;
+			}
 			if rule.can_read() || rule.can_search() || rule.can_write() {
 				let path = rule.path().to_string_lossy().to_string();
 				return Some(normalize_path(&path));
@@ -1894,23 +3862,116 @@ async fn fetch_cpus(
 	(peer_id, result)
 }
 
+async fn fetch_overview(
+	peer: Arc<PuppyPeer>,
+	peer_id: String,
+) -> (String, Result<SystemOverview, String>) {
+	let result = match PeerId::from_str(&peer_id) {
+		Ok(id) => peer
+			.system_overview(id)
+			.await
+			.map_err(|err| err.to_string()),
+		Err(err) => Err(err.to_string()),
+	};
+	(peer_id, result)
+}
+
+async fn fetch_interfaces(
+	peer: Arc<PuppyPeer>,
+	peer_id: String,
+) -> (String, Result<Vec<InterfaceInfo>, String>) {
+	let result = match PeerId::from_str(&peer_id) {
+		Ok(id) => peer
+			.list_interfaces(id)
+			.await
+			.map_err(|err| err.to_string()),
+		Err(err) => Err(err.to_string()),
+	};
+	(peer_id, result)
+}
+
+async fn shared_folder_usage(peer: Arc<PuppyPeer>) -> Result<Vec<(FolderRule, DirSize)>, String> {
+	map_result(peer.shared_folder_usage().await)
+}
+
 async fn search_files(
-	_peer: Arc<PuppyPeer>,
-	_query: String,
+	peer: Arc<PuppyPeer>,
+	query: String,
 	mime: Option<String>,
 	sort_desc: bool,
 ) -> Result<(Vec<FileSearchEntry>, Vec<String>), String> {
-	// Placeholder in-memory search over local sqlite not yet wired: return empty until DB API exposed.
-	// For now, we just simulate no results but allow UI to function.
-	let _ = (_query, mime, sort_desc); // suppress warnings
-	Ok((Vec::new(), Vec::new()))
+	let (files, mime_types) =
+		map_result(peer.search_files(&query, mime.as_deref(), sort_desc).await)?;
+	Ok((
+		files.into_iter().map(FileSearchEntry::from).collect(),
+		mime_types,
+	))
+}
+
+async fn scan_folder(
+	peer: Arc<PuppyPeer>,
+	path: String,
+	progress: Arc<std::sync::Mutex<Option<ScanProgress>>>,
+	cancel: Arc<AtomicBool>,
+) -> Result<String, String> {
+	let result = tokio::task::spawn_blocking(move || {
+		peer.scan_folder(
+			&path,
+			&ScanOptions::default(),
+			|report| {
+				if let Ok(mut latest) = progress.lock() {
+					*latest = Some(report);
+				}
+			},
+			&cancel,
+		)
+	})
+	.await
+	.map_err(|err| format!("{err}"))?;
+	map_result(result).map(|result| {
+		format!(
+			"{}{} inserted, {} updated, {} removed, {} skipped in {:.1}s",
+			if result.cancelled { "cancelled, " } else { "" },
+			result.inserted_count,
+			result.updated_count,
+			result.removed_count,
+			result.errors.len(),
+			result.duration.as_secs_f64()
+		)
+	})
+}
+
+async fn sync_plan(
+	peer: Arc<PuppyPeer>,
+	peer_id: String,
+	local_path: String,
+	remote_path: String,
+	mode: SyncMode,
+) -> Result<SyncPlan, String> {
+	let target = PeerId::from_str(&peer_id).map_err(|err| format!("{err}"))?;
+	map_result(peer.sync_plan(target, local_path, remote_path, mode).await)
+}
+
+async fn apply_sync_plan(peer: Arc<PuppyPeer>, plan: SyncPlan) -> Result<String, String> {
+	let report = peer.apply_sync_plan(&plan).await;
+	Ok(format!(
+		"{} pushed, {} pulled, {} unchanged, {} conflicts, {} failed",
+		report.pushed, report.pulled, report.unchanged, report.conflicts, report.failed
+	))
 }
 
 pub fn run(app_title: String) -> iced::Result {
 	let mut settings = Settings::default();
 	settings.window.size = iced::Size::new(1024.0, 720.0);
 	settings.flags = app_title;
-	GuiApp::run(settings)
+	let result = GuiApp::run(settings);
+	let temp_dir = open_temp_dir(&PuppyConfig::load().download_dir);
+	if temp_dir.exists()
+		&& let Err(err) = std::fs::remove_dir_all(&temp_dir)
+	{
+		log::warn!("failed to remove {}: {err}", temp_dir.display());
+	}
+	result
 }
 
 #[cfg(test)]
@@ -1918,6 +3979,7 @@ mod tests {
 	use super::*;
 
 	use libp2p::PeerId;
+	use puppypeer_core::{PlannedFile, SyncFileState, SyncReason};
 	use std::fs;
 	use std::path::{Path, PathBuf};
 	use std::time::{SystemTime, UNIX_EPOCH};
@@ -1931,42 +3993,37 @@ mod tests {
 		result
 	}
 
-	fn temporary_key_path(test: &str) -> PathBuf {
-		let mut path = std::env::temp_dir();
+	fn temporary_dir(test: &str) -> PathBuf {
 		let unique = SystemTime::now()
 			.duration_since(UNIX_EPOCH)
 			.unwrap_or_default()
 			.as_nanos();
-		path.push(format!(
-			"puppypeer-test-{}-{}-{}.key",
+		let path = std::env::temp_dir().join(format!(
+			"puppypeer-test-{}-{}-{}",
 			test,
 			std::process::id(),
 			unique
 		));
-		if path.exists() {
-			let _ = fs::remove_file(&path);
-		}
+		fs::create_dir_all(&path).expect("create temp dir");
 		path
 	}
 
-	fn set_keypair_var(path: &Path) {
-		unsafe {
-			std::env::set_var("KEYPAIR", path);
-		}
-	}
-
-	fn clear_keypair_var() {
-		unsafe {
-			std::env::remove_var("KEYPAIR");
-		}
+	/// An app whose peer keeps its identity and database in `root`, not the working dir.
+	fn test_app(root: &Path) -> GuiApp {
+		let peer = PuppyPeer::with_config(PuppyConfig {
+			keypair_path: Some(root.join("peer.key")),
+			db_path: Some(root.join("peer.db")),
+			listen_addrs: Vec::new(),
+			..PuppyConfig::default()
+		});
+		GuiApp::with_peer(Arc::new(peer), String::from("Test Title")).0
 	}
 
 	#[test]
 	fn selecting_peers_refreshes_from_state() {
 		with_runtime(|| {
-			let key_path = temporary_key_path("refresh");
-			set_keypair_var(&key_path);
-			let (mut app, _) = GuiApp::new(String::from("Test Title"));
+			let root = temporary_dir("refresh");
+			let mut app = test_app(&root);
 			let new_peer = PeerId::random();
 			{
 				let state = app.peer.state();
@@ -1978,17 +4035,15 @@ mod tests {
 			assert!(matches!(app.mode, Mode::Peers));
 			assert!(app.peers.iter().any(|row| row.id == new_peer.to_string()));
 			assert!(app.status.contains("Showing peers"));
-			let _ = fs::remove_file(&key_path);
-			clear_keypair_var();
+			let _ = fs::remove_dir_all(&root);
 		});
 	}
 
 	#[test]
 	fn selecting_graph_rebuilds_nodes() {
 		with_runtime(|| {
-			let key_path = temporary_key_path("graph");
-			set_keypair_var(&key_path);
-			let (mut app, _) = GuiApp::new(String::from("Test Title"));
+			let root = temporary_dir("graph");
+			let mut app = test_app(&root);
 			let peer_a = PeerId::random();
 			let peer_b = PeerId::random();
 			{
@@ -2002,8 +4057,193 @@ mod tests {
 			assert!(matches!(app.mode, Mode::PeersGraph));
 			assert_eq!(app.graph.nodes.len(), 3); // includes local peer
 			assert!(app.status.contains("Graph overview"));
-			let _ = fs::remove_file(&key_path);
-			clear_keypair_var();
+			let _ = fs::remove_dir_all(&root);
+		});
+	}
+
+	#[test]
+	fn interfaces_button_fetches_and_opens_the_table() {
+		with_runtime(|| {
+			let root = temporary_dir("interfaces");
+			let mut app = test_app(&root);
+			let peer = PeerId::random();
+			{
+				let state = app.peer.state();
+				let mut guard = state.lock().expect("state lock");
+				guard.peer_discovered(peer, "/ip4/127.0.0.1/tcp/7003".parse().unwrap());
+			}
+			let command = app.update(GuiMessage::InterfacesRequested(peer.to_string()));
+			assert_eq!(command.actions().len(), 1);
+			assert!(app.status.contains("Loading interfaces"));
+
+			let interface = InterfaceInfo {
+				name: String::from("eth0"),
+				mac: String::from("00:11:22:33:44:55"),
+				ips: vec![String::from("192.168.1.20/24")],
+				total_received: 2048,
+				total_transmitted: 1024,
+				packets_received: 4,
+				packets_transmitted: 2,
+				errors_on_received: 0,
+				errors_on_transmitted: 1,
+				mtu: 1500,
+			};
+			let _ = app.update(GuiMessage::InterfacesLoaded(
+				peer.to_string(),
+				Ok(vec![interface]),
+			));
+			assert!(
+				matches!(&app.mode, Mode::PeerInterfaces(state) if state.interfaces.len() == 1)
+			);
+			let _ = fs::remove_dir_all(&root);
+		});
+	}
+
+	#[test]
+	fn overwriting_sync_asks_for_confirmation() {
+		with_runtime(|| {
+			let root = temporary_dir("confirm");
+			let mut app = test_app(&root);
+			let existing = Some(SyncFileState {
+				size: 1,
+				modified: None,
+			});
+			let mut state = SyncState::new(PeerId::random().to_string());
+			state.plan = Some(SyncPlan {
+				peer: PeerId::random(),
+				local_root: PathBuf::from("/local"),
+				remote_root: PathBuf::from("/remote"),
+				files: vec![PlannedFile {
+					path: PathBuf::from("notes.txt"),
+					operation: SyncOperation::Upload,
+					reason: SyncReason::OneWay,
+					local: existing,
+					remote: existing,
+					conflict: true,
+					verify: true,
+				}],
+			});
+			app.mode = Mode::SyncFolder(state);
+
+			let _ = app.update(GuiMessage::SyncConfirmed);
+			assert!(
+				matches!(&app.mode, Mode::Confirm(confirm) if confirm.prompt.contains("1 existing file"))
+			);
+			let _ = app.update(GuiMessage::ConfirmCancelled);
+			assert!(
+				matches!(&app.mode, Mode::SyncFolder(state) if state.plan.is_some() && !state.busy)
+			);
+			let _ = fs::remove_dir_all(&root);
+		});
+	}
+
+	#[test]
+	fn local_peer_row_follows_toggle() {
+		let mut state = State::default();
+		let me = state.me;
+		state.peer_discovered(PeerId::random(), "/ip4/127.0.0.1/tcp/7001".parse().unwrap());
+		state.peer_discovered(me, "/ip4/127.0.0.1/tcp/7000".parse().unwrap());
+
+		let shown = aggregate_peers(&state, true);
+		assert_eq!(shown.len(), 2);
+		assert!(
+			shown
+				.iter()
+				.any(|row| row.id == me.to_string() && row.is_local())
+		);
+		let hidden = aggregate_peers(&state, false);
+		assert_eq!(hidden.len(), 1);
+		assert!(hidden.iter().all(|row| !row.is_local()));
+	}
+
+	#[test]
+	fn executables_are_detected_by_extension_or_mime() {
+		assert!(looks_executable("/srv/share/setup.EXE", None));
+		assert!(looks_executable("/srv/share/install.sh", None));
+		assert!(looks_executable(
+			"/srv/share/tool",
+			Some("application/x-executable")
+		));
+		assert!(!looks_executable(
+			"/srv/share/notes.txt",
+			Some("text/plain")
+		));
+		assert!(!looks_executable("/srv/share/photo", Some("image/png")));
+	}
+
+	#[test]
+	fn file_viewer_keeps_a_bounded_window() {
+		let browser = FileBrowserState::new(String::from("peer"), String::from("/"));
+		let mut state = FileViewerState::new(
+			browser,
+			String::from("peer"),
+			String::from("/big.bin"),
+			None,
+		);
+		let chunk = |offset: u64| FileChunk {
+			offset,
+			data: vec![(offset / FILE_VIEW_CHUNK_SIZE) as u8; FILE_VIEW_CHUNK_SIZE as usize],
+			eof: false,
+		};
+		let chunks = FILE_VIEW_WINDOW / FILE_VIEW_CHUNK_SIZE;
+		for idx in 0..chunks + 2 {
+			state.apply_chunk(chunk(idx * FILE_VIEW_CHUNK_SIZE));
+		}
+		assert_eq!(state.data.len() as u64, FILE_VIEW_WINDOW);
+		assert_eq!(state.start, 2 * FILE_VIEW_CHUNK_SIZE);
+		assert_eq!(state.offset, (chunks + 2) * FILE_VIEW_CHUNK_SIZE);
+		assert_eq!(state.data[0], 2);
+
+		// scrolling back refetches the dropped chunk and trims the far end instead
+		let previous = state.previous_offset().unwrap();
+		assert_eq!(previous, FILE_VIEW_CHUNK_SIZE);
+		state.apply_chunk(chunk(previous));
+		assert_eq!(state.data.len() as u64, FILE_VIEW_WINDOW);
+		assert_eq!(
+			state.resident(),
+			FILE_VIEW_CHUNK_SIZE..(chunks + 1) * FILE_VIEW_CHUNK_SIZE
+		);
+		assert_eq!(state.data[0], 1);
+		assert!(!state.eof);
+
+		// a far-away chunk replaces the window
+		state.apply_chunk(FileChunk {
+			offset: 100 * FILE_VIEW_CHUNK_SIZE,
+			data: vec![9; 10],
+			eof: true,
+		});
+		assert_eq!(
+			state.resident(),
+			100 * FILE_VIEW_CHUNK_SIZE..100 * FILE_VIEW_CHUNK_SIZE + 10
+		);
+		assert!(state.eof && !state.is_complete());
+	}
+
+	#[test]
+	fn only_complete_text_files_are_editable() {
+		let browser = FileBrowserState::new(String::from("peer"), String::from("/"));
+		let mut state = FileViewerState::new(
+			browser,
+			String::from("peer"),
+			String::from("/notes.txt"),
+			Some(String::from("text/plain")),
+		);
+		state.apply_chunk(FileChunk {
+			offset: 0,
+			data: b"draft".to_vec(),
+			eof: false,
+		});
+		assert_eq!(state.editable_text(), None);
+		state.apply_chunk(FileChunk {
+			offset: 5,
+			data: b" two".to_vec(),
+			eof: true,
 		});
+		assert_eq!(state.editable_text(), Some("draft two"));
+		state.data = vec![0xff, 0xfe];
+		assert_eq!(state.editable_text(), None);
+		state.mime = Some(String::from("image/png"));
+		state.data = b"text".to_vec();
+		assert_eq!(state.editable_text(), None);
 	}
 }
@@ -0,0 +1,24 @@
+use puppypeer_core::{PuppyConfig, PuppyPeer};
+
+use crate::utility::print_json;
+
+/// Replace this node's keypair and explain what the new peer id means for its peers.
+pub fn rotate(json: bool) -> anyhow::Result<()> {
+	let rotation = PuppyPeer::rotate_identity(&PuppyConfig::load())?;
+	if json {
+		return print_json(&rotation);
+	}
+	println!("old peer id: {}", rotation.old_peer_id);
+	println!("new peer id: {}", rotation.new_peer_id);
+	println!("backup:      {}", rotation.backup.display());
+	println!(
+		"moved {} permission rule(s) this node granted to the new id",
+		rotation.migrated_grants
+	);
+	eprintln!(
+		"warning: peers know this node as {}. Permissions and nicknames they keep for \
+		 that id do not carry over; ask them to grant access to {} again.",
+		rotation.old_peer_id, rotation.new_peer_id
+	);
+	Ok(())
+}
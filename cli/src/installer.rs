@@ -1,13 +1,21 @@
+use serde::Serialize;
 use service_manager::*;
 use std::{env, path::PathBuf};
 
 const SERVICE_LABEL: &str = "com.puppy.puppyagent";
 
+/// Output of `puppypeer install --json` and `puppypeer uninstall --json`.
+#[derive(Debug, Serialize)]
+pub struct ServiceOutput {
+	pub service: &'static str,
+	pub installed: bool,
+}
+
 fn current_exe() -> PathBuf {
 	env::current_exe().expect("failed to get current exe")
 }
 
-pub fn install() {
+pub fn install() -> ServiceOutput {
 	let label: ServiceLabel = SERVICE_LABEL.parse().unwrap();
 	let manager = <dyn ServiceManager>::native().expect("no supported service manager found");
 	manager
@@ -25,11 +33,19 @@ pub fn install() {
 		.unwrap();
 	log::info!("Service installed: {}", SERVICE_LABEL);
 	manager.start(ServiceStartCtx { label }).unwrap();
+	ServiceOutput {
+		service: SERVICE_LABEL,
+		installed: true,
+	}
 }
 
-pub fn uninstall() {
+pub fn uninstall() -> ServiceOutput {
 	let label: ServiceLabel = SERVICE_LABEL.parse().unwrap();
 	let manager = <dyn ServiceManager>::native().unwrap();
 	manager.uninstall(ServiceUninstallCtx { label }).unwrap();
 	log::info!("Service uninstalled: {}", SERVICE_LABEL);
+	ServiceOutput {
+		service: SERVICE_LABEL,
+		installed: false,
+	}
 }
@@ -1,57 +1,116 @@
-use args::Command;
-use clap::Parser;
+use args::{Command, ConfigCommand, IdentityCommand};
+use clap::{CommandFactory, Parser};
 use puppypeer_core::PuppyPeer;
+use puppypeer_core::scan::ScanOptions;
+use std::path::Path;
+use std::time::Duration;
 
 mod args;
+mod bench;
+mod config;
+mod doctor;
+mod fsck;
 mod gui;
+mod identity;
 mod installer;
+#[cfg(feature = "fuse")]
+mod mount;
+mod peers;
+mod pipe;
+mod scan;
 mod service;
 mod shell;
+mod trust;
 mod types;
 mod updater;
 mod utility;
+#[cfg(feature = "web")]
+mod web;
 
 #[tokio::main]
 async fn main() {
 	let args = args::Args::parse();
+	// before anything loads the config, so every path below resolves inside the profile
+	if let Some(name) = &args.profile
+		&& let Err(err) = puppypeer_core::select_profile(name)
+	{
+		eprintln!("cannot use profile {name}: {err:#}");
+		std::process::exit(1);
+	}
 	let init_logging = match &args.command {
-		Some(Command::Tui) => false,
+		Some(Command::Tui)
+		| Some(Command::Doctor)
+		| Some(Command::Peers { .. })
+		| Some(Command::Scan { .. })
+		| Some(Command::Config { .. })
+		| Some(Command::Identity { .. })
+		| Some(Command::Cat { .. })
+		| Some(Command::Put { .. })
+		| Some(Command::Mount { .. })
+		| Some(Command::Bench { .. })
+		| Some(Command::Fsck { .. })
+		| Some(Command::Completions { .. }) => false,
 		_ => true,
 	};
 	if init_logging {
 		simple_logger::init_with_level(log::Level::Info).unwrap();
 	}
 
-	let version_label = utility::get_version_label().unwrap_or("dev");
-	log::info!("puppyagent version {}", version_label);
-
-	#[cfg(feature = "rayon")]
-	log::info!("rayon enabled");
-	#[cfg(feature = "ring")]
-	log::info!("ring enabled");
+	let version_label = utility::VERSION_LABEL;
+	log::info!("puppypeer version {}", utility::VERSION_DISPLAY);
+	log::info!("build: {}", PuppyPeer::build_info());
+	if args.verify_on_start
+		&& let Err(err) = trust::verify_current_exe()
+	{
+		eprintln!("refusing to start: {err:#}");
+		std::process::exit(1);
+	}
 
 	match &args.command {
 		Some(Command::Copy { src, dest }) => {
 			log::info!("copying {} to {}", src, dest);
 		}
-		Some(Command::Scan { path }) => {
-			log::info!("scanning {} (database disabled)", path);
+		Some(Command::Scan {
+			path,
+			include,
+			exclude,
+			follow_symlinks,
+		}) => {
+			let options = ScanOptions {
+				include: include.clone(),
+				exclude: exclude.clone(),
+				follow_symlinks: *follow_symlinks,
+			};
+			if let Err(err) = scan::run(path.clone(), options, args.json).await {
+				eprintln!("scan failed: {err:#}");
+				std::process::exit(1);
+			}
 			return;
 		}
 		Some(Command::Install) => {
-			installer::install();
+			let output = installer::install();
+			if args.json {
+				print_json_or_exit(&output);
+			}
 			return;
 		}
 		Some(Command::Uninstall) => {
-			installer::uninstall();
+			let output = installer::uninstall();
+			if args.json {
+				print_json_or_exit(&output);
+			}
 			return;
 		}
-		Some(Command::Update { version }) => {
-			if let Err(err) = updater::update(version.as_deref()).await {
-				log::error!("failed to update: {err:?}");
-				std::process::exit(1);
+		Some(Command::Update { version, source }) => {
+			match updater::update(version.as_deref(), source.as_deref()).await {
+				Ok(output) if args.json => print_json_or_exit(&output),
+				Ok(output) if output.updated => log::info!("update completed successfully"),
+				Ok(_) => {}
+				Err(err) => {
+					log::error!("failed to update: {err:?}");
+					std::process::exit(1);
+				}
 			}
-			log::info!("update completed successfully");
 			return;
 		}
 		Some(Command::Tui) => {
@@ -73,22 +132,166 @@ async fn main() {
 			log::warn!("Daemon mode: disabled modules");
 			return;
 		}
+		Some(Command::Doctor) => {
+			if let Err(err) = doctor::run(args.json) {
+				eprintln!("doctor failed: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Peers { wait }) => {
+			if let Err(err) = peers::run(args.json, Duration::from_secs(*wait)).await {
+				eprintln!("failed to export peers: {err:?}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Config {
+			command: ConfigCommand::Show,
+		}) => {
+			if let Err(err) = config::show(&args, args.json) {
+				eprintln!("failed to show config: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Identity {
+			command: IdentityCommand::Rotate,
+		}) => {
+			if let Err(err) = identity::rotate(args.json) {
+				eprintln!("failed to rotate identity: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Cat { peer, path, wait }) => {
+			if let Err(err) = pipe::cat(peer, path, Duration::from_secs(*wait)).await {
+				eprintln!("cat failed: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Put { peer, path, wait }) => {
+			if let Err(err) = pipe::put(peer, path, Duration::from_secs(*wait)).await {
+				eprintln!("put failed: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Mount {
+			peer,
+			remote_path,
+			mountpoint,
+			wait,
+		}) => {
+			#[cfg(feature = "fuse")]
+			{
+				let wait = Duration::from_secs(*wait);
+				if let Err(err) = mount::run(peer, remote_path, Path::new(mountpoint), wait).await {
+					eprintln!("mount failed: {err:#}");
+					std::process::exit(1);
+				}
+				return;
+			}
+			#[cfg(not(feature = "fuse"))]
+			{
+				let _ = (peer, remote_path, mountpoint, wait);
+				eprintln!(
+					"cannot mount: this build has no FUSE support, rebuild with --features fuse"
+				);
+				std::process::exit(1);
+			}
+		}
+		Some(Command::Bench {
+			peer,
+			requests,
+			concurrency,
+			dir,
+			file,
+			chunk_size,
+			wait,
+		}) => {
+			let options = bench::BenchOptions {
+				requests: *requests,
+				concurrency: *concurrency,
+				dir: dir.clone(),
+				file: file.clone(),
+				chunk_size: *chunk_size,
+			};
+			let wait = Duration::from_secs(*wait);
+			if let Err(err) = bench::run(peer.as_deref(), options, wait, args.json).await {
+				eprintln!("bench failed: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Fsck { repo, delete }) => {
+			if let Err(err) = fsck::run(Path::new(repo), *delete, args.json) {
+				eprintln!("fsck failed: {err:#}");
+				std::process::exit(1);
+			}
+			return;
+		}
+		Some(Command::Completions { shell }) => {
+			clap_complete::generate(
+				*shell,
+				&mut args::Args::command(),
+				"puppypeer",
+				&mut std::io::stdout(),
+			);
+			return;
+		}
 		None => {
+			#[cfg(not(feature = "web"))]
+			if let Some(addr) = &args.web_addr {
+				log::error!(
+					"cannot serve {addr}: this build has no web UI, rebuild with --features web"
+				);
+				std::process::exit(1);
+			}
 			let peer = PuppyPeer::new();
 			for path in &args.read {
 				if let Err(err) = peer.share_read_only_folder(path) {
-					log::error!("failed to share {} for read: {err:?}", path);
+					log::error!("cannot share for read: {err:#}");
 					std::process::exit(1);
 				}
 			}
 			for path in &args.write {
 				if let Err(err) = peer.share_read_write_folder(path) {
-					log::error!("failed to share {} for read/write: {err:?}", path);
+					log::error!("cannot share for read/write: {err:#}");
+					std::process::exit(1);
+				}
+				if let Err(err) = peer.set_share_quota(path, args.quota) {
+					log::error!("failed to set quota for {}: {err:?}", path);
 					std::process::exit(1);
 				}
+				if let Err(err) = peer.set_share_modes(path, args.file_mode, args.dir_mode) {
+					log::error!("failed to set modes for {}: {err:?}", path);
+					std::process::exit(1);
+				}
+			}
+			#[cfg(feature = "web")]
+			if let Some(addr) = &args.web_addr {
+				let options = web::WebOptions {
+					store_dir: args.store_dir.as_deref(),
+					cors_origins: &args.cors_origins,
+					max_upload: args.max_upload,
+				};
+				if let Err(err) = web::run(peer, addr, options).await {
+					log::error!("web UI failed: {err:#}");
+					std::process::exit(1);
+				}
+				return;
 			}
 			peer.wait().await;
 			return;
 		}
 	}
 }
+
+fn print_json_or_exit<T: serde::Serialize>(value: &T) {
+	if let Err(err) = utility::print_json(value) {
+		eprintln!("failed to print output: {err:#}");
+		std::process::exit(1);
+	}
+}
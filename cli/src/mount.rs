@@ -0,0 +1,323 @@
+//! `puppypeer mount`: a peer's folder as a local FUSE filesystem.
+//!
+//! Every callback blocks on a request to the peer: `getattr` on `StatFile`, `readdir` and
+//! `lookup` on `ListDir`, `read` on `ReadFile` and `write` on an in-place `WriteFile`.
+
+use crate::pipe;
+use anyhow::{Context, Result, bail};
+use fuser::{
+	FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry,
+	ReplyWrite, Request,
+};
+use futures::executor::block_on;
+use libp2p::PeerId;
+use puppypeer_core::PuppyPeer;
+use puppypeer_core::p2p::{DirEntry, ErrorCode, WriteMode};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Inode of the mounted remote directory.
+const ROOT_INODE: u64 = fuser::FUSE_ROOT_ID;
+/// How long a directory listing is reused before it is fetched again.
+const LISTING_TTL: Duration = Duration::from_secs(2);
+/// How long the kernel may trust attributes and names we hand out.
+const ATTR_TTL: Duration = Duration::from_secs(1);
+
+/// Stable inode numbers for remote paths, handed out on first sight.
+struct Inodes {
+	paths: HashMap<u64, String>,
+	by_path: HashMap<String, u64>,
+}
+
+impl Inodes {
+	fn new(root: String) -> Self {
+		let mut inodes = Inodes {
+			paths: HashMap::new(),
+			by_path: HashMap::new(),
+		};
+		inodes.paths.insert(ROOT_INODE, root.clone());
+		inodes.by_path.insert(root, ROOT_INODE);
+		inodes
+	}
+
+	fn intern(&mut self, path: String) -> u64 {
+		if let Some(ino) = self.by_path.get(&path) {
+			return *ino;
+		}
+		let ino = ROOT_INODE + self.paths.len() as u64;
+		self.paths.insert(ino, path.clone());
+		self.by_path.insert(path, ino);
+		ino
+	}
+
+	fn path(&self, ino: u64) -> Option<&str> {
+		self.paths.get(&ino).map(String::as_str)
+	}
+}
+
+struct RemoteFs {
+	peer: PuppyPeer,
+	target: PeerId,
+	inodes: Inodes,
+	listings: HashMap<u64, (Instant, Vec<DirEntry>)>,
+}
+
+/// Errno for a failed peer call.
+fn errno(err: &anyhow::Error) -> i32 {
+	match ErrorCode::of(err) {
+		Some(ErrorCode::NotFound) => libc::ENOENT,
+		Some(ErrorCode::AccessDenied) => libc::EACCES,
+		Some(ErrorCode::IsADirectory) => libc::EISDIR,
+		Some(ErrorCode::NotADirectory) => libc::ENOTDIR,
+		Some(ErrorCode::InvalidArgument) => libc::EINVAL,
+		Some(ErrorCode::LimitExceeded) => libc::EFBIG,
+		Some(ErrorCode::NoSpace) => libc::ENOSPC,
+		Some(ErrorCode::Io) | None => libc::EIO,
+	}
+}
+
+fn file_attr(ino: u64, entry: &DirEntry) -> FileAttr {
+	let time = |at: Option<chrono::DateTime<chrono::Utc>>| {
+		at.map(SystemTime::from).unwrap_or(SystemTime::UNIX_EPOCH)
+	};
+	let mtime = time(entry.modified_at);
+	let (kind, perm) = if entry.is_navigable() {
+		(FileType::Directory, 0o755)
+	} else {
+		(FileType::RegularFile, 0o644)
+	};
+	FileAttr {
+		ino,
+		size: entry.size,
+		blocks: entry.size.div_ceil(512),
+		atime: time(entry.accessed_at),
+		mtime,
+		ctime: mtime,
+		crtime: time(entry.created_at),
+		kind,
+		perm,
+		nlink: 1,
+		// SAFETY: getuid and getgid cannot fail
+		uid: unsafe { libc::getuid() },
+		gid: unsafe { libc::getgid() },
+		rdev: 0,
+		blksize: 512,
+		flags: 0,
+	}
+}
+
+impl RemoteFs {
+	fn new(peer: PuppyPeer, target: PeerId, root: String) -> Self {
+		RemoteFs {
+			peer,
+			target,
+			inodes: Inodes::new(root),
+			listings: HashMap::new(),
+		}
+	}
+
+	fn path(&self, ino: u64) -> Result<String, i32> {
+		self.inodes
+			.path(ino)
+			.map(str::to_string)
+			.ok_or(libc::ENOENT)
+	}
+
+	/// List the directory `ino`, reusing a listing fetched within `LISTING_TTL`.
+	fn listing(&mut self, ino: u64) -> Result<Vec<(u64, DirEntry)>, i32> {
+		let cached = self
+			.listings
+			.get(&ino)
+			.filter(|(fetched, _)| fetched.elapsed() < LISTING_TTL)
+			.map(|(_, entries)| entries.clone());
+		let path = self.path(ino)?;
+		let entries = match cached {
+			Some(entries) => entries,
+			None => {
+				let entries = self
+					.peer
+					.list_dir_blocking(self.target, path.clone())
+					.map_err(|err| errno(&err))?;
+				self.listings.insert(ino, (Instant::now(), entries.clone()));
+				entries
+			}
+		};
+		Ok(entries
+			.into_iter()
+			.map(|entry| {
+				let child = format!("{}/{}", path.trim_end_matches('/'), entry.name);
+				(self.inodes.intern(child), entry)
+			})
+			.collect())
+	}
+}
+
+impl Filesystem for RemoteFs {
+	fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+		let listing = match self.listing(parent) {
+			Ok(listing) => listing,
+			Err(code) => return reply.error(code),
+		};
+		match listing
+			.iter()
+			.find(|(_, entry)| OsStr::new(&entry.name) == name)
+		{
+			Some((ino, entry)) => reply.entry(&ATTR_TTL, &file_attr(*ino, entry), 0),
+			None => reply.error(libc::ENOENT),
+		}
+	}
+
+	fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+		let path = match self.path(ino) {
+			Ok(path) => path,
+			Err(code) => return reply.error(code),
+		};
+		match self.peer.stat_file_blocking(self.target, path) {
+			Ok(entry) => reply.attr(&ATTR_TTL, &file_attr(ino, &entry)),
+			Err(err) => reply.error(errno(&err)),
+		}
+	}
+
+	fn readdir(
+		&mut self,
+		_req: &Request<'_>,
+		ino: u64,
+		_fh: u64,
+		offset: i64,
+		mut reply: ReplyDirectory,
+	) {
+		let listing = match self.listing(ino) {
+			Ok(listing) => listing,
+			Err(code) => return reply.error(code),
+		};
+		// the parent's inode is not tracked, and the kernel resolves `..` itself
+		let dots = [
+			(ino, FileType::Directory, String::from(".")),
+			(ino, FileType::Directory, String::from("..")),
+		];
+		let entries = dots
+			.into_iter()
+			.chain(listing.into_iter().map(|(ino, entry)| {
+				let kind = if entry.is_navigable() {
+					FileType::Directory
+				} else {
+					FileType::RegularFile
+				};
+				(ino, kind, entry.name)
+			}));
+		// an entry's offset is where the next readdir call continues
+		for (i, (ino, kind, name)) in entries.enumerate().skip(offset as usize) {
+			if reply.add(ino, i as i64 + 1, kind, name) {
+				break;
+			}
+		}
+		reply.ok();
+	}
+
+	fn read(
+		&mut self,
+		_req: &Request<'_>,
+		ino: u64,
+		_fh: u64,
+		offset: i64,
+		size: u32,
+		_flags: i32,
+		_lock_owner: Option<u64>,
+		reply: ReplyData,
+	) {
+		let path = match self.path(ino) {
+			Ok(path) => path,
+			Err(code) => return reply.error(code),
+		};
+		match self
+			.peer
+			.read_file_blocking(self.target, path, offset as u64, Some(size as u64))
+		{
+			Ok(chunk) => reply.data(&chunk.data),
+			Err(err) => reply.error(errno(&err)),
+		}
+	}
+
+	fn write(
+		&mut self,
+		_req: &Request<'_>,
+		ino: u64,
+		_fh: u64,
+		offset: i64,
+		data: &[u8],
+		_write_flags: u32,
+		_flags: i32,
+		_lock_owner: Option<u64>,
+		reply: ReplyWrite,
+	) {
+		let path = match self.path(ino) {
+			Ok(path) => path,
+			Err(code) => return reply.error(code),
+		};
+		let written = block_on(self.peer.write_file(
+			self.target,
+			path,
+			offset as u64,
+			data.to_vec(),
+			WriteMode::InPlace,
+			None,
+		));
+		match written {
+			Ok(_) => {
+				// sizes changed, so cached listings may be stale
+				self.listings.clear();
+				reply.written(data.len() as u32);
+			}
+			Err(err) => reply.error(errno(&err)),
+		}
+	}
+}
+
+/// Mount `remote_path` on `target`, a peer id or nickname, at `mountpoint` until Ctrl-C.
+pub async fn run(target: &str, remote_path: &str, mountpoint: &Path, wait: Duration) -> Result<()> {
+	if !mountpoint.is_dir() {
+		bail!("mountpoint {} is not a directory", mountpoint.display());
+	}
+	let (peer, id) = pipe::connect(target, wait).await?;
+	let root = peer
+		.stat_file(id, remote_path)
+		.await
+		.with_context(|| format!("cannot open {remote_path} on {target}"))?;
+	if !root.is_navigable() {
+		bail!("{remote_path} on {target} is not a directory");
+	}
+	let fs = RemoteFs::new(peer, id, remote_path.to_string());
+	let options = [
+		MountOption::FSName(format!("puppypeer:{target}:{remote_path}")),
+		MountOption::Subtype(String::from("puppypeer")),
+		MountOption::NoExec,
+	];
+	// unmounted when the session is dropped
+	let _session = fuser::spawn_mount2(fs, mountpoint, &options)
+		.with_context(|| format!("cannot mount at {}", mountpoint.display()))?;
+	eprintln!(
+		"mounted {remote_path} from {target} at {}, Ctrl-C to unmount",
+		mountpoint.display()
+	);
+	tokio::signal::ctrl_c().await?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn inodes_are_stable_per_path() {
+		let mut inodes = Inodes::new(String::from("/share"));
+		let a = inodes.intern(String::from("/share/a"));
+		let b = inodes.intern(String::from("/share/b"));
+		assert_ne!(a, ROOT_INODE);
+		assert_ne!(a, b);
+		assert_eq!(inodes.intern(String::from("/share/a")), a);
+		assert_eq!(inodes.path(ROOT_INODE), Some("/share"));
+		assert_eq!(inodes.path(b + 1), None);
+	}
+}
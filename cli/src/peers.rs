@@ -0,0 +1,35 @@
+use anyhow::Result;
+use puppypeer_core::PuppyPeer;
+use std::time::Duration;
+
+use crate::utility::{abbreviate_peer_id, print_json};
+
+/// Start a peer, give mDNS `wait` to find the neighbours and print what is known once.
+pub async fn run(json: bool, wait: Duration) -> Result<()> {
+	let peer = PuppyPeer::new();
+	peer.await_ready().await;
+	tokio::time::sleep(wait).await;
+	let peers = peer.export_peers().await?;
+	if json {
+		return print_json(&peers);
+	}
+	println!(
+		"{:<22} {:<16} {:<16} {:<10} {:<20} ADDRESSES",
+		"PEER", "NICKNAME", "HOSTNAME", "STATUS", "LAST SEEN"
+	);
+	for summary in peers {
+		println!(
+			"{:<22} {:<16} {:<16} {:<10} {:<20} {}",
+			abbreviate_peer_id(&summary.peer_id),
+			summary.nickname.as_deref().unwrap_or("-"),
+			summary.hostname.as_deref().unwrap_or("-"),
+			summary.status,
+			summary
+				.last_seen
+				.map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+				.unwrap_or_else(|| String::from("-")),
+			summary.addresses.join(", ")
+		);
+	}
+	Ok(())
+}
@@ -0,0 +1,90 @@
+use anyhow::{Result, bail};
+use futures::StreamExt;
+use libp2p::PeerId;
+use puppypeer_core::PuppyPeer;
+use puppypeer_core::p2p::WriteMode;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Bytes moved per request by `cat` and `put`.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Start a peer and wait until `target`, a peer id or nickname, is connected.
+pub(crate) async fn connect(target: &str, wait: Duration) -> Result<(PuppyPeer, PeerId)> {
+	let peer = PuppyPeer::new();
+	peer.await_ready().await;
+	let deadline = Instant::now() + wait;
+	loop {
+		let found = {
+			let state = peer.state();
+			let state = state.lock().unwrap();
+			let id = PeerId::from_str(target).ok().or_else(|| {
+				state
+					.peers
+					.iter()
+					.find(|p| p.name.as_deref() == Some(target))
+					.map(|p| p.id)
+			});
+			id.filter(|id| *id == state.me || state.connections.iter().any(|c| c.peer_id == *id))
+		};
+		if let Some(id) = found {
+			return Ok((peer, id));
+		}
+		if Instant::now() >= deadline {
+			bail!("peer {} not connected after {}s", target, wait.as_secs());
+		}
+		tokio::time::sleep(CONNECT_POLL_INTERVAL).await;
+	}
+}
+
+/// Stream a remote file to stdout, flushing after every chunk.
+pub async fn cat(target: &str, path: &str, wait: Duration) -> Result<()> {
+	let (peer, id) = connect(target, wait).await?;
+	let mut stdout = tokio::io::stdout();
+	let mut chunks = std::pin::pin!(peer.read_file_stream(id, path, CHUNK_SIZE));
+	while let Some(chunk) = chunks.next().await {
+		let chunk = chunk?;
+		let written = async {
+			stdout.write_all(&chunk.data).await?;
+			stdout.flush().await
+		};
+		match written.await {
+			Ok(()) => {}
+			// the reader went away, e.g. `| head`, which is not an error for a pipe
+			Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => return Ok(()),
+			Err(err) => return Err(err.into()),
+		}
+	}
+	Ok(())
+}
+
+/// Write stdin to a remote file, replacing it atomically once stdin is closed.
+pub async fn put(target: &str, path: &str, wait: Duration) -> Result<()> {
+	let (peer, id) = connect(target, wait).await?;
+	let mut stdin = tokio::io::stdin();
+	let mut offset = 0;
+	loop {
+		// fill a whole chunk, since pipes hand out data in smaller pieces
+		let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+		let eof = (&mut stdin)
+			.take(CHUNK_SIZE)
+			.read_to_end(&mut chunk)
+			.await? < CHUNK_SIZE as usize;
+		let len = chunk.len() as u64;
+		peer.write_file(
+			id,
+			path,
+			offset,
+			chunk,
+			WriteMode::Atomic { last: eof },
+			None,
+		)
+		.await?;
+		offset += len;
+		if eof {
+			return Ok(());
+		}
+	}
+}
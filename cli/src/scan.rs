@@ -0,0 +1,137 @@
+use anyhow::Result;
+use puppypeer_core::PuppyPeer;
+use puppypeer_core::scan::{ScanError, ScanOptions, ScanProgress};
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::utility::print_json;
+
+const BAR_WIDTH: usize = 30;
+/// Longest tail of the current path shown after the bar.
+const PATH_WIDTH: usize = 40;
+
+/// Output of `puppypeer scan --json`.
+#[derive(Debug, Default, Serialize)]
+struct ScanOutput {
+	inserted: u64,
+	updated: u64,
+	removed: u64,
+	/// Stopped by Ctrl+C; the files processed before that were kept.
+	cancelled: bool,
+	/// Files that vanished or could not be read, and were skipped.
+	errors: Vec<String>,
+	duration_secs: f64,
+}
+
+/// One line of progress, e.g. `[#######-------]  120/400  …/photos/img_0120.jpg`.
+fn progress_line(progress: &ScanProgress) -> String {
+	let filled = (progress.files_scanned * BAR_WIDTH as u64)
+		.checked_div(progress.total_files)
+		.map_or(BAR_WIDTH, |filled| filled as usize);
+	let path = progress.current_path.display().to_string();
+	let chars: Vec<char> = path.chars().collect();
+	let path = if chars.len() > PATH_WIDTH {
+		let tail: String = chars[chars.len() - (PATH_WIDTH - 1)..].iter().collect();
+		format!("…{}", tail)
+	} else {
+		path
+	};
+	format!(
+		"[{}{}] {:>6}/{:<6} {}",
+		"#".repeat(filled),
+		"-".repeat(BAR_WIDTH - filled),
+		progress.files_scanned,
+		progress.total_files,
+		path
+	)
+}
+
+/// Scan a local folder into the database, drawing a progress bar on stderr.
+///
+/// Ctrl+C stops the scan; the files processed so far are kept.
+pub async fn run(path: String, options: ScanOptions, json: bool) -> Result<()> {
+	let peer = PuppyPeer::new();
+	let cancel = Arc::new(AtomicBool::new(false));
+	let interrupt = tokio::spawn({
+		let cancel = cancel.clone();
+		async move {
+			if tokio::signal::ctrl_c().await.is_ok() {
+				cancel.store(true, Ordering::Relaxed);
+			}
+		}
+	});
+	let result = tokio::task::spawn_blocking(move || {
+		peer.scan_folder(
+			&path,
+			&options,
+			|progress| {
+				let mut stderr = std::io::stderr();
+				let _ = write!(stderr, "\r\x1b[2K{}", progress_line(&progress));
+				let _ = stderr.flush();
+			},
+			&cancel,
+		)
+	})
+	.await?;
+	interrupt.abort();
+	eprintln!();
+	let result = match result {
+		Err(err) if matches!(err.downcast_ref(), Some(ScanError::Canceled)) => {
+			if json {
+				return print_json(&ScanOutput {
+					cancelled: true,
+					..ScanOutput::default()
+				});
+			}
+			println!("scan cancelled before any file was processed");
+			return Ok(());
+		}
+		result => result?,
+	};
+	if json {
+		return print_json(&ScanOutput {
+			inserted: result.inserted_count,
+			updated: result.updated_count,
+			removed: result.removed_count,
+			cancelled: result.cancelled,
+			errors: result.errors.iter().map(ToString::to_string).collect(),
+			duration_secs: result.duration.as_secs_f64(),
+		});
+	}
+	if result.cancelled {
+		println!("scan cancelled, keeping the files processed so far");
+	}
+	for err in &result.errors {
+		eprintln!("skipped: {err}");
+	}
+	println!(
+		"{} inserted, {} updated, {} removed, {} skipped in {:.1}s",
+		result.inserted_count,
+		result.updated_count,
+		result.removed_count,
+		result.errors.len(),
+		result.duration.as_secs_f64()
+	);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::PathBuf;
+
+	#[test]
+	fn progress_line_fills_bar_and_shortens_path() {
+		let progress = ScanProgress {
+			files_scanned: 5,
+			total_files: 10,
+			current_path: PathBuf::from(format!("/data/{}/file.bin", "x".repeat(60))),
+		};
+		let line = progress_line(&progress);
+		assert!(line.starts_with(&format!("[{}{}]", "#".repeat(15), "-".repeat(15))));
+		assert!(line.ends_with("/file.bin"));
+		assert!(line.contains('…'));
+	}
+}
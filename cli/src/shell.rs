@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{self, Stdout};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -12,8 +12,8 @@ use crossterm::{
 };
 use libp2p::PeerId;
 use puppypeer_core::{
-	PuppyPeer, State,
-	p2p::{CpuInfo, DirEntry},
+	DirSize, PeerMessage, PuppyPeer, RecentFile, Rule, State,
+	p2p::{CpuInfo, DirEntry, DiskInfo, ErrorCode, SUBTREE_DEPTH, SystemLoad, SystemOverview},
 };
 use ratatui::{
 	Frame, Terminal,
@@ -21,20 +21,142 @@ use ratatui::{
 	layout::{Constraint, Direction, Layout, Rect},
 	style::{Color, Modifier, Style},
 	widgets::{
-		Block, Borders, List, ListItem, ListState, Paragraph, Wrap,
+		Block, Borders, List, ListItem, ListState, Paragraph, Sparkline, Wrap,
 		canvas::{Canvas, Line, Points},
 	},
 };
+
+use crate::utility::{format_load, format_uptime, peer_label};
+
 const LOCAL_LISTEN_MULTIADDR: &str = "/ip4/0.0.0.0:8336";
+const CPU_HISTORY_WINDOW: chrono::Duration = chrono::Duration::hours(1);
+/// Type-ahead search ends after this long without a keystroke.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1500);
+/// Bytes before the end of a file shown when a tail view opens.
+const TAIL_FROM_END: u64 = 8 * 1024;
+/// Lines a tail view keeps; older ones scroll away.
+const TAIL_MAX_LINES: usize = 1000;
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long answers to a discovery query are collected before counting new peers.
+const DISCOVERY_WAIT: Duration = Duration::from_secs(2);
 
 enum Mode {
 	Menu,
 	Peers(PeersView),
 	PeerActions(PeerActionsState),
+	PeerOverview(PeerOverviewView),
 	PeerCpus(PeerCpuView),
+	PeerDisks(PeerDiskView),
 	FileBrowser(FileBrowserView),
 	CreateUser(CreateUserForm),
 	PeersGraph(GraphView),
+	RenamePeer(RenamePeerForm),
+	TailFile(TailView),
+	RecentFiles(RecentFilesView),
+	Inbox(InboxView),
+	SendMessage(SendMessageForm),
+}
+
+/// Notes received from peers, newest first; `r` replies to the selected one.
+#[derive(Default)]
+struct InboxView {
+	selected: usize,
+}
+
+impl InboxView {
+	fn next(&mut self, count: usize) {
+		if self.selected + 1 < count {
+			self.selected += 1;
+		}
+	}
+
+	fn previous(&mut self) {
+		self.selected = self.selected.saturating_sub(1);
+	}
+
+	/// The selected note, counting from the newest.
+	fn selected_message<'a>(&self, messages: &'a VecDeque<PeerMessage>) -> Option<&'a PeerMessage> {
+		messages.iter().rev().nth(self.selected)
+	}
+}
+
+struct SendMessageForm {
+	peer_id: String,
+	text: String,
+	// Opened with `r` from the inbox, so Esc and Enter go back there
+	from_inbox: bool,
+}
+
+/// Files recently followed on one peer, newest first; Enter follows one again.
+struct RecentFilesView {
+	peer_id: String,
+	files: Vec<RecentFile>,
+	selected: usize,
+}
+
+impl RecentFilesView {
+	fn next(&mut self) {
+		if self.selected + 1 < self.files.len() {
+			self.selected += 1;
+		}
+	}
+
+	fn previous(&mut self) {
+		self.selected = self.selected.saturating_sub(1);
+	}
+}
+
+/// Live view of lines appended to a remote file, like `tail -f`.
+struct TailView {
+	// The browser the view was opened from, restored on Esc
+	browser: FileBrowserView,
+	path: String,
+	// End of the bytes read so far; `None` before the first read
+	offset: Option<u64>,
+	lines: VecDeque<String>,
+	// Text after the last newline, completed by a later read
+	partial: String,
+	last_poll: Option<Instant>,
+}
+
+impl TailView {
+	fn new(browser: FileBrowserView, path: String) -> Self {
+		Self {
+			browser,
+			path,
+			offset: None,
+			lines: VecDeque::new(),
+			partial: String::new(),
+			last_poll: None,
+		}
+	}
+
+	fn poll_due(&self) -> bool {
+		self.last_poll
+			.is_none_or(|last| last.elapsed() >= TAIL_POLL_INTERVAL)
+	}
+
+	/// Add a chunk read at `offset`, starting over when the file was truncated.
+	fn push(&mut self, offset: u64, data: &[u8]) {
+		if self.offset.is_some_and(|end| offset < end) {
+			self.partial.clear();
+			self.push_line(String::from("--- file truncated ---"));
+		}
+		self.offset = Some(offset + data.len() as u64);
+		self.partial.push_str(&String::from_utf8_lossy(data));
+		while let Some(idx) = self.partial.find('\n') {
+			let line = self.partial[..idx].trim_end_matches('\r').to_string();
+			self.partial.drain(..=idx);
+			self.push_line(line);
+		}
+	}
+
+	fn push_line(&mut self, line: String) {
+		self.lines.push_back(line);
+		while self.lines.len() > TAIL_MAX_LINES {
+			self.lines.pop_front();
+		}
+	}
 }
 
 struct GraphView {
@@ -46,6 +168,7 @@ struct PeerNode {
 	id: String,
 	// Precomputed polar angle for layout (radians)
 	angle: f64,
+	local: bool,
 }
 
 impl GraphView {
@@ -69,14 +192,15 @@ impl GraphView {
 			}
 		}
 	}
-	fn set_peers(&mut self, peer_ids: &[String]) {
-		let count = peer_ids.len().max(1);
-		self.peers = peer_ids
+	fn set_peers(&mut self, peers: &[PeerRow]) {
+		let count = peers.len().max(1);
+		self.peers = peers
 			.iter()
 			.enumerate()
-			.map(|(i, id)| PeerNode {
-				id: id.clone(),
+			.map(|(i, peer)| PeerNode {
+				id: peer.id.clone(),
 				angle: (i as f64) * (std::f64::consts::TAU / count as f64),
+				local: peer.is_local(),
 			})
 			.collect();
 		if self.selected >= self.peers.len() {
@@ -85,9 +209,65 @@ impl GraphView {
 	}
 }
 
+/// Incremental search over the names of a list, started with `/`.
+#[derive(Default)]
+struct TypeAhead {
+	query: String,
+	// Time of the last keystroke; `None` when no search is running
+	last_key: Option<Instant>,
+}
+
+impl TypeAhead {
+	fn start(&mut self) {
+		self.query.clear();
+		self.last_key = Some(Instant::now());
+	}
+
+	fn is_active(&self) -> bool {
+		self.last_key
+			.is_some_and(|last| last.elapsed() < TYPE_AHEAD_TIMEOUT)
+	}
+
+	fn push(&mut self, c: char) {
+		self.query.push(c);
+		self.last_key = Some(Instant::now());
+	}
+
+	fn pop(&mut self) {
+		self.query.pop();
+		self.last_key = Some(Instant::now());
+	}
+
+	fn clear(&mut self) {
+		self.query.clear();
+		self.last_key = None;
+	}
+
+	/// Index of the first name starting with the query, else the first containing it,
+	/// ignoring case.
+	fn find<'a>(&self, mut names: impl Iterator<Item = &'a str> + Clone) -> Option<usize> {
+		let query = self.query.to_lowercase();
+		names
+			.clone()
+			.position(|name| name.to_lowercase().starts_with(&query))
+			.or_else(|| names.position(|name| name.to_lowercase().contains(&query)))
+	}
+
+	fn status(&self, found: bool) -> String {
+		if found {
+			format!("Find: {}", self.query)
+		} else {
+			format!("Find: {} (no match)", self.query)
+		}
+	}
+}
+
 struct PeersView {
 	peers: Vec<PeerRow>,
 	selected: usize,
+	find: TypeAhead,
+	scroll: usize,
+	viewport: usize,
 }
 
 impl PeersView {
@@ -95,27 +275,84 @@ impl PeersView {
 		Self {
 			peers: Vec::new(),
 			selected: 0,
+			find: TypeAhead::default(),
+			scroll: 0,
+			viewport: 1,
+		}
+	}
+
+	/// Select the first peer matching the type-ahead query; returns whether one matched.
+	fn jump_to_match(&mut self) -> bool {
+		let labels: Vec<String> = self.peers.iter().map(PeerRow::label).collect();
+		match self.find.find(labels.iter().map(String::as_str)) {
+			Some(idx) => {
+				self.selected = idx;
+				self.clamp_scroll();
+				true
+			}
+			None => false,
 		}
 	}
+
 	fn next(&mut self) {
-		if !self.peers.is_empty() {
-			self.selected = (self.selected + 1) % self.peers.len();
+		if self.peers.is_empty() {
+			return;
 		}
+		self.selected = if self.selected + 1 < self.peers.len() {
+			self.selected + 1
+		} else {
+			self.scroll = 0;
+			0
+		};
+		self.clamp_scroll();
 	}
+
 	fn previous(&mut self) {
-		if !self.peers.is_empty() {
-			if self.selected == 0 {
-				self.selected = self.peers.len() - 1;
-			} else {
-				self.selected -= 1;
-			}
+		if self.peers.is_empty() {
+			return;
 		}
+		self.selected = if self.selected == 0 {
+			let last = self.peers.len().saturating_sub(1);
+			self.scroll = self.peers.len().saturating_sub(self.viewport);
+			last
+		} else {
+			self.selected - 1
+		};
+		self.clamp_scroll();
 	}
+
 	fn set_peers(&mut self, peers: Vec<PeerRow>) {
 		self.peers = peers;
 		if self.selected >= self.peers.len() {
 			self.selected = 0;
 		}
+		self.clamp_scroll();
+	}
+
+	fn set_viewport(&mut self, viewport: usize) {
+		self.viewport = viewport.max(1);
+		self.clamp_scroll();
+	}
+
+	fn clamp_scroll(&mut self) {
+		if self.peers.is_empty() {
+			self.selected = 0;
+			self.scroll = 0;
+			return;
+		}
+		if self.selected >= self.peers.len() {
+			self.selected = self.peers.len().saturating_sub(1);
+		}
+		let window = self.viewport.min(self.peers.len());
+		let max_scroll = self.peers.len().saturating_sub(window);
+		if self.selected < self.scroll {
+			self.scroll = self.selected;
+		} else if self.selected >= self.scroll + window {
+			self.scroll = self.selected + 1 - window;
+		}
+		if self.scroll > max_scroll {
+			self.scroll = max_scroll;
+		}
 	}
 }
 
@@ -128,15 +365,28 @@ impl Default for PeersView {
 #[derive(Clone)]
 struct PeerRow {
 	id: String,
+	name: Option<String>,
 	address: String,
 	status: String,
 }
 
+impl PeerRow {
+	fn label(&self) -> String {
+		peer_label(self.name.as_deref(), &self.id)
+	}
+
+	fn is_local(&self) -> bool {
+		self.status == "local"
+	}
+}
+
 // Removed placeholder sample peers; UI now populated from live State.
 
 struct PeerActionsState {
 	view: PeersView,
 	menu: PeerActionsMenu,
+	/// Uptime and load of the peer for the info panel, fetched on each refresh.
+	load: Option<SystemLoad>,
 }
 
 impl PeerActionsState {
@@ -144,6 +394,7 @@ impl PeerActionsState {
 		Self {
 			menu: PeerActionsMenu::new(peer),
 			view,
+			load: None,
 		}
 	}
 
@@ -168,7 +419,17 @@ impl PeerActionsMenu {
 	fn new(peer: PeerRow) -> Self {
 		Self {
 			peer,
-			items: vec!["cpu info", "file browser", "back"],
+			items: vec![
+				"overview",
+				"cpu info",
+				"disk info",
+				"file browser",
+				"recent files",
+				"send message",
+				"rename",
+				"disconnect",
+				"back",
+			],
 			selected: 0,
 		}
 	}
@@ -206,8 +467,20 @@ impl PeerActionsMenu {
 
 struct FileBrowserView {
 	peer_id: String,
+	// Empty while listing the shared folders in `roots` rather than a directory
 	path: String,
+	roots: Vec<String>,
+	// Single files shared on their own, listed after `roots`
+	files: Vec<String>,
+	// Everything the peer returned; `entries` is the filtered list on screen
+	all_entries: Vec<DirEntry>,
 	entries: Vec<DirEntry>,
+	show_hidden: bool,
+	find: TypeAhead,
+	// Size computed on demand for the named child directory
+	dir_size: Option<(String, DirSize)>,
+	// Path the peer refused to list because it is a file, which `t` follows instead
+	listed_file: Option<String>,
 	selected: usize,
 	scroll: usize,
 	viewport: usize,
@@ -215,14 +488,23 @@ struct FileBrowserView {
 
 impl FileBrowserView {
 	fn new(peer_id: String, path: String, entries: Vec<DirEntry>) -> Self {
-		Self {
+		let mut view = Self {
 			peer_id,
 			path,
-			entries,
+			roots: Vec::new(),
+			files: Vec::new(),
+			all_entries: Vec::new(),
+			entries: Vec::new(),
+			show_hidden: false,
+			find: TypeAhead::default(),
+			dir_size: None,
+			listed_file: None,
 			selected: 0,
 			scroll: 0,
 			viewport: 1,
-		}
+		};
+		view.replace_entries(view.path.clone(), entries);
+		view
 	}
 
 	fn next(&mut self) {
@@ -288,11 +570,62 @@ impl FileBrowserView {
 
 	fn replace_entries(&mut self, path: String, entries: Vec<DirEntry>) {
 		self.path = path;
-		self.entries = entries;
+		self.all_entries = entries;
+		self.dir_size = None;
+		self.listed_file = None;
+		self.apply_filter();
 		self.selected = 0;
 		self.scroll = 0;
 		self.clamp_scroll();
 	}
+
+	fn at_shares(&self) -> bool {
+		self.path.is_empty()
+	}
+
+	fn show_shares(&mut self) {
+		let entries = self
+			.roots
+			.iter()
+			.map(|root| share_entry(root, true))
+			.chain(self.files.iter().map(|file| share_entry(file, false)))
+			.collect();
+		self.replace_entries(String::new(), entries);
+	}
+
+	/// Select the first entry matching the type-ahead query; returns whether one matched.
+	fn jump_to_match(&mut self) -> bool {
+		match self
+			.find
+			.find(self.entries.iter().map(|entry| entry.name.as_str()))
+		{
+			Some(idx) => {
+				self.selected = idx;
+				self.clamp_scroll();
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn toggle_hidden(&mut self) {
+		let selected_name = self.selected_entry().map(|entry| entry.name.clone());
+		self.show_hidden = !self.show_hidden;
+		self.apply_filter();
+		self.selected = selected_name
+			.and_then(|name| self.entries.iter().position(|entry| entry.name == name))
+			.unwrap_or(0);
+		self.clamp_scroll();
+	}
+
+	fn apply_filter(&mut self) {
+		self.entries = self
+			.all_entries
+			.iter()
+			.filter(|entry| self.show_hidden || !entry.is_hidden())
+			.cloned()
+			.collect();
+	}
 }
 
 struct PeerCpuView {
@@ -302,6 +635,10 @@ struct PeerCpuView {
 	scroll: usize,
 	viewport: usize,
 	last_refresh: Instant,
+	// Usage samples (percent) of the selected CPU, oldest first
+	history: Vec<u64>,
+	// Hottest sensor reading (°C) of each sampling round, oldest first
+	temperatures: Vec<u64>,
 }
 
 impl PeerCpuView {
@@ -313,6 +650,8 @@ impl PeerCpuView {
 			scroll: 0,
 			viewport: 1,
 			last_refresh: Instant::now(),
+			history: Vec::new(),
+			temperatures: Vec::new(),
 		};
 		view.replace_cpus(cpus);
 		view
@@ -394,6 +733,126 @@ impl PeerCpuView {
 	}
 }
 
+/// Mounted disks of one peer, refreshed on the same interval as the CPU view.
+struct PeerDiskView {
+	peer_id: String,
+	disks: Vec<DiskInfo>,
+	selected: usize,
+	scroll: usize,
+	viewport: usize,
+	last_refresh: Instant,
+}
+
+impl PeerDiskView {
+	fn new(peer_id: String, disks: Vec<DiskInfo>) -> Self {
+		let mut view = Self {
+			peer_id,
+			disks: Vec::new(),
+			selected: 0,
+			scroll: 0,
+			viewport: 1,
+			last_refresh: Instant::now(),
+		};
+		view.replace_disks(disks);
+		view
+	}
+
+	fn next(&mut self) {
+		if self.selected + 1 < self.disks.len() {
+			self.selected += 1;
+		}
+		self.clamp_scroll();
+	}
+
+	fn previous(&mut self) {
+		self.selected = self.selected.saturating_sub(1);
+		self.clamp_scroll();
+	}
+
+	fn set_viewport(&mut self, viewport: usize) {
+		self.viewport = viewport.max(1);
+		self.clamp_scroll();
+	}
+
+	fn clamp_scroll(&mut self) {
+		self.selected = self.selected.min(self.disks.len().saturating_sub(1));
+		if self.selected < self.scroll {
+			self.scroll = self.selected;
+		} else if self.selected >= self.scroll + self.viewport {
+			self.scroll = self.selected + 1 - self.viewport;
+		}
+		self.scroll = self
+			.scroll
+			.min(self.disks.len().saturating_sub(self.viewport));
+	}
+
+	fn replace_disks(&mut self, disks: Vec<DiskInfo>) {
+		self.disks = disks;
+		self.clamp_scroll();
+		self.mark_refreshed();
+	}
+
+	fn mark_refreshed(&mut self) {
+		self.last_refresh = Instant::now();
+	}
+}
+
+/// One `SystemOverview` of a peer, refreshed like the CPU and disk views.
+struct PeerOverviewView {
+	peer_id: String,
+	overview: SystemOverview,
+	last_refresh: Instant,
+}
+
+impl PeerOverviewView {
+	fn lines(&self) -> Vec<String> {
+		let o = &self.overview;
+		let percent = |used: u64, total: u64| {
+			if total == 0 {
+				0.0
+			} else {
+				used as f64 / total as f64 * 100.0
+			}
+		};
+		vec![
+			format!(
+				"CPU:     {:.1}% average over {} CPUs",
+				o.cpu_avg, o.cpu_count
+			),
+			format!("Load:    {} (1, 5, 15 min)", format_load(o.load.as_ref())),
+			format!(
+				"Memory:  {} of {} ({:.0}%)",
+				format_size(o.mem.used),
+				format_size(o.mem.total),
+				percent(o.mem.used, o.mem.total)
+			),
+			format!(
+				"Swap:    {} of {}",
+				format_size(o.mem.swap_used),
+				format_size(o.mem.swap_total)
+			),
+			format!(
+				"Disks:   {} free of {} on {} disks",
+				format_size(o.disks_summary.available_space),
+				format_size(o.disks_summary.total_space),
+				o.disks_summary.count
+			),
+			format!(
+				"Network: {} in, {} out on {} interfaces",
+				format_size(o.net_summary.total_received),
+				format_size(o.net_summary.total_transmitted),
+				o.net_summary.interfaces
+			),
+			format!("Uptime:  {}", format_uptime(o.uptime)),
+		]
+	}
+}
+
+struct RenamePeerForm {
+	peer_id: String,
+	name: String,
+}
+
 struct CreateUserForm {
 	username: String,
 	password: String,
@@ -429,17 +888,24 @@ struct ShellApp {
 	refresh_interval: Duration,
 	refresh_count: u64,
 	latest_state: Option<State>,
+	// Whether the peers list and graph include this node; toggled with `l`
+	show_local_peer: bool,
+	// mDNS query in flight: when it was sent and the peers discovered before it
+	discovery: Option<(Instant, HashSet<PeerId>)>,
 }
 
 impl ShellApp {
 	fn new() -> Self {
 		let mut state = ListState::default();
 		state.select(Some(0));
+		let peer = PuppyPeer::new();
+		let show_local_peer = peer.config().show_local_peer;
 		Self {
 			should_quit: false,
 			menu_items: vec![
 				"peers",
 				"peers graph",
+				"inbox",
 				"create token",
 				"create user",
 				"quit",
@@ -447,11 +913,13 @@ impl ShellApp {
 			menu_state: state,
 			status_line: "Use ↑/↓ to navigate, Enter to select, q to quit".to_string(),
 			mode: Mode::Menu,
-			peer: PuppyPeer::new(),
+			peer,
 			last_refresh: Instant::now(),
 			refresh_interval: Duration::from_secs(5),
 			refresh_count: 0,
 			latest_state: None,
+			show_local_peer,
+			discovery: None,
 		}
 	}
 
@@ -491,7 +959,8 @@ impl ShellApp {
 					"peers" => {
 						self.mode = Mode::Peers(PeersView::new());
 						self.status_line =
-							"Peers view. Auto-refresh every 5s. ↑/↓ navigate, Esc back".into();
+							"Peers view. Auto-refresh every 5s. ↑/↓ navigate, / find, Esc back"
+								.into();
 					}
 					"create token" => {
 						self.status_line = "Token created (placeholder)".into();
@@ -505,6 +974,19 @@ impl ShellApp {
 						self.status_line =
 							"Graph view. Auto-refresh every 5s. ←/→ select, Esc back".into();
 					}
+					"inbox" => {
+						self.latest_state = self.peer.state().lock().ok().map(|s| s.clone());
+						let count = self
+							.latest_state
+							.as_ref()
+							.map_or(0, |state| state.messages.len());
+						self.mode = Mode::Inbox(InboxView::default());
+						self.status_line = if count == 0 {
+							String::from("No messages yet. Send one from a peer's actions")
+						} else {
+							format!("{} messages. ↑/↓ navigate, r reply, Esc back", count)
+						};
+					}
 					_ => {}
 				}
 			}
@@ -515,6 +997,12 @@ impl ShellApp {
 		if let Event::Key(key) = event {
 			let mut next_mode: Option<Mode> = None;
 			let mut pending_peer_actions: Option<String> = None;
+			let mut pending_tail: Option<String> = None;
+			let mut pending_reopen: Option<(String, String)> = None;
+			// Status to show once back on the peer actions, which set their own
+			let mut pending_status: Option<String> = None;
+			let mut leave_tail = false;
+			let mut discover = false;
 			match &mut self.mode {
 				Mode::Menu => match key.code {
 					KeyCode::Char('q') => self.should_quit = true,
@@ -524,6 +1012,24 @@ impl ShellApp {
 					_ => {}
 				},
 				Mode::Peers(view) => match key.code {
+					KeyCode::Esc if view.find.is_active() => {
+						view.find.clear();
+						self.status_line = "Search cleared".into();
+					}
+					KeyCode::Char(c) if view.find.is_active() => {
+						view.find.push(c);
+						let found = view.jump_to_match();
+						self.status_line = view.find.status(found);
+					}
+					KeyCode::Backspace if view.find.is_active() => {
+						view.find.pop();
+						let found = view.jump_to_match();
+						self.status_line = view.find.status(found);
+					}
+					KeyCode::Char('/') => {
+						view.find.start();
+						self.status_line = view.find.status(true);
+					}
 					KeyCode::Esc => {
 						self.mode = Mode::Menu;
 						self.status_line = "Back to menu".into();
@@ -537,10 +1043,23 @@ impl ShellApp {
 								"Peer actions for {}. ↑/↓ navigate, Enter select, Esc back",
 								peer.id
 							);
-							next_mode =
-								Some(Mode::PeerActions(PeerActionsState::new(snapshot, peer)));
+							let mut actions = PeerActionsState::new(snapshot, peer);
+							actions.load = Self::peer_load(
+								&self.peer,
+								&self.latest_state,
+								&actions.menu.peer.id,
+							);
+							next_mode = Some(Mode::PeerActions(actions));
+						}
+					}
+					KeyCode::Char('l') => {
+						self.show_local_peer = !self.show_local_peer;
+						if let Some(state) = &self.latest_state {
+							view.set_peers(Self::aggregate_peers(state, self.show_local_peer));
 						}
+						self.status_line = Self::local_peer_status(self.show_local_peer);
 					}
+					KeyCode::Char('d') => discover = true,
 					KeyCode::Char('r') => {}
 					KeyCode::Char('q') => {
 						self.should_quit = true;
@@ -556,6 +1075,19 @@ impl ShellApp {
 					KeyCode::Down => state.menu.next(),
 					KeyCode::Up => state.menu.previous(),
 					KeyCode::Enter => match state.menu.selected_item() {
+						Some("overview") => {
+							let peer_id = state.menu.peer.id.clone();
+							match self.create_overview_view(peer_id.clone()) {
+								Ok(view) => {
+									self.status_line = format!("Overview of {}", peer_id);
+									next_mode = Some(Mode::PeerOverview(view));
+								}
+								Err(err) => {
+									self.status_line =
+										format!("Failed to fetch the overview: {}", err);
+								}
+							}
+						}
 						Some("cpu info") => {
 							let peer_id = state.menu.peer.id.clone();
 							match self.create_cpu_view(peer_id.clone()) {
@@ -568,11 +1100,27 @@ impl ShellApp {
 								}
 							}
 						}
+						Some("disk info") => {
+							let peer_id = state.menu.peer.id.clone();
+							match self.create_disk_view(peer_id) {
+								Ok(view) => {
+									self.status_line = Self::disk_summary(&view);
+									next_mode = Some(Mode::PeerDisks(view));
+								}
+								Err(err) => {
+									self.status_line = format!("Failed to fetch disks: {}", err);
+								}
+							}
+						}
 						Some("file browser") => {
 							let peer_id = state.menu.peer.id.clone();
-							match self.create_file_browser_view(peer_id.clone(), "/") {
+							match self.create_file_browser_view(peer_id.clone()) {
 								Ok(view) => {
-									self.status_line = format!("Browsing / on {}", peer_id);
+									self.status_line = if view.at_shares() {
+										format!("Shared folders on {}", peer_id)
+									} else {
+										format!("Browsing / on {}", peer_id)
+									};
 									next_mode = Some(Mode::FileBrowser(view));
 								}
 								Err(err) => {
@@ -581,6 +1129,63 @@ impl ShellApp {
 								}
 							}
 						}
+						Some("recent files") => {
+							let peer_id = state.menu.peer.id.clone();
+							let files = PeerId::from_str(&peer_id)
+								.map_err(anyhow::Error::from)
+								.and_then(|target| self.peer.recent_files(Some(target)));
+							match files {
+								Ok(files) => {
+									self.status_line = if files.is_empty() {
+										format!("No files followed on {} yet", peer_id)
+									} else {
+										String::from("Enter to follow a file again, Esc to go back")
+									};
+									next_mode = Some(Mode::RecentFiles(RecentFilesView {
+										peer_id,
+										files,
+										selected: 0,
+									}));
+								}
+								Err(err) => {
+									self.status_line =
+										format!("Failed to load recent files: {}", err);
+								}
+							}
+						}
+						Some("send message") => {
+							let peer = &state.menu.peer;
+							self.status_line = format!(
+								"Message for {}. Enter to send, Esc to cancel",
+								peer.label()
+							);
+							next_mode = Some(Mode::SendMessage(SendMessageForm {
+								peer_id: peer.id.clone(),
+								text: String::new(),
+								from_inbox: false,
+							}));
+						}
+						Some("rename") => {
+							let peer = &state.menu.peer;
+							self.status_line = format!(
+								"Nickname for {}. Enter to save (empty clears), Esc to cancel",
+								peer.id
+							);
+							next_mode = Some(Mode::RenamePeer(RenamePeerForm {
+								peer_id: peer.id.clone(),
+								name: peer.name.clone().unwrap_or_default(),
+							}));
+						}
+						Some("disconnect") => {
+							let peer_id = state.menu.peer.id.clone();
+							let result = PeerId::from_str(&peer_id)
+								.map_err(anyhow::Error::from)
+								.and_then(|target| self.peer.disconnect_blocking(target));
+							self.status_line = match result {
+								Ok(()) => format!("Disconnected from {}", peer_id),
+								Err(err) => format!("Failed to disconnect {}: {}", peer_id, err),
+							};
+						}
 						Some("back") => {
 							let peer_id = state.menu.peer.id.clone();
 							self.status_line = format!("Returning from actions for {}", peer_id);
@@ -594,16 +1199,27 @@ impl ShellApp {
 					KeyCode::Char('r') => {}
 					_ => {}
 				},
-				Mode::PeerCpus(view) => match key.code {
+				Mode::PeerOverview(view) => match key.code {
 					KeyCode::Esc => {
 						pending_peer_actions = Some(view.peer_id.clone());
 					}
-					KeyCode::Down => {
-						view.next();
+					KeyCode::Char('q') => {
+						self.should_quit = true;
+					}
+					_ => {}
+				},
+				Mode::PeerCpus(view) => match key.code {
+					KeyCode::Esc => {
+						pending_peer_actions = Some(view.peer_id.clone());
+					}
+					KeyCode::Down => {
+						view.next();
+						Self::load_cpu_history(&self.peer, view);
 						self.status_line = Self::cpu_summary(view);
 					}
 					KeyCode::Up => {
 						view.previous();
+						Self::load_cpu_history(&self.peer, view);
 						self.status_line = Self::cpu_summary(view);
 					}
 					KeyCode::Char('q') => {
@@ -611,7 +1227,36 @@ impl ShellApp {
 					}
 					_ => {}
 				},
+				Mode::PeerDisks(view) => match key.code {
+					KeyCode::Esc => {
+						pending_peer_actions = Some(view.peer_id.clone());
+					}
+					KeyCode::Down => view.next(),
+					KeyCode::Up => view.previous(),
+					KeyCode::Char('q') => {
+						self.should_quit = true;
+					}
+					_ => {}
+				},
 				Mode::FileBrowser(view) => match key.code {
+					KeyCode::Esc if view.find.is_active() => {
+						view.find.clear();
+						self.status_line = "Search cleared".into();
+					}
+					KeyCode::Char(c) if view.find.is_active() => {
+						view.find.push(c);
+						let found = view.jump_to_match();
+						self.status_line = view.find.status(found);
+					}
+					KeyCode::Backspace if view.find.is_active() => {
+						view.find.pop();
+						let found = view.jump_to_match();
+						self.status_line = view.find.status(found);
+					}
+					KeyCode::Char('/') => {
+						view.find.start();
+						self.status_line = view.find.status(true);
+					}
 					KeyCode::Esc => {
 						pending_peer_actions = Some(view.peer_id.clone());
 					}
@@ -619,23 +1264,14 @@ impl ShellApp {
 					KeyCode::Up => view.previous(),
 					KeyCode::Enter => {
 						if let Some(entry) = view.selected_entry().cloned() {
-							if entry.is_dir {
-								let peer_id = view.peer_id.clone();
+							if view.at_shares() && !entry.is_dir {
+								self.status_line =
+									format!("{} is a shared file, not a folder", entry.name);
+							} else if view.at_shares() {
+								self.status_line = Self::browse_to(&self.peer, view, entry.name);
+							} else if entry.is_navigable() {
 								let target = join_child_path(&view.path, &entry.name);
-								match self
-									.peer
-									.list_dir_blocking(peer_id.parse().unwrap(), target.clone())
-								{
-									Ok(entries) => {
-										view.replace_entries(target.clone(), entries);
-										self.status_line =
-											format!("Browsing {} on {}", target, peer_id);
-									}
-									Err(err) => {
-										self.status_line =
-											format!("Failed to open {}: {}", target, err);
-									}
-								}
+								self.status_line = Self::browse_to(&self.peer, view, target);
 							} else {
 								self.status_line = format!(
 									"Selected file {} ({}). Enter directories to navigate",
@@ -645,26 +1281,78 @@ impl ShellApp {
 							}
 						}
 					}
-					KeyCode::Backspace | KeyCode::Left => {
-						let parent = parent_path(&view.path);
-						if parent != view.path {
-							let peer_id = view.peer_id.clone();
-							match self
-								.peer
-								.list_dir_blocking(peer_id.parse().unwrap(), parent.clone())
-							{
-								Ok(entries) => {
-									view.replace_entries(parent.clone(), entries);
-									self.status_line =
-										format!("Browsing {} on {}", parent, peer_id);
-								}
-								Err(err) => {
-									self.status_line =
-										format!("Failed to open {}: {}", parent, err);
+					KeyCode::Char('t') => {
+						let target = view.selected_entry().and_then(|entry| {
+							let path = if view.at_shares() {
+								entry.name.clone()
+							} else {
+								join_child_path(&view.path, &entry.name)
+							};
+							// a link can look like a folder until the peer says it is a file
+							let is_file = !entry.is_navigable()
+								|| view.listed_file.as_deref() == Some(path.as_str());
+							is_file.then_some(path)
+						});
+						match target {
+							Some(path) => pending_tail = Some(path),
+							None => self.status_line = String::from("Select a file to follow"),
+						}
+					}
+					KeyCode::Char('s') => {
+						if let Some(entry) = view.selected_entry().cloned() {
+							if entry.is_navigable() {
+								let target = join_child_path(&view.path, &entry.name);
+								match self.peer.dir_size_blocking(
+									view.peer_id.parse().unwrap(),
+									target.clone(),
+								) {
+									Ok(size) => {
+										self.status_line = format!(
+											"{} is {}{}",
+											target,
+											format_size(size.total_bytes),
+											if size.partial { " (partial)" } else { "" }
+										);
+										view.dir_size = Some((entry.name.clone(), size));
+									}
+									Err(err) => {
+										self.status_line =
+											format!("Failed to size {}: {}", target, err);
+									}
 								}
+							} else {
+								self.status_line =
+									String::from("Select a directory to compute its size");
 							}
 						}
 					}
+					KeyCode::Char('x') => {
+						self.status_line = if view.at_shares() {
+							String::from("Open a shared folder to load its subtree")
+						} else {
+							Self::load_subtree(&self.peer, view)
+						};
+					}
+					KeyCode::Char('.') => {
+						view.toggle_hidden();
+						self.status_line = if view.show_hidden {
+							String::from("Showing hidden files")
+						} else {
+							String::from("Hiding hidden files")
+						};
+					}
+					KeyCode::Backspace | KeyCode::Left => {
+						let parent = parent_path(&view.path);
+						if view.at_shares() {
+							self.status_line = String::from("Already at shared folders");
+						} else if view.roots.contains(&view.path) {
+							// up from a share's top directory goes back to the list of shares
+							view.show_shares();
+							self.status_line = format!("Shared folders on {}", view.peer_id);
+						} else if parent != view.path {
+							self.status_line = Self::browse_to(&self.peer, view, parent);
+						}
+					}
 					KeyCode::Char('q') => {
 						self.should_quit = true;
 					}
@@ -677,12 +1365,44 @@ impl ShellApp {
 					}
 					KeyCode::Left => graph.previous(),
 					KeyCode::Right => graph.next(),
+					KeyCode::Char('l') => {
+						self.show_local_peer = !self.show_local_peer;
+						if let Some(state) = &self.latest_state {
+							graph.set_peers(&Self::aggregate_peers(state, self.show_local_peer));
+						}
+						self.status_line = Self::local_peer_status(self.show_local_peer);
+					}
+					KeyCode::Char('d') => discover = true,
 					KeyCode::Char('r') => {}
 					KeyCode::Char('q') => {
 						self.should_quit = true;
 					}
 					_ => {}
 				},
+				Mode::RenamePeer(form) => match key.code {
+					KeyCode::Esc => {
+						pending_peer_actions = Some(form.peer_id.clone());
+					}
+					KeyCode::Enter => {
+						let result = form
+							.peer_id
+							.parse()
+							.map_err(anyhow::Error::from)
+							.and_then(|peer_id| self.peer.set_peer_name(peer_id, Some(&form.name)));
+						if let Err(err) = result {
+							self.status_line =
+								format!("Failed to rename {}: {}", form.peer_id, err);
+						} else {
+							self.latest_state = self.peer.state().lock().ok().map(|s| s.clone());
+							pending_peer_actions = Some(form.peer_id.clone());
+						}
+					}
+					KeyCode::Char(c) => form.name.push(c),
+					KeyCode::Backspace => {
+						form.name.pop();
+					}
+					_ => {}
+				},
 				Mode::CreateUser(form) => match key.code {
 					KeyCode::Esc => {
 						self.mode = Mode::Menu;
@@ -719,10 +1439,133 @@ impl ShellApp {
 					KeyCode::Left | KeyCode::Right => {}
 					_ => {}
 				},
+				Mode::TailFile(_) => match key.code {
+					KeyCode::Esc => leave_tail = true,
+					KeyCode::Char('q') => {
+						self.should_quit = true;
+					}
+					_ => {}
+				},
+				Mode::Inbox(view) => match key.code {
+					KeyCode::Esc => {
+						self.mode = Mode::Menu;
+						self.status_line = "Back to menu".into();
+					}
+					KeyCode::Down => {
+						let count = self
+							.latest_state
+							.as_ref()
+							.map_or(0, |state| state.messages.len());
+						view.next(count);
+					}
+					KeyCode::Up => view.previous(),
+					KeyCode::Char('r') => {
+						let from = self
+							.latest_state
+							.as_ref()
+							.and_then(|state| view.selected_message(&state.messages))
+							.map(|message| message.from);
+						match from {
+							Some(from) => {
+								self.status_line =
+									format!("Reply to {}. Enter to send, Esc to cancel", from);
+								next_mode = Some(Mode::SendMessage(SendMessageForm {
+									peer_id: from.to_string(),
+									text: String::new(),
+									from_inbox: true,
+								}));
+							}
+							None => self.status_line = String::from("Select a message to reply to"),
+						}
+					}
+					KeyCode::Char('q') => {
+						self.should_quit = true;
+					}
+					_ => {}
+				},
+				Mode::SendMessage(form) => match key.code {
+					KeyCode::Esc if form.from_inbox => {
+						self.status_line = String::from("Reply cancelled");
+						next_mode = Some(Mode::Inbox(InboxView::default()));
+					}
+					KeyCode::Esc => {
+						pending_peer_actions = Some(form.peer_id.clone());
+					}
+					KeyCode::Enter => {
+						let result =
+							form.peer_id
+								.parse()
+								.map_err(anyhow::Error::from)
+								.and_then(|peer_id| {
+									self.peer.send_message_blocking(peer_id, form.text.clone())
+								});
+						match result {
+							Err(err) => {
+								self.status_line =
+									format!("Failed to message {}: {}", form.peer_id, err);
+							}
+							Ok(()) if form.from_inbox => {
+								self.status_line = format!("Replied to {}", form.peer_id);
+								next_mode = Some(Mode::Inbox(InboxView::default()));
+							}
+							Ok(()) => {
+								pending_status = Some(format!("Sent message to {}", form.peer_id));
+								pending_peer_actions = Some(form.peer_id.clone());
+							}
+						}
+					}
+					KeyCode::Char(c) => form.text.push(c),
+					KeyCode::Backspace => {
+						form.text.pop();
+					}
+					_ => {}
+				},
+				Mode::RecentFiles(view) => match key.code {
+					KeyCode::Esc => {
+						pending_peer_actions = Some(view.peer_id.clone());
+					}
+					KeyCode::Down => view.next(),
+					KeyCode::Up => view.previous(),
+					KeyCode::Enter => {
+						if let Some(file) = view.files.get(view.selected) {
+							pending_reopen = Some((view.peer_id.clone(), file.path.clone()));
+						}
+					}
+					KeyCode::Char('q') => {
+						self.should_quit = true;
+					}
+					_ => {}
+				},
 			}
 			if let Some(mode) = next_mode {
 				self.mode = mode;
 			}
+			if let Some(path) = pending_tail
+				&& let Mode::FileBrowser(view) = std::mem::replace(&mut self.mode, Mode::Menu)
+			{
+				self.start_tail(view, path);
+			}
+			if let Some((peer_id, path)) = pending_reopen {
+				// Esc from the tail lands in the file's folder, as if it was opened from there
+				match self.create_file_browser_view(peer_id) {
+					Ok(mut view) => {
+						Self::browse_to(&self.peer, &mut view, parent_path(&path));
+						self.start_tail(view, path);
+					}
+					Err(err) => {
+						self.status_line = format!("Failed to open {}: {}", path, err);
+					}
+				}
+			}
+			if discover {
+				self.start_discovery();
+			}
+			if leave_tail
+				&& let Mode::TailFile(view) = std::mem::replace(&mut self.mode, Mode::Menu)
+			{
+				self.status_line = format!("Stopped following {}", view.path);
+				self.mode = Mode::FileBrowser(view.browser);
+			}
 			if let Some(peer_id) = pending_peer_actions {
 				if let Some((state, status)) = self.peer_actions_state_for(&peer_id) {
 					self.status_line = status;
@@ -732,12 +1575,26 @@ impl ShellApp {
 					self.mode = Mode::Menu;
 				}
 			}
+			if let Some(status) = pending_status {
+				self.status_line = status;
+			}
+		}
+	}
+
+	/// Uptime and load of `peer_id` for the info panel. Peers we are not connected to are
+	/// skipped, as dialing them would stall the screen.
+	fn peer_load(peer: &PuppyPeer, core: &Option<State>, peer_id: &str) -> Option<SystemLoad> {
+		let id = PeerId::from_str(peer_id).ok()?;
+		let core = core.as_ref()?;
+		if id != core.me && core.connection_routes(id).is_empty() {
+			return None;
 		}
+		peer.system_load_blocking(id).ok()
 	}
 
 	fn peer_actions_state_for(&self, peer_id: &str) -> Option<(PeerActionsState, String)> {
 		let state = self.latest_state.as_ref()?;
-		let aggregated = Self::aggregate_peers(state);
+		let aggregated = Self::aggregate_peers(state, self.show_local_peer);
 		let mut view = PeersView::new();
 		view.set_peers(aggregated.clone());
 		if view.peers.is_empty() {
@@ -752,17 +1609,171 @@ impl ShellApp {
 		Some((actions, format!("Peer actions for {}", selected.id)))
 	}
 
-	fn create_file_browser_view(&self, peer_id: String, path: &str) -> Result<FileBrowserView> {
-		let entries = self
+	/// Follow `path`, returning to `browser` on Esc, and remember it in the recent files.
+	fn start_tail(&mut self, browser: FileBrowserView, path: String) {
+		let recorded = browser
+			.peer_id
+			.parse()
+			.map_err(anyhow::Error::from)
+			.and_then(|peer_id| self.peer.record_recent_file(peer_id, &path));
+		self.status_line = match recorded {
+			Ok(()) => format!("Following {}. Esc to stop", path),
+			Err(err) => format!(
+				"Following {}. Esc to stop (not added to recent files: {})",
+				path, err
+			),
+		};
+		self.mode = Mode::TailFile(TailView::new(browser, path));
+	}
+
+	/// Open the browser at the peer's shared folders, or at `/` when it shares none with us.
+	fn create_file_browser_view(&self, peer_id: String) -> Result<FileBrowserView> {
+		let target = peer_id.parse()?;
+		let permissions = self
 			.peer
-			.list_dir_blocking(peer_id.parse()?, path.to_string())
-			.with_context(|| format!("listing {} locally", path))?;
-		Ok(FileBrowserView::new(peer_id, path.to_string(), entries))
+			.list_permissions_blocking(target)
+			.context("listing shared folders")?;
+		let mut files: Vec<String> = permissions
+			.iter()
+			.filter_map(|permission| match permission.rule() {
+				Rule::Folder(rule) if rule.is_file() => {
+					Some(rule.path().to_string_lossy().to_string())
+				}
+				_ => None,
+			})
+			.collect();
+		files.sort();
+		files.dedup();
+		let mut roots: Vec<String> = permissions
+			.iter()
+			.filter_map(|permission| match permission.rule() {
+				Rule::Folder(rule) if !rule.is_file() => {
+					let path = rule.path().to_string_lossy();
+					match path.trim_end_matches('/') {
+						"" => Some(String::from("/")),
+						trimmed => Some(trimmed.to_string()),
+					}
+				}
+				_ => None,
+			})
+			.collect();
+		roots.sort();
+		roots.dedup();
+		if roots.is_empty() && files.is_empty() {
+			let entries = self
+				.peer
+				.list_dir_blocking(target, String::from("/"))
+				.context("listing / locally")?;
+			return Ok(FileBrowserView::new(peer_id, String::from("/"), entries));
+		}
+		let mut view = FileBrowserView::new(peer_id, String::new(), Vec::new());
+		view.roots = roots;
+		view.files = files;
+		view.show_shares();
+		Ok(view)
+	}
+
+	/// List `target` into the view, popping back to the nearest existing parent when the
+	/// folder was deleted. Returns the status line to show.
+	fn browse_to(peer: &PuppyPeer, view: &mut FileBrowserView, target: String) -> String {
+		let Ok(peer_id) = view.peer_id.parse() else {
+			return format!("Invalid peer id {}", view.peer_id);
+		};
+		match peer.list_dir_nearest_blocking(peer_id, target.clone()) {
+			Ok((path, entries)) => {
+				view.replace_entries(path.clone(), entries);
+				if path == target {
+					format!("Browsing {} on {}", path, view.peer_id)
+				} else {
+					format!("Folder {} no longer exists, showing {}", target, path)
+				}
+			}
+			Err(err) if ErrorCode::of(&err) == Some(ErrorCode::NotADirectory) => {
+				let status = format!("{} is a file, not a folder. Press t to follow it", target);
+				view.listed_file = Some(target);
+				status
+			}
+			Err(err) => format!("Failed to open {}: {}", target, err),
+		}
+	}
+
+	/// Replace the listing with every entry below the current folder.
+	fn load_subtree(peer: &PuppyPeer, view: &mut FileBrowserView) -> String {
+		let Ok(peer_id) = view.peer_id.parse() else {
+			return format!("Invalid peer id {}", view.peer_id);
+		};
+		match peer.list_dir_recursive_blocking(peer_id, view.path.clone(), SUBTREE_DEPTH) {
+			Ok(tree) => {
+				let count = tree.entries.len();
+				view.replace_entries(view.path.clone(), tree.entries);
+				format!(
+					"Loaded {} entries below {}{}",
+					count,
+					view.path,
+					if tree.truncated {
+						" (truncated by the peer)"
+					} else {
+						""
+					}
+				)
+			}
+			Err(err) => format!("Failed to load the subtree of {}: {}", view.path, err),
+		}
 	}
 
 	fn create_cpu_view(&self, peer_id: String) -> Result<PeerCpuView> {
 		let cpus = self.peer.list_cpus_blocking(peer_id.parse()?)?;
-		Ok(PeerCpuView::new(peer_id, cpus))
+		let mut view = PeerCpuView::new(peer_id, cpus);
+		Self::load_cpu_history(&self.peer, &mut view);
+		Ok(view)
+	}
+
+	fn load_cpu_history(peer: &PuppyPeer, view: &mut PeerCpuView) {
+		let Ok(peer_id) = view.peer_id.parse() else {
+			return;
+		};
+		let since = chrono::Utc::now() - CPU_HISTORY_WINDOW;
+		match peer.temperature_history(peer_id, since) {
+			Ok(samples) => {
+				// every sensor of one round shares its timestamp, keep the hottest
+				let mut rounds: Vec<(chrono::DateTime<chrono::Utc>, f32)> = Vec::new();
+				for sample in samples {
+					match rounds.last_mut() {
+						Some((at, hottest)) if *at == sample.sampled_at => {
+							*hottest = hottest.max(sample.temperature);
+						}
+						_ => rounds.push((sample.sampled_at, sample.temperature)),
+					}
+				}
+				view.temperatures = rounds
+					.iter()
+					.map(|(_, hottest)| hottest.round().max(0.0) as u64)
+					.collect();
+			}
+			Err(err) => {
+				log::warn!(
+					"failed to load temperature history for {}: {err}",
+					view.peer_id
+				);
+				view.temperatures.clear();
+			}
+		}
+		let Some(name) = view.selected_cpu().map(|cpu| cpu.name.clone()) else {
+			view.history.clear();
+			return;
+		};
+		match peer.cpu_history(peer_id, &name, since) {
+			Ok(samples) => {
+				view.history = samples
+					.iter()
+					.map(|sample| sample.usage.round().clamp(0.0, 100.0) as u64)
+					.collect();
+			}
+			Err(err) => {
+				log::warn!("failed to load cpu history for {}: {err}", view.peer_id);
+				view.history.clear();
+			}
+		}
 	}
 
 	// fn fetch_remote_cpus(peer: &PuppyPeer, peer_id: &str) -> Result<Vec<CpuInfo>> {
@@ -785,6 +1796,34 @@ impl ShellApp {
 	// 		.collect()
 	// }
 
+	fn create_overview_view(&self, peer_id: String) -> Result<PeerOverviewView> {
+		let overview = self.peer.system_overview_blocking(peer_id.parse()?)?;
+		Ok(PeerOverviewView {
+			peer_id,
+			overview,
+			last_refresh: Instant::now(),
+		})
+	}
+
+	fn create_disk_view(&self, peer_id: String) -> Result<PeerDiskView> {
+		let disks = self.peer.list_disks_blocking(peer_id.parse()?)?;
+		Ok(PeerDiskView::new(peer_id, disks))
+	}
+
+	fn disk_summary(view: &PeerDiskView) -> String {
+		if view.disks.is_empty() {
+			return format!("{} reported no disks", view.peer_id);
+		}
+		let total: u64 = view.disks.iter().map(|disk| disk.total_space).sum();
+		let available: u64 = view.disks.iter().map(|disk| disk.available_space).sum();
+		format!(
+			"{} disks, {} free of {}",
+			view.disks.len(),
+			format_size(available),
+			format_size(total)
+		)
+	}
+
 	fn cpu_summary(view: &PeerCpuView) -> String {
 		view.selected_cpu()
 			.map(|cpu| {
@@ -850,7 +1889,7 @@ impl ShellApp {
 					])
 					.split(main_area);
 
-				let title = format!("Actions for {}", state.menu.peer.id);
+				let title = format!("Actions for {}", state.menu.peer.label());
 				let header = Paragraph::new(title)
 					.style(Style::default().fg(Color::Green))
 					.block(Block::default().borders(Borders::ALL).title("Header"));
@@ -889,6 +1928,35 @@ impl ShellApp {
 					.block(Block::default().borders(Borders::ALL).title("Status"));
 				f.render_widget(status, chunks[2]);
 			}
+			Mode::PeerOverview(view) => {
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(9),    // figures
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let header = Paragraph::new("System Overview")
+					.style(Style::default().fg(Color::Magenta))
+					.block(
+						Block::default()
+							.borders(Borders::ALL)
+							.title(format!("Peer: {}", view.peer_id)),
+					);
+				f.render_widget(header, chunks[0]);
+
+				let figures = Paragraph::new(view.lines().join("\n")).block(
+					Block::default()
+						.borders(Borders::ALL)
+						.title("Health (Esc=back)"),
+				);
+				f.render_widget(figures, chunks[1]);
+
+				let status = Paragraph::new(self.status_line.as_str());
+				f.render_widget(status, chunks[2]);
+			}
 			Mode::PeerCpus(view) => {
 				use ratatui::widgets::{Row, Table};
 				let chunks = Layout::default()
@@ -896,6 +1964,7 @@ impl ShellApp {
 					.constraints([
 						Constraint::Length(3), // title
 						Constraint::Min(5),    // table
+						Constraint::Length(6), // usage and temperature history
 						Constraint::Length(1), // status
 					])
 					.split(main_area);
@@ -957,11 +2026,293 @@ impl ShellApp {
 					.highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 				f.render_widget(table, chunks[1]);
 
+				let history_chunks = Layout::default()
+					.direction(Direction::Horizontal)
+					.constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+					.split(chunks[2]);
+				let history_title = match view.selected_cpu() {
+					Some(cpu) => format!("{} usage (last hour)", cpu.name),
+					None => String::from("Usage (last hour)"),
+				};
+				// Show the most recent samples that fit inside the block borders
+				let width = history_chunks[0].width.saturating_sub(2) as usize;
+				let start = view.history.len().saturating_sub(width);
+				let sparkline = Sparkline::default()
+					.block(Block::default().borders(Borders::ALL).title(history_title))
+					.data(&view.history[start..])
+					.max(100)
+					.style(Style::default().fg(Color::Magenta));
+				f.render_widget(sparkline, history_chunks[0]);
+
+				let temperature_title = match view.temperatures.last() {
+					Some(latest) => format!("Hottest sensor {latest}°C (last hour)"),
+					None => String::from("Temperature (no samples)"),
+				};
+				let width = history_chunks[1].width.saturating_sub(2) as usize;
+				let start = view.temperatures.len().saturating_sub(width);
+				let sparkline = Sparkline::default()
+					.block(
+						Block::default()
+							.borders(Borders::ALL)
+							.title(temperature_title),
+					)
+					.data(&view.temperatures[start..])
+					.max(100)
+					.style(Style::default().fg(Color::Red));
+				f.render_widget(sparkline, history_chunks[1]);
+
+				let status = Paragraph::new(self.status_line.as_str())
+					.block(Block::default().borders(Borders::ALL).title("Status"));
+				f.render_widget(status, chunks[3]);
+			}
+			Mode::PeerDisks(view) => {
+				use ratatui::widgets::{Row, Table};
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(5),    // table
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let header = Paragraph::new("Disk Usage")
+					.style(Style::default().fg(Color::Magenta))
+					.block(
+						Block::default()
+							.borders(Borders::ALL)
+							.title(format!("Peer: {}", view.peer_id)),
+					);
+				f.render_widget(header, chunks[0]);
+
+				// borders and the header row
+				let viewport = chunks[1].height.saturating_sub(3).max(1) as usize;
+				view.set_viewport(viewport);
+
+				let header_row = Row::new(vec![
+					"Mount",
+					"Filesystem",
+					"Total",
+					"Available",
+					"Used",
+					"Flags",
+				])
+				.style(Style::default().add_modifier(Modifier::BOLD));
+				let rows: Vec<Row> = view
+					.disks
+					.iter()
+					.enumerate()
+					.skip(view.scroll)
+					.take(view.viewport)
+					.map(|(idx, disk)| {
+						let style = if idx == view.selected {
+							Style::default().fg(Color::Cyan)
+						} else {
+							Style::default()
+						};
+						Row::new(vec![
+							disk.mount_path.clone(),
+							disk.filesystem.clone(),
+							format_size(disk.total_space),
+							format_size(disk.available_space),
+							format!("{:.1}%", disk.usage_percent),
+							disk_flags(disk),
+						])
+						.style(style)
+					})
+					.collect();
+
+				let widths = [
+					Constraint::Percentage(35),
+					Constraint::Length(12),
+					Constraint::Length(10),
+					Constraint::Length(10),
+					Constraint::Length(7),
+					Constraint::Length(14),
+				];
+
+				let table = Table::new(rows, &widths).header(header_row).block(
+					Block::default()
+						.borders(Borders::ALL)
+						.title("Disks (↑/↓ scroll, Esc=back)"),
+				);
+				f.render_widget(table, chunks[1]);
+
+				let status = Paragraph::new(self.status_line.as_str());
+				f.render_widget(status, chunks[2]);
+			}
+			Mode::FileBrowser(view) => {
+				use ratatui::widgets::{Row, Table};
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(5),    // table
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let location = if view.at_shares() {
+					"shared folders"
+				} else {
+					view.path.as_str()
+				};
+				let header = Paragraph::new(format!("File Browser — {}", location))
+					.style(Style::default().fg(Color::Blue))
+					.block(
+						Block::default()
+							.borders(Borders::ALL)
+							.title(format!("Peer: {}", view.peer_id)),
+					);
+				f.render_widget(header, chunks[0]);
+
+				let viewport = if chunks[1].height > 1 {
+					(chunks[1].height - 1) as usize
+				} else {
+					1
+				};
+				view.set_viewport(viewport);
+
+				let header_row = Row::new(vec!["Idx", "Name", "Type", "Size"])
+					.style(Style::default().add_modifier(Modifier::BOLD));
+				let rows: Vec<Row> = view
+					.entries
+					.iter()
+					.enumerate()
+					.skip(view.scroll)
+					.take(view.viewport)
+					.map(|(idx, entry)| {
+						let style = if idx == view.selected {
+							Style::default().fg(Color::Cyan)
+						} else {
+							Style::default()
+						};
+						let display_name = if entry.is_navigable() {
+							format!("{}/", entry.name)
+						} else {
+							entry.name.clone()
+						};
+						let entry_type = match (entry.is_symlink, entry.is_navigable()) {
+							(true, true) => "link/dir",
+							(true, false) => "link",
+							(false, true) => "dir",
+							(false, false) => "file",
+						};
+						Row::new(vec![
+							format!("{}", idx),
+							display_name,
+							entry_type.into(),
+							format_size(entry.size),
+						])
+						.style(style)
+					})
+					.collect();
+
+				let widths = [
+					Constraint::Length(4),
+					Constraint::Percentage(60),
+					Constraint::Length(6),
+					Constraint::Length(12),
+				];
+
+				let table = Table::new(rows, &widths)
+					.header(header_row)
+					.block(Block::default().borders(Borders::ALL).title(
+						"Files (Enter=open, Backspace=up, s=size, x=subtree, t=tail, .=hidden, Esc=back)",
+					))
+					.highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+				f.render_widget(table, chunks[1]);
+
+				let status = Paragraph::new(self.status_line.as_str())
+					.block(Block::default().borders(Borders::ALL).title("Status"));
+				f.render_widget(status, chunks[2]);
+			}
+			Mode::TailFile(view) => {
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(5),    // log lines
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let header = Paragraph::new(format!("Tail — {}", view.path))
+					.style(Style::default().fg(Color::Blue))
+					.block(
+						Block::default()
+							.borders(Borders::ALL)
+							.title(format!("Peer: {}", view.browser.peer_id)),
+					);
+				f.render_widget(header, chunks[0]);
+
+				let visible = chunks[1].height.saturating_sub(2) as usize;
+				let skip = view.lines.len().saturating_sub(visible);
+				let body: Vec<&str> = view.lines.iter().skip(skip).map(String::as_str).collect();
+				let log = Paragraph::new(body.join("\n")).block(
+					Block::default()
+						.borders(Borders::ALL)
+						.title("Log (follows new lines, Esc=back)"),
+				);
+				f.render_widget(log, chunks[1]);
+
+				let status = Paragraph::new(self.status_line.as_str())
+					.block(Block::default().borders(Borders::ALL).title("Status"));
+				f.render_widget(status, chunks[2]);
+			}
+			Mode::RecentFiles(view) => {
+				use ratatui::widgets::{Row, Table};
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(5),    // table
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let header = Paragraph::new(format!("Recent files on {}", view.peer_id))
+					.style(Style::default().fg(Color::Blue))
+					.block(Block::default().borders(Borders::ALL).title("Header"));
+				f.render_widget(header, chunks[0]);
+
+				let visible = chunks[1].height.saturating_sub(3) as usize;
+				let skip = (view.selected + 1).saturating_sub(visible);
+				let header_row = Row::new(vec!["Opened", "Path"])
+					.style(Style::default().add_modifier(Modifier::BOLD));
+				let rows: Vec<Row> = view
+					.files
+					.iter()
+					.enumerate()
+					.skip(skip)
+					.take(visible)
+					.map(|(idx, file)| {
+						let style = if idx == view.selected {
+							Style::default().fg(Color::Cyan)
+						} else {
+							Style::default()
+						};
+						let opened = file.opened_at.with_timezone(&chrono::Local);
+						Row::new(vec![
+							opened.format("%Y-%m-%d %H:%M").to_string(),
+							file.path.clone(),
+						])
+						.style(style)
+					})
+					.collect();
+				let widths = [Constraint::Length(17), Constraint::Percentage(80)];
+				let table = Table::new(rows, &widths).header(header_row).block(
+					Block::default()
+						.borders(Borders::ALL)
+						.title("Files (Enter=follow, Esc=back)"),
+				);
+				f.render_widget(table, chunks[1]);
+
 				let status = Paragraph::new(self.status_line.as_str())
 					.block(Block::default().borders(Borders::ALL).title("Status"));
 				f.render_widget(status, chunks[2]);
 			}
-			Mode::FileBrowser(view) => {
+			Mode::Peers(view) => {
 				use ratatui::widgets::{Row, Table};
 				let chunks = Layout::default()
 					.direction(Direction::Vertical)
@@ -972,47 +2323,35 @@ impl ShellApp {
 					])
 					.split(main_area);
 
-				let header = Paragraph::new(format!("File Browser — {}", view.path))
-					.style(Style::default().fg(Color::Blue))
-					.block(
-						Block::default()
-							.borders(Borders::ALL)
-							.title(format!("Peer: {}", view.peer_id)),
-					);
+				let header = Paragraph::new("Peers")
+					.style(Style::default().fg(Color::Green))
+					.block(Block::default().borders(Borders::ALL).title("Header"));
 				f.render_widget(header, chunks[0]);
 
-				let viewport = if chunks[1].height > 1 {
-					(chunks[1].height - 1) as usize
-				} else {
-					1
-				};
-				view.set_viewport(viewport);
+				// rows left once the borders and the header row are drawn
+				view.set_viewport(chunks[1].height.saturating_sub(3) as usize);
 
-				let header_row = Row::new(vec!["Idx", "Name", "Type", "Size"])
+				let header_row = Row::new(vec!["Idx", "Peer", "Address", "Status"])
 					.style(Style::default().add_modifier(Modifier::BOLD));
 				let rows: Vec<Row> = view
-					.entries
+					.peers
 					.iter()
 					.enumerate()
 					.skip(view.scroll)
 					.take(view.viewport)
-					.map(|(idx, entry)| {
-						let style = if idx == view.selected {
+					.map(|(i, p)| {
+						let style = if i == view.selected {
 							Style::default().fg(Color::Cyan)
+						} else if p.is_local() {
+							Style::default().fg(Color::Yellow)
 						} else {
 							Style::default()
 						};
-						let display_name = if entry.is_dir {
-							format!("{}/", entry.name)
-						} else {
-							entry.name.clone()
-						};
-						let entry_type = if entry.is_dir { "dir" } else { "file" };
 						Row::new(vec![
-							format!("{}", idx),
-							display_name,
-							entry_type.into(),
-							format_size(entry.size),
+							format!("{}", i),
+							p.label(),
+							p.address.clone(),
+							p.status.clone(),
 						])
 						.style(style)
 					})
@@ -1020,17 +2359,16 @@ impl ShellApp {
 
 				let widths = [
 					Constraint::Length(4),
-					Constraint::Percentage(60),
-					Constraint::Length(6),
+					Constraint::Length(32),
+					Constraint::Percentage(50),
 					Constraint::Length(12),
 				];
-
 				let table = Table::new(rows, &widths)
 					.header(header_row)
 					.block(
 						Block::default()
 							.borders(Borders::ALL)
-							.title("Files (Enter=open, Backspace=up, Esc=back)"),
+							.title("Peers (r=refresh, d=discover, l=toggle this node, Esc=back)"),
 					)
 					.highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 				f.render_widget(table, chunks[1]);
@@ -1039,7 +2377,7 @@ impl ShellApp {
 					.block(Block::default().borders(Borders::ALL).title("Status"));
 				f.render_widget(status, chunks[2]);
 			}
-			Mode::Peers(view) => {
+			Mode::Inbox(view) => {
 				use ratatui::widgets::{Row, Table};
 				let chunks = Layout::default()
 					.direction(Direction::Vertical)
@@ -1050,53 +2388,117 @@ impl ShellApp {
 					])
 					.split(main_area);
 
-				let header = Paragraph::new("Peers")
-					.style(Style::default().fg(Color::Green))
+				let header = Paragraph::new("Inbox")
+					.style(Style::default().fg(Color::Blue))
 					.block(Block::default().borders(Borders::ALL).title("Header"));
 				f.render_widget(header, chunks[0]);
 
-				let header_row = Row::new(vec!["Idx", "Peer ID", "Address", "Status"])
+				let visible = chunks[1].height.saturating_sub(3) as usize;
+				let skip = (view.selected + 1).saturating_sub(visible);
+				let header_row = Row::new(vec!["Received", "From", "Message"])
 					.style(Style::default().add_modifier(Modifier::BOLD));
-				let rows: Vec<Row> = view
-					.peers
+				let rows: Vec<Row> = self
+					.latest_state
 					.iter()
+					.flat_map(|state| {
+						state.messages.iter().rev().map(move |message| {
+							let from = message.from.to_string();
+							(peer_label(state.peer_name(&message.from), &from), message)
+						})
+					})
 					.enumerate()
-					.map(|(i, p)| {
-						let style = if i == view.selected {
+					.skip(skip)
+					.take(visible)
+					.map(|(idx, (from, message))| {
+						let style = if idx == view.selected {
 							Style::default().fg(Color::Cyan)
 						} else {
 							Style::default()
 						};
+						let received = message.received_at.with_timezone(&chrono::Local);
 						Row::new(vec![
-							format!("{}", i),
-							p.id.clone(),
-							p.address.clone(),
-							p.status.clone(),
+							received.format("%Y-%m-%d %H:%M").to_string(),
+							from,
+							message.text.lines().next().unwrap_or_default().to_string(),
 						])
 						.style(style)
 					})
 					.collect();
-
 				let widths = [
-					Constraint::Length(4),
-					Constraint::Length(16),
-					Constraint::Percentage(50),
-					Constraint::Length(12),
+					Constraint::Length(17),
+					Constraint::Length(24),
+					Constraint::Percentage(60),
 				];
-				let table = Table::new(rows, &widths)
-					.header(header_row)
-					.block(
-						Block::default()
-							.borders(Borders::ALL)
-							.title("Peers (r=refresh, Esc=back)"),
-					)
-					.highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+				let table = Table::new(rows, &widths).header(header_row).block(
+					Block::default()
+						.borders(Borders::ALL)
+						.title("Messages (r=reply, Esc=back)"),
+				);
 				f.render_widget(table, chunks[1]);
 
 				let status = Paragraph::new(self.status_line.as_str())
 					.block(Block::default().borders(Borders::ALL).title("Status"));
 				f.render_widget(status, chunks[2]);
 			}
+			Mode::SendMessage(form) => {
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(5),    // form
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let header = Paragraph::new(format!("Message {}", form.peer_id))
+					.style(Style::default().fg(Color::Magenta))
+					.block(Block::default().borders(Borders::ALL).title("Header"));
+				f.render_widget(header, chunks[0]);
+
+				let form_chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.margin(1)
+					.constraints([Constraint::Length(3), Constraint::Min(1)])
+					.split(chunks[1]);
+				let text = Paragraph::new(form.text.as_str())
+					.style(Style::default().fg(Color::Cyan))
+					.wrap(Wrap { trim: false })
+					.block(Block::default().borders(Borders::ALL).title("[Message]*"));
+				f.render_widget(text, form_chunks[0]);
+
+				let status = Paragraph::new(self.status_line.as_str())
+					.block(Block::default().borders(Borders::ALL).title("Status"));
+				f.render_widget(status, chunks[2]);
+			}
+			Mode::RenamePeer(form) => {
+				let chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.constraints([
+						Constraint::Length(3), // title
+						Constraint::Min(5),    // form
+						Constraint::Length(1), // status
+					])
+					.split(main_area);
+
+				let header = Paragraph::new(format!("Rename {}", form.peer_id))
+					.style(Style::default().fg(Color::Magenta))
+					.block(Block::default().borders(Borders::ALL).title("Header"));
+				f.render_widget(header, chunks[0]);
+
+				let form_chunks = Layout::default()
+					.direction(Direction::Vertical)
+					.margin(1)
+					.constraints([Constraint::Length(3), Constraint::Min(1)])
+					.split(chunks[1]);
+				let name = Paragraph::new(format!("Nickname: {}", form.name))
+					.style(Style::default().fg(Color::Cyan))
+					.block(Block::default().borders(Borders::ALL).title("[Nickname]*"));
+				f.render_widget(name, form_chunks[0]);
+
+				let status = Paragraph::new(self.status_line.as_str())
+					.block(Block::default().borders(Borders::ALL).title("Status"));
+				f.render_widget(status, chunks[2]);
+			}
 			Mode::CreateUser(form) => {
 				let chunks = Layout::default()
 					.direction(Direction::Vertical)
@@ -1186,22 +2588,20 @@ impl ShellApp {
 					.peers
 					.iter()
 					.enumerate()
-					.map(|(i, n)| (i, n.id.clone(), n.angle))
+					.map(|(i, n)| (i, n.id.clone(), n.angle, n.local))
 					.collect::<Vec<_>>();
 				let selected = graph.selected;
 				let canvas = Canvas::default()
-					.block(
-						Block::default()
-							.borders(Borders::ALL)
-							.title("Graph (r=refresh, ←/→ select, Esc back)"),
-					)
+					.block(Block::default().borders(Borders::ALL).title(
+						"Graph (r=refresh, d=discover, ←/→ select, l toggle this node, Esc back)",
+					))
 					.x_bounds([-1.3, 1.3])
 					.y_bounds([-1.1, 1.1])
 					.paint(move |ctx| {
-						for (i1, _id1, a1) in &peers_clone {
+						for (i1, _id1, a1, _) in &peers_clone {
 							let x1 = a1.cos();
 							let y1 = a1.sin();
-							for (i2, _id2, a2) in &peers_clone {
+							for (i2, _id2, a2, _) in &peers_clone {
 								if i1 < i2 {
 									let x2 = a2.cos();
 									let y2 = a2.sin();
@@ -1215,11 +2615,13 @@ impl ShellApp {
 								}
 							}
 						}
-						for (i, id, a) in &peers_clone {
+						for (i, id, a, local) in &peers_clone {
 							let x = a.cos();
 							let y = a.sin();
 							let color = if *i == selected {
 								Color::Cyan
+							} else if *local {
+								Color::Yellow
 							} else {
 								Color::White
 							};
@@ -1242,91 +2644,239 @@ impl ShellApp {
 		render_peer_info(f, info_area, self);
 	}
 
+	/// Fetch whatever was appended to the followed file since the last poll.
+	fn poll_tail(&mut self) {
+		let Mode::TailFile(view) = &mut self.mode else {
+			return;
+		};
+		if !view.poll_due() {
+			return;
+		}
+		view.last_poll = Some(Instant::now());
+		let Ok(peer_id) = view.browser.peer_id.parse() else {
+			return;
+		};
+		// drain a burst of appended data before drawing again
+		loop {
+			match self.peer.read_tail_blocking(
+				peer_id,
+				view.path.clone(),
+				view.offset,
+				TAIL_FROM_END,
+			) {
+				Ok(chunk) => {
+					let more = !chunk.eof && !chunk.data.is_empty();
+					view.push(chunk.offset, &chunk.data);
+					if !more {
+						break;
+					}
+				}
+				Err(err) => {
+					self.status_line = format!("Failed to read {}: {}", view.path, err);
+					break;
+				}
+			}
+		}
+	}
+
+	/// Ask the local network for peers now; `poll_discovery` reports what turned up.
+	fn start_discovery(&mut self) {
+		let known = self
+			.peer
+			.state()
+			.lock()
+			.map(|state| state.discovered_peer_ids())
+			.unwrap_or_default();
+		match self.peer.query_mdns() {
+			Ok(()) => {
+				self.discovery = Some((Instant::now(), known));
+				self.status_line = String::from("Looking for peers on the local network...");
+			}
+			Err(err) => self.status_line = format!("Discovery failed: {err}"),
+		}
+	}
+
+	fn poll_discovery(&mut self) {
+		let Some((sent, _)) = &self.discovery else {
+			return;
+		};
+		if sent.elapsed() < DISCOVERY_WAIT {
+			return;
+		}
+		let Some((_, known)) = self.discovery.take() else {
+			return;
+		};
+		let found = self
+			.peer
+			.state()
+			.lock()
+			.map(|state| state.discovered_peer_ids().difference(&known).count())
+			.unwrap_or(0);
+		// show the answers right away instead of at the next auto-refresh
+		self.refresh_views();
+		self.status_line = format!(
+			"Discovery found {} new peer{}",
+			found,
+			if found == 1 { "" } else { "s" }
+		);
+	}
+
 	fn periodic_refresh(&mut self) {
 		if self.last_refresh.elapsed() >= self.refresh_interval {
-			// Pull latest core state (Arc<Mutex<State>>) via instance and take a snapshot clone
-			let state_arc = self.peer.state();
-			let snapshot = state_arc.lock().ok().map(|s| s.clone());
-			if let Some(state) = snapshot.clone() {
-				self.latest_state = Some(state);
-			}
-			// Update active views from snapshot (if open)
-			if let Some(state) = snapshot {
-				let aggregated = Self::aggregate_peers(&state);
-				match &mut self.mode {
-					Mode::Peers(view) => {
-						view.set_peers(aggregated.clone());
-						self.status_line =
-							format!("Auto-refreshed peers ({} entries)", view.peers.len());
-					}
-					Mode::PeerActions(state) => {
-						state.view.set_peers(aggregated.clone());
-						if let Some(idx) = state
-							.view
-							.peers
-							.iter()
-							.position(|p| p.id == state.menu.peer.id)
-						{
-							state.view.selected = idx;
-						} else if !state.view.peers.is_empty() {
-							if state.view.selected >= state.view.peers.len() {
-								state.view.selected = state.view.peers.len() - 1;
-							}
+			self.refresh_views();
+		}
+	}
+
+	/// Update the open view from a fresh snapshot of the core state.
+	fn refresh_views(&mut self) {
+		// Pull latest core state (Arc<Mutex<State>>) via instance and take a snapshot clone
+		let state_arc = self.peer.state();
+		let snapshot = state_arc.lock().ok().map(|s| s.clone());
+		let newest_before = self
+			.latest_state
+			.as_ref()
+			.and_then(|state| state.messages.back().cloned());
+		if let Some(state) = snapshot.clone() {
+			self.latest_state = Some(state);
+		}
+		// Update active views from snapshot (if open)
+		if let Some(state) = snapshot {
+			let aggregated = Self::aggregate_peers(&state, self.show_local_peer);
+			match &mut self.mode {
+				Mode::Peers(view) => {
+					view.set_peers(aggregated.clone());
+					self.status_line =
+						format!("Auto-refreshed peers ({} entries)", view.peers.len());
+				}
+				Mode::PeerActions(state) => {
+					state.view.set_peers(aggregated.clone());
+					if let Some(idx) = state
+						.view
+						.peers
+						.iter()
+						.position(|p| p.id == state.menu.peer.id)
+					{
+						state.view.selected = idx;
+					} else if !state.view.peers.is_empty()
+						&& state.view.selected >= state.view.peers.len()
+					{
+						state.view.selected = state.view.peers.len() - 1;
+					}
+					state.ensure_selected_peer();
+					state.load =
+						Self::peer_load(&self.peer, &self.latest_state, &state.menu.peer.id);
+					self.status_line = format!(
+						"Auto-refreshed peer actions ({} peers)",
+						state.view.peers.len()
+					);
+				}
+				Mode::PeersGraph(graph) => {
+					graph.set_peers(&aggregated);
+					self.status_line =
+						format!("Auto-refreshed graph ({} nodes)", graph.peers.len());
+				}
+				Mode::PeerOverview(view)
+					if view.last_refresh.elapsed() >= self.refresh_interval =>
+				{
+					// keep the last good figures on screen when a refresh fails
+					view.last_refresh = Instant::now();
+					match self
+						.peer
+						.system_overview_blocking(view.peer_id.parse().unwrap())
+					{
+						Ok(overview) => {
+							view.overview = overview;
+							self.status_line = format!("Refreshed overview of {}", view.peer_id);
 						}
-						state.ensure_selected_peer();
-						self.status_line = format!(
-							"Auto-refreshed peer actions ({} peers)",
-							state.view.peers.len()
-						);
-					}
-					Mode::PeersGraph(graph) => {
-						let ids: Vec<String> = aggregated.iter().map(|p| p.id.clone()).collect();
-						graph.set_peers(&ids);
-						self.status_line =
-							format!("Auto-refreshed graph ({} nodes)", graph.peers.len());
-					}
-					Mode::PeerCpus(view) => {
-						if view.last_refresh.elapsed() >= self.refresh_interval {
-							match self.peer.list_cpus_blocking(view.peer_id.parse().unwrap()) {
-								Ok(cpus) => {
-									view.replace_cpus(cpus);
-									let headline = Self::cpu_summary(view);
-									self.status_line = format!("Refreshed CPUs — {}", headline);
-								}
-								Err(err) => {
-									view.mark_refreshed();
-									self.status_line =
-										format!("CPU refresh failed for {}: {}", view.peer_id, err);
-								}
-							}
+						Err(err) => {
+							self.status_line =
+								format!("Overview refresh failed for {}: {}", view.peer_id, err);
 						}
 					}
-					_ => {}
 				}
-			} else {
-				self.status_line = "Auto-refresh failed to lock state".into();
+				Mode::PeerCpus(view) if view.last_refresh.elapsed() >= self.refresh_interval => {
+					match self.peer.list_cpus_blocking(view.peer_id.parse().unwrap()) {
+						Ok(cpus) => {
+							view.replace_cpus(cpus);
+							Self::load_cpu_history(&self.peer, view);
+							let headline = Self::cpu_summary(view);
+							self.status_line = format!("Refreshed CPUs — {}", headline);
+						}
+						Err(err) => {
+							view.mark_refreshed();
+							self.status_line =
+								format!("CPU refresh failed for {}: {}", view.peer_id, err);
+						}
+					}
+				}
+				Mode::Inbox(view) => {
+					view.selected = view.selected.min(state.messages.len().saturating_sub(1));
+				}
+				Mode::PeerDisks(view) if view.last_refresh.elapsed() >= self.refresh_interval => {
+					match self.peer.list_disks_blocking(view.peer_id.parse().unwrap()) {
+						Ok(disks) => {
+							view.replace_disks(disks);
+							self.status_line =
+								format!("Refreshed disks — {}", Self::disk_summary(view));
+						}
+						Err(err) => {
+							// keep the last good listing on screen
+							view.mark_refreshed();
+							self.status_line =
+								format!("Disk refresh failed for {}: {}", view.peer_id, err);
+						}
+					}
+				}
+				_ => {}
 			}
-			// legacy post-refresh per-mode adjustments removed (state-based updates already applied)
-			self.refresh_count += 1;
-			self.last_refresh = Instant::now();
+			// a note can arrive on any screen, so announce it over the refresh status
+			if let Some(message) = state.messages.back()
+				&& newest_before.as_ref() != Some(message)
+			{
+				let from = message.from.to_string();
+				self.status_line = format!(
+					"New message from {}: {}",
+					peer_label(state.peer_name(&message.from), &from),
+					message.text.lines().next().unwrap_or_default()
+				);
+			}
+		} else {
+			self.status_line = "Auto-refresh failed to lock state".into();
+		}
+		// legacy post-refresh per-mode adjustments removed (state-based updates already applied)
+		self.refresh_count += 1;
+		self.last_refresh = Instant::now();
+	}
+
+	fn local_peer_status(show: bool) -> String {
+		if show {
+			String::from("Showing this node in the peers list (l to hide)")
+		} else {
+			String::from("Hiding this node from the peers list (l to show)")
 		}
 	}
 
-	fn aggregate_peers(state: &State) -> Vec<PeerRow> {
+	fn aggregate_peers(state: &State, show_local: bool) -> Vec<PeerRow> {
 		// Map peer_id -> (address (first), status)
 		let mut rows: HashMap<String, PeerRow> = HashMap::new();
 		// Discovered peers (addresses)
 		for d in &state.discovered_peers {
 			let id_str = format!("{}", d.peer_id);
+			let address = d
+				.addresses
+				.first()
+				.map(|addr| addr.to_string())
+				.unwrap_or_default();
 			rows.entry(id_str.clone())
 				.and_modify(|r| {
 					if r.address.is_empty() {
-						r.address = d.multiaddr.to_string();
+						r.address = address.clone();
 					}
 				})
 				.or_insert(PeerRow {
 					id: id_str,
-					address: d.multiaddr.to_string(),
+					name: None,
+					address,
 					status: "discovered".into(),
 				});
 		}
@@ -1339,6 +2889,7 @@ impl ShellApp {
 				})
 				.or_insert(PeerRow {
 					id: id_str,
+					name: None,
 					address: String::new(),
 					status: "connected".into(),
 				});
@@ -1346,25 +2897,33 @@ impl ShellApp {
 		// Explicit peers list (metadata like names) ensure presence
 		for p in &state.peers {
 			let id_str = format!("{}", p.id);
-			rows.entry(id_str.clone()).or_insert(PeerRow {
-				id: id_str,
-				address: String::new(),
-				status: String::new(),
-			});
+			rows.entry(id_str.clone())
+				.or_insert(PeerRow {
+					id: id_str,
+					name: None,
+					address: String::new(),
+					status: String::new(),
+				})
+				.name = p.name.clone();
 		}
 		let me_id = format!("{}", state.me);
-		rows.entry(me_id.clone())
-			.and_modify(|r| {
-				if r.address.is_empty() {
-					r.address = LOCAL_LISTEN_MULTIADDR.into();
-				}
-				r.status = "local".into();
-			})
-			.or_insert(PeerRow {
-				id: me_id,
-				address: LOCAL_LISTEN_MULTIADDR.into(),
-				status: "local".into(),
-			});
+		if show_local {
+			rows.entry(me_id.clone())
+				.and_modify(|r| {
+					if r.address.is_empty() {
+						r.address = LOCAL_LISTEN_MULTIADDR.into();
+					}
+					r.status = "local".into();
+				})
+				.or_insert(PeerRow {
+					id: me_id,
+					name: None,
+					address: LOCAL_LISTEN_MULTIADDR.into(),
+					status: "local".into(),
+				});
+		} else {
+			rows.remove(&me_id);
+		}
 		let mut vec: Vec<PeerRow> = rows.into_iter().map(|(_, v)| v).collect();
 		vec.sort_by(|a, b| a.id.cmp(&b.id));
 		vec
@@ -1373,13 +2932,12 @@ impl ShellApp {
 	fn gather_known_addresses(&self, peer_id: &str) -> Vec<String> {
 		if let Some(state) = &self.latest_state {
 			if let Ok(target) = PeerId::from_str(peer_id) {
-				let mut addresses = Vec::new();
-				for discovered in &state.discovered_peers {
-					if discovered.peer_id == target {
-						addresses.push(discovered.multiaddr.to_string());
-					}
-				}
-				addresses
+				state
+					.discovered_peers
+					.iter()
+					.filter(|p| p.peer_id == target)
+					.flat_map(|p| p.addresses.iter().map(|addr| addr.to_string()))
+					.collect()
 			} else {
 				Vec::new()
 			}
@@ -1388,11 +2946,26 @@ impl ShellApp {
 		}
 	}
 
+	/// Direction and route of each open connection to the peer.
+	fn connection_summaries(&self, peer_id: &str) -> Vec<String> {
+		match (&self.latest_state, PeerId::from_str(peer_id)) {
+			(Some(state), Ok(target)) => state
+				.connection_routes(target)
+				.iter()
+				.map(|(direction, route)| format!("{} {}", direction, route))
+				.collect(),
+			_ => Vec::new(),
+		}
+	}
+
 	fn peer_panel_content(&self) -> (String, Vec<String>) {
 		match &self.mode {
 			Mode::Peers(view) if !view.peers.is_empty() => {
 				let peer = &view.peers[view.selected];
 				let mut lines = Vec::new();
+				if let Some(name) = &peer.name {
+					lines.push(format!("Name: {}", name));
+				}
 				lines.push(format!("Peer ID: {}", peer.id));
 				let mut addresses = Vec::new();
 				if !peer.address.is_empty() {
@@ -1416,11 +2989,18 @@ impl ShellApp {
 				if !peer.status.is_empty() {
 					lines.push(format!("Status: {}", peer.status));
 				}
+				let directions = self.connection_summaries(&peer.id);
+				if !directions.is_empty() {
+					lines.push(format!("Connections: {}", directions.join(", ")));
+				}
 				("Selected Peer".into(), lines)
 			}
 			Mode::PeerActions(state) => {
 				let peer = &state.menu.peer;
 				let mut lines = Vec::new();
+				if let Some(name) = &peer.name {
+					lines.push(format!("Name: {}", name));
+				}
 				lines.push(format!("Peer ID: {}", peer.id));
 				let mut addresses = Vec::new();
 				if !peer.address.is_empty() {
@@ -1444,22 +3024,49 @@ impl ShellApp {
 				if !peer.status.is_empty() {
 					lines.push(format!("Status: {}", peer.status));
 				}
+				let directions = self.connection_summaries(&peer.id);
+				if !directions.is_empty() {
+					lines.push(format!("Connections: {}", directions.join(", ")));
+				}
+				if let Some(load) = &state.load {
+					lines.push(format!("Uptime: {}", format_uptime(load.uptime)));
+					lines.push(format!("Load: {}", format_load(load.load.as_ref())));
+				}
 				("Peer Actions".into(), lines)
 			}
 			Mode::FileBrowser(view) => {
 				let mut lines = Vec::new();
 				lines.push(format!("Peer: {}", view.peer_id));
-				lines.push(format!("Path: {}", view.path));
+				if !view.at_shares() {
+					lines.push(format!("Path: {}", view.path));
+				}
 				if let Some(entry) = view.selected_entry() {
 					lines.push(format!("Name: {}", entry.name));
 					lines.push(format!(
 						"Type: {}",
-						if entry.is_dir { "directory" } else { "file" }
+						match (entry.is_symlink, entry.is_navigable()) {
+							(true, true) => "symlink to directory",
+							(true, false) => "symlink",
+							(false, true) => "directory",
+							(false, false) => "file",
+						}
 					));
 					if let Some(ext) = &entry.extension {
 						lines.push(format!("Extension: {}", ext));
 					}
 					lines.push(format!("Size: {}", format_size(entry.size)));
+					if let Some((_, size)) = view
+						.dir_size
+						.as_ref()
+						.filter(|(name, _)| *name == entry.name)
+					{
+						lines.push(format!(
+							"Total size: {} in {} files{}",
+							format_size(size.total_bytes),
+							size.file_count,
+							if size.partial { " (partial)" } else { "" }
+						));
+					}
 					if let Some(modified) = entry.modified_at {
 						lines.push(format!("Modified: {}", modified.to_rfc3339()));
 					}
@@ -1471,6 +3078,11 @@ impl ShellApp {
 				}
 				("File Browser".into(), lines)
 			}
+			Mode::PeerOverview(view) => {
+				let mut lines = vec![format!("Peer: {}", view.peer_id)];
+				lines.extend(view.lines());
+				("Overview".into(), lines)
+			}
 			Mode::PeerCpus(view) => {
 				let mut lines = Vec::new();
 				lines.push(format!("Peer: {}", view.peer_id));
@@ -1500,6 +3112,46 @@ impl ShellApp {
 				}
 				("CPU Info".into(), lines)
 			}
+			Mode::PeerDisks(view) => {
+				let mut lines = vec![format!("Peer: {}", view.peer_id)];
+				match view.disks.get(view.selected) {
+					Some(disk) => {
+						lines.push(format!("Name: {}", disk.name));
+						lines.push(format!("Mount: {}", disk.mount_path));
+						lines.push(format!("Kind: {}", disk.kind));
+						lines.push(format!("Read: {}", format_size(disk.total_read_bytes)));
+						lines.push(format!(
+							"Written: {}",
+							format_size(disk.total_written_bytes)
+						));
+					}
+					None => lines.push("No disks reported".into()),
+				}
+				("Disk Info".into(), lines)
+			}
+			Mode::Inbox(view) => {
+				let message = self
+					.latest_state
+					.as_ref()
+					.and_then(|state| Some((state, view.selected_message(&state.messages)?)));
+				match message {
+					Some((state, message)) => {
+						let from = message.from.to_string();
+						let received = message.received_at.with_timezone(&chrono::Local);
+						let mut lines = vec![
+							format!(
+								"From: {}",
+								peer_label(state.peer_name(&message.from), &from)
+							),
+							format!("Received: {}", received.format("%Y-%m-%d %H:%M:%S")),
+							String::new(),
+						];
+						lines.extend(message.text.lines().map(str::to_string));
+						("Message".into(), lines)
+					}
+					None => ("Message".into(), vec!["No messages".into()]),
+				}
+			}
 			Mode::PeersGraph(graph) if !graph.peers.is_empty() => {
 				let node = &graph.peers[graph.selected];
 				let mut lines = Vec::new();
@@ -1527,7 +3179,9 @@ impl ShellApp {
 					} else {
 						lines.push("Known peers:".into());
 						for (idx, peer) in state.discovered_peers.iter().take(5).enumerate() {
-							lines.push(format!("{}: {}", idx + 1, peer.multiaddr));
+							let addresses: Vec<String> =
+								peer.addresses.iter().map(|addr| addr.to_string()).collect();
+							lines.push(format!("{}: {}", idx + 1, addresses.join(", ")));
 						}
 						if state.discovered_peers.len() > 5 {
 							lines.push(format!("(+{} more)", state.discovered_peers.len() - 5));
@@ -1558,9 +3212,12 @@ pub fn run() -> io::Result<()> {
 
 fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
 	let mut app = ShellApp::new();
+	app.start_discovery();
 
 	while !app.should_quit {
 		app.periodic_refresh();
+		app.poll_tail();
+		app.poll_discovery();
 		terminal.draw(|f| app.render(f))?;
 
 		if event::poll(Duration::from_millis(200))? {
@@ -1576,6 +3233,29 @@ fn render_peer_info(f: &mut Frame<'_>, area: Rect, app: &ShellApp) {
 	if area.width == 0 || area.height == 0 {
 		return;
 	}
+	let warning = app
+		.latest_state
+		.as_ref()
+		.and_then(|state| state.identity_warning.clone());
+	let area = match warning {
+		Some(warning) => {
+			let chunks = Layout::default()
+				.direction(Direction::Vertical)
+				.constraints([Constraint::Length(8), Constraint::Min(0)])
+				.split(area);
+			let alert = Paragraph::new(warning)
+				.style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+				.block(
+					Block::default()
+						.borders(Borders::ALL)
+						.title("Identity conflict"),
+				)
+				.wrap(Wrap { trim: true });
+			f.render_widget(alert, chunks[0]);
+			chunks[1]
+		}
+		None => area,
+	};
 	let (title, lines) = app.peer_panel_content();
 	let body = if lines.is_empty() {
 		String::from("No peer information available")
@@ -1596,6 +3276,22 @@ fn join_child_path(base: &str, child: &str) -> String {
 	}
 }
 
+/// Directory entry standing for a shared folder or file in the shares list.
+fn share_entry(root: &str, is_dir: bool) -> DirEntry {
+	DirEntry {
+		name: root.to_string(),
+		is_dir,
+		is_symlink: false,
+		target_is_dir: false,
+		extension: None,
+		mime: None,
+		size: 0,
+		created_at: None,
+		modified_at: None,
+		accessed_at: None,
+	}
+}
+
 fn parent_path(path: &str) -> String {
 	if path == "/" {
 		return "/".into();
@@ -1639,6 +3335,20 @@ fn format_frequency(freq_mhz: u64) -> String {
 	}
 }
 
+/// `ro` and `removable` markers for the disk table, `-` when neither applies.
+fn disk_flags(disk: &DiskInfo) -> String {
+	let flags: Vec<&str> = [(disk.read_only, "ro"), (disk.removable, "removable")]
+		.into_iter()
+		.filter(|(set, _)| *set)
+		.map(|(_, flag)| flag)
+		.collect();
+	if flags.is_empty() {
+		String::from("-")
+	} else {
+		flags.join(",")
+	}
+}
+
 fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
 	disable_raw_mode()?;
 	execute!(
@@ -1649,3 +3359,131 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Re
 	terminal.show_cursor()?;
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn type_ahead_prefers_prefix_over_substring() {
+		let mut find = TypeAhead::default();
+		find.start();
+		let names = ["notes.txt", "Photos", "photo-backup"];
+		find.push('p');
+		assert_eq!(find.find(names.iter().copied()), Some(1));
+		find.push('h');
+		find.push('o');
+		find.push('t');
+		find.push('o');
+		find.push('-');
+		assert_eq!(find.find(names.iter().copied()), Some(2));
+		find.clear();
+		find.push('e');
+		find.push('s');
+		assert_eq!(find.find(names.iter().copied()), Some(0));
+		find.push('z');
+		assert_eq!(find.find(names.iter().copied()), None);
+		assert!(find.is_active());
+		find.clear();
+		assert!(!find.is_active());
+	}
+
+	#[test]
+	fn peers_view_scrolls_to_keep_selection_visible() {
+		let mut view = PeersView::new();
+		view.set_peers(
+			(0..20)
+				.map(|i| PeerRow {
+					id: format!("peer-{i:02}"),
+					name: None,
+					address: String::new(),
+					status: String::from("discovered"),
+				})
+				.collect(),
+		);
+		view.set_viewport(5);
+		for _ in 0..7 {
+			view.next();
+		}
+		assert_eq!((view.selected, view.scroll), (7, 3));
+		view.previous();
+		assert_eq!(view.scroll, 3);
+		view.selected = 0;
+		view.previous();
+		assert_eq!((view.selected, view.scroll), (19, 15));
+		view.set_peers(Vec::new());
+		assert_eq!((view.selected, view.scroll), (0, 0));
+	}
+
+	#[test]
+	fn disk_view_keeps_selection_in_range_after_refresh() {
+		let disk = |mount: &str| DiskInfo {
+			name: String::from("sda"),
+			mount_path: mount.to_string(),
+			filesystem: String::from("ext4"),
+			total_space: 100,
+			available_space: 40,
+			usage_percent: 60.0,
+			total_read_bytes: 0,
+			total_written_bytes: 0,
+			read_only: false,
+			removable: false,
+			kind: String::from("SSD"),
+		};
+		let mut view = PeerDiskView::new(
+			String::from("peer"),
+			(0..6).map(|i| disk(&format!("/mnt/{i}"))).collect(),
+		);
+		view.set_viewport(2);
+		for _ in 0..10 {
+			view.next();
+		}
+		assert_eq!((view.selected, view.scroll), (5, 4));
+		view.replace_disks(vec![disk("/"), disk("/home")]);
+		assert_eq!((view.selected, view.scroll), (1, 0));
+		view.replace_disks(Vec::new());
+		assert_eq!((view.selected, view.scroll), (0, 0));
+		let mut flagged = disk("/media/usb");
+		assert_eq!(disk_flags(&flagged), "-");
+		flagged.read_only = true;
+		flagged.removable = true;
+		assert_eq!(disk_flags(&flagged), "ro,removable");
+	}
+
+	#[test]
+	fn tail_view_joins_split_lines_and_notices_truncation() {
+		let browser = FileBrowserView::new(String::from("peer"), String::from("/"), Vec::new());
+		let mut view = TailView::new(browser, String::from("/var/log/app.log"));
+		view.push(0, b"first\nsec");
+		view.push(10, b"ond\r\n");
+		assert_eq!(view.lines, ["first", "second"]);
+		assert_eq!(view.offset, Some(15));
+		view.push(0, b"fresh\n");
+		assert_eq!(
+			view.lines,
+			["first", "second", "--- file truncated ---", "fresh"]
+		);
+	}
+
+	#[test]
+	fn inbox_lists_the_newest_message_first() {
+		let from = PeerId::random();
+		let messages: VecDeque<PeerMessage> = ["first", "second", "third"]
+			.into_iter()
+			.map(|text| PeerMessage {
+				from,
+				text: text.to_string(),
+				received_at: chrono::Utc::now(),
+			})
+			.collect();
+		let mut view = InboxView::default();
+		let selected = |view: &InboxView| view.selected_message(&messages).unwrap().text.clone();
+		assert_eq!(selected(&view), "third");
+		view.next(messages.len());
+		view.next(messages.len());
+		view.next(messages.len());
+		assert_eq!(selected(&view), "first");
+		view.previous();
+		assert_eq!(selected(&view), "second");
+	}
+}
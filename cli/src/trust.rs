@@ -0,0 +1,147 @@
+use anyhow::{Context, bail};
+use puppypeer_core::puppy_home;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::updater::verify_signature;
+
+/// Where the last verified binary is recorded.
+pub fn trust_file_path() -> PathBuf {
+	puppy_home().join("trust.json")
+}
+
+/// Identity of a binary that passed signature verification.
+#[derive(Debug, PartialEq, Eq)]
+struct TrustRecord {
+	path: String,
+	size: u64,
+	modified_nanos: u128,
+	sha256: String,
+}
+
+impl TrustRecord {
+	fn load(trust_path: &Path) -> Option<Self> {
+		let raw = std::fs::read_to_string(trust_path).ok()?;
+		let value: Value = serde_json::from_str(&raw).ok()?;
+		Some(Self {
+			path: value["path"].as_str()?.to_string(),
+			size: value["size"].as_u64()?,
+			modified_nanos: value["modified_nanos"].as_str()?.parse().ok()?,
+			sha256: value["sha256"].as_str()?.to_string(),
+		})
+	}
+
+	fn save(&self, trust_path: &Path) -> anyhow::Result<()> {
+		if let Some(parent) = trust_path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		let value = json!({
+			"path": self.path,
+			"size": self.size,
+			// Nanoseconds overflow JSON numbers, so keep them as a string
+			"modified_nanos": self.modified_nanos.to_string(),
+			"sha256": self.sha256,
+		});
+		std::fs::write(trust_path, serde_json::to_string_pretty(&value)?)
+			.with_context(|| format!("failed to write {}", trust_path.display()))
+	}
+}
+
+fn file_stamp(bin: &Path) -> anyhow::Result<(String, u64, u128)> {
+	let meta = std::fs::metadata(bin)
+		.with_context(|| format!("failed to read metadata of {}", bin.display()))?;
+	let modified_nanos = meta
+		.modified()?
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_nanos();
+	Ok((bin.display().to_string(), meta.len(), modified_nanos))
+}
+
+fn file_sha256(bin: &Path) -> anyhow::Result<String> {
+	let data = std::fs::read(bin).with_context(|| format!("failed to read {}", bin.display()))?;
+	Ok(Sha256::digest(&data)
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect())
+}
+
+/// Remember `bin` as verified so later launches can skip the signature check.
+pub fn record_trusted(bin: &Path, trust_path: &Path) -> anyhow::Result<()> {
+	let (path, size, modified_nanos) = file_stamp(bin)?;
+	TrustRecord {
+		path,
+		size,
+		modified_nanos,
+		sha256: file_sha256(bin)?,
+	}
+	.save(trust_path)
+}
+
+/// Verify `bin` against `sig` unless it is unchanged since the last verification.
+///
+/// The binary is always hashed, as size and modification time are easy to fake, and only
+/// a hash that differs from the recorded one triggers the full RSA verification.
+pub fn verify_binary(bin: &Path, sig: &Path, trust_path: &Path) -> anyhow::Result<()> {
+	let (path, size, modified_nanos) = file_stamp(bin)?;
+	let record = TrustRecord::load(trust_path).filter(|record| record.path == path);
+	let sha256 = file_sha256(bin)?;
+	let known = record.is_some_and(|record| record.sha256 == sha256);
+	if known {
+		log::debug!("{} unchanged since last verification", path);
+	} else {
+		if !sig.exists() {
+			bail!("{} changed and has no signature at {}", path, sig.display());
+		}
+		if !verify_signature(bin, sig)? {
+			bail!("signature verification failed for {}", path);
+		}
+		log::info!("verified signature of {}", path);
+	}
+	TrustRecord {
+		path,
+		size,
+		modified_nanos,
+		sha256,
+	}
+	.save(trust_path)
+}
+
+/// Verify the running executable against the `.sig` file next to it.
+pub fn verify_current_exe() -> anyhow::Result<()> {
+	let exe = std::env::current_exe()?;
+	verify_binary(&exe, &exe.with_extension("sig"), &trust_file_path())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recorded_binary_is_trusted_until_it_changes() {
+		let dir = std::env::temp_dir().join(format!("puppypeer-trust-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let bin = dir.join("puppypeer");
+		let sig = dir.join("puppypeer.sig");
+		let trust_path = dir.join("trust.json");
+		std::fs::write(&bin, b"binary v1").unwrap();
+
+		assert!(verify_binary(&bin, &sig, &trust_path).is_err());
+		record_trusted(&bin, &trust_path).unwrap();
+		assert!(verify_binary(&bin, &sig, &trust_path).is_ok());
+
+		// same size and a restored mtime, as `touch -r` would leave it
+		let modified = std::fs::metadata(&bin).unwrap().modified().unwrap();
+		std::fs::write(&bin, b"binary v9").unwrap();
+		let file = std::fs::File::options().write(true).open(&bin).unwrap();
+		file.set_modified(modified).unwrap();
+		assert!(verify_binary(&bin, &sig, &trust_path).is_err());
+
+		std::fs::write(&bin, b"binary v2, tampered").unwrap();
+		assert!(verify_binary(&bin, &sig, &trust_path).is_err());
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+}
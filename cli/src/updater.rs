@@ -1,32 +1,144 @@
 use std::{
 	io::BufReader,
 	path::{Path, PathBuf},
+	time::Duration,
 };
 
 use anyhow::bail;
+use chrono::{DateTime, Utc};
 use flate2::read::GzDecoder;
+use puppypeer_core::PuppyConfig;
+use reqwest::{Client, Response, StatusCode, header::HeaderMap};
 use rsa::signature::Verifier;
 use rsa::{RsaPublicKey, pkcs1v15, pkcs8::DecodePublicKey};
+use serde::Serialize;
 use serde_json::Value;
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
 use tar::Archive;
 use tokio::{fs::File, io::AsyncWriteExt};
 
-use crate::utility::get_version;
+use crate::utility::{VERSION_DISPLAY, build_version, is_newer_release};
 
 // Path resolution: this file is cli/src/updater.rs; the key lives at repository root.
 pub const PUBLIC_KEY: &str = include_str!("../../public_key.pem");
 
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Maximum gap between received bytes; downloads may take longer than this overall
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Where releases come from when neither `--source`, the env var nor the config set one.
+pub const DEFAULT_UPDATE_SOURCE: &str = "github:j45k4/puppypeer";
+pub const UPDATE_SOURCE_ENV: &str = "PUPPYPEER_UPDATE_SOURCE";
+const GITHUB_API: &str = "https://api.github.com";
+
 pub fn verify_signature(bin: &Path, sig: &Path) -> anyhow::Result<bool> {
 	log::info!("verifying {} with {}", bin.display(), sig.display());
-	let public_key = RsaPublicKey::from_public_key_pem(PUBLIC_KEY).unwrap();
-	let verifying_key = pkcs1v15::VerifyingKey::<Sha256>::new(public_key);
 	let signature = std::fs::read(sig)?;
-	let signature = rsa::pkcs1v15::Signature::try_from(signature.as_slice())?;
 	let data = std::fs::read(bin)?;
+	verify_signature_bytes(&data, &signature)
+}
+
+fn verify_signature_bytes(data: &[u8], signature: &[u8]) -> anyhow::Result<bool> {
 	let public_key = RsaPublicKey::from_public_key_pem(PUBLIC_KEY).unwrap();
 	let verifying_key = pkcs1v15::VerifyingKey::<Sha256>::new(public_key);
-	Ok(verifying_key.verify(&data, &signature).is_ok())
+	let signature = rsa::pkcs1v15::Signature::try_from(signature)?;
+	Ok(verifying_key.verify(data, &signature).is_ok())
+}
+
+/// Check `data` against a `.sha256` file body (`<hex digest> [file name]`).
+fn verify_sha256(data: &[u8], checksum: &str) -> anyhow::Result<()> {
+	let expected = checksum
+		.split_whitespace()
+		.next()
+		.ok_or_else(|| anyhow::anyhow!("empty checksum file"))?;
+	let actual: String = Sha256::digest(data)
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect();
+	if !actual.eq_ignore_ascii_case(expected) {
+		bail!("checksum mismatch: expected {}, got {}", expected, actual);
+	}
+	Ok(())
+}
+
+/// Where `puppypeer update` looks for releases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ReleaseSource {
+	/// A GitHub compatible releases API, e.g. `github:owner/repo` or
+	/// `github:https://ghe.example.com/api/v3/owner/repo`.
+	GitHub { api: String, repo: String },
+	/// An HTTP(S) URL or local directory holding `puppypeer-<os>-<arch>.tar.gz` with its
+	/// `.sha256` and `.sig`, plus an optional `latest` file naming the release tag.
+	/// Pinned versions live in a `<tag>/` subdirectory.
+	Mirror { base: String },
+}
+
+impl ReleaseSource {
+	fn parse(value: &str) -> anyhow::Result<Self> {
+		let value = value.trim();
+		if let Some(rest) = value.strip_prefix("github:") {
+			let rest = rest.trim_end_matches('/');
+			let (prefix, repo_name) = rest
+				.rsplit_once('/')
+				.ok_or_else(|| anyhow::anyhow!("expected github:<owner>/<repo>, got {}", value))?;
+			let (api, owner) = match prefix.rsplit_once('/') {
+				Some((api, owner)) => (api.to_string(), owner),
+				None => (GITHUB_API.to_string(), prefix),
+			};
+			if owner.is_empty() || repo_name.is_empty() {
+				bail!("expected github:<owner>/<repo>, got {}", value);
+			}
+			return Ok(ReleaseSource::GitHub {
+				api,
+				repo: format!("{}/{}", owner, repo_name),
+			});
+		}
+		if value.is_empty() {
+			bail!("empty update source");
+		}
+		Ok(ReleaseSource::Mirror {
+			base: value.trim_end_matches('/').to_string(),
+		})
+	}
+
+	/// Pick the source from `--source`, then `PUPPYPEER_UPDATE_SOURCE`, then the config.
+	fn resolve(flag: Option<&str>) -> anyhow::Result<Self> {
+		let configured = flag
+			.map(str::to_string)
+			.or_else(|| std::env::var(UPDATE_SOURCE_ENV).ok())
+			.or_else(|| PuppyConfig::load().update_source)
+			.unwrap_or_else(|| DEFAULT_UPDATE_SOURCE.to_string());
+		Self::parse(&configured)
+	}
+}
+
+impl std::fmt::Display for ReleaseSource {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ReleaseSource::GitHub { api, repo } => write!(f, "{} ({})", repo, api),
+			ReleaseSource::Mirror { base } => write!(f, "{}", base),
+		}
+	}
+}
+
+/// A release archive to install, resolved from either source kind.
+struct Release {
+	/// `None` when a mirror does not publish a `latest` file.
+	tag: Option<String>,
+	archive_name: String,
+	archive_url: String,
+	sha256_url: Option<String>,
+	sig_url: Option<String>,
+}
+
+fn mirror_archive_name() -> String {
+	format!(
+		"puppypeer-{}-{}.tar.gz",
+		std::env::consts::OS,
+		std::env::consts::ARCH
+	)
 }
 
 fn get_os_name() -> String {
@@ -51,67 +163,107 @@ fn bin_dir() -> PathBuf {
 	path
 }
 
-async fn fetch_release(version: Option<&str>) -> anyhow::Result<Value> {
-	let client = reqwest::Client::new();
-	let url = match version {
-		Some(tag) => format!(
-			"https://api.github.com/repos/j45k4/puppypeer/releases/tags/{}",
-			tag
+fn http_client() -> anyhow::Result<Client> {
+	Ok(Client::builder()
+		.user_agent("puppypeer")
+		.connect_timeout(CONNECT_TIMEOUT)
+		.read_timeout(READ_TIMEOUT)
+		.build()?)
+}
+
+/// Explain a GitHub rate limit rejection (403/429 with no requests remaining), if it is one.
+fn rate_limit_message(status: StatusCode, headers: &HeaderMap) -> Option<String> {
+	if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+		return None;
+	}
+	let remaining = headers.get("x-ratelimit-remaining")?.to_str().ok()?;
+	if remaining.trim() != "0" {
+		return None;
+	}
+	let reset = headers
+		.get("x-ratelimit-reset")
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.trim().parse::<i64>().ok())
+		.and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0));
+	Some(match reset {
+		Some(reset) => format!(
+			"GitHub API rate limit exceeded; it resets at {} (in {} minutes)",
+			reset.format("%Y-%m-%d %H:%M:%S UTC"),
+			(reset - Utc::now()).num_minutes().max(0) + 1
 		),
-		None => "https://api.github.com/repos/j45k4/puppypeer/releases/latest".to_string(),
-	};
-	let res = client
-		.get(url)
-		.header("User-Agent", "puppypeer")
-		.send()
-		.await?
-		.error_for_status()?;
-	let body = res.text().await?;
+		None => String::from("GitHub API rate limit exceeded; try again later"),
+	})
+}
 
-	Ok(serde_json::from_str::<Value>(&body)?)
+/// GET `url`, retrying connection failures, timeouts and 5xx responses with backoff.
+async fn get_with_retry(client: &Client, url: &str) -> anyhow::Result<Response> {
+	let mut backoff = RETRY_BACKOFF;
+	let mut attempt = 1;
+	loop {
+		match client.get(url).send().await {
+			Ok(res) if res.status().is_success() => return Ok(res),
+			Ok(res) => {
+				if let Some(message) = rate_limit_message(res.status(), res.headers()) {
+					bail!(message);
+				}
+				if !res.status().is_server_error() || attempt >= MAX_ATTEMPTS {
+					bail!("request to {} failed: HTTP {}", url, res.status());
+				}
+				log::warn!(
+					"request to {} failed with HTTP {} (attempt {}/{})",
+					url,
+					res.status(),
+					attempt,
+					MAX_ATTEMPTS
+				);
+			}
+			Err(err) if attempt < MAX_ATTEMPTS && (err.is_timeout() || err.is_connect()) => {
+				log::warn!(
+					"request to {} failed: {} (attempt {}/{})",
+					url,
+					err,
+					attempt,
+					MAX_ATTEMPTS
+				);
+			}
+			Err(err) => return Err(err.into()),
+		}
+		tokio::time::sleep(backoff).await;
+		backoff *= 2;
+		attempt += 1;
+	}
 }
 
-async fn dowload_bin(url: &str, filename: &str) -> anyhow::Result<PathBuf> {
-	let res = reqwest::get(url).await?;
-	if !res.status().is_success() {
-		bail!("Failed to download asset. HTTP status: {}", res.status());
+/// Fetch `location`, which is either an HTTP(S) URL or a local path.
+async fn fetch_bytes(client: &Client, location: &str) -> anyhow::Result<Vec<u8>> {
+	if location.starts_with("http://") || location.starts_with("https://") {
+		let res = get_with_retry(client, location).await?;
+		return Ok(res.bytes().await?.to_vec());
 	}
-	let bytes = res.bytes().await?;
-	let path = app_dir().join(&filename);
-	let mut file = File::create(&path).await?;
-	file.write_all(&bytes).await?;
-	Ok(path)
+	let path = location.strip_prefix("file://").unwrap_or(location);
+	tokio::fs::read(path)
+		.await
+		.map_err(|err| anyhow::anyhow!("failed to read {}: {}", path, err))
 }
 
-pub async fn update(version: Option<&str>) -> anyhow::Result<()> {
-	let res = fetch_release(version).await?;
+async fn fetch_github_release(
+	client: &Client,
+	api: &str,
+	repo: &str,
+	version: Option<&str>,
+) -> anyhow::Result<Release> {
+	let url = match version {
+		Some(tag) => format!("{}/repos/{}/releases/tags/{}", api, repo, tag),
+		None => format!("{}/repos/{}/releases/latest", api, repo),
+	};
+	let res = get_with_retry(client, &url).await?;
+	let body = res.text().await?;
+	let res = serde_json::from_str::<Value>(&body)?;
+
 	let tag = match res["tag_name"].as_str() {
-		Some(tag) => tag,
+		Some(tag) => tag.to_string(),
 		None => bail!("release response missing tag_name"),
 	};
-	let current = get_version();
-
-	if let Some(requested_tag) = version {
-		log::info!("requested tag: {}", requested_tag);
-	}
-	log::info!("current: {}", current);
-	log::info!("release tag: {}", tag);
-
-	if version.is_none() {
-		if let Ok(tag_number) = tag.parse::<u32>() {
-			log::info!("latest numeric tag: {}", tag_number);
-			if tag_number <= current {
-				log::info!("Already up to date");
-				return Ok(());
-			}
-		} else {
-			log::info!(
-				"latest release tag {} is not numeric; skipping automatic version comparison",
-				tag
-			);
-		}
-	}
-
 	let assets = match res["assets"] {
 		Value::Array(ref assets) => assets,
 		_ => bail!("no assets found"),
@@ -129,21 +281,127 @@ pub async fn update(version: Option<&str>) -> anyhow::Result<()> {
 		None => bail!("no asset found for os: {}", os_name),
 	};
 
-	let download_url = asset["browser_download_url"]
+	let archive_url = asset["browser_download_url"]
 		.as_str()
-		.ok_or_else(|| anyhow::anyhow!("no download url found"))?;
-
-	log::info!("download_url: {}", download_url);
+		.ok_or_else(|| anyhow::anyhow!("no download url found"))?
+		.to_string();
 
 	// Attempt to derive a local filename from the asset name
-	let filename = asset["name"]
+	let archive_name = asset["name"]
 		.as_str()
 		.map(|s| s.to_string())
 		.unwrap_or_else(|| "downloaded_binary".to_string());
 
-	log::info!("Downloading asset: {}", filename);
+	Ok(Release {
+		tag: Some(tag),
+		archive_name,
+		archive_url,
+		sha256_url: None,
+		sig_url: None,
+	})
+}
+
+async fn fetch_mirror_release(
+	client: &Client,
+	base: &str,
+	version: Option<&str>,
+) -> anyhow::Result<Release> {
+	let (dir, tag) = match version {
+		Some(tag) => (format!("{}/{}", base, tag), Some(tag.to_string())),
+		None => {
+			let tag = match fetch_bytes(client, &format!("{}/latest", base)).await {
+				Ok(bytes) => Some(String::from_utf8_lossy(&bytes).trim().to_string()),
+				Err(err) => {
+					log::info!("mirror has no readable latest file: {err}");
+					None
+				}
+			};
+			(base.to_string(), tag.filter(|tag| !tag.is_empty()))
+		}
+	};
+	let archive_name = mirror_archive_name();
+	let archive_url = format!("{}/{}", dir, archive_name);
+	Ok(Release {
+		tag,
+		sha256_url: Some(format!("{}.sha256", archive_url)),
+		sig_url: Some(format!("{}.sig", archive_url)),
+		archive_name,
+		archive_url,
+	})
+}
+
+/// Output of `puppypeer update --json`.
+#[derive(Debug, Serialize)]
+pub struct UpdateOutput {
+	/// Build version before the update; 0 for untagged builds.
+	pub current: u32,
+	/// Tag of the release found at the source, when it publishes one.
+	pub release: Option<String>,
+	/// A new binary was installed; false when already up to date.
+	pub updated: bool,
+}
+
+pub async fn update(version: Option<&str>, source: Option<&str>) -> anyhow::Result<UpdateOutput> {
+	let source = ReleaseSource::resolve(source)?;
+	log::info!("update source: {}", source);
+	let client = http_client()?;
+	let release = match &source {
+		ReleaseSource::GitHub { api, repo } => {
+			fetch_github_release(&client, api, repo, version).await?
+		}
+		ReleaseSource::Mirror { base } => fetch_mirror_release(&client, base, version).await?,
+	};
+	let current = build_version();
 
-	let path = dowload_bin(download_url, &filename).await?;
+	if let Some(requested_tag) = version {
+		log::info!("requested tag: {}", requested_tag);
+	}
+	log::info!("current: {} ({})", current, VERSION_DISPLAY);
+
+	match (&release.tag, version) {
+		(_, Some(_)) => {}
+		(Some(tag), None) => {
+			log::info!("release tag: {}", tag);
+			match is_newer_release(tag) {
+				Some(false) => {
+					log::info!("Already up to date");
+					return Ok(UpdateOutput {
+						current,
+						release: release.tag.clone(),
+						updated: false,
+					});
+				}
+				Some(true) => log::info!("newer release {} available", tag),
+				None => log::info!(
+					"latest release tag {} is not numeric; skipping automatic version comparison",
+					tag
+				),
+			}
+		}
+		(None, None) => {
+			log::info!("source does not publish a release tag; skipping version comparison")
+		}
+	}
+
+	log::info!("download_url: {}", release.archive_url);
+	log::info!("Downloading asset: {}", release.archive_name);
+
+	let archive = fetch_bytes(&client, &release.archive_url).await?;
+	if let Some(sha256_url) = &release.sha256_url {
+		let checksum = fetch_bytes(&client, sha256_url).await?;
+		verify_sha256(&archive, &String::from_utf8_lossy(&checksum))?;
+		log::info!("checksum verified");
+	}
+	if let Some(sig_url) = &release.sig_url {
+		let signature = fetch_bytes(&client, sig_url).await?;
+		if !verify_signature_bytes(&archive, &signature)? {
+			bail!("Archive signature verification failed");
+		}
+		log::info!("archive signature verified");
+	}
+	let path = app_dir().join(&release.archive_name);
+	let mut file = File::create(&path).await?;
+	file.write_all(&archive).await?;
 
 	log::info!("Downloaded asset to: {:?}", path);
 
@@ -168,8 +426,69 @@ pub async fn update(version: Option<&str>) -> anyhow::Result<()> {
 	if !verify_signature(&bin_path, &sig_path)? {
 		bail!("Signature verification failed");
 	}
-	tokio::fs::copy(&bin_path, bin_dir().join("puppypeer")).await?;
+	let installed = bin_dir().join("puppypeer");
+	tokio::fs::copy(&bin_path, &installed).await?;
+	// Keep the signature next to the binary for --verify-on-start
+	tokio::fs::copy(&sig_path, installed.with_extension("sig")).await?;
 	tokio::fs::remove_file(&bin_path).await?;
 	tokio::fs::remove_file(&sig_path).await?;
-	Ok(())
+	if let Err(err) = crate::trust::record_trusted(&installed, &crate::trust::trust_file_path()) {
+		log::warn!("failed to record verified binary: {err:#}");
+	}
+	Ok(UpdateOutput {
+		current,
+		release: release.tag,
+		updated: true,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use reqwest::header::HeaderValue;
+
+	#[test]
+	fn rate_limit_is_reported_only_when_exhausted() {
+		let mut headers = HeaderMap::new();
+		headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+		headers.insert("x-ratelimit-reset", HeaderValue::from_static("1700000000"));
+		let message = rate_limit_message(StatusCode::FORBIDDEN, &headers).unwrap();
+		assert!(message.contains("2023-11-14 22:13:20 UTC"), "{message}");
+
+		assert!(rate_limit_message(StatusCode::NOT_FOUND, &headers).is_none());
+		headers.insert("x-ratelimit-remaining", HeaderValue::from_static("12"));
+		assert!(rate_limit_message(StatusCode::FORBIDDEN, &headers).is_none());
+	}
+
+	#[test]
+	fn release_sources_are_parsed() {
+		assert_eq!(
+			ReleaseSource::parse(DEFAULT_UPDATE_SOURCE).unwrap(),
+			ReleaseSource::GitHub {
+				api: GITHUB_API.to_string(),
+				repo: String::from("j45k4/puppypeer"),
+			}
+		);
+		assert_eq!(
+			ReleaseSource::parse("github:https://ghe.example.com/api/v3/acme/puppypeer").unwrap(),
+			ReleaseSource::GitHub {
+				api: String::from("https://ghe.example.com/api/v3"),
+				repo: String::from("acme/puppypeer"),
+			}
+		);
+		assert_eq!(
+			ReleaseSource::parse("https://mirror.example.com/puppypeer/").unwrap(),
+			ReleaseSource::Mirror {
+				base: String::from("https://mirror.example.com/puppypeer"),
+			}
+		);
+		assert!(ReleaseSource::parse("github:puppypeer").is_err());
+	}
+
+	#[test]
+	fn sha256_checksum_files_are_checked() {
+		let checksum = "d5b7f828235a92d3d280fa08f3ddb9e5b6947123b44091c92db7594aa1408614  a.tar.gz";
+		assert!(verify_sha256(b"new data", checksum).is_ok());
+		assert!(verify_sha256(b"other data", checksum).is_err());
+	}
 }
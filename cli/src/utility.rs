@@ -1,10 +1,71 @@
-pub fn get_version() -> u32 {
-	match option_env!("VERSION") {
-		Some(ver) => ver.parse().unwrap_or(0),
-		None => 0,
+use puppypeer_core::p2p::LoadAverage;
+
+/// Release label passed in through the `VERSION` env var at build time (the git tag in CI).
+pub const VERSION_LABEL: &str = match option_env!("VERSION") {
+	Some(label) => label,
+	None => "dev",
+};
+
+/// Human readable version, e.g. `42 (1a2b3c4, built 2025-03-01)`.
+pub const VERSION_DISPLAY: &str = env!("PUPPYPEER_VERSION_DISPLAY");
+
+/// Parse a release tag such as `42` or `v42` into its build number.
+pub fn parse_version(tag: &str) -> Option<u32> {
+	let tag = tag.trim();
+	tag.strip_prefix('v').unwrap_or(tag).parse().ok()
+}
+
+/// Numeric build version used for update checks; 0 for untagged builds.
+pub fn build_version() -> u32 {
+	parse_version(VERSION_LABEL).unwrap_or(0)
+}
+
+/// Whether `tag` names a newer release than this build, or `None` when it isn't numeric.
+pub fn is_newer_release(tag: &str) -> Option<bool> {
+	parse_version(tag).map(|release| release > build_version())
+}
+
+/// Print `value` as pretty JSON on stdout, the output of every command run with `--json`.
+pub fn print_json<T: serde::Serialize>(value: &T) -> anyhow::Result<()> {
+	println!("{}", serde_json::to_string_pretty(value)?);
+	Ok(())
+}
+
+/// Shorten a peer id to `12D3KooW…a1b2c3` for display.
+pub fn abbreviate_peer_id(id: &str) -> String {
+	const PREFIX: usize = 8;
+	const SUFFIX: usize = 6;
+	if id.len() <= PREFIX + SUFFIX + 1 {
+		id.to_string()
+	} else {
+		format!("{}…{}", &id[..PREFIX], &id[id.len() - SUFFIX..])
+	}
+}
+
+/// Local nickname followed by the abbreviated id, or just the full id when unnamed.
+pub fn peer_label(name: Option<&str>, id: &str) -> String {
+	match name {
+		Some(name) => format!("{} ({})", name, abbreviate_peer_id(id)),
+		None => id.to_string(),
 	}
 }
 
-pub fn get_version_label() -> Option<&'static str> {
-	option_env!("VERSION")
+/// Uptime as `3d 4h 12m`, leaving out leading zero units.
+pub fn format_uptime(secs: u64) -> String {
+	let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+	if days > 0 {
+		format!("{days}d {hours}h {minutes}m")
+	} else if hours > 0 {
+		format!("{hours}h {minutes}m")
+	} else {
+		format!("{minutes}m")
+	}
+}
+
+/// 1, 5 and 15 minute load averages, or `n/a` where the peer has none.
+pub fn format_load(load: Option<&LoadAverage>) -> String {
+	match load {
+		Some(load) => format!("{:.2} {:.2} {:.2}", load.one, load.five, load.fifteen),
+		None => String::from("n/a"),
+	}
 }
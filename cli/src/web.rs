@@ -0,0 +1,664 @@
+//! Browser access to a running node: a small JSON API and a single-page frontend.
+//!
+//! Every `/api` route needs the access token, sent as `Authorization: Bearer <token>` or,
+//! for `EventSource` which cannot set headers, as a `token` query parameter. Pages from
+//! other origins can only call the API when allowed with `--cors-origin`.
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::body::Body;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, header};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Json, Response};
+use axum::routing::{get, put};
+use futures::executor::block_on;
+use futures::{Stream, StreamExt};
+use libp2p::PeerId;
+use puppypeer_core::p2p::{DirEntry, MAX_CATALOG_PAGE};
+use puppypeer_core::scan::Sha256Hasher;
+use puppypeer_core::{CatalogPage, PeerSummary, PuppyPeer};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::fsck::{DATA_PREFIX, store_name};
+
+/// Largest file chunk one `/file` request returns.
+const MAX_READ_BYTES: u64 = 4 * 1024 * 1024;
+const DEFAULT_CATALOG_LIMIT: u64 = 200;
+const INDEX_HTML: &str = include_str!("web.html");
+const X_EOF: HeaderName = HeaderName::from_static("x-eof");
+
+/// How `run` serves the web UI besides its address.
+pub struct WebOptions<'a> {
+	/// Content store taking uploads; uploads are refused without one.
+	pub store_dir: Option<&'a str>,
+	/// Origins whose pages may call the API, or `*` for any. Empty allows none.
+	pub cors_origins: &'a [String],
+	/// Largest upload in bytes.
+	pub max_upload: u64,
+}
+
+#[derive(Clone)]
+struct WebState {
+	peer: Arc<PuppyPeer>,
+	token: Arc<str>,
+	/// Content store taking uploads; uploads are refused without one.
+	store_dir: Option<Arc<std::path::Path>>,
+	max_upload: u64,
+}
+
+/// Failures shown to the browser as a status code and a plain text reason.
+#[derive(Debug)]
+struct WebError(StatusCode, String);
+
+impl IntoResponse for WebError {
+	fn into_response(self) -> Response {
+		(self.0, self.1).into_response()
+	}
+}
+
+impl From<anyhow::Error> for WebError {
+	fn from(err: anyhow::Error) -> Self {
+		WebError(StatusCode::BAD_GATEWAY, format!("{err:#}"))
+	}
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+	token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PathQuery {
+	path: String,
+}
+
+#[derive(Deserialize)]
+struct ReadQuery {
+	path: String,
+	#[serde(default)]
+	offset: u64,
+	length: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+	#[serde(default)]
+	q: String,
+	mime: Option<String>,
+}
+
+/// A file from this node's index, with its hash in hex.
+#[derive(Serialize)]
+struct SearchHit {
+	hash: String,
+	size: i64,
+	mime_type: Option<String>,
+	first_seen: String,
+	last_seen: String,
+}
+
+#[derive(Serialize)]
+struct SearchResults {
+	files: Vec<SearchHit>,
+	/// Every indexed mime type, to offer as filters.
+	mime_types: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CatalogQuery {
+	#[serde(default)]
+	prefix: String,
+	#[serde(default)]
+	offset: u64,
+	limit: Option<u64>,
+}
+
+/// Compare without returning early, so response times do not leak how much of a guess
+/// was right.
+fn token_matches(given: &str, token: &str) -> bool {
+	given.len() == token.len()
+		&& given
+			.bytes()
+			.zip(token.bytes())
+			.fold(0u8, |diff, (a, b)| diff | (a ^ b))
+			== 0
+}
+
+fn authorized(headers: &HeaderMap, uri: &Uri, token: &str) -> bool {
+	let from_header = headers
+		.get(header::AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+		.map(str::to_string);
+	let from_query = || Query::<TokenQuery>::try_from_uri(uri).ok()?.0.token;
+	from_header
+		.or_else(from_query)
+		.is_some_and(|given| token_matches(&given, token))
+}
+
+async fn require_token(State(state): State<WebState>, request: Request, next: Next) -> Response {
+	if !authorized(request.headers(), request.uri(), &state.token) {
+		return (StatusCode::UNAUTHORIZED, "missing or wrong access token").into_response();
+	}
+	next.run(request).await
+}
+
+/// Find a peer by id or nickname.
+fn resolve_peer(peer: &PuppyPeer, target: &str) -> Result<PeerId, WebError> {
+	if let Ok(id) = PeerId::from_str(target) {
+		return Ok(id);
+	}
+	let state = peer.state();
+	let state = state.lock().unwrap();
+	state
+		.peers
+		.iter()
+		.find(|p| p.name.as_deref() == Some(target))
+		.map(|p| p.id)
+		.ok_or_else(|| WebError(StatusCode::NOT_FOUND, format!("unknown peer {target}")))
+}
+
+async fn index() -> Html<&'static str> {
+	Html(INDEX_HTML)
+}
+
+async fn peers(State(state): State<WebState>) -> Result<Json<Vec<PeerSummary>>, WebError> {
+	Ok(Json(state.peer.export_peers().await?))
+}
+
+async fn list_dir(
+	State(state): State<WebState>,
+	Path(target): Path<String>,
+	Query(query): Query<PathQuery>,
+) -> Result<Json<Vec<DirEntry>>, WebError> {
+	let id = resolve_peer(&state.peer, &target)?;
+	Ok(Json(state.peer.list_dir(id, query.path).await?))
+}
+
+/// Raw bytes of one chunk; `X-Eof` tells whether the file ends there.
+async fn read_file(
+	State(state): State<WebState>,
+	Path(target): Path<String>,
+	Query(query): Query<ReadQuery>,
+) -> Result<Response, WebError> {
+	let id = resolve_peer(&state.peer, &target)?;
+	let length = query.length.unwrap_or(MAX_READ_BYTES).min(MAX_READ_BYTES);
+	let chunk = state
+		.peer
+		.read_file(id, query.path, query.offset, Some(length))
+		.await?;
+	Ok((
+		[
+			(header::CONTENT_TYPE, "application/octet-stream"),
+			(X_EOF, if chunk.eof { "true" } else { "false" }),
+		],
+		chunk.data,
+	)
+		.into_response())
+}
+
+async fn catalog(
+	State(state): State<WebState>,
+	Path(target): Path<String>,
+	Query(query): Query<CatalogQuery>,
+) -> Result<Json<CatalogPage>, WebError> {
+	let id = resolve_peer(&state.peer, &target)?;
+	// the peer refuses larger pages, so ask for the most it serves instead
+	let limit = query
+		.limit
+		.unwrap_or(DEFAULT_CATALOG_LIMIT)
+		.min(MAX_CATALOG_PAGE);
+	Ok(Json(
+		state
+			.peer
+			.browse_catalog(id, query.prefix, query.offset, limit)
+			.await?,
+	))
+}
+
+/// Files in this node's index whose hash or mime type contains `q`, newest first.
+async fn search(
+	State(state): State<WebState>,
+	Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResults>, WebError> {
+	let peer = state.peer.clone();
+	// SQLite blocks, so keep it off the async workers
+	let (files, mime_types) = tokio::task::spawn_blocking(move || {
+		block_on(peer.search_files(&query.q, query.mime.as_deref(), true))
+	})
+	.await
+	.context("search task failed")??;
+	let files = files
+		.into_iter()
+		.map(|file| SearchHit {
+			hash: hex(&file.hash),
+			size: file.size,
+			mime_type: file.mime_type,
+			first_seen: file.first_datetime,
+			last_seen: file.latest_datetime,
+		})
+		.collect();
+	Ok(Json(SearchResults { files, mime_types }))
+}
+
+fn too_large(max: u64) -> WebError {
+	WebError(
+		StatusCode::PAYLOAD_TOO_LARGE,
+		format!("uploads are limited to {max} bytes"),
+	)
+}
+
+/// Write `body` to `temp` while hashing it, so the upload is read once, and return its
+/// SHA-256. Stops with 413 once more than `max` bytes arrive, since a chunked upload has
+/// no length to check up front.
+async fn write_hashed(temp: &std::path::Path, body: Body, max: u64) -> Result<[u8; 32], WebError> {
+	let mut file = tokio::fs::File::create(temp)
+		.await
+		.with_context(|| format!("cannot create {}", temp.display()))?;
+	let mut hasher = Sha256Hasher::new();
+	let mut written = 0u64;
+	let mut chunks = body.into_data_stream();
+	while let Some(chunk) = chunks.next().await {
+		let chunk = chunk.context("upload interrupted")?;
+		written += chunk.len() as u64;
+		if written > max {
+			return Err(too_large(max));
+		}
+		hasher.update(&chunk);
+		file.write_all(&chunk)
+			.await
+			.with_context(|| format!("cannot write {}", temp.display()))?;
+	}
+	file.sync_all()
+		.await
+		.with_context(|| format!("cannot write {}", temp.display()))?;
+	Ok(hasher.finish())
+}
+
+/// Zero-padded lowercase hex, the form `sha256sum` prints and `/api/search` returns.
+fn hex(hash: &[u8]) -> String {
+	hash.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Decode a SHA-256 given as 64 hex digits in either case.
+fn parse_hash(hash: &str) -> Option<[u8; 32]> {
+	if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+		return None;
+	}
+	let mut bytes = [0u8; 32];
+	for (byte, pair) in bytes.iter_mut().zip(hash.as_bytes().chunks(2)) {
+		*byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+	}
+	Some(bytes)
+}
+
+/// Store an upload whose SHA-256 is `hash`, 64 hex digits, as `epic-shelter-data-<name>`.
+/// The blob only appears under its final name once its content is known to match `hash`;
+/// a mismatch is refused with 422.
+async fn put_blob(
+	State(state): State<WebState>,
+	Path(hash): Path<String>,
+	headers: HeaderMap,
+	body: Body,
+) -> Result<StatusCode, WebError> {
+	let Some(dir) = &state.store_dir else {
+		return Err(WebError(
+			StatusCode::NOT_FOUND,
+			String::from("uploads are off; start with --store-dir"),
+		));
+	};
+	let Some(expected) = parse_hash(&hash) else {
+		return Err(WebError(
+			StatusCode::BAD_REQUEST,
+			format!("{hash} is not a SHA-256 in 64 hex digits"),
+		));
+	};
+	let length = headers
+		.get(header::CONTENT_LENGTH)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.parse::<u64>().ok());
+	if length.is_some_and(|length| length > state.max_upload) {
+		return Err(too_large(state.max_upload));
+	}
+	store_blob(dir, &expected, body, state.max_upload).await
+}
+
+/// Move an upload into `dir`, refusing it when its content does not hash to `expected`.
+/// The blob is named with `store_name`, the unpadded form `fsck` checks against.
+async fn store_blob(
+	dir: &std::path::Path,
+	expected: &[u8; 32],
+	body: Body,
+	max: u64,
+) -> Result<StatusCode, WebError> {
+	let temp = dir.join(format!(".upload-{}", uuid::Uuid::new_v4().simple()));
+	let actual = match write_hashed(&temp, body, max).await {
+		Ok(actual) => actual,
+		Err(err) => {
+			let _ = tokio::fs::remove_file(&temp).await;
+			return Err(err);
+		}
+	};
+	if actual != *expected {
+		let _ = tokio::fs::remove_file(&temp).await;
+		return Err(WebError(
+			StatusCode::UNPROCESSABLE_ENTITY,
+			format!("content hashes to {}, not {}", hex(&actual), hex(expected)),
+		));
+	}
+	let target = dir.join(format!("{DATA_PREFIX}{}", store_name(&actual)));
+	if tokio::fs::try_exists(&target).await.unwrap_or(false) {
+		// same name means same content, so the stored copy stays
+		let _ = tokio::fs::remove_file(&temp).await;
+		return Ok(StatusCode::OK);
+	}
+	tokio::fs::rename(&temp, &target)
+		.await
+		.with_context(|| format!("cannot move upload to {}", target.display()))?;
+	Ok(StatusCode::CREATED)
+}
+
+/// Stream `PeerEvent`s as server-sent events, each a JSON object with a `type`. A client
+/// that falls behind gets a `lagged` event and should reload the peer list.
+async fn events(
+	State(state): State<WebState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+	let rx = state.peer.subscribe();
+	let stream = futures::stream::unfold(rx, |mut rx| async move {
+		let event = match rx.recv().await {
+			Ok(event) => Event::default().json_data(event),
+			Err(RecvError::Lagged(missed)) => {
+				Ok(Event::default().event("lagged").data(missed.to_string()))
+			}
+			Err(RecvError::Closed) => return None,
+		};
+		Some((event, rx))
+	});
+	Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Answer preflights and add CORS headers for `origins`, or for any origin with `*`.
+/// The token is still required, so an allowed page must know it too.
+fn cors_layer(origins: &[String]) -> Result<Option<CorsLayer>> {
+	if origins.is_empty() {
+		return Ok(None);
+	}
+	let allow_origin = if origins.iter().any(|origin| origin == "*") {
+		AllowOrigin::any()
+	} else {
+		let origins = origins
+			.iter()
+			.map(|origin| {
+				HeaderValue::from_str(origin).with_context(|| format!("bad CORS origin {origin}"))
+			})
+			.collect::<Result<Vec<_>>>()?;
+		AllowOrigin::list(origins)
+	};
+	Ok(Some(
+		CorsLayer::new()
+			.allow_origin(allow_origin)
+			.allow_methods([Method::GET, Method::PUT])
+			.allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+			.expose_headers([X_EOF]),
+	))
+}
+
+fn router(state: WebState, cors: Option<CorsLayer>) -> Router {
+	let api = Router::new()
+		.route("/events", get(events))
+		.route("/peers", get(peers))
+		.route("/peers/:peer/dir", get(list_dir))
+		.route("/peers/:peer/file", get(read_file))
+		.route("/peers/:peer/catalog", get(catalog))
+		.route("/search", get(search))
+		.route("/store/:hash", put(put_blob))
+		.route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+	let router = Router::new()
+		.route("/", get(index))
+		.nest("/api", api)
+		.with_state(state);
+	match cors {
+		Some(cors) => router.layer(cors),
+		None => router,
+	}
+}
+
+/// Serve the web UI for `peer` on `addr` until Ctrl+C, then shut the peer down.
+///
+/// The access token comes from `WEB_TOKEN`; without it a random one is made and logged.
+pub async fn run(peer: PuppyPeer, addr: &str, options: WebOptions<'_>) -> Result<()> {
+	let token = env::var("WEB_TOKEN")
+		.ok()
+		.filter(|token| !token.is_empty())
+		.unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+	let cors = cors_layer(options.cors_origins)?;
+	let store_dir = match options.store_dir {
+		Some(dir) => {
+			std::fs::create_dir_all(dir).with_context(|| format!("cannot create {dir}"))?;
+			Some(Arc::from(PathBuf::from(dir).as_path()))
+		}
+		None => None,
+	};
+	let listener = tokio::net::TcpListener::bind(addr)
+		.await
+		.with_context(|| format!("cannot listen on {addr}"))?;
+	log::info!(
+		"web UI at http://{}/#token={}",
+		listener.local_addr()?,
+		token
+	);
+	let peer = Arc::new(peer);
+	let state = WebState {
+		peer: peer.clone(),
+		token: token.into(),
+		store_dir,
+		max_upload: options.max_upload,
+	};
+	axum::serve(listener, router(state, cors))
+		.with_graceful_shutdown(async {
+			if let Err(err) = tokio::signal::ctrl_c().await {
+				log::error!("failed to listen for ctrl_c: {err}");
+			}
+			log::info!("interrupt received, shutting down");
+		})
+		.await?;
+	match Arc::try_unwrap(peer) {
+		Ok(peer) => peer.shutdown().await,
+		Err(_) => log::warn!("web requests still running; exiting without a clean shutdown"),
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_the_exact_token_is_accepted() {
+		let uri = Uri::from_static("/api/peers");
+		let mut headers = HeaderMap::new();
+		assert!(!authorized(&headers, &uri, "secret"));
+		headers.insert(
+			header::AUTHORIZATION,
+			HeaderValue::from_static("Bearer secret"),
+		);
+		assert!(authorized(&headers, &uri, "secret"));
+		headers.insert(
+			header::AUTHORIZATION,
+			HeaderValue::from_static("Bearer secreT"),
+		);
+		assert!(!authorized(&headers, &uri, "secret"));
+		headers.insert(
+			header::AUTHORIZATION,
+			HeaderValue::from_static("Bearer secret2"),
+		);
+		assert!(!authorized(&headers, &uri, "secret"));
+		headers.insert(header::AUTHORIZATION, HeaderValue::from_static("secret"));
+		assert!(!authorized(&headers, &uri, "secret"));
+		let empty = HeaderMap::new();
+		let uri = Uri::from_static("/api/events?a=1&token=s%20cret");
+		assert!(authorized(&empty, &uri, "s cret"));
+		assert!(!authorized(&empty, &uri, "secret"));
+	}
+
+	#[tokio::test]
+	async fn uploads_are_hashed_while_written() {
+		let temp = std::env::temp_dir().join(format!("puppypeer-upload-{}", std::process::id()));
+		let digest = write_hashed(&temp, Body::from("blob"), 4).await.unwrap();
+		assert_eq!(std::fs::read(&temp).unwrap(), b"blob");
+		let expected = puppypeer_core::scan::sha256_hash(&b"blob"[..]).unwrap();
+		assert_eq!(digest, expected);
+		let err = write_hashed(&temp, Body::from("blobs"), 4)
+			.await
+			.err()
+			.unwrap();
+		assert_eq!(err.0, StatusCode::PAYLOAD_TOO_LARGE);
+		std::fs::remove_file(temp).unwrap();
+	}
+
+	#[tokio::test]
+	async fn catalog_limits_are_clamped_to_the_largest_page() {
+		let root = std::env::temp_dir().join(format!("puppypeer-catalog-{}", std::process::id()));
+		std::fs::create_dir_all(root.join("files")).unwrap();
+		std::fs::write(root.join("files/notes.txt"), b"hello").unwrap();
+		let peer = PuppyPeer::with_config(puppypeer_core::PuppyConfig {
+			keypair_path: Some(root.join("peer.key")),
+			db_path: Some(root.join("peer.db")),
+			listen_addrs: Vec::new(),
+			..puppypeer_core::PuppyConfig::default()
+		});
+		let cancel = std::sync::atomic::AtomicBool::new(false);
+		let options = puppypeer_core::scan::ScanOptions::default();
+		peer.scan_folder(root.join("files"), &options, |_| {}, &cancel)
+			.unwrap();
+		let me = peer.state().lock().unwrap().me;
+		let state = WebState {
+			peer: Arc::new(peer),
+			token: Arc::from("secret"),
+			store_dir: None,
+			max_upload: 0,
+		};
+
+		let query = Query(CatalogQuery {
+			prefix: root.join("files").to_string_lossy().into_owned(),
+			offset: 0,
+			limit: Some(MAX_CATALOG_PAGE * 5),
+		});
+		let Json(page) = catalog(State(state.clone()), Path(me.to_string()), query)
+			.await
+			.unwrap();
+		assert_eq!(page.entries.len(), 1);
+		assert!(!page.has_more);
+
+		let Ok(peer) = Arc::try_unwrap(state.peer) else {
+			panic!("the peer is still shared");
+		};
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn search_finds_indexed_files_by_mime_type() {
+		let root = std::env::temp_dir().join(format!("puppypeer-search-{}", std::process::id()));
+		std::fs::create_dir_all(root.join("files")).unwrap();
+		std::fs::write(root.join("files/notes.txt"), b"hello").unwrap();
+		let peer = PuppyPeer::with_config(puppypeer_core::PuppyConfig {
+			keypair_path: Some(root.join("peer.key")),
+			db_path: Some(root.join("peer.db")),
+			listen_addrs: Vec::new(),
+			..puppypeer_core::PuppyConfig::default()
+		});
+		let cancel = std::sync::atomic::AtomicBool::new(false);
+		let options = puppypeer_core::scan::ScanOptions::default();
+		peer.scan_folder(root.join("files"), &options, |_| {}, &cancel)
+			.unwrap();
+		let state = WebState {
+			peer: Arc::new(peer),
+			token: Arc::from("secret"),
+			store_dir: None,
+			max_upload: 0,
+		};
+		let query = |q: &str| {
+			Query(SearchQuery {
+				q: q.to_string(),
+				mime: None,
+			})
+		};
+
+		let Json(found) = search(State(state.clone()), query("text/plain"))
+			.await
+			.unwrap();
+		assert_eq!(found.files.len(), 1);
+		assert_eq!(found.files[0].size, 5);
+		assert_eq!(found.files[0].hash.len(), 64);
+		assert!(found.mime_types.iter().any(|mime| mime == "text/plain"));
+		let Json(found) = search(State(state.clone()), query("image/")).await.unwrap();
+		assert!(found.files.is_empty());
+
+		let Ok(peer) = Arc::try_unwrap(state.peer) else {
+			panic!("the peer is still shared");
+		};
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[test]
+	fn only_64_digit_hashes_parse() {
+		let hash = hex(&puppypeer_core::scan::sha256_hash(&b"blob"[..]).unwrap());
+		assert_eq!(parse_hash(&hash), parse_hash(&hash.to_ascii_uppercase()));
+		assert!(parse_hash(&hash).is_some());
+		assert_eq!(parse_hash(&hash[1..]), None);
+		assert_eq!(parse_hash(&format!("{hash}0")), None);
+		assert_eq!(parse_hash(&format!("g{}", &hash[1..])), None);
+	}
+
+	#[tokio::test]
+	async fn padded_hashes_store_one_fsck_named_blob() {
+		let dir = std::env::temp_dir().join(format!("puppypeer-store-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		// a digest with a byte below 0x10, which the padded and legacy forms spell apart
+		let (body, digest) = (0..)
+			.map(|i| format!("blob{i}"))
+			.map(|body| {
+				let digest = puppypeer_core::scan::sha256_hash(body.as_bytes()).unwrap();
+				(body, digest)
+			})
+			.find(|(_, digest)| digest.iter().any(|byte| *byte < 0x10))
+			.unwrap();
+		let padded = hex(&digest);
+		assert_ne!(padded, store_name(&digest));
+		let max = body.len() as u64;
+
+		let upper = parse_hash(&padded.to_ascii_uppercase()).unwrap();
+		let status = store_blob(&dir, &upper, Body::from(body.clone()), max)
+			.await
+			.unwrap();
+		assert_eq!(status, StatusCode::CREATED);
+		let lower = parse_hash(&padded).unwrap();
+		let status = store_blob(&dir, &lower, Body::from(body), max)
+			.await
+			.unwrap();
+		assert_eq!(status, StatusCode::OK);
+		let err = store_blob(&dir, &lower, Body::from("other"), max)
+			.await
+			.err()
+			.unwrap();
+		assert_eq!(err.0, StatusCode::UNPROCESSABLE_ENTITY);
+		let names: Vec<String> = std::fs::read_dir(&dir)
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+			.collect();
+		assert_eq!(names, [format!("{DATA_PREFIX}{}", store_name(&digest))]);
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
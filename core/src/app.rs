@@ -1,33 +1,64 @@
-use crate::p2p::{AuthMethod, CpuInfo, DirEntry, FileWriteAck, InterfaceInfo, PeerReq, PeerRes};
-use crate::types::FileChunk;
+use crate::p2p::{
+	AuthMethod, BuildInfo, CpuInfo, DirEntry, DiskInfo, DisksSummary, ErrorCode, FileTimestamps,
+	FileWriteAck, InterfaceInfo, IssuedToken, MemorySummary, NetSummary, PeerError, PeerReq,
+	PeerRes, PermissionGrant, SessionInfo, SystemLoad, SystemOverview, TokenInfo, WriteMode,
+	normalize_request_path, send_mdns_query,
+};
+use crate::types::{
+	BackupLocations, CatalogPage, ConflictPolicy, DirSize, DirTree, DiskSpace, FileChunk,
+	FilePreview, IdentityRotation, PeerEvent, PeerMessage, PeerSummary, PlannedFile, RecentFile,
+	RelayProgress, RelayReport, ReplicatedFile, ReplicationOutcome, ReplicationReport, SyncAction,
+	SyncFileState, SyncMode, SyncOperation, SyncPlan, SyncReason, SyncReport, SyncedFile,
+};
 use crate::{
-	db::{load_peer_permissions, open_db, run_migrations},
-	p2p::{AgentBehaviour, AgentEvent, build_swarm, load_or_generate_keypair},
-	state::{Connection, FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FolderRule, Permission, State},
+	config::PuppyConfig,
+	db::{
+		CpuSample, FileEntry, ListArgs, TemperatureSample, catalog_page, fetch_cpu_history,
+		fetch_temperature_history, find_token, get_mime_types, hash_locations, list_files,
+		load_peer_names, load_peer_permissions, load_recent_files, move_granted_permissions,
+		open_db, save_cpu_samples, save_peer_name, save_recent_file, save_temperature_samples,
+		save_token,
+	},
+	p2p::{
+		AgentBehaviour, AgentEvent, DIR_SIZE_MAX_ENTRIES, DIR_SIZE_TIMEOUT, MAX_BATCH_BYTES,
+		MAX_BATCH_FILES, MAX_CATALOG_PAGE, MAX_FIND_HASHES, MAX_MESSAGE_BYTES, MAX_PREVIEW_BYTES,
+		build_swarm, collect_disk_info, collect_interface_info, generate_token,
+		granted_permissions, hash_token, load_or_generate_keypair, natural_cmp, now_timestamp,
+		rotate_keypair, session_for_credentials, session_for_token,
+	},
+	scan::{FileHash, ScanOptions, ScanProgress, ScanResult, Sha256Hasher, scan},
+	state::{
+		Connection, Direction, FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FolderRule, Permission, Route,
+		State, ip_of,
+	},
 };
 use anyhow::{Result, anyhow, bail};
 use chrono::{DateTime, Utc};
 use futures::StreamExt;
 use futures::executor::block_on;
-use libp2p::{PeerId, Swarm, mdns, swarm::SwarmEvent};
-use std::collections::HashMap;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use libp2p::multiaddr::Protocol;
+use libp2p::swarm::{ConnectionId, DialError, ListenError, dial_opts::DialOpts};
+use libp2p::{Multiaddr, PeerId, Swarm, mdns, swarm::SwarmEvent};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::num::NonZeroU8;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
-use std::{
-	env,
-	path::{Path, PathBuf},
-};
-use sysinfo::{Networks, System};
+use sysinfo::{Components, Disks, System};
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::{
 	sync::{
-		mpsc::{UnboundedReceiver, UnboundedSender},
-		oneshot,
+		OwnedSemaphorePermit, Semaphore, broadcast,
+		mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender},
+		oneshot, watch,
 	},
 	task::JoinHandle,
 };
 
-use libp2p::request_response::OutboundRequestId;
+use libp2p::request_response::{OutboundFailure, OutboundRequestId, ResponseChannel};
 
 pub struct ReadFileCmd {
 	peer_id: libp2p::PeerId,
@@ -42,20 +73,134 @@ pub enum Command {
 		peer_id: libp2p::PeerId,
 		addr: libp2p::Multiaddr,
 	},
+	Disconnect {
+		peer: PeerId,
+		tx: oneshot::Sender<Result<()>>,
+	},
 	ListDir {
 		peer: libp2p::PeerId,
 		path: String,
 		tx: oneshot::Sender<Result<Vec<DirEntry>>>,
 	},
+	ListDirRecursive {
+		peer: PeerId,
+		path: String,
+		max_depth: u32,
+		tx: oneshot::Sender<Result<DirTree>>,
+	},
+	StatFile {
+		peer: PeerId,
+		path: String,
+		tx: oneshot::Sender<Result<DirEntry>>,
+	},
 	ListCpus {
 		tx: oneshot::Sender<Result<Vec<CpuInfo>>>,
 		peer_id: PeerId,
 	},
+	ListDisks {
+		tx: oneshot::Sender<Result<Vec<DiskInfo>>>,
+		peer_id: PeerId,
+	},
+	ListInterfaces {
+		tx: oneshot::Sender<Result<Vec<InterfaceInfo>>>,
+		peer_id: PeerId,
+	},
+	SystemOverview {
+		tx: oneshot::Sender<Result<SystemOverview>>,
+		peer_id: PeerId,
+	},
+	SystemLoad {
+		tx: oneshot::Sender<Result<SystemLoad>>,
+		peer_id: PeerId,
+	},
 	ListPermissions {
 		peer: PeerId,
 		tx: oneshot::Sender<Result<Vec<Permission>>>,
 	},
 	ReadFile(ReadFileCmd),
+	ReadFiles {
+		peer: PeerId,
+		requests: Vec<(String, u64, Option<u64>)>,
+		tx: oneshot::Sender<Result<Vec<Result<FileChunk, ErrorCode>>>>,
+	},
+	PreviewFile {
+		peer: PeerId,
+		path: String,
+		bytes: u64,
+		tx: oneshot::Sender<Result<FilePreview>>,
+	},
+	SpaceFor {
+		peer: PeerId,
+		path: String,
+		tx: oneshot::Sender<Result<DiskSpace>>,
+	},
+	TailFile {
+		peer: PeerId,
+		path: String,
+		offset: Option<u64>,
+		from_end_bytes: u64,
+		tx: oneshot::Sender<Result<FileChunk>>,
+	},
+	DirSize {
+		peer: PeerId,
+		path: String,
+		tx: oneshot::Sender<Result<DirSize>>,
+	},
+	BrowseCatalog {
+		peer: PeerId,
+		prefix: String,
+		offset: u64,
+		limit: u64,
+		tx: oneshot::Sender<Result<CatalogPage>>,
+	},
+	FindHashes {
+		peer: PeerId,
+		hashes: Vec<FileHash>,
+		tx: oneshot::Sender<Result<Vec<FileHash>>>,
+	},
+	WriteFile {
+		peer: PeerId,
+		path: String,
+		offset: u64,
+		data: Vec<u8>,
+		mode: WriteMode,
+		times: Option<FileTimestamps>,
+		tx: oneshot::Sender<Result<FileWriteAck>>,
+	},
+	CreateDir {
+		peer: PeerId,
+		path: String,
+		tx: oneshot::Sender<Result<String>>,
+	},
+	Identify {
+		peer: PeerId,
+		tx: oneshot::Sender<Result<BuildInfo>>,
+	},
+	SendMessage {
+		peer: PeerId,
+		text: String,
+		tx: oneshot::Sender<Result<()>>,
+	},
+	Authenticate {
+		peer: PeerId,
+		method: AuthMethod,
+		tx: oneshot::Sender<Result<SessionInfo>>,
+	},
+	CreateToken {
+		peer: PeerId,
+		username: String,
+		label: Option<String>,
+		expires_in: Option<u64>,
+		permissions: Vec<PermissionGrant>,
+		tx: oneshot::Sender<Result<IssuedToken>>,
+	},
+	GrantAccess {
+		peer: PeerId,
+		target: PeerId,
+		permissions: Vec<PermissionGrant>,
+		merge: bool,
+		tx: oneshot::Sender<Result<Vec<PermissionGrant>>>,
+	},
 }
 
 async fn read_file(path: &Path, offset: u64, length: Option<u64>) -> Result<FileChunk> {
@@ -90,6 +235,133 @@ async fn read_file(path: &Path, offset: u64, length: Option<u64>) -> Result<File
 	})
 }
 
+/// Where a tail read of a `len` byte file starts: at `offset` while the file still
+/// reaches it, back at 0 once it was truncated or rotated, and `from_end_bytes` before
+/// the end on the first read.
+fn tail_start(len: u64, offset: Option<u64>, from_end_bytes: u64) -> u64 {
+	match offset {
+		Some(offset) if offset <= len => offset,
+		Some(_) => 0,
+		None => len.saturating_sub(from_end_bytes),
+	}
+}
+
+/// Serve a batch of reads, checking each path with `allow` independently.
+///
+/// Lengths are clamped so the whole batch stays under `MAX_BATCH_BYTES`; reads past
+/// the budget fail with `ErrorCode::LimitExceeded`.
+async fn read_files(
+	requests: Vec<(String, u64, Option<u64>)>,
+	allow: impl Fn(&Path) -> bool,
+) -> Vec<Result<FileChunk, ErrorCode>> {
+	let mut budget = MAX_BATCH_BYTES;
+	let mut results = Vec::with_capacity(requests.len());
+	for (path, offset, length) in requests {
+		if budget == 0 {
+			results.push(Err(ErrorCode::LimitExceeded));
+			continue;
+		}
+		let canonical = match fs::canonicalize(&path).await {
+			Ok(p) => p,
+			Err(err) => {
+				results.push(Err(ErrorCode::from_io(&err)));
+				continue;
+			}
+		};
+		if !allow(&canonical) {
+			results.push(Err(ErrorCode::AccessDenied));
+			continue;
+		}
+		match fs::metadata(&canonical).await {
+			Ok(meta) if meta.is_dir() => {
+				results.push(Err(ErrorCode::IsADirectory));
+				continue;
+			}
+			Ok(_) => {}
+			Err(err) => {
+				results.push(Err(ErrorCode::from_io(&err)));
+				continue;
+			}
+		}
+		let length = length.unwrap_or(budget).min(budget);
+		let result = read_file(&canonical, offset, Some(length))
+			.await
+			.map_err(|err| match err.downcast_ref::<std::io::Error>() {
+				Some(io) => ErrorCode::from_io(io),
+				None => ErrorCode::Io,
+			});
+		if let Ok(chunk) = &result {
+			budget -= chunk.data.len() as u64;
+		}
+		results.push(result);
+	}
+	results
+}
+
+async fn preview_file(path: &Path, bytes: u64) -> Result<FilePreview> {
+	let file = fs::File::open(path).await?;
+	let metadata = file.metadata().await?;
+	if metadata.is_dir() {
+		bail!("path is a directory")
+	}
+	let limit = bytes.min(MAX_PREVIEW_BYTES);
+	let mut head = Vec::with_capacity(limit as usize);
+	file.take(limit).read_to_end(&mut head).await?;
+	// Prefer magic-byte detection and fall back to the extension for text formats
+	let mime = infer::get(&head)
+		.map(|kind| kind.mime_type().to_string())
+		.or_else(|| {
+			mime_guess::from_path(path)
+				.first_raw()
+				.map(|value| value.to_string())
+		});
+	Ok(FilePreview {
+		mime,
+		truncated: metadata.len() > head.len() as u64,
+		head,
+	})
+}
+
+async fn dir_size(path: PathBuf) -> Result<DirSize> {
+	tokio::task::spawn_blocking(move || {
+		let started = std::time::Instant::now();
+		let mut size = DirSize {
+			total_bytes: 0,
+			file_count: 0,
+			partial: false,
+		};
+		let mut visited = 0u64;
+		for entry in walkdir::WalkDir::new(&path) {
+			visited += 1;
+			if visited > DIR_SIZE_MAX_ENTRIES || started.elapsed() > DIR_SIZE_TIMEOUT {
+				size.partial = true;
+				break;
+			}
+			let entry = match entry {
+				Ok(entry) => entry,
+				Err(err) => {
+					log::warn!("dir size skipped entry under {}: {err}", path.display());
+					size.partial = true;
+					continue;
+				}
+			};
+			if !entry.file_type().is_file() {
+				continue;
+			}
+			match entry.metadata() {
+				Ok(meta) => {
+					size.total_bytes += meta.len();
+					size.file_count += 1;
+				}
+				Err(_) => size.partial = true,
+			}
+		}
+		size
+	})
+	.await
+	.map_err(|err| anyhow!("dir size task failed: {err}"))
+}
+
 async fn write_file(path: &Path, offset: u64, data: &[u8]) -> Result<FileWriteAck> {
 	// Open (or create) file with write capability
 	let mut file = match fs::OpenOptions::new()
@@ -124,15 +396,493 @@ async fn write_file(path: &Path, offset: u64, data: &[u8]) -> Result<FileWriteAc
 	}
 	Ok(FileWriteAck {
 		bytes_written: data.len() as u64,
+		total_len: Some(current_len.max(required_len)),
+		sha256: None,
+	})
+}
+
+/// Give a file or directory created for a peer the mode its share asks for. Modes only
+/// exist on Unix; elsewhere this does nothing.
+async fn apply_create_mode(path: &Path, mode: Option<u32>) -> std::io::Result<()> {
+	#[cfg(unix)]
+	if let Some(mode) = mode {
+		use std::os::unix::fs::PermissionsExt;
+		fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await?;
+	}
+	#[cfg(not(unix))]
+	let _ = (path, mode);
+	Ok(())
+}
+
+async fn file_hash(path: &Path) -> Result<FileHash> {
+	let path = path.to_path_buf();
+	let hash = tokio::task::spawn_blocking(move || {
+		std::fs::File::open(&path).and_then(crate::scan::sha256_hash)
 	})
+	.await
+	.map_err(|e| anyhow!("hash task failed: {}", e))??;
+	Ok(hash)
+}
+
+/// The `hashes` indexed on `node_id` at some path that `allow` accepts.
+fn readable_hashes(
+	conn: &rusqlite::Connection,
+	node_id: &[u8],
+	hashes: Vec<FileHash>,
+	allow: impl Fn(&Path) -> bool,
+) -> Result<Vec<FileHash>> {
+	let mut found = Vec::new();
+	for hash in hashes {
+		let paths = hash_locations(conn, node_id, &hash)?;
+		if paths.iter().any(|path| allow(path)) {
+			found.push(hash);
+		}
+	}
+	Ok(found)
+}
+
+fn hex(hash: &FileHash) -> String {
+	hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn file_sha256(path: &Path) -> Result<String> {
+	Ok(hex(&file_hash(path).await?))
+}
+
+/// Whole-file writes that are being staged in temp files, keyed by writer and destination.
+#[derive(Default)]
+struct AtomicWrites {
+	staged: HashMap<(PeerId, PathBuf), PathBuf>,
+}
+
+impl AtomicWrites {
+	async fn write(
+		&mut self,
+		peer: PeerId,
+		path: &Path,
+		offset: u64,
+		data: &[u8],
+		last: bool,
+		mode: Option<u32>,
+	) -> Result<FileWriteAck> {
+		let key = (peer, path.to_path_buf());
+		if offset == 0 {
+			// A fresh upload replaces whatever was staged before
+			if let Some(stale) = self.staged.remove(&key) {
+				let _ = fs::remove_file(stale).await;
+			}
+		}
+		let temp = match self.staged.get(&key) {
+			Some(temp) => temp.clone(),
+			None if offset == 0 => {
+				let mut name = path.as_os_str().to_owned();
+				name.push(format!(".tmp-{}", uuid::Uuid::new_v4()));
+				let temp = PathBuf::from(name);
+				self.staged.insert(key.clone(), temp.clone());
+				temp
+			}
+			None => bail!("no atomic write in progress for {}", path.display()),
+		};
+		let written = match write_file(&temp, offset, data).await {
+			// set the mode while the file is staged, so it is never exposed more widely
+			Ok(ack) if offset == 0 => apply_create_mode(&temp, mode)
+				.await
+				.map(|()| ack)
+				.map_err(|e| anyhow!("setting mode failed: {}", e)),
+			written => written,
+		};
+		let mut ack = match written {
+			Ok(ack) => ack,
+			Err(err) => {
+				self.staged.remove(&key);
+				let _ = fs::remove_file(&temp).await;
+				return Err(err);
+			}
+		};
+		if last {
+			self.staged.remove(&key);
+			let renamed = match file_sha256(&temp).await {
+				Ok(sha256) => fs::rename(&temp, path)
+					.await
+					.map(|()| sha256)
+					.map_err(|e| anyhow!("rename failed: {}", e)),
+				Err(err) => Err(err),
+			};
+			match renamed {
+				Ok(sha256) => ack.sha256 = Some(sha256),
+				Err(err) => {
+					// no longer staged, so abort_peer would never find it
+					let _ = fs::remove_file(&temp).await;
+					return Err(err);
+				}
+			}
+		}
+		Ok(ack)
+	}
+
+	/// Current length of the staged file for `path`, if an atomic write is in progress.
+	async fn staged_len(&self, peer: PeerId, path: &Path) -> Option<u64> {
+		let temp = self.staged.get(&(peer, path.to_path_buf()))?;
+		fs::metadata(temp).await.ok().map(|meta| meta.len())
+	}
+
+	/// Drop every staged write from `peer`, leaving the destinations untouched.
+	async fn abort_peer(&mut self, peer: PeerId) {
+		let keys: Vec<_> = self
+			.staged
+			.keys()
+			.filter(|(p, _)| *p == peer)
+			.cloned()
+			.collect();
+		for key in keys {
+			if let Some(temp) = self.staged.remove(&key) {
+				log::info!("discarding interrupted write to {}", key.1.display());
+				let _ = fs::remove_file(temp).await;
+			}
+		}
+	}
+}
+
+/// Space on the filesystem holding `path`, picking the most specific mount point.
+fn disk_space(path: &Path) -> Option<DiskSpace> {
+	let disks = Disks::new_with_refreshed_list();
+	disks
+		.list()
+		.iter()
+		.filter(|disk| path.starts_with(disk.mount_point()))
+		.max_by_key(|disk| disk.mount_point().components().count())
+		.map(|disk| DiskSpace {
+			available: disk.available_space(),
+			total: disk.total_space(),
+		})
+}
+
+fn available_space(path: &Path) -> Option<u64> {
+	disk_space(path).map(|space| space.available)
+}
+
+/// Canonical form of a path to share, and whether it is a single file.
+///
+/// The errors name the usual mistakes, a missing path or one this process may not
+/// read, instead of the bare OS error.
+fn resolve_share_path(path: &Path) -> Result<(PathBuf, bool)> {
+	let denied = |what: &str| {
+		anyhow!(
+			"cannot {} {}: permission denied; run puppypeer as a user that can read it or \
+			 change its permissions",
+			what,
+			path.display()
+		)
+	};
+	let canonical = match std::fs::canonicalize(path) {
+		Ok(canonical) => canonical,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => bail!(
+			"{} does not exist; check the spelling or create it first",
+			path.display()
+		),
+		Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+			return Err(denied("access"));
+		}
+		Err(err) => bail!("cannot access {}: {err}", path.display()),
+	};
+	let meta = std::fs::metadata(&canonical)
+		.map_err(|err| anyhow!("cannot access {}: {err}", path.display()))?;
+	let readable = if meta.is_dir() {
+		std::fs::read_dir(&canonical).map(|_| ())
+	} else if meta.is_file() {
+		std::fs::File::open(&canonical).map(|_| ())
+	} else {
+		bail!(
+			"{} is neither a folder nor a regular file; share the folder containing it",
+			path.display()
+		);
+	};
+	match readable {
+		Ok(()) => Ok((canonical, meta.is_file())),
+		Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+			Err(denied(if meta.is_dir() { "list" } else { "read" }))
+		}
+		Err(err) => bail!("cannot read {}: {err}", path.display()),
+	}
+}
+
+/// Canonical form of the deepest existing ancestor of `path`, `path` itself included.
+async fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+	for ancestor in path.ancestors() {
+		if let Ok(canonical) = fs::canonicalize(ancestor).await {
+			return Some(canonical);
+		}
+	}
+	None
+}
+
+/// How long a computed share usage is trusted before walking the share again.
+const SHARE_USAGE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often this node's CPU usage and temperatures are written to the history tables.
+const SYSTEM_SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Write one round of this node's CPU usage and sensor readings to the history tables.
+fn record_system_samples(db_path: &Path, me: &PeerId, cpus: &[CpuInfo]) -> Result<()> {
+	record_cpu_samples(db_path, me, cpus)?;
+	let sampled_at = Utc::now();
+	let samples: Vec<TemperatureSample> = Components::new_with_refreshed_list()
+		.iter()
+		.filter_map(|component| {
+			Some(TemperatureSample {
+				label: component.label().to_string(),
+				temperature: component.temperature()?,
+				sampled_at,
+			})
+		})
+		.collect();
+	if samples.is_empty() {
+		return Ok(());
+	}
+	let mut conn = open_db(db_path)?;
+	save_temperature_samples(&mut conn, &me.to_bytes(), &samples)
+}
+
+fn record_cpu_samples(db_path: &Path, peer: &PeerId, cpus: &[CpuInfo]) -> Result<()> {
+	let sampled_at = Utc::now();
+	let samples: Vec<CpuSample> = cpus
+		.iter()
+		.map(|cpu| CpuSample {
+			name: cpu.name.clone(),
+			usage: cpu.usage,
+			frequency: cpu.frequency_hz,
+			sampled_at,
+		})
+		.collect();
+	let mut conn = open_db(db_path)?;
+	save_cpu_samples(&mut conn, &peer.to_bytes(), &samples)
+}
+
+const EXPORT_IDENTIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+/// Peers asked at the same time by `find_backups`.
+const FIND_BACKUPS_CONCURRENCY: usize = 4;
+/// A peer that takes longer than this for one `FindHashes` batch counts as unknown.
+const FIND_HASHES_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Bytes sent per `WriteFile` request by `replicate_folder`.
+const REPLICATE_CHUNK_SIZE: u64 = 1024 * 1024;
+/// Bytes per chunk read from the source and written to the destination by `relay_copy`.
+const RELAY_CHUNK_SIZE: u64 = 1024 * 1024;
+/// Chunks `relay_copy` reads ahead of the destination, bounding its memory use.
+const RELAY_PIPELINE_DEPTH: usize = 4;
+/// Suffix of the hidden temp files sync downloads into; the local walk skips them.
+const SYNC_TEMP_SUFFIX: &str = ".puppypeer-sync";
+/// Most bytes one `TailFile` answer carries; a follower asks again right away for more.
+const MAX_TAIL_CHUNK: u64 = 256 * 1024;
+/// How often `tail_file` asks an idle file for appended bytes.
+const TAIL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+/// How long `discover_now` waits for answers to its mDNS query.
+const DISCOVERY_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+/// Commands queued for the swarm loop before `PuppyPeer` calls wait for room.
+const COMMAND_CHANNEL_CAPACITY: usize = 256;
+/// Files remembered by `PuppyPeer::record_recent_file`, across all peers.
+const RECENT_FILES_KEPT: usize = 100;
+/// Peer events kept for subscribers that fall behind; older ones are dropped.
+const PEER_EVENT_CAPACITY: usize = 256;
+/// Most queued commands handled in one pass of the swarm loop before it checks for
+/// swarm events again.
+const COMMAND_BATCH: usize = 16;
+
+/// How likely a dial to `addr` is to work, lowest first: private LAN addresses, then
+/// public ones, then loopback, link-local and anything else.
+fn address_score(addr: &Multiaddr) -> u8 {
+	match addr.iter().next() {
+		Some(Protocol::Ip4(ip)) if ip.is_private() => 0,
+		Some(Protocol::Ip6(ip)) if (ip.segments()[0] & 0xfe00) == 0xfc00 => 0,
+		Some(Protocol::Ip4(ip))
+			if !(ip.is_loopback()
+				|| ip.is_link_local()
+				|| ip.is_unspecified()
+				|| ip.is_multicast()) =>
+		{
+			1
+		}
+		Some(Protocol::Ip6(ip))
+			if !(ip.is_loopback()
+				|| ip.is_unspecified()
+				|| ip.is_multicast()
+				|| (ip.segments()[0] & 0xffc0) == 0xfe80) =>
+		{
+			1
+		}
+		Some(Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_)) => 1,
+		_ => 2,
+	}
+}
+
+/// `addresses` without repeats, in the order they should be dialed.
+fn dial_order(addresses: impl IntoIterator<Item = Multiaddr>) -> Vec<Multiaddr> {
+	let mut ordered: Vec<Multiaddr> = Vec::new();
+	for addr in addresses {
+		if !ordered.contains(&addr) {
+			ordered.push(addr);
+		}
+	}
+	// stable, so addresses with the same score keep the order they were learned in
+	ordered.sort_by_key(address_score);
+	ordered
+}
+
+/// Files on one side of a sync, keyed by relative path.
+type SyncListing = BTreeMap<PathBuf, SyncFileState>;
+
+/// Same modification time at the second precision every filesystem keeps.
+fn same_mtime(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> bool {
+	a.map(|a| a.timestamp()) == b.map(|b| b.timestamp())
+}
+
+fn set_mtime(path: &Path, modified: DateTime<Utc>) -> std::io::Result<()> {
+	std::fs::File::options()
+		.write(true)
+		.open(path)?
+		.set_modified(modified.into())
+}
+
+/// Apply the timestamps a `WriteFile` request asked for.
+async fn set_file_times(path: &Path, times: FileTimestamps) -> std::io::Result<()> {
+	let mut file_times = std::fs::FileTimes::new();
+	if let Some(modified) = times.modified {
+		file_times = file_times.set_modified(modified.into());
+	}
+	if let Some(accessed) = times.accessed {
+		file_times = file_times.set_accessed(accessed.into());
+	}
+	#[cfg(windows)]
+	if let Some(created) = times.created {
+		use std::os::windows::fs::FileTimesExt;
+		file_times = file_times.set_created(created.into());
+	}
+	#[cfg(target_os = "macos")]
+	if let Some(created) = times.created {
+		use std::os::macos::fs::FileTimesExt;
+		file_times = file_times.set_created(created.into());
+	}
+	let file = fs::OpenOptions::new().write(true).open(path).await?;
+	file.into_std().await.set_times(file_times)
+}
+
+/// Temp file a pulled file is downloaded to before it replaces `local`.
+fn sync_temp_path(local: &Path) -> PathBuf {
+	let name = local
+		.file_name()
+		.map(|name| name.to_string_lossy().into_owned())
+		.unwrap_or_default();
+	local.with_file_name(format!(".{}{}", name, SYNC_TEMP_SUFFIX))
+}
+
+/// Move a downloaded temp file into place and give it the remote modification time.
+async fn finish_pull(temp: &Path, local: &Path, modified: Option<DateTime<Utc>>) -> Result<()> {
+	fs::rename(temp, local).await?;
+	if let Some(modified) = modified {
+		set_mtime(local, modified)?;
+	}
+	Ok(())
+}
+
+/// Decide what sync does with one file from the two listings.
+fn plan_file(
+	path: &Path,
+	local: Option<SyncFileState>,
+	remote: Option<SyncFileState>,
+	mode: SyncMode,
+) -> PlannedFile {
+	let (operation, reason) = match (local, remote) {
+		(Some(_), None) => (SyncOperation::Upload, SyncReason::OnlyLocal),
+		(None, _) if mode == SyncMode::OneWay => (SyncOperation::Skip, SyncReason::OneWay),
+		(None, _) => (SyncOperation::Download, SyncReason::OnlyRemote),
+		(Some(local), Some(remote))
+			if local.size == remote.size && same_mtime(local.modified, remote.modified) =>
+		{
+			(SyncOperation::Skip, SyncReason::Unchanged)
+		}
+		(Some(local), Some(remote)) => match mode {
+			SyncMode::OneWay => (SyncOperation::Upload, SyncReason::OneWay),
+			SyncMode::TwoWay(ConflictPolicy::PreferLocal) => {
+				(SyncOperation::Upload, SyncReason::Preferred)
+			}
+			SyncMode::TwoWay(ConflictPolicy::PreferRemote) => {
+				(SyncOperation::Download, SyncReason::Preferred)
+			}
+			SyncMode::TwoWay(ConflictPolicy::NewestWins) if remote.modified > local.modified => {
+				(SyncOperation::Download, SyncReason::Newer)
+			}
+			SyncMode::TwoWay(ConflictPolicy::NewestWins) => {
+				(SyncOperation::Upload, SyncReason::Newer)
+			}
+		},
+	};
+	let both = local.zip(remote);
+	PlannedFile {
+		path: path.to_path_buf(),
+		operation,
+		reason,
+		local,
+		remote,
+		conflict: both.is_some() && reason != SyncReason::Unchanged,
+		verify: operation != SyncOperation::Skip && both.is_some_and(|(l, r)| l.size == r.size),
+	}
+}
+
+fn local_sync_listing(root: &Path) -> SyncListing {
+	walkdir::WalkDir::new(root)
+		.into_iter()
+		.filter_map(|entry| entry.ok())
+		.filter(|entry| entry.file_type().is_file())
+		// leftovers of an interrupted pull are not the user's files
+		.filter(|entry| {
+			!entry
+				.file_name()
+				.to_string_lossy()
+				.ends_with(SYNC_TEMP_SUFFIX)
+		})
+		.filter_map(|entry| {
+			let meta = entry.metadata().ok()?;
+			let relative = entry.path().strip_prefix(root).ok()?.to_path_buf();
+			let modified = meta.modified().ok().map(DateTime::<Utc>::from);
+			Some((
+				relative,
+				SyncFileState {
+					size: meta.len(),
+					modified,
+				},
+			))
+		})
+		.collect()
 }
 
 pub struct App {
 	state: Arc<Mutex<State>>,
 	swarm: Swarm<AgentBehaviour>,
-	rx: UnboundedReceiver<Command>,
-	pending_requests: HashMap<OutboundRequestId, PendingRequest>,
+	rx: Receiver<Command>,
+	pending_requests: HashMap<OutboundRequestId, InFlight>,
+	// Requests handed back by the backoff timer after a dropped connection
+	retry_tx: UnboundedSender<InFlight>,
+	retry_rx: UnboundedReceiver<InFlight>,
 	system: System,
+	atomic_writes: AtomicWrites,
+	config: PuppyConfig,
+	// Bytes used per quota-limited share, measured off the loop every SHARE_USAGE_TTL
+	share_usage: HashMap<PathBuf, ShareUsage>,
+	// Share walks report their result back to the loop through this channel
+	usage_tx: UnboundedSender<(PathBuf, Option<u64>)>,
+	usage_rx: UnboundedReceiver<(PathBuf, Option<u64>)>,
+	// Set once the swarm listens, or failed to; `me` is already known by then
+	ready: watch::Sender<bool>,
+	bootstrap: Vec<BootstrapPeer>,
+	// Dials of `bootstrap` entries, by index, until they connect or fail
+	bootstrap_dials: HashMap<ConnectionId, usize>,
+	// Answers to requests too slow for the swarm loop, sent once their task finishes
+	answers: FuturesUnordered<BoxFuture<'static, Answer>>,
+	// Ticks every SYSTEM_SAMPLE_INTERVAL to record this node's CPU and temperature history
+	sample_timer: tokio::time::Interval,
+	// Bootstrap entries handed back by the backoff timer
+	redial_tx: UnboundedSender<usize>,
+	redial_rx: UnboundedReceiver<usize>,
+	events: broadcast::Sender<PeerEvent>,
 }
 
 trait ResponseDecoder: Sized + Send + 'static {
@@ -148,75 +898,410 @@ impl ResponseDecoder for Vec<DirEntry> {
 	}
 }
 
-impl ResponseDecoder for Vec<CpuInfo> {
+impl ResponseDecoder for DirTree {
 	fn decode(response: PeerRes) -> anyhow::Result<Self> {
 		match response {
-			PeerRes::Cpus(cpus) => Ok(cpus),
+			PeerRes::DirTree(tree) => Ok(tree),
 			other => Err(anyhow!("unexpected response: {:?}", other)),
 		}
 	}
 }
 
-impl ResponseDecoder for Vec<Permission> {
+impl ResponseDecoder for DirEntry {
 	fn decode(response: PeerRes) -> anyhow::Result<Self> {
 		match response {
-			PeerRes::Permissions(perms) => Ok(perms),
+			PeerRes::FileStat(entry) => Ok(entry),
 			other => Err(anyhow!("unexpected response: {:?}", other)),
 		}
 	}
 }
 
-impl ResponseDecoder for FileChunk {
+impl ResponseDecoder for Vec<CpuInfo> {
 	fn decode(response: PeerRes) -> anyhow::Result<Self> {
 		match response {
-			PeerRes::FileChunk(chunk) => Ok(chunk),
+			PeerRes::Cpus(cpus) => Ok(cpus),
 			other => Err(anyhow!("unexpected response: {:?}", other)),
 		}
 	}
 }
 
-trait PendingResponseHandler: Send {
-	fn complete(self: Box<Self>, response: PeerRes);
-	fn fail(self: Box<Self>, error: anyhow::Error);
+impl ResponseDecoder for Vec<DiskInfo> {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Disks(disks) => Ok(disks),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
 }
 
-struct Pending<T: ResponseDecoder> {
-	tx: oneshot::Sender<Result<T>>,
+impl ResponseDecoder for Vec<InterfaceInfo> {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Interfaces(interfaces) => Ok(interfaces),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
 }
 
-impl<T: ResponseDecoder> Pending<T> {
-	fn new(tx: oneshot::Sender<Result<T>>) -> PendingRequest {
-		Box::new(Self { tx })
+impl ResponseDecoder for SystemOverview {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Overview(overview) => Ok(overview),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
 	}
 }
 
-impl<T: ResponseDecoder> PendingResponseHandler for Pending<T> {
-	fn complete(self: Box<Self>, response: PeerRes) {
-		let result = match response {
-			PeerRes::Error(err) => Err(anyhow!(err)),
-			other => T::decode(other),
-		};
-		let _ = self.tx.send(result);
+impl ResponseDecoder for SystemLoad {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Load(load) => Ok(load),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
 	}
+}
 
-	fn fail(self: Box<Self>, error: anyhow::Error) {
-		let _ = self.tx.send(Err(error));
+impl ResponseDecoder for Vec<Permission> {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Permissions(perms) => Ok(perms),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
 	}
 }
 
-type PendingRequest = Box<dyn PendingResponseHandler>;
-
-impl App {
-	fn can_access(&self, peer: PeerId, path: &Path, access: u8) -> bool {
+impl ResponseDecoder for FileChunk {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::FileChunk(chunk) => Ok(chunk),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for BuildInfo {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Identity(info) => Ok(info),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for () {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::MessageAck => Ok(()),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for SessionInfo {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::AuthSuccess { session } => Ok(session),
+			PeerRes::AuthFailure { reason } => Err(PeerError {
+				code: ErrorCode::AccessDenied,
+				message: reason,
+			}
+			.into()),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for IssuedToken {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::TokenIssued {
+				token,
+				token_id,
+				username,
+				permissions,
+				expires_at,
+			} => Ok(IssuedToken {
+				token,
+				token_id,
+				username,
+				permissions,
+				expires_at,
+			}),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for Vec<PermissionGrant> {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::AccessGranted { permissions, .. } => Ok(permissions),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for DiskSpace {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Space { available, total } => Ok(DiskSpace { available, total }),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for DirSize {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::DirSize(size) => Ok(size),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for FileWriteAck {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::WriteAck(ack) => Ok(ack),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for String {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::DirCreated(path) => Ok(path),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for CatalogPage {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::Catalog(page) => Ok(page),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for Vec<FileHash> {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::HashesFound(hashes) => Ok(hashes),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for Vec<Result<FileChunk, ErrorCode>> {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::FileChunks(chunks) => Ok(chunks),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+impl ResponseDecoder for FilePreview {
+	fn decode(response: PeerRes) -> anyhow::Result<Self> {
+		match response {
+			PeerRes::FilePreview(preview) => Ok(preview),
+			other => Err(anyhow!("unexpected response: {:?}", other)),
+		}
+	}
+}
+
+trait PendingResponseHandler: Send {
+	fn complete(self: Box<Self>, response: PeerRes);
+	fn fail(self: Box<Self>, error: anyhow::Error);
+}
+
+struct Pending<T: ResponseDecoder> {
+	tx: oneshot::Sender<Result<T>>,
+}
+
+impl<T: ResponseDecoder> Pending<T> {
+	fn new(tx: oneshot::Sender<Result<T>>) -> PendingRequest {
+		Box::new(Self { tx })
+	}
+}
+
+impl<T: ResponseDecoder> PendingResponseHandler for Pending<T> {
+	fn complete(self: Box<Self>, response: PeerRes) {
+		let result = match response {
+			PeerRes::Error(err) => Err(anyhow!(err)),
+			PeerRes::Failure { code, message } => Err(PeerError { code, message }.into()),
+			other => T::decode(other),
+		};
+		let _ = self.tx.send(result);
+	}
+
+	fn fail(self: Box<Self>, error: anyhow::Error) {
+		let _ = self.tx.send(Err(error));
+	}
+}
+
+type PendingRequest = Box<dyn PendingResponseHandler>;
+
+/// Delay before the first retry of a request lost to a dropped connection; doubles after that.
+const RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// A request answered from a task instead of the swarm loop.
+struct Answer {
+	peer: PeerId,
+	kind: &'static str,
+	channel: ResponseChannel<PeerRes>,
+	result: anyhow::Result<PeerRes>,
+}
+
+/// What is known about the bytes stored in a quota-limited share.
+struct ShareUsage {
+	// None until a walk finished, or when the last one failed or was cut short
+	used: Option<u64>,
+	measured_at: std::time::Instant,
+	measuring: bool,
+}
+
+/// An address from `bootstrap_peers` that is kept connected.
+struct BootstrapPeer {
+	addr: Multiaddr,
+	// From the address's `/p2p` suffix, or learned on the first connection
+	peer: Option<PeerId>,
+	failures: u32,
+	// Closed by `PuppyPeer::disconnect`; not redialed until connected again explicitly
+	disconnected: bool,
+}
+
+/// An outbound request waiting for its response.
+struct InFlight {
+	peer: PeerId,
+	/// A copy of the request, kept only when it is safe to send again.
+	retry: Option<PeerReq>,
+	/// Times the request has been sent.
+	attempts: u32,
+	pending: PendingRequest,
+}
+
+impl InFlight {
+	fn new(peer: PeerId, req: &PeerReq, pending: PendingRequest) -> Self {
+		Self {
+			peer,
+			retry: req.is_retryable().then(|| req.clone()),
+			attempts: 1,
+			pending,
+		}
+	}
+
+	/// Count another attempt and return the delay before it, or `None` when the request
+	/// has to fail.
+	///
+	/// Only failures a reconnect can fix are retried, at most `max_retries` times.
+	fn next_attempt(
+		&mut self,
+		error: &OutboundFailure,
+		max_retries: u32,
+	) -> Option<std::time::Duration> {
+		let transient = matches!(
+			error,
+			OutboundFailure::DialFailure
+				| OutboundFailure::ConnectionClosed
+				| OutboundFailure::Io(_)
+		);
+		if !transient || self.retry.is_none() || self.attempts > max_retries {
+			return None;
+		}
+		let delay = RETRY_BACKOFF * 2u32.pow(self.attempts - 1);
+		self.attempts += 1;
+		Some(delay)
+	}
+}
+
+impl App {
+	fn can_access(&self, peer: PeerId, path: &Path, access: u8) -> bool {
 		self.state
 			.lock()
 			.map(|state| state.has_fs_access(peer, path, access))
 			.unwrap_or(false)
 	}
 
-	pub fn new(state: Arc<Mutex<State>>) -> (Self, tokio::sync::mpsc::UnboundedSender<Command>) {
-		let key_path = env::var("KEYPAIR").unwrap_or_else(|_| String::from("peer_keypair.bin"));
-		let key_path = Path::new(&key_path);
+	fn send_request(&mut self, peer: PeerId, req: PeerReq, pending: PendingRequest) {
+		let in_flight = InFlight::new(peer, &req, pending);
+		self.send_in_flight(req, in_flight);
+	}
+
+	fn send_in_flight(&mut self, req: PeerReq, in_flight: InFlight) {
+		let request_id = self
+			.swarm
+			.behaviour_mut()
+			.puppypeer
+			.send_request(&in_flight.peer, req);
+		self.pending_requests.insert(request_id, in_flight);
+	}
+
+	/// Send a request again once its backoff has passed.
+	fn resend(&mut self, in_flight: InFlight) {
+		match in_flight.retry.clone() {
+			Some(req) => self.send_in_flight(req, in_flight),
+			None => in_flight.pending.fail(anyhow!("request cannot be retried")),
+		}
+	}
+
+	/// Answer `req` as if `peer` had sent it, so local calls get the same checks as remote
+	/// ones.
+	async fn serve_locally(&mut self, peer: PeerId, req: PeerReq, pending: PendingRequest) {
+		match self.handle_puppy_peer_req(peer, req).await {
+			Ok(res) => pending.complete(res),
+			Err(err) => pending.fail(err),
+		}
+	}
+
+	/// Page of the local index under `prefix`, skipping files `peer` may not read. The
+	/// rules are copied out of the state up front, so the query runs on a blocking thread
+	/// without holding the lock.
+	fn browse_readable_catalog(
+		&self,
+		peer: PeerId,
+		prefix: String,
+		offset: u64,
+		limit: u64,
+	) -> impl Future<Output = Result<CatalogPage>> + Send + 'static {
+		let snapshot = self
+			.state
+			.lock()
+			.map(|state| {
+				let readable = state.fs_access_rules(peer, FLAG_READ | FLAG_SEARCH);
+				(state.me.to_bytes(), readable)
+			})
+			.map_err(|_| anyhow!("state lock poisoned"));
+		let db_path = self.config.db_path();
+		async move {
+			let (me, readable) = snapshot?;
+			tokio::task::spawn_blocking(move || {
+				let conn = open_db(&db_path)?;
+				catalog_page(&conn, &me, &prefix, readable.as_deref(), offset, limit)
+			})
+			.await
+			.map_err(|e| anyhow!("catalog task failed: {}", e))?
+		}
+	}
+
+	/// The `hashes` indexed by the local scanner at a path `peer` may read.
+	fn find_readable_hashes(&self, peer: PeerId, hashes: Vec<FileHash>) -> Result<Vec<FileHash>> {
+		let me = self.state.lock().unwrap().me.to_bytes();
+		readable_hashes(&self.db()?, &me, hashes, |path| {
+			self.can_access(peer, path, FLAG_READ | FLAG_SEARCH)
+		})
+	}
+
+	fn db(&self) -> Result<rusqlite::Connection> {
+		open_db(&self.config.db_path())
+	}
+
+	pub fn new(state: Arc<Mutex<State>>, config: PuppyConfig) -> (Self, Sender<Command>) {
+		let key_path = config.keypair_path();
+		let key_path = key_path.as_path();
 		if !key_path.exists() {
 			log::warn!(
 				"keypair file {} does not exist, generating new keypair",
@@ -232,27 +1317,57 @@ impl App {
 		});
 		let peer_id = PeerId::from(id_keys.public());
 
-		let mut swarm = build_swarm(id_keys, peer_id).unwrap();
-		let stored_permissions = {
-			let mut conn = open_db();
-			if let Err(err) = run_migrations(&mut conn) {
-				log::error!("failed to run database migrations: {err}");
-				Vec::new()
-			} else {
-				match load_peer_permissions(&conn, &peer_id) {
+		let mut swarm = build_swarm(id_keys, peer_id, config.reconnect.idle_timeout()).unwrap();
+		let (stored_permissions, stored_names) = match open_db(&config.db_path()) {
+			Err(err) => {
+				log::error!("{err:#}; starting without stored permissions and nicknames");
+				(Vec::new(), Vec::new())
+			}
+			Ok(conn) => {
+				let permissions = match load_peer_permissions(&conn, &peer_id) {
 					Ok(perms) => perms,
 					Err(err) => {
 						log::error!("failed to load peer permissions: {err}");
 						Vec::new()
 					}
-				}
+				};
+				let names = load_peer_names(&conn).unwrap_or_else(|err| {
+					log::error!("failed to load peer names: {err}");
+					Vec::new()
+				});
+				(permissions, names)
 			}
 		};
-		let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+		// bounded so a flood of calls waits for the swarm loop instead of queueing without limit
+		let (tx, rx) = tokio::sync::mpsc::channel(COMMAND_CHANNEL_CAPACITY);
+		let (retry_tx, retry_rx) = tokio::sync::mpsc::unbounded_channel();
+		let (redial_tx, redial_rx) = tokio::sync::mpsc::unbounded_channel();
+		let (usage_tx, usage_rx) = tokio::sync::mpsc::unbounded_channel();
+		let bootstrap: Vec<BootstrapPeer> = config
+			.bootstrap_peers
+			.iter()
+			.map(|addr| BootstrapPeer {
+				addr: addr.clone(),
+				peer: match addr.iter().last() {
+					Some(Protocol::P2p(peer)) => Some(peer),
+					_ => None,
+				},
+				failures: 0,
+				disconnected: false,
+			})
+			.collect();
 
-		let listen_addr = "/ip4/0.0.0.0/tcp/0".parse().unwrap();
-		if let Err(err) = swarm.listen_on(listen_addr) {
-			log::warn!("failed to start swarm listener: {err}");
+		// without a listener the peer can still dial out, so it counts as ready at once
+		let (ready, _) = watch::channel(false);
+		let mut listening = false;
+		for addr in &config.listen_addrs {
+			match swarm.listen_on(addr.clone()) {
+				Ok(_) => listening = true,
+				Err(err) => log::warn!("failed to start swarm listener on {addr}: {err}"),
+			}
+		}
+		if !listening {
+			ready.send_replace(true);
 		}
 		{
 			if let Ok(mut s) = state.lock() {
@@ -260,18 +1375,257 @@ impl App {
 				for (target, permissions) in stored_permissions {
 					s.set_peer_permissions_from_storage(target, permissions);
 				}
+				for (target, name) in stored_names {
+					s.set_peer_name(target, Some(name));
+				}
+				// list bootstrap peers with a known id before they connect
+				for entry in &bootstrap {
+					if let Some(target) = entry.peer {
+						s.peer_discovered(target, entry.addr.clone());
+						swarm.behaviour_mut().keep_alive(target, true);
+					}
+				}
 			}
 		}
-		(
-			App {
-				state,
-				swarm,
-				rx,
-				pending_requests: HashMap::new(),
-				system: System::new(),
-			},
-			tx,
-		)
+		// usage is measured between two refreshes, so take the first one now
+		let mut system = System::new();
+		system.refresh_cpu_usage();
+		let mut sample_timer = tokio::time::interval_at(
+			tokio::time::Instant::now() + SYSTEM_SAMPLE_INTERVAL,
+			SYSTEM_SAMPLE_INTERVAL,
+		);
+		sample_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+		let mut app = App {
+			state,
+			swarm,
+			rx,
+			pending_requests: HashMap::new(),
+			retry_tx,
+			retry_rx,
+			system,
+			atomic_writes: AtomicWrites::default(),
+			config,
+			share_usage: HashMap::new(),
+			usage_tx,
+			usage_rx,
+			ready,
+			bootstrap,
+			bootstrap_dials: HashMap::new(),
+			answers: FuturesUnordered::new(),
+			sample_timer,
+			redial_tx,
+			redial_rx,
+			events: broadcast::channel(PEER_EVENT_CAPACITY).0,
+		};
+		for idx in 0..app.bootstrap.len() {
+			app.dial_bootstrap(idx);
+		}
+		(app, tx)
+	}
+
+	fn dial_bootstrap(&mut self, idx: usize) {
+		let entry = &self.bootstrap[idx];
+		if entry.disconnected
+			|| entry
+				.peer
+				.is_some_and(|peer| self.swarm.is_connected(&peer))
+		{
+			return;
+		}
+		let opts = DialOpts::unknown_peer_id()
+			.address(entry.addr.clone())
+			.build();
+		let connection_id = opts.connection_id();
+		match self.swarm.dial(opts) {
+			Ok(()) => {
+				self.bootstrap_dials.insert(connection_id, idx);
+			}
+			Err(err) => {
+				log::warn!("failed to dial bootstrap peer {}: {err}", entry.addr);
+				self.schedule_redial(idx);
+			}
+		}
+	}
+
+	/// Dial `peer_id` on the best of `addresses` plus the ones it was discovered on, one
+	/// address at a time, stopping at the first that connects.
+	fn dial_peer(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>) {
+		let known = self
+			.state
+			.lock()
+			.ok()
+			.and_then(|state| {
+				state
+					.discovered_peers
+					.iter()
+					.find(|p| p.peer_id == peer_id)
+					.map(|p| p.addresses.clone())
+			})
+			.unwrap_or_default();
+		let ordered = dial_order(addresses.into_iter().chain(known));
+		let opts = DialOpts::peer_id(peer_id)
+			.addresses(ordered)
+			.override_dial_concurrency_factor(NonZeroU8::MIN)
+			.build();
+		match self.swarm.dial(opts) {
+			Ok(()) => {}
+			// already connected or a dial is under way
+			Err(DialError::DialPeerConditionFalse(_)) => {}
+			Err(err) => log::warn!("failed to dial {}: {err}", peer_id),
+		}
+	}
+
+	/// Whether `addr` points at this machine: loopback, or an address we listen on.
+	fn is_own_address(&self, addr: &Multiaddr) -> bool {
+		let Some(ip) = ip_of(addr) else {
+			return false;
+		};
+		ip.is_loopback()
+			|| self
+				.swarm
+				.listeners()
+				.any(|listen| ip_of(listen) == Some(ip))
+	}
+
+	/// A connection at `addr` claimed this node's own peer id. Dialing ourselves explains
+	/// that; another machine means it runs with a copy of our keypair.
+	fn check_own_id_at(&self, addr: &Multiaddr) {
+		if self.is_own_address(addr) {
+			log::debug!("ignoring connection to ourselves at {}", addr);
+			return;
+		}
+		self.warn_identity(format!(
+			"Another machine at {} uses this node's peer id, probably from a copied keypair \
+			 file. Delete {} on one of them and restart it to get a new identity.",
+			addr,
+			self.config.keypair_path().display()
+		));
+	}
+
+	fn warn_identity(&self, warning: String) {
+		if let Ok(mut state) = self.state.lock() {
+			if state.identity_warning.as_ref() == Some(&warning) {
+				return;
+			}
+			log::warn!("{warning}");
+			state.identity_warning = Some(warning);
+		}
+	}
+
+	fn schedule_redial(&mut self, idx: usize) {
+		let entry = &mut self.bootstrap[idx];
+		if !self.config.reconnect.enabled || entry.disconnected {
+			return;
+		}
+		let delay = self.config.reconnect.backoff(entry.failures);
+		entry.failures += 1;
+		log::info!("redialing bootstrap peer {} in {:?}", entry.addr, delay);
+		let redial_tx = self.redial_tx.clone();
+		tokio::spawn(async move {
+			tokio::time::sleep(delay).await;
+			let _ = redial_tx.send(idx);
+		});
+	}
+
+	/// Receiver that turns `true` once the peer is listening for connections.
+	pub fn ready(&self) -> watch::Receiver<bool> {
+		self.ready.subscribe()
+	}
+
+	/// Sender that `PuppyPeer::subscribe` hands out receivers of.
+	pub fn events(&self) -> broadcast::Sender<PeerEvent> {
+		self.events.clone()
+	}
+
+	fn emit(&self, event: PeerEvent) {
+		// no subscribers is the normal case, not an error
+		let _ = self.events.send(event);
+	}
+
+	fn send_answer(&mut self, answer: Answer) {
+		let Answer {
+			peer,
+			kind,
+			channel,
+			result,
+		} = answer;
+		let res = match result {
+			Ok(res) => res,
+			Err(err) => {
+				log::warn!("failed to handle {} from {}: {err:#}", kind, peer);
+				PeerRes::Error("Internal error".into())
+			}
+		};
+		if self
+			.swarm
+			.behaviour_mut()
+			.puppypeer
+			.send_response(channel, res)
+			.is_err()
+		{
+			// the channel closes when the requester disconnects or gives up waiting
+			log::warn!(
+				"could not answer {} from {}: the request channel is closed",
+				kind,
+				peer
+			);
+			if let Ok(mut state) = self.state.lock() {
+				state.failed_responses += 1;
+			}
+		}
+	}
+
+	/// The walk answering `req` when it is a `DirSize` request `peer` may make. Anything
+	/// else, including a refused request, is answered in place by `handle_puppy_peer_req`.
+	async fn dir_size_walk(
+		&mut self,
+		peer: PeerId,
+		req: &PeerReq,
+	) -> Option<impl Future<Output = anyhow::Result<PeerRes>> + Send + 'static> {
+		if !matches!(req, PeerReq::DirSize { .. })
+			|| req.validate().is_err()
+			|| req.has_parent_dir()
+		{
+			return None;
+		}
+		let PeerReq::DirSize { path } = req.clone().with_normalized_paths() else {
+			return None;
+		};
+		let canonical = fs::canonicalize(&path).await.ok()?;
+		if !self.can_access(peer, &canonical, FLAG_READ | FLAG_SEARCH) {
+			return None;
+		}
+		log::info!("[{}] DirSize {}", peer, path);
+		Some(async move { Ok(PeerRes::DirSize(dir_size(canonical).await?)) })
+	}
+
+	/// The query answering `req` when it is a `BrowseCatalog` request within the page
+	/// limit. Anything else is answered in place by `handle_puppy_peer_req`.
+	fn catalog_query(
+		&self,
+		peer: PeerId,
+		req: &PeerReq,
+	) -> Option<impl Future<Output = anyhow::Result<PeerRes>> + Send + 'static> {
+		if !matches!(req, PeerReq::BrowseCatalog { .. })
+			|| req.validate().is_err()
+			|| req.has_parent_dir()
+		{
+			return None;
+		}
+		let PeerReq::BrowseCatalog {
+			prefix,
+			offset,
+			limit,
+		} = req.clone().with_normalized_paths()
+		else {
+			return None;
+		};
+		if limit > MAX_CATALOG_PAGE {
+			return None;
+		}
+		log::info!("[{}] BrowseCatalog {} ({}+{})", peer, prefix, offset, limit);
+		let page = self.browse_readable_catalog(peer, prefix, offset, limit);
+		Some(async move { Ok(PeerRes::Catalog(page.await?)) })
 	}
 
 	async fn handle_puppy_peer_req(
@@ -279,11 +1633,32 @@ impl App {
 		peer: PeerId,
 		req: PeerReq,
 	) -> anyhow::Result<PeerRes> {
-		let res = match req {
-			PeerReq::ListDir { path } => {
+		if let Err(message) = req.validate() {
+			log::warn!("peer {} sent an invalid {}: {}", peer, req.kind(), message);
+			return Ok(PeerRes::Failure {
+				code: ErrorCode::InvalidArgument,
+				message,
+			});
+		}
+		if req.has_parent_dir() {
+			log::warn!("peer {} sent a path with .. in {:?}", peer, req.paths());
+			return Ok(PeerRes::Failure {
+				code: ErrorCode::InvalidArgument,
+				message: String::from("Path must not contain .."),
+			});
+		}
+		let res = match req.with_normalized_paths().with_clamped_lengths() {
+			PeerReq::ListDir { path } => {
 				log::info!("[{}] ListDir {}", peer, path);
 				let canonical = match fs::canonicalize(&path).await {
 					Ok(p) => p,
+					Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+						log::info!("[{}] ListDir {} no longer exists", peer, path);
+						return Ok(PeerRes::Failure {
+							code: ErrorCode::NotFound,
+							message: String::from("Folder no longer exists"),
+						});
+					}
 					Err(err) => {
 						log::warn!("failed to canonicalize directory {}: {err}", path);
 						return Ok(PeerRes::Error(format!("Failed to access directory: {err}")));
@@ -297,8 +1672,77 @@ impl App {
 					);
 					return Ok(PeerRes::Error("Access denied".into()));
 				}
-				let entries = Self::collect_dir_entries(&canonical).await?;
-				PeerRes::DirEntries(entries)
+				let state = self.state.clone();
+				let listed = Self::collect_dir_entries(&canonical, move |target| {
+					state
+						.lock()
+						.map(|state| state.has_fs_access(peer, target, FLAG_READ | FLAG_SEARCH))
+						.unwrap_or(false)
+				})
+				.await;
+				match listed {
+					Ok(entries) => PeerRes::DirEntries(entries),
+					// e.g. a file was asked for, which the requester can open instead
+					Err(err) => match err.downcast::<PeerError>() {
+						Ok(PeerError { code, message }) => PeerRes::Failure { code, message },
+						Err(err) => return Err(err),
+					},
+				}
+			}
+			PeerReq::ListDirRecursive { path, max_depth } => {
+				log::info!(
+					"[{}] ListDirRecursive {} ({} levels)",
+					peer,
+					path,
+					max_depth
+				);
+				if max_depth == 0 {
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::InvalidArgument,
+						message: String::from("max_depth must be at least 1"),
+					});
+				}
+				let canonical = match fs::canonicalize(&path).await {
+					Ok(p) => p,
+					Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+						return Ok(PeerRes::Failure {
+							code: ErrorCode::NotFound,
+							message: String::from("Folder no longer exists"),
+						});
+					}
+					Err(err) => {
+						log::warn!("failed to canonicalize directory {}: {err}", path);
+						return Ok(PeerRes::Error(format!("Failed to access directory: {err}")));
+					}
+				};
+				if !self.can_access(peer, &canonical, FLAG_READ | FLAG_SEARCH) {
+					log::warn!(
+						"peer {} denied recursive listing for {}",
+						peer,
+						canonical.display()
+					);
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				let state = self.state.clone();
+				let listed = Self::collect_dir_tree(
+					&canonical,
+					max_depth,
+					self.config.max_tree_entries,
+					move |target| {
+						state
+							.lock()
+							.map(|state| state.has_fs_access(peer, target, FLAG_READ | FLAG_SEARCH))
+							.unwrap_or(false)
+					},
+				)
+				.await;
+				match listed {
+					Ok(tree) => PeerRes::DirTree(tree),
+					Err(err) => match err.downcast::<PeerError>() {
+						Ok(PeerError { code, message }) => PeerRes::Failure { code, message },
+						Err(err) => return Err(err),
+					},
+				}
 			}
 			PeerReq::StatFile { path } => {
 				log::info!("[{}] StatFile {}", peer, path);
@@ -331,6 +1775,8 @@ impl App {
 						.and_then(|s| s.to_str().map(|s| s.to_string()))
 						.unwrap_or_default(),
 					is_dir: file_type.is_dir(),
+					is_symlink: false,
+					target_is_dir: false,
 					extension: ext,
 					mime,
 					size: meta.len(),
@@ -373,13 +1819,140 @@ impl App {
 				}
 				PeerRes::FileChunk(read_file(canonical.as_path(), offset, length).await?)
 			}
-			PeerReq::WriteFile { path, offset, data } => {
+			PeerReq::ReadFiles { requests } => {
+				log::info!("[{}] ReadFiles ({} files)", peer, requests.len());
+				if requests.len() > MAX_BATCH_FILES {
+					return Ok(PeerRes::Error(format!(
+						"Too many files in batch (max {MAX_BATCH_FILES})"
+					)));
+				}
+				let state = self.state.clone();
+				PeerRes::FileChunks(
+					read_files(requests, move |target| {
+						state
+							.lock()
+							.map(|state| state.has_fs_access(peer, target, FLAG_READ | FLAG_SEARCH))
+							.unwrap_or(false)
+					})
+					.await,
+				)
+			}
+			PeerReq::PreviewFile { path, bytes } => {
+				log::info!("[{}] PreviewFile {} ({} bytes)", peer, path, bytes);
+				let canonical = match fs::canonicalize(&path).await {
+					Ok(p) => p,
+					Err(err) => {
+						log::warn!("failed to canonicalize preview path {}: {err}", path);
+						return Ok(PeerRes::Error(format!("Failed to access file: {err}")));
+					}
+				};
+				if !self.can_access(peer, &canonical, FLAG_READ | FLAG_SEARCH) {
+					log::warn!("peer {} denied preview for {}", peer, canonical.display());
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				PeerRes::FilePreview(preview_file(canonical.as_path(), bytes).await?)
+			}
+			PeerReq::TailFile {
+				path,
+				offset,
+				from_end_bytes,
+			} => {
+				log::debug!("[{}] TailFile {} from {:?}", peer, path, offset);
+				let canonical = match fs::canonicalize(&path).await {
+					Ok(p) => p,
+					Err(err) => {
+						log::warn!("failed to canonicalize tail path {}: {err}", path);
+						return Ok(PeerRes::Error(format!("Failed to access file: {err}")));
+					}
+				};
+				if !self.can_access(peer, &canonical, FLAG_READ | FLAG_SEARCH) {
+					log::warn!("peer {} denied tail for {}", peer, canonical.display());
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				let len = fs::metadata(&canonical).await?.len();
+				let start = tail_start(len, offset, from_end_bytes);
+				PeerRes::FileChunk(
+					read_file(canonical.as_path(), start, Some(MAX_TAIL_CHUNK)).await?,
+				)
+			}
+			PeerReq::DirSize { path } => {
+				log::info!("[{}] DirSize {}", peer, path);
+				let canonical = match fs::canonicalize(&path).await {
+					Ok(p) => p,
+					Err(err) => {
+						log::warn!("failed to canonicalize directory {}: {err}", path);
+						return Ok(PeerRes::Error(format!("Failed to access directory: {err}")));
+					}
+				};
+				if !self.can_access(peer, &canonical, FLAG_READ | FLAG_SEARCH) {
+					log::warn!("peer {} denied dir size for {}", peer, canonical.display());
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				PeerRes::DirSize(dir_size(canonical).await?)
+			}
+			PeerReq::SpaceFor { path } => {
+				log::info!("[{}] SpaceFor {}", peer, path);
+				let Some(canonical) = existing_ancestor(Path::new(&path)).await else {
+					return Ok(PeerRes::Error("Invalid path".into()));
+				};
+				if !self.can_access(peer, &canonical, FLAG_READ)
+					&& !self.can_access(peer, &canonical, FLAG_WRITE)
+				{
+					log::warn!("peer {} denied space for {}", peer, canonical.display());
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				match disk_space(&canonical) {
+					Some(space) => PeerRes::Space {
+						available: space.available,
+						total: space.total,
+					},
+					None => PeerRes::Failure {
+						code: ErrorCode::NotFound,
+						message: String::from("No filesystem found for path"),
+					},
+				}
+			}
+			PeerReq::FindHashes { hashes } => {
+				log::info!("[{}] FindHashes {}", peer, hashes.len());
+				if hashes.len() > MAX_FIND_HASHES {
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::LimitExceeded,
+						message: format!("at most {} hashes per request", MAX_FIND_HASHES),
+					});
+				}
+				PeerRes::HashesFound(self.find_readable_hashes(peer, hashes)?)
+			}
+			PeerReq::BrowseCatalog {
+				prefix,
+				offset,
+				limit,
+			} => {
+				log::info!("[{}] BrowseCatalog {} ({}+{})", peer, prefix, offset, limit);
+				if limit > MAX_CATALOG_PAGE {
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::LimitExceeded,
+						message: format!("at most {} entries per page", MAX_CATALOG_PAGE),
+					});
+				}
+				PeerRes::Catalog(
+					self.browse_readable_catalog(peer, prefix, offset, limit)
+						.await?,
+				)
+			}
+			PeerReq::WriteFile {
+				path,
+				offset,
+				data,
+				mode,
+				times,
+			} => {
 				log::info!(
-					"[{}] WriteFile {} (offset {}, {} bytes)",
+					"[{}] WriteFile {} (offset {}, {} bytes, {:?})",
 					peer,
 					path,
 					offset,
-					data.len()
+					data.len(),
+					mode
 				);
 				let requested_path = PathBuf::from(&path);
 				let canonical = match fs::metadata(&requested_path).await {
@@ -411,6 +1984,7 @@ impl App {
 							}
 						};
 						match requested_path.file_name() {
+							// a single normal component, so the target stays in the parent
 							Some(name) => canonical_parent.join(name),
 							None => {
 								log::warn!(
@@ -427,36 +2001,185 @@ impl App {
 					log::warn!("peer {} denied write for {}", peer, canonical.display());
 					return Ok(PeerRes::Error("Access denied".into()));
 				}
-				PeerRes::WriteAck(write_file(canonical.as_path(), offset, &data).await?)
+				// this node owns every path, but a folder it shares read-only stays read-only
+				// for its own writes too, so local edits match what peers are told
+				let read_only = {
+					let state = self.state.lock().unwrap();
+					peer == state.me
+						&& state
+							.shared_folder_for(&canonical)
+							.is_some_and(|rule| !rule.can_write())
+				};
+				if read_only {
+					log::warn!(
+						"denied local write to read-only share {}",
+						canonical.display()
+					);
+					return Ok(PeerRes::Error(
+						"Access denied: folder is shared read-only".into(),
+					));
+				}
+				let destination = fs::metadata(&canonical)
+					.await
+					.map(|meta| meta.len())
+					.unwrap_or(0);
+				// an atomic write replaces the destination, so only bytes beyond both it
+				// and what is already staged are new
+				let existing = match mode {
+					WriteMode::Atomic { .. } if offset > 0 => self
+						.atomic_writes
+						.staged_len(peer, &canonical)
+						.await
+						.unwrap_or(0)
+						.max(destination),
+					WriteMode::Atomic { .. } | WriteMode::InPlace => destination,
+				};
+				let growth = offset
+					.saturating_add(data.len() as u64)
+					.saturating_sub(existing);
+				if let Some(rejection) = self.check_write_space(&canonical, growth) {
+					log::warn!(
+						"peer {} write to {} rejected: {:?}",
+						peer,
+						canonical.display(),
+						rejection
+					);
+					return Ok(rejection);
+				}
+				let (file_mode, _) = self.create_modes(&canonical);
+				let res = match mode {
+					WriteMode::InPlace => {
+						let created = fs::metadata(&canonical).await.is_err();
+						let ack = write_file(canonical.as_path(), offset, &data).await?;
+						if created && let Err(err) = apply_create_mode(&canonical, file_mode).await
+						{
+							log::warn!("failed to set mode of {}: {err}", canonical.display());
+							return Ok(PeerRes::Failure {
+								code: ErrorCode::from_io(&err),
+								message: format!("Failed to set file mode: {err}"),
+							});
+						}
+						PeerRes::WriteAck(ack)
+					}
+					WriteMode::Atomic { last } => match self
+						.atomic_writes
+						.write(peer, &canonical, offset, &data, last, file_mode)
+						.await
+					{
+						Ok(ack) => PeerRes::WriteAck(ack),
+						Err(err) => {
+							log::warn!("atomic write to {} failed: {err}", canonical.display());
+							PeerRes::Error(format!("Write failed: {err}"))
+						}
+					},
+				};
+				if !matches!(res, PeerRes::WriteAck(_)) {
+					return Ok(res);
+				}
+				self.record_share_growth(&canonical, growth);
+				let in_place =
+					matches!(mode, WriteMode::InPlace | WriteMode::Atomic { last: true });
+				if let Some(times) = times
+					&& in_place && let Err(err) = set_file_times(&canonical, times).await
+				{
+					log::warn!("failed to set times of {}: {err}", canonical.display());
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::from_io(&err),
+						message: format!("Failed to set file times: {err}"),
+					});
+				}
+				res
+			}
+			PeerReq::CreateDir { path } => {
+				log::info!("[{}] CreateDir {}", peer, path);
+				let requested = PathBuf::from(&path);
+				// resolve the deepest existing ancestor so symlinks cannot lead out of a share
+				let mut existing = requested.as_path();
+				let mut missing = Vec::new();
+				let canonical = loop {
+					match fs::canonicalize(existing).await {
+						Ok(p) => break p,
+						Err(_) => match (existing.parent(), existing.file_name()) {
+							(Some(parent), Some(name)) => {
+								missing.push(name.to_os_string());
+								existing = parent;
+							}
+							_ => {
+								log::warn!("peer {} provided invalid directory {}", peer, path);
+								return Ok(PeerRes::Error("Invalid path".into()));
+							}
+						},
+					}
+				};
+				let created: Vec<PathBuf> = missing
+					.iter()
+					.rev()
+					.scan(canonical.clone(), |dir, name| {
+						*dir = dir.join(name);
+						Some(dir.clone())
+					})
+					.collect();
+				let target = created.last().cloned().unwrap_or(canonical);
+				if !self.can_access(peer, &target, FLAG_WRITE | FLAG_READ | FLAG_SEARCH) {
+					log::warn!("peer {} denied mkdir for {}", peer, target.display());
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				let (_, dir_mode) = self.create_modes(&target);
+				if let Err(err) = fs::create_dir_all(&target).await {
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::from_io(&err),
+						message: format!("Failed to create directory: {err}"),
+					});
+				}
+				for dir in &created {
+					if let Err(err) = apply_create_mode(dir, dir_mode).await {
+						log::warn!("failed to set mode of {}: {err}", dir.display());
+						return Ok(PeerRes::Failure {
+							code: ErrorCode::from_io(&err),
+							message: format!("Failed to set directory mode: {err}"),
+						});
+					}
+				}
+				PeerRes::DirCreated(target.to_string_lossy().into_owned())
 			}
 			PeerReq::ListCpus => {
 				let cpus = self.collect_cpu_info();
 				PeerRes::Cpus(cpus)
 			}
-			PeerReq::ListDisks => PeerRes::Error("ListDisks not implemented".into()),
-			PeerReq::ListInterfaces => {
-				let networks = Networks::new_with_refreshed_list();
-				let infos = networks
-					.iter()
-					.map(|(name, data)| InterfaceInfo {
-						name: name.clone(),
-						mac: data.mac_address().to_string(),
-						ips: data.ip_networks().iter().map(|ip| ip.to_string()).collect(),
-						total_received: data.total_received(),
-						total_transmitted: data.total_transmitted(),
-						packets_received: data.total_packets_received(),
-						packets_transmitted: data.total_packets_transmitted(),
-						errors_on_received: data.total_errors_on_received(),
-						errors_on_transmitted: data.total_errors_on_transmitted(),
-						mtu: data.mtu(),
-					})
-					.collect();
-				PeerRes::Interfaces(infos)
+			PeerReq::ListDisks => match collect_disk_info() {
+				Ok(disks) => PeerRes::Disks(disks),
+				Err(err) => PeerRes::Error(format!("Failed to list disks: {err}")),
+			},
+			PeerReq::ListInterfaces => PeerRes::Interfaces(collect_interface_info()),
+			PeerReq::SystemOverview => PeerRes::Overview(self.collect_overview()),
+			PeerReq::SystemLoad => PeerRes::Load(SystemLoad::current()),
+			PeerReq::Identify => {
+				log::info!("[{}] Identify", peer);
+				PeerRes::Identity(BuildInfo::current())
+			}
+			PeerReq::Message { text } => {
+				log::info!("[{}] Message ({} bytes)", peer, text.len());
+				if text.len() > MAX_MESSAGE_BYTES {
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::InvalidArgument,
+						message: format!("Message is longer than {MAX_MESSAGE_BYTES} bytes"),
+					});
+				}
+				self.state.lock().unwrap().push_message(PeerMessage {
+					from: peer,
+					text: text.clone(),
+					received_at: Utc::now(),
+				});
+				self.emit(PeerEvent::Message {
+					peer_id: peer.to_string(),
+					text,
+				});
+				PeerRes::MessageAck
 			}
 			PeerReq::ListPermissions => {
 				log::info!("[{}] ListPermissions", peer);
 				let permissions = match self.state.lock() {
-					Ok(state) => state.permissions_for_peer(&peer),
+					Ok(state) => state.effective_permissions(&peer),
 					Err(err) => {
 						log::error!("state lock poisoned while listing permissions: {}", err);
 						return Ok(PeerRes::Error("State unavailable".into()));
@@ -464,18 +2187,51 @@ impl App {
 				};
 				PeerRes::Permissions(permissions)
 			}
-			PeerReq::Authenticate { method } => match method {
-				AuthMethod::Token { token } => todo!(),
-				AuthMethod::Credentials { username, password } => todo!(),
-			},
+			PeerReq::Authenticate { method } => {
+				let session = match method {
+					AuthMethod::Token { token } => {
+						log::info!("[{}] Authenticate with a token", peer);
+						let found = find_token(&self.db()?, &hash_token(&token))?;
+						session_for_token(found, now_timestamp())
+					}
+					AuthMethod::Credentials { username, password } => {
+						log::info!("[{}] Authenticate as {}", peer, username);
+						let state = self.state.lock().unwrap();
+						let user = state.users.iter().find(|user| user.name == username);
+						session_for_credentials(user, &password, now_timestamp())
+					}
+				};
+				match session {
+					Ok(session) => {
+						log::info!("peer {} signed in as {}", peer, session.username);
+						self.swarm.behaviour_mut().keep_alive(peer, true);
+						self.state
+							.lock()
+							.unwrap()
+							.sessions
+							.insert(peer, session.clone());
+						PeerRes::AuthSuccess { session }
+					}
+					Err(reason) => {
+						log::warn!("peer {} failed to authenticate: {}", peer, reason);
+						PeerRes::AuthFailure { reason }
+					}
+				}
+			}
 			PeerReq::CreateUser {
 				username,
 				password,
 				roles,
 				permissions,
 			} => {
+				log::info!("[{}] CreateUser {}", peer, username);
 				let mut state = self.state.lock().unwrap();
-				state.create_user(username.clone(), password)?;
+				// users sign in with the permissions given here, so only owners may add them
+				if !state.is_owner(&peer) {
+					log::warn!("peer {} denied creating user {}", peer, username);
+					return Ok(PeerRes::Error("Access denied".into()));
+				}
+				state.create_user(username.clone(), password, roles, permissions)?;
 				PeerRes::UserCreated { username }
 			}
 			PeerReq::CreateToken {
@@ -484,19 +2240,81 @@ impl App {
 				expires_in,
 				permissions,
 			} => {
-				let mut state = self.state.lock().unwrap();
-				if !state.users.iter().any(|u| u.name == username) {
-					return Ok(PeerRes::Error("User does not exist".into()));
+				log::info!("[{}] CreateToken for {}", peer, username);
+				{
+					let state = self.state.lock().unwrap();
+					if !state.is_owner(&peer) {
+						log::warn!("peer {} denied creating a token for {}", peer, username);
+						return Ok(PeerRes::Error("Access denied".into()));
+					}
+					if !state.users.iter().any(|u| u.name == username) {
+						return Ok(PeerRes::Error("User does not exist".into()));
+					}
 				}
+				let token = generate_token();
+				let now = now_timestamp();
+				let info = TokenInfo {
+					id: uuid::Uuid::new_v4().simple().to_string(),
+					username,
+					label,
+					permissions,
+					expires_at: expires_in.map(|secs| now.saturating_add(secs)),
+					revoked: false,
+					issued_at: now,
+					issued_by: peer.to_string(),
+				};
+				save_token(&self.db()?, &info, &hash_token(&token))?;
 				PeerRes::TokenIssued {
-					token: "".into(),
-					token_id: "".into(),
-					username: username.clone(),
-					permissions: Vec::new(),
-					expires_at: None,
+					token,
+					token_id: info.id,
+					username: info.username,
+					permissions: info.permissions,
+					expires_at: info.expires_at,
+				}
+			}
+			PeerReq::GrantAccess {
+				peer_id,
+				permissions,
+				merge,
+			} => {
+				log::info!("[{}] GrantAccess to {} (merge: {})", peer, peer_id, merge);
+				let target = match peer_id.parse::<PeerId>() {
+					Ok(target) => target,
+					Err(err) => {
+						return Ok(PeerRes::Failure {
+							code: ErrorCode::InvalidArgument,
+							message: format!("Invalid peer id {peer_id}: {err}"),
+						});
+					}
+				};
+				let mut state = self.state.lock().unwrap();
+				if !state.is_owner(&peer) {
+					log::warn!("peer {} denied granting access to {}", peer, target);
+					return Ok(PeerRes::Failure {
+						code: ErrorCode::AccessDenied,
+						message: String::from("Only owners may grant access"),
+					});
+				}
+				let existing = state.permissions_granted_to_peer(&target);
+				let granted = match granted_permissions(existing, &permissions, merge) {
+					Ok(granted) => granted,
+					Err(message) => {
+						return Ok(PeerRes::Failure {
+							code: ErrorCode::InvalidArgument,
+							message,
+						});
+					}
+				};
+				state.set_peer_permissions(target, granted.clone());
+				state.save_changes(&self.config.db_path())?;
+				PeerRes::AccessGranted {
+					peer_id,
+					permissions: granted
+						.iter()
+						.map(|permission| PermissionGrant::from_rule(permission.rule()))
+						.collect(),
 				}
 			}
-			PeerReq::GrantAccess { .. } => PeerRes::Error("GrantAccess not implemented".into()),
 			PeerReq::ListUsers => PeerRes::Error("ListUsers not implemented".into()),
 			PeerReq::ListTokens { .. } => PeerRes::Error("ListTokens not implemented".into()),
 			PeerReq::RevokeToken { .. } => PeerRes::Error("RevokeToken not implemented".into()),
@@ -505,6 +2323,128 @@ impl App {
 		Ok(res)
 	}
 
+	/// Modes the share holding `target` gives to new files and directories.
+	fn create_modes(&self, target: &Path) -> (Option<u32>, Option<u32>) {
+		self.state
+			.lock()
+			.ok()
+			.and_then(|state| {
+				state
+					.shared_folder_for(target)
+					.map(|rule| (rule.file_mode(), rule.dir_mode()))
+			})
+			.unwrap_or_default()
+	}
+
+	/// Refuse writes that would eat into the free-space reserve or exceed a share quota.
+	/// A share whose usage is not known, because it is still being measured or its last
+	/// walk was cut short, refuses every write rather than guessing.
+	fn check_write_space(&mut self, target: &Path, growth: u64) -> Option<PeerRes> {
+		if growth == 0 {
+			return None;
+		}
+		if let Some(available) = available_space(target)
+			&& available.saturating_sub(growth) < self.config.min_free_space
+		{
+			return Some(PeerRes::Failure {
+				code: ErrorCode::NoSpace,
+				message: format!(
+					"Write of {} bytes would leave less than {} bytes free",
+					growth, self.config.min_free_space
+				),
+			});
+		}
+		let share = self
+			.state
+			.lock()
+			.ok()
+			.and_then(|state| state.shared_folder_for(target).cloned())?;
+		let quota = share.max_bytes()?;
+		let usage = self
+			.share_usage
+			.entry(share.path().to_path_buf())
+			.or_insert(ShareUsage {
+				used: None,
+				measured_at: std::time::Instant::now(),
+				measuring: false,
+			});
+		let stale = usage.used.is_none() || usage.measured_at.elapsed() >= SHARE_USAGE_TTL;
+		if stale && !usage.measuring {
+			// a walk takes up to DIR_SIZE_TIMEOUT, so it runs in a task and reports back
+			usage.measuring = true;
+			let root = share.path().to_path_buf();
+			let usage_tx = self.usage_tx.clone();
+			tokio::spawn(async move {
+				let used = match dir_size(root.clone()).await {
+					Ok(size) if !size.partial => Some(size.total_bytes),
+					Ok(_) => {
+						log::warn!("share {} could not be measured completely", root.display());
+						None
+					}
+					Err(err) => {
+						log::warn!("failed to measure share {}: {err}", root.display());
+						None
+					}
+				};
+				let _ = usage_tx.send((root, used));
+			});
+		}
+		let Some(used) = usage.used else {
+			return Some(PeerRes::Failure {
+				code: ErrorCode::Io,
+				message: format!(
+					"Usage of the share {} is not known yet, retry shortly",
+					share.path().display()
+				),
+			});
+		};
+		if used.saturating_add(growth) > quota {
+			return Some(PeerRes::Failure {
+				code: ErrorCode::NoSpace,
+				message: format!(
+					"Share quota of {} bytes exceeded ({} bytes used)",
+					quota, used
+				),
+			});
+		}
+		None
+	}
+
+	/// Store the result of a share walk started by `check_write_space`.
+	fn share_measured(&mut self, root: PathBuf, used: Option<u64>) {
+		self.share_usage.insert(
+			root,
+			ShareUsage {
+				used,
+				measured_at: std::time::Instant::now(),
+				measuring: false,
+			},
+		);
+	}
+
+	fn record_share_growth(&mut self, target: &Path, growth: u64) {
+		if let Some(used) = self
+			.share_usage
+			.iter_mut()
+			.find(|(root, _)| target.starts_with(root))
+			.and_then(|(_, usage)| usage.used.as_mut())
+		{
+			*used = used.saturating_add(growth);
+		}
+	}
+
+	/// Record this node's CPU usage and temperatures, writing them off the swarm loop.
+	fn sample_system(&mut self) {
+		let cpus = self.collect_cpu_info();
+		let me = self.state.lock().unwrap().me;
+		let db_path = self.config.db_path();
+		tokio::task::spawn_blocking(move || {
+			if let Err(err) = record_system_samples(&db_path, &me, &cpus) {
+				log::warn!("failed to record system history: {err:#}");
+			}
+		});
+	}
+
 	fn collect_cpu_info(&mut self) -> Vec<CpuInfo> {
 		self.system.refresh_cpu_usage();
 		self.system
@@ -518,12 +2458,78 @@ impl App {
 			.collect()
 	}
 
-	async fn collect_dir_entries(path: impl AsRef<Path>) -> Result<Vec<DirEntry>> {
+	/// Snapshot for `SystemOverview`, built from the collectors behind the separate
+	/// listings. Disks that cannot be listed are left out of the totals.
+	fn collect_overview(&mut self) -> SystemOverview {
+		let cpus = self.collect_cpu_info();
+		let cpu_avg = if cpus.is_empty() {
+			0.0
+		} else {
+			cpus.iter().map(|cpu| cpu.usage).sum::<f32>() / cpus.len() as f32
+		};
+		self.system.refresh_memory();
+		let load = SystemLoad::current();
+		let disks = collect_disk_info().unwrap_or_else(|err| {
+			log::warn!("failed to list disks for the overview: {err}");
+			Vec::new()
+		});
+		SystemOverview {
+			cpu_avg,
+			cpu_count: cpus.len(),
+			mem: MemorySummary {
+				total: self.system.total_memory(),
+				used: self.system.used_memory(),
+				swap_total: self.system.total_swap(),
+				swap_used: self.system.used_swap(),
+			},
+			disks_summary: DisksSummary::of(&disks),
+			net_summary: NetSummary::of(&collect_interface_info()),
+			uptime: load.uptime,
+			load: load.load,
+		}
+	}
+
+	/// List `path`, resolving symlinks. `allow_target` decides whether a link's
+	/// canonical target may be followed; links escaping it are never marked navigable.
+	async fn collect_dir_entries(
+		path: impl AsRef<Path>,
+		allow_target: impl Fn(&Path) -> bool,
+	) -> Result<Vec<DirEntry>> {
 		let path = path.as_ref();
+		if !fs::metadata(path).await?.is_dir() {
+			return Err(PeerError {
+				code: ErrorCode::NotADirectory,
+				message: format!("{} is a file, not a folder", path.display()),
+			}
+			.into());
+		}
 		let mut entries = Vec::new();
 		let mut reader = fs::read_dir(path).await?;
 		while let Some(entry) = reader.next_entry().await? {
 			let file_type = entry.file_type().await?;
+			let is_symlink = file_type.is_symlink();
+			let target_is_dir = if is_symlink {
+				match fs::canonicalize(entry.path()).await {
+					Ok(target) if allow_target(&target) => fs::metadata(&target)
+						.await
+						.map(|meta| meta.is_dir())
+						.unwrap_or(false),
+					Ok(target) => {
+						log::warn!(
+							"symlink {:?} points outside shared folders ({})",
+							entry.path(),
+							target.display()
+						);
+						false
+					}
+					Err(err) => {
+						log::warn!("failed to resolve symlink {:?}: {err}", entry.path());
+						false
+					}
+				}
+			} else {
+				false
+			};
 			let metadata = match entry.metadata().await {
 				Ok(m) => m,
 				Err(err) => {
@@ -535,7 +2541,7 @@ impl App {
 				.path()
 				.extension()
 				.and_then(|s| s.to_str().map(|s| s.to_string()));
-			let mime = if file_type.is_dir() {
+			let mime = if file_type.is_dir() || target_is_dir {
 				None
 			} else {
 				mime_guess::from_path(entry.path())
@@ -545,6 +2551,8 @@ impl App {
 			entries.push(DirEntry {
 				name: entry.file_name().to_string_lossy().to_string(),
 				is_dir: file_type.is_dir(),
+				is_symlink,
+				target_is_dir,
 				extension,
 				mime,
 				size: metadata.len(),
@@ -562,18 +2570,77 @@ impl App {
 					.and_then(|t| DateTime::<Utc>::from(t).into()),
 			});
 		}
-		entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+		entries.sort_by(|a, b| match (a.is_navigable(), b.is_navigable()) {
 			(true, false) => std::cmp::Ordering::Less,
 			(false, true) => std::cmp::Ordering::Greater,
-			_ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+			_ => natural_cmp(&a.name, &b.name),
 		});
 		Ok(entries)
 	}
 
+	/// Walk `path` depth first down to `max_depth` levels, keeping at most `max_entries`
+	/// entries named by their path relative to `path`. Only real directories that
+	/// `allow_target` accepts are entered; one that cannot be listed is skipped.
+	async fn collect_dir_tree(
+		path: &Path,
+		max_depth: u32,
+		max_entries: usize,
+		allow_target: impl Fn(&Path) -> bool,
+	) -> Result<DirTree> {
+		let mut tree = DirTree {
+			entries: Vec::new(),
+			truncated: false,
+		};
+		let mut top = Self::collect_dir_entries(path, &allow_target).await?;
+		top.reverse();
+		// (relative path, entries still to visit in reverse order, depth)
+		let mut stack = vec![(String::new(), top, 1)];
+		while let Some((prefix, pending, depth)) = stack.last_mut() {
+			let Some(mut entry) = pending.pop() else {
+				stack.pop();
+				continue;
+			};
+			if tree.entries.len() >= max_entries {
+				tree.truncated = true;
+				break;
+			}
+			if !prefix.is_empty() {
+				entry.name = format!("{}/{}", prefix, entry.name);
+			}
+			let relative = entry.name.clone();
+			let descend = entry.is_dir && !entry.is_symlink && *depth < max_depth;
+			let depth = *depth;
+			tree.entries.push(entry);
+			if !descend {
+				continue;
+			}
+			let child = path.join(&relative);
+			if !allow_target(&child) {
+				continue;
+			}
+			match Self::collect_dir_entries(&child, &allow_target).await {
+				Ok(mut entries) => {
+					entries.reverse();
+					stack.push((relative, entries, depth + 1));
+				}
+				Err(err) => log::warn!("skipped {} in recursive listing: {err}", child.display()),
+			}
+		}
+		Ok(tree)
+	}
+
 	async fn handle_agent_event(&mut self, event: AgentEvent) {
 		match event {
 			AgentEvent::Ping(event) => {
 				log::info!("Ping event: {:?}", event);
+				// kept-alive connections never idle out, so a failed ping is what ends a dead one
+				if let Err(err) = event.result {
+					log::warn!(
+						"ping to {} failed, closing the connection: {err}",
+						event.peer
+					);
+					self.swarm.close_connection(event.connection);
+				}
 			}
 			AgentEvent::PuppyPeer(event) => {
 				match event {
@@ -588,26 +2655,42 @@ impl App {
 								request,
 								channel,
 							} => {
-								if let Ok(res) = self.handle_puppy_peer_req(peer, request).await {
-									let _ = self
-										.swarm
-										.behaviour_mut()
-										.puppypeer
-										.send_response(channel, res);
+								let kind = request.kind();
+								// a directory walk takes up to DIR_SIZE_TIMEOUT and a catalog
+								// page may query a large index, so both are answered from a
+								// task while the loop keeps running
+								let slow: Option<BoxFuture<'static, anyhow::Result<PeerRes>>> =
+									match self.dir_size_walk(peer, &request).await {
+										Some(walk) => Some(Box::pin(walk)),
+										None => self
+											.catalog_query(peer, &request)
+											.map(|query| Box::pin(query) as BoxFuture<'static, _>),
+									};
+								if let Some(answer) = slow {
+									self.answers.push(Box::pin(async move {
+										Answer {
+											peer,
+											kind,
+											channel,
+											result: answer.await,
+										}
+									}));
 								} else {
-									let _ = self.swarm.behaviour_mut().puppypeer.send_response(
+									let result = self.handle_puppy_peer_req(peer, request).await;
+									self.send_answer(Answer {
+										peer,
+										kind,
 										channel,
-										PeerRes::Error("Internal error".into()),
-									);
+										result,
+									});
 								}
-								// self.swarm.behaviour_mut().puppypeer.send_response(channel, PuppyPeerResponse::)
 							}
 							libp2p::request_response::Message::Response {
 								request_id,
 								response,
 							} => {
-								if let Some(pending) = self.pending_requests.remove(&request_id) {
-									pending.complete(response);
+								if let Some(in_flight) = self.pending_requests.remove(&request_id) {
+									in_flight.pending.complete(response);
 								}
 							}
 						}
@@ -619,8 +2702,23 @@ impl App {
 						error,
 					} => {
 						log::warn!("outbound request to {} failed: {error}", peer);
-						if let Some(pending) = self.pending_requests.remove(&request_id) {
-							pending.fail(anyhow!("request failed: {error}"));
+						if let Some(mut in_flight) = self.pending_requests.remove(&request_id) {
+							match in_flight.next_attempt(&error, self.config.request_retries) {
+								Some(delay) => {
+									log::info!(
+										"retrying request to {} in {:?} (attempt {})",
+										peer,
+										delay,
+										in_flight.attempts
+									);
+									let retry_tx = self.retry_tx.clone();
+									tokio::spawn(async move {
+										tokio::time::sleep(delay).await;
+										let _ = retry_tx.send(in_flight);
+									});
+								}
+								None => in_flight.pending.fail(anyhow!("request failed: {error}")),
+							}
 						}
 					}
 					libp2p::request_response::Event::InboundFailure {
@@ -642,20 +2740,35 @@ impl App {
 			}
 			AgentEvent::Mdns(event) => match event {
 				mdns::Event::Discovered(items) => {
+					let mut discovered = Vec::new();
 					for (peer_id, multiaddr) in items {
 						log::info!("mDNS discovered peer {} at {}", peer_id, multiaddr);
 						if let Ok(mut state) = self.state.lock() {
 							state.peer_discovered(peer_id, multiaddr.clone());
 						}
-						self.swarm.dial(multiaddr).unwrap();
+						self.emit(PeerEvent::Discovered {
+							peer_id: peer_id.to_string(),
+							address: multiaddr.to_string(),
+						});
+						if !discovered.contains(&peer_id) {
+							discovered.push(peer_id);
+						}
+					}
+					// one dial per peer over all its addresses, instead of one per address
+					for peer_id in discovered {
+						self.dial_peer(peer_id, Vec::new());
 					}
 				}
 				mdns::Event::Expired(items) => {
 					for (peer_id, multiaddr) in items {
 						log::info!("mDNS expired peer {} at {}", peer_id, multiaddr);
 						if let Ok(mut state) = self.state.lock() {
-							state.peer_expired(peer_id, multiaddr);
+							state.peer_expired(peer_id, multiaddr.clone());
 						}
+						self.emit(PeerEvent::Expired {
+							peer_id: peer_id.to_string(),
+							address: multiaddr.to_string(),
+						});
 					}
 				}
 			},
@@ -668,31 +2781,86 @@ impl App {
 			SwarmEvent::ConnectionEstablished {
 				peer_id,
 				connection_id,
-				endpoint: _,
-				num_established: _,
+				endpoint,
+				num_established,
 				concurrent_dial_errors: _,
 				established_in: _,
 			} => {
 				log::info!("Connected to peer {}", peer_id);
+				if num_established.get() == 1 {
+					self.emit(PeerEvent::Connected {
+						peer_id: peer_id.to_string(),
+						address: endpoint.get_remote_address().to_string(),
+					});
+				}
+				let bootstrap_addr = self.bootstrap_dials.remove(&connection_id).map(|idx| {
+					let entry = &mut self.bootstrap[idx];
+					entry.peer = Some(peer_id);
+					entry.failures = 0;
+					entry.addr.clone()
+				});
+				if bootstrap_addr.is_some() {
+					self.swarm.behaviour_mut().keep_alive(peer_id, true);
+				}
 				if let Ok(mut state) = self.state.lock() {
+					if let Some(addr) = bootstrap_addr {
+						state.peer_discovered(peer_id, addr);
+					}
+					state.mark_seen(peer_id);
 					state.connections.push(Connection {
 						peer_id,
 						connection_id,
+						direction: Direction::from(&endpoint),
+						route: Route::from(&endpoint),
+						remote_addr: endpoint.get_remote_address().clone(),
 					});
 				}
+				let conflict = self
+					.state
+					.lock()
+					.ok()
+					.and_then(|state| state.conflicting_addresses(peer_id));
+				if let Some((first, second)) = conflict {
+					self.warn_identity(format!(
+						"Peer {} is connected from both {} and {}. Two machines may share its \
+						 keypair file; delete it on one of them and restart that node.",
+						peer_id, first, second
+					));
+				}
 			}
 			SwarmEvent::ConnectionClosed {
 				peer_id,
 				connection_id,
 				endpoint: _,
-				num_established: _,
+				num_established,
 				cause: _,
 			} => {
 				log::info!("Disconnected from peer {}", peer_id);
+				if num_established == 0 {
+					self.emit(PeerEvent::Disconnected {
+						peer_id: peer_id.to_string(),
+					});
+					self.atomic_writes.abort_peer(peer_id).await;
+					if let Some(idx) = self
+						.bootstrap
+						.iter()
+						.position(|entry| entry.peer == Some(peer_id))
+					{
+						self.schedule_redial(idx);
+					} else {
+						// the session goes with the connection, and so does the reason to keep it
+						self.swarm.behaviour_mut().keep_alive(peer_id, false);
+					}
+				}
 				if let Ok(mut state) = self.state.lock() {
+					state.mark_seen(peer_id);
 					state
 						.connections
 						.retain(|c| c.connection_id != connection_id);
+					if num_established == 0 {
+						// a session is bound to the connection it was opened on
+						state.sessions.remove(&peer_id);
+					}
 				}
 			}
 			SwarmEvent::IncomingConnection {
@@ -700,6 +2868,13 @@ impl App {
 				local_addr: _,
 				send_back_addr: _,
 			} => {}
+			SwarmEvent::IncomingConnectionError {
+				connection_id: _,
+				local_addr: _,
+				send_back_addr,
+				peer_id: _,
+				error: ListenError::LocalPeerId { .. },
+			} => self.check_own_id_at(&send_back_addr),
 			SwarmEvent::IncomingConnectionError {
 				connection_id: _,
 				local_addr: _,
@@ -708,14 +2883,25 @@ impl App {
 				peer_id: _,
 			} => {}
 			SwarmEvent::OutgoingConnectionError {
-				connection_id: _,
-				peer_id: _,
-				error: _,
-			} => {}
-			SwarmEvent::Dialing {
+				connection_id,
 				peer_id: _,
-				connection_id: _,
-			} => {}
+				error,
+			} => {
+				if let DialError::LocalPeerId { address, .. } = &error {
+					self.check_own_id_at(address);
+				}
+				if let Some(idx) = self.bootstrap_dials.remove(&connection_id) {
+					log::warn!(
+						"failed to connect to bootstrap peer {}: {error}",
+						self.bootstrap[idx].addr
+					);
+					self.schedule_redial(idx);
+				}
+			}
+			SwarmEvent::Dialing {
+				peer_id: _,
+				connection_id: _,
+			} => {}
 			SwarmEvent::NewExternalAddrCandidate { address: _ } => {}
 			SwarmEvent::ExternalAddrConfirmed { address: _ } => {}
 			SwarmEvent::ExternalAddrExpired { address: _ } => {}
@@ -728,6 +2914,7 @@ impl App {
 				address,
 			} => {
 				log::info!("listener address added: {:?}", address);
+				self.ready.send_replace(true);
 			}
 			SwarmEvent::ExpiredListenAddr {
 				listener_id: _,
@@ -746,285 +2933,3076 @@ impl App {
 		}
 	}
 
-	async fn handle_cmd(&mut self, cmd: Command) {
-		match cmd {
-			Command::Connect { peer_id: _, addr } => {
-				if let Err(err) = self.swarm.dial(addr) {
-					log::error!("dial failed: {err}");
-				}
-			}
-			Command::ListDir { peer, path, tx } => {
-				let is_self = {
-					self.state
-						.lock()
-						.map(|state| state.me == peer)
-						.unwrap_or(false)
-				};
-				if is_self {
-					let result = Self::collect_dir_entries(Path::new(&path)).await;
-					let _ = tx.send(result);
-					return;
-				}
-				let request_id = self
-					.swarm
-					.behaviour_mut()
-					.puppypeer
-					.send_request(&peer, PeerReq::ListDir { path: path.clone() });
-				if let Some(prev) = self
-					.pending_requests
-					.insert(request_id, Pending::<Vec<DirEntry>>::new(tx))
-				{
-					prev.fail(anyhow!("pending ListDir request was replaced"));
-				}
-			}
-			Command::ListCpus { tx, peer_id } => {
-				if self.state.lock().unwrap().me == peer_id {
-					let cpus = self.collect_cpu_info();
-					let _ = tx.send(Ok(cpus));
-					return;
-				}
-				let request_id = self
-					.swarm
-					.behaviour_mut()
-					.puppypeer
-					.send_request(&peer_id, PeerReq::ListCpus);
-				self.pending_requests
-					.insert(request_id, Pending::<Vec<CpuInfo>>::new(tx));
-			}
-			Command::ListPermissions { peer, tx } => {
-				let local_permissions = match self.state.lock() {
-					Ok(state) => {
-						if state.me == peer {
-							Some(state.permissions_for_peer(&peer))
-						} else {
-							None
-						}
-					}
-					Err(err) => {
-						let _ = tx.send(Err(anyhow!("state lock poisoned: {}", err)));
-						return;
-					}
-				};
-				if let Some(permissions) = local_permissions {
-					let _ = tx.send(Ok(permissions));
-					return;
-				}
-				let request_id = self
-					.swarm
-					.behaviour_mut()
-					.puppypeer
-					.send_request(&peer, PeerReq::ListPermissions);
-				if let Some(prev) = self
-					.pending_requests
-					.insert(request_id, Pending::<Vec<Permission>>::new(tx))
-				{
-					prev.fail(anyhow!("pending ListPermissions request was replaced"));
-				}
+	async fn handle_cmd(&mut self, cmd: Command) {
+		match cmd {
+			Command::Connect { peer_id, addr } => {
+				// connecting again undoes a disconnect, so a bootstrap peer is kept again
+				for entry in &mut self.bootstrap {
+					if entry.peer == Some(peer_id) && entry.disconnected {
+						entry.disconnected = false;
+						entry.failures = 0;
+						self.swarm.behaviour_mut().keep_alive(peer_id, true);
+					}
+				}
+				self.dial_peer(peer_id, vec![addr]);
+			}
+			Command::Disconnect { peer, tx } => {
+				if self.swarm.disconnect_peer_id(peer).is_err() {
+					let _ = tx.send(Err(anyhow!("not connected to {}", peer)));
+					return;
+				}
+				// a bootstrap peer the user hung up on stays down until connected again
+				for entry in &mut self.bootstrap {
+					if entry.peer == Some(peer) {
+						entry.disconnected = true;
+					}
+				}
+				self.swarm.behaviour_mut().keep_alive(peer, false);
+				// ConnectionClosed follows, but the UI should not show the peer as
+				// connected until then
+				if let Ok(mut state) = self.state.lock() {
+					state.connections.retain(|c| c.peer_id != peer);
+				}
+				let _ = tx.send(Ok(()));
+			}
+			Command::ListDir { peer, path, tx } => {
+				let is_self = {
+					self.state
+						.lock()
+						.map(|state| state.me == peer)
+						.unwrap_or(false)
+				};
+				if is_self {
+					let result = Self::collect_dir_entries(Path::new(&path), |_| true).await;
+					let _ = tx.send(result);
+					return;
+				}
+				self.send_request(
+					peer,
+					PeerReq::ListDir { path: path.clone() },
+					Pending::<Vec<DirEntry>>::new(tx),
+				);
+			}
+			Command::ListDirRecursive {
+				peer,
+				path,
+				max_depth,
+				tx,
+			} => {
+				let req = PeerReq::ListDirRecursive { path, max_depth };
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<DirTree>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<DirTree>::new(tx));
+			}
+			Command::StatFile { peer, path, tx } => {
+				let req = PeerReq::StatFile { path };
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<DirEntry>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<DirEntry>::new(tx));
+			}
+			Command::ListCpus { tx, peer_id } => {
+				if self.state.lock().unwrap().me == peer_id {
+					let cpus = self.collect_cpu_info();
+					let _ = tx.send(Ok(cpus));
+					return;
+				}
+				self.send_request(peer_id, PeerReq::ListCpus, Pending::<Vec<CpuInfo>>::new(tx));
+			}
+			Command::ListDisks { tx, peer_id } => {
+				if self.state.lock().unwrap().me == peer_id {
+					let disks = collect_disk_info().map_err(|err| anyhow!(err));
+					let _ = tx.send(disks);
+					return;
+				}
+				self.send_request(
+					peer_id,
+					PeerReq::ListDisks,
+					Pending::<Vec<DiskInfo>>::new(tx),
+				);
+			}
+			Command::ListInterfaces { tx, peer_id } => {
+				if self.state.lock().unwrap().me == peer_id {
+					let _ = tx.send(Ok(collect_interface_info()));
+					return;
+				}
+				self.send_request(
+					peer_id,
+					PeerReq::ListInterfaces,
+					Pending::<Vec<InterfaceInfo>>::new(tx),
+				);
+			}
+			Command::SystemOverview { tx, peer_id } => {
+				if self.state.lock().unwrap().me == peer_id {
+					let _ = tx.send(Ok(self.collect_overview()));
+					return;
+				}
+				self.send_request(
+					peer_id,
+					PeerReq::SystemOverview,
+					Pending::<SystemOverview>::new(tx),
+				);
+			}
+			Command::SystemLoad { tx, peer_id } => {
+				if self.state.lock().unwrap().me == peer_id {
+					let _ = tx.send(Ok(SystemLoad::current()));
+					return;
+				}
+				self.send_request(peer_id, PeerReq::SystemLoad, Pending::<SystemLoad>::new(tx));
+			}
+			Command::ListPermissions { peer, tx } => {
+				let local_permissions = match self.state.lock() {
+					Ok(state) => {
+						if state.me == peer {
+							Some(state.effective_permissions(&peer))
+						} else {
+							None
+						}
+					}
+					Err(err) => {
+						let _ = tx.send(Err(anyhow!("state lock poisoned: {}", err)));
+						return;
+					}
+				};
+				if let Some(permissions) = local_permissions {
+					let _ = tx.send(Ok(permissions));
+					return;
+				}
+				self.send_request(
+					peer,
+					PeerReq::ListPermissions,
+					Pending::<Vec<Permission>>::new(tx),
+				);
+			}
+			Command::ReadFile(req) => {
+				if self.state.lock().unwrap().me == req.peer_id {
+					let chunk = read_file(Path::new(&req.path), req.offset, req.length).await;
+					let _ = req.tx.send(chunk);
+					return;
+				}
+				self.send_request(
+					req.peer_id,
+					PeerReq::ReadFile {
+						path: req.path.clone(),
+						offset: req.offset,
+						length: req.length,
+					},
+					Pending::<FileChunk>::new(req.tx),
+				);
+			}
+			Command::ReadFiles { peer, requests, tx } => {
+				if self.state.lock().unwrap().me == peer {
+					let _ = tx.send(Ok(read_files(requests, |_| true).await));
+					return;
+				}
+				self.send_request(
+					peer,
+					PeerReq::ReadFiles { requests },
+					Pending::<Vec<Result<FileChunk, ErrorCode>>>::new(tx),
+				);
+			}
+			Command::PreviewFile {
+				peer,
+				path,
+				bytes,
+				tx,
+			} => {
+				if self.state.lock().unwrap().me == peer {
+					let _ = tx.send(preview_file(Path::new(&path), bytes).await);
+					return;
+				}
+				self.send_request(
+					peer,
+					PeerReq::PreviewFile { path, bytes },
+					Pending::<FilePreview>::new(tx),
+				);
+			}
+			Command::Identify { peer, tx } => {
+				if self.state.lock().unwrap().me == peer {
+					let _ = tx.send(Ok(BuildInfo::current()));
+					return;
+				}
+				self.send_request(peer, PeerReq::Identify, Pending::<BuildInfo>::new(tx));
+			}
+			Command::SendMessage { peer, text, tx } => {
+				let req = PeerReq::Message { text };
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<()>::new(tx)).await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<()>::new(tx));
+			}
+			Command::Authenticate { peer, method, tx } => {
+				let req = PeerReq::Authenticate { method };
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<SessionInfo>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<SessionInfo>::new(tx));
+			}
+			Command::CreateToken {
+				peer,
+				username,
+				label,
+				expires_in,
+				permissions,
+				tx,
+			} => {
+				let req = PeerReq::CreateToken {
+					username,
+					label,
+					expires_in,
+					permissions,
+				};
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<IssuedToken>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<IssuedToken>::new(tx));
+			}
+			Command::GrantAccess {
+				peer,
+				target,
+				permissions,
+				merge,
+				tx,
+			} => {
+				let req = PeerReq::GrantAccess {
+					peer_id: target.to_string(),
+					permissions,
+					merge,
+				};
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<Vec<PermissionGrant>>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<Vec<PermissionGrant>>::new(tx));
+			}
+			Command::TailFile {
+				peer,
+				path,
+				offset,
+				from_end_bytes,
+				tx,
+			} => {
+				let req = PeerReq::TailFile {
+					path,
+					offset,
+					from_end_bytes,
+				};
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<FileChunk>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<FileChunk>::new(tx));
+			}
+			Command::SpaceFor { peer, path, tx } => {
+				let req = PeerReq::SpaceFor { path };
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<DiskSpace>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<DiskSpace>::new(tx));
+			}
+			Command::DirSize { peer, path, tx } => {
+				if self.state.lock().unwrap().me == peer {
+					tokio::spawn(async move {
+						let _ = tx.send(dir_size(PathBuf::from(path)).await);
+					});
+					return;
+				}
+				self.send_request(peer, PeerReq::DirSize { path }, Pending::<DirSize>::new(tx));
+			}
+			Command::BrowseCatalog {
+				peer,
+				prefix,
+				offset,
+				limit,
+				tx,
+			} => {
+				let req = PeerReq::BrowseCatalog {
+					prefix,
+					offset,
+					limit,
+				};
+				if self.state.lock().unwrap().me == peer {
+					let pending = Pending::<CatalogPage>::new(tx);
+					match self.catalog_query(peer, &req) {
+						Some(query) => {
+							tokio::spawn(async move {
+								match query.await {
+									Ok(res) => pending.complete(res),
+									Err(err) => pending.fail(err),
+								}
+							});
+						}
+						None => self.serve_locally(peer, req, pending).await,
+					}
+					return;
+				}
+				self.send_request(peer, req, Pending::<CatalogPage>::new(tx));
+			}
+			Command::FindHashes { peer, hashes, tx } => {
+				if self.state.lock().unwrap().me == peer {
+					let _ = tx.send(self.find_readable_hashes(peer, hashes));
+					return;
+				}
+				self.send_request(
+					peer,
+					PeerReq::FindHashes { hashes },
+					Pending::<Vec<FileHash>>::new(tx),
+				);
+			}
+			Command::WriteFile {
+				peer,
+				path,
+				offset,
+				data,
+				mode,
+				times,
+				tx,
+			} => {
+				let req = PeerReq::WriteFile {
+					path,
+					offset,
+					data,
+					mode,
+					times,
+				};
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<FileWriteAck>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<FileWriteAck>::new(tx));
+			}
+			Command::CreateDir { peer, path, tx } => {
+				let req = PeerReq::CreateDir { path };
+				if self.state.lock().unwrap().me == peer {
+					self.serve_locally(peer, req, Pending::<String>::new(tx))
+						.await;
+					return;
+				}
+				self.send_request(peer, req, Pending::<String>::new(tx));
+			}
+		}
+	}
+
+	pub async fn run(&mut self) {
+		tokio::select! {
+			event = self.swarm.select_next_some() => {
+				self.handle_swarm_event(event).await;
+			}
+			cmd = self.rx.recv() => {
+				if let Some(cmd) = cmd {
+					self.handle_cmd(cmd).await;
+					// dispatch a burst right away, but not so many that swarm events wait long
+					for _ in 1..COMMAND_BATCH {
+						match self.rx.try_recv() {
+							Ok(cmd) => self.handle_cmd(cmd).await,
+							Err(_) => break,
+						}
+					}
+				}
+			}
+			Some(in_flight) = self.retry_rx.recv() => {
+				self.resend(in_flight);
+			}
+			Some(idx) = self.redial_rx.recv() => {
+				self.dial_bootstrap(idx);
+			}
+			Some((root, used)) = self.usage_rx.recv() => {
+				self.share_measured(root, used);
+			}
+			Some(answer) = self.answers.next(), if !self.answers.is_empty() => {
+				self.send_answer(answer);
+			}
+			_ = self.sample_timer.tick() => {
+				self.sample_system();
+			}
+		}
+	}
+}
+
+/// Caps the reads one `PuppyPeer` has in flight to each peer, so batch and recursive
+/// transfers queue up locally instead of flooding the peer.
+struct ReadLimits {
+	per_peer: usize,
+	peers: Mutex<HashMap<PeerId, Arc<Semaphore>>>,
+}
+
+impl ReadLimits {
+	fn new(per_peer: usize) -> Self {
+		Self {
+			per_peer: per_peer.max(1),
+			peers: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Wait for a free read slot to `peer`, held until the permit is dropped.
+	async fn acquire(&self, peer: PeerId) -> OwnedSemaphorePermit {
+		let semaphore = self
+			.peers
+			.lock()
+			.unwrap()
+			.entry(peer)
+			.or_insert_with(|| Arc::new(Semaphore::new(self.per_peer)))
+			.clone();
+		semaphore
+			.acquire_owned()
+			.await
+			.expect("read semaphores are never closed")
+	}
+}
+
+pub struct PuppyPeer {
+	shutdown_tx: Option<oneshot::Sender<()>>,
+	handle: JoinHandle<()>,
+	state: Arc<Mutex<State>>,
+	cmd_tx: Sender<Command>,
+	read_limits: ReadLimits,
+	config: PuppyConfig,
+	ready: watch::Receiver<bool>,
+	events: broadcast::Sender<PeerEvent>,
+}
+
+impl PuppyPeer {
+	pub fn new() -> Self {
+		Self::with_config(PuppyConfig::load())
+	}
+
+	pub fn with_config(config: PuppyConfig) -> Self {
+		let state = Arc::new(Mutex::new(State::default()));
+		// channel to request shutdown
+		let (shutdown_tx, shutdown_rx) = oneshot::channel();
+		let state_clone = state.clone();
+		let (mut app, cmd_tx) = App::new(state_clone, config.clone());
+		let ready = app.ready();
+		let events = app.events();
+		let mut shutdown_rx = shutdown_rx;
+		let handle: JoinHandle<()> = tokio::spawn(async move {
+			loop {
+				tokio::select! {
+					_ = &mut shutdown_rx => {
+						log::info!("PuppyPeer shutting down");
+						break;
+					}
+					_ = app.run() => {}
+				}
+			}
+		});
+
+		PuppyPeer {
+			shutdown_tx: Some(shutdown_tx),
+			handle,
+			state,
+			cmd_tx,
+			read_limits: ReadLimits::new(config.max_reads_per_peer),
+			config,
+			ready,
+			events,
+		}
+	}
+
+	pub fn config(&self) -> &PuppyConfig {
+		&self.config
+	}
+
+	fn db(&self) -> Result<rusqlite::Connection> {
+		open_db(&self.config.db_path())
+	}
+
+	/// Receive peers being discovered, expiring, connecting and disconnecting from now on.
+	///
+	/// A receiver that falls more than `PEER_EVENT_CAPACITY` events behind loses the oldest
+	/// ones and gets `RecvError::Lagged`; `state()` has the full picture again.
+	pub fn subscribe(&self) -> broadcast::Receiver<PeerEvent> {
+		self.events.subscribe()
+	}
+
+	/// Whether the background task is up and listening for connections.
+	///
+	/// `new` returns before that happens. The local peer id in `state().me` is set by
+	/// then, but the swarm may not have a listen address yet.
+	pub fn is_ready(&self) -> bool {
+		*self.ready.borrow()
+	}
+
+	/// Wait until `is_ready`, returning at once when the peer already is.
+	pub async fn await_ready(&self) {
+		let mut ready = self.ready.clone();
+		// an error means the background task stopped, so there is nothing to wait for
+		let _ = ready.wait_for(|ready| *ready).await;
+	}
+
+	pub fn await_ready_blocking(&self) {
+		block_on(self.await_ready())
+	}
+
+	/// Ask peers on the local network to announce themselves now instead of at the next
+	/// mDNS cycle. Answers land in `State::discovered_peers` as they arrive.
+	pub fn query_mdns(&self) -> Result<()> {
+		send_mdns_query().map_err(|err| anyhow!("failed to send mDNS query: {err}"))
+	}
+
+	/// `query_mdns`, then wait `DISCOVERY_WINDOW` for answers. Returns how many peers were
+	/// discovered that were not known before.
+	pub async fn discover_now(&self) -> Result<usize> {
+		let known = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?
+			.discovered_peer_ids();
+		self.query_mdns()?;
+		tokio::time::sleep(DISCOVERY_WINDOW).await;
+		let state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		Ok(state.discovered_peer_ids().difference(&known).count())
+	}
+
+	/// Dial `peer_id` at `addr`. Addresses the peer was discovered on are tried too, best
+	/// first, so a stale `addr` does not keep the peer unreachable.
+	pub async fn connect(&self, peer_id: PeerId, addr: Multiaddr) -> Result<()> {
+		self.cmd_tx
+			.send(Command::Connect { peer_id, addr })
+			.await
+			.map_err(|e| anyhow!("failed to send Connect command: {e}"))
+	}
+
+	/// Close every connection to `peer`. Bootstrap peers are redialed as usual, and mDNS
+	/// dials the peer again when it next announces itself.
+	pub async fn disconnect(&self, peer: PeerId) -> Result<()> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::Disconnect { peer, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send Disconnect command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("Disconnect response channel closed: {e}"))?
+	}
+
+	pub fn disconnect_blocking(&self, peer: PeerId) -> Result<()> {
+		block_on(self.disconnect(peer))
+	}
+
+	/// Version, enabled features and protocol of this build.
+	pub fn build_info() -> BuildInfo {
+		BuildInfo::current()
+	}
+
+	/// Give this node a new peer id. The old keypair is backed up next to the new one and
+	/// the permissions this node granted move to the new id. Permissions other peers granted
+	/// to the old id do not follow; they have to be granted again. Run it while the node is
+	/// stopped, since a running node keeps the identity it started with.
+	pub fn rotate_identity(config: &PuppyConfig) -> Result<IdentityRotation> {
+		let (old, new, backup) = rotate_keypair(&config.keypair_path())?;
+		let migrated_grants = match open_db(&config.db_path()) {
+			Ok(conn) => move_granted_permissions(&conn, &old, &new).unwrap_or_else(|err| {
+				log::warn!("failed to move granted permissions to {new}: {err:#}");
+				0
+			}),
+			Err(err) => {
+				log::warn!("{err:#}; granted permissions were not moved");
+				0
+			}
+		};
+		Ok(IdentityRotation {
+			old_peer_id: old.to_string(),
+			new_peer_id: new.to_string(),
+			backup,
+			migrated_grants,
+		})
+	}
+
+	/// Ask `peer` which version, features and protocol it runs.
+	pub async fn identify(&self, peer: PeerId) -> Result<BuildInfo> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::Identify { peer, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send Identify command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("Identify response channel closed: {e}"))?
+	}
+
+	pub fn identify_blocking(&self, peer: PeerId) -> Result<BuildInfo> {
+		block_on(self.identify(peer))
+	}
+
+	/// Send a short note to `peer`, shown in its inbox and sent to its subscribers as a
+	/// `PeerEvent::Message`. Notes are at most `MAX_MESSAGE_BYTES` and are not retried.
+	pub async fn send_message(&self, peer: PeerId, text: impl Into<String>) -> Result<()> {
+		let text = text.into();
+		if text.trim().is_empty() {
+			bail!("message is empty");
+		}
+		if text.len() > MAX_MESSAGE_BYTES {
+			bail!("message is longer than {MAX_MESSAGE_BYTES} bytes");
+		}
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::SendMessage { peer, text, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send SendMessage command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("SendMessage response channel closed: {e}"))?
+	}
+
+	pub fn send_message_blocking(&self, peer: PeerId, text: impl Into<String>) -> Result<()> {
+		block_on(self.send_message(peer, text))
+	}
+
+	/// Sign in to `peer`. On success the peer grants this node the session's permissions
+	/// until it expires or the connection drops; a refused sign-in fails with
+	/// `ErrorCode::AccessDenied` and the peer's reason.
+	pub async fn authenticate(&self, peer: PeerId, method: AuthMethod) -> Result<SessionInfo> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::Authenticate { peer, method, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send Authenticate command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("Authenticate response channel closed: {e}"))?
+	}
+
+	pub fn authenticate_blocking(&self, peer: PeerId, method: AuthMethod) -> Result<SessionInfo> {
+		block_on(self.authenticate(peer, method))
+	}
+
+	/// Have `peer` issue a token for `username` that signs in with `permissions`, valid for
+	/// `expires_in` seconds or until revoked. Only owners of `peer` may issue tokens.
+	pub async fn create_token(
+		&self,
+		peer: PeerId,
+		username: impl Into<String>,
+		label: Option<String>,
+		expires_in: Option<u64>,
+		permissions: Vec<PermissionGrant>,
+	) -> Result<IssuedToken> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::CreateToken {
+				peer,
+				username: username.into(),
+				label,
+				expires_in,
+				permissions,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send CreateToken command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("CreateToken response channel closed: {e}"))?
+	}
+
+	/// Have `peer` grant `target` the given permissions, added to what it has when `merge`
+	/// is set or replacing it otherwise, and return everything `target` is granted now.
+	/// Only owners of `peer` may grant access, and only grants with a filesystem rule
+	/// (`Owner`, `Viewer` and `Files`) can be given to a peer.
+	pub async fn grant_access(
+		&self,
+		peer: PeerId,
+		target: PeerId,
+		permissions: Vec<PermissionGrant>,
+		merge: bool,
+	) -> Result<Vec<PermissionGrant>> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::GrantAccess {
+				peer,
+				target,
+				permissions,
+				merge,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send GrantAccess command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("GrantAccess response channel closed: {e}"))?
+	}
+
+	fn register_shared_folder(&self, path: &Path, flags: u8) -> anyhow::Result<()> {
+		let (canonical, file) = resolve_share_path(path)?;
+		let mut state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		state.add_shared_folder(FolderRule::new(canonical, flags).with_file(file));
+		Ok(())
+	}
+
+	/// Share a folder, or a single file, read-only with every peer.
+	pub fn share_read_only_folder(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+		self.register_shared_folder(path.as_ref(), FLAG_READ | FLAG_SEARCH)
+	}
+
+	/// Share a folder, or a single file, for reading and writing with every peer.
+	pub fn share_read_write_folder(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+		self.register_shared_folder(path.as_ref(), FLAG_READ | FLAG_WRITE | FLAG_SEARCH)
+	}
+
+	/// Share a single file with every peer, read-only unless `writable`. Unlike the folder
+	/// shares, only the exact path is accessible.
+	pub fn share_file(&self, path: impl AsRef<Path>, writable: bool) -> anyhow::Result<()> {
+		let path = path.as_ref();
+		let (canonical, file) = resolve_share_path(path)?;
+		if !file {
+			bail!(
+				"{} is a folder; share it with share_read_only_folder or share_read_write_folder",
+				path.display()
+			);
+		}
+		let mut flags = FLAG_READ | FLAG_SEARCH;
+		if writable {
+			flags |= FLAG_WRITE;
+		}
+		let mut state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		state.add_shared_folder(FolderRule::new(canonical, flags).with_file(true));
+		Ok(())
+	}
+
+	/// Shared folders together with the bytes currently stored under each of them.
+	pub async fn shared_folder_usage(&self) -> Result<Vec<(FolderRule, DirSize)>> {
+		let folders = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?
+			.shared_folders
+			.clone();
+		let mut usage = Vec::with_capacity(folders.len());
+		for folder in folders {
+			let size = dir_size(folder.path().to_path_buf()).await?;
+			usage.push((folder, size));
+		}
+		Ok(usage)
+	}
+
+	/// Limit how many bytes peers may store under an already shared folder.
+	pub fn set_share_quota(
+		&self,
+		path: impl AsRef<Path>,
+		quota: Option<u64>,
+	) -> anyhow::Result<()> {
+		let canonical = std::fs::canonicalize(path.as_ref())
+			.map_err(|err| anyhow!("failed to canonicalize path: {err}"))?;
+		let mut state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		let rule = state
+			.shared_folders
+			.iter_mut()
+			.find(|rule| rule.path() == canonical)
+			.ok_or_else(|| anyhow!("{} is not shared", canonical.display()))?;
+		*rule = rule.clone().with_max_bytes(quota);
+		Ok(())
+	}
+
+	/// Set the Unix modes of files and directories peers create under an already shared
+	/// folder. `None` leaves them to the process umask.
+	pub fn set_share_modes(
+		&self,
+		path: impl AsRef<Path>,
+		file_mode: Option<u32>,
+		dir_mode: Option<u32>,
+	) -> anyhow::Result<()> {
+		let canonical = std::fs::canonicalize(path.as_ref())
+			.map_err(|err| anyhow!("failed to canonicalize path: {err}"))?;
+		let mut state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		let rule = state
+			.shared_folders
+			.iter_mut()
+			.find(|rule| rule.path() == canonical)
+			.ok_or_else(|| anyhow!("{} is not shared", canonical.display()))?;
+		*rule = rule.clone().with_create_modes(file_mode, dir_mode);
+		Ok(())
+	}
+
+	pub fn set_peer_permissions(
+		&self,
+		peer: PeerId,
+		permissions: Vec<Permission>,
+	) -> anyhow::Result<()> {
+		let mut state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		state.set_peer_permissions(peer, permissions);
+		state.save_changes(&self.config.db_path())
+	}
+
+	pub fn state(&self) -> Arc<Mutex<State>> {
+		self.state.clone()
+	}
+
+	pub async fn list_dir(&self, peer: PeerId, path: impl Into<String>) -> Result<Vec<DirEntry>> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ListDir { peer, path, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send ListDir command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ListDir response channel closed: {e}"))?
+	}
+
+	pub fn list_dir_blocking(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+	) -> Result<Vec<DirEntry>> {
+		block_on(self.list_dir(peer, path))
+	}
+
+	/// `list_dir` for a local `Path`, e.g. one built on Windows with `\` separators, which
+	/// is sent in the `/` form every peer understands.
+	pub async fn list_dir_path(
+		&self,
+		peer: PeerId,
+		path: impl AsRef<Path>,
+	) -> Result<Vec<DirEntry>> {
+		let path = normalize_request_path(&path.as_ref().to_string_lossy());
+		self.list_dir(peer, path).await
+	}
+
+	/// Entries below `path` on `peer`, down to `max_depth` levels, each named by its path
+	/// relative to `path`. The peer stops at `max_tree_entries` from its config and sets
+	/// `DirTree::truncated`.
+	pub async fn list_dir_recursive(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		max_depth: u32,
+	) -> Result<DirTree> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ListDirRecursive {
+				peer,
+				path,
+				max_depth,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send ListDirRecursive command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ListDirRecursive response channel closed: {e}"))?
+	}
+
+	pub fn list_dir_recursive_blocking(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		max_depth: u32,
+	) -> Result<DirTree> {
+		block_on(self.list_dir_recursive(peer, path, max_depth))
+	}
+
+	/// Metadata of a single file or directory on `peer`.
+	pub async fn stat_file(&self, peer: PeerId, path: impl Into<String>) -> Result<DirEntry> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::StatFile { peer, path, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send StatFile command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("StatFile response channel closed: {e}"))?
+	}
+
+	pub fn stat_file_blocking(&self, peer: PeerId, path: impl Into<String>) -> Result<DirEntry> {
+		block_on(self.stat_file(peer, path))
+	}
+
+	/// List `path`, falling back to its closest existing ancestor when it was deleted.
+	///
+	/// Returns the path that was actually listed. The walk stops at the first error that
+	/// is not `ErrorCode::NotFound` (e.g. leaving the share), returning the original error.
+	pub async fn list_dir_nearest(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+	) -> Result<(String, Vec<DirEntry>)> {
+		let path = path.into();
+		let first_err = match self.list_dir(peer, path.clone()).await {
+			Ok(entries) => return Ok((path, entries)),
+			Err(err) if ErrorCode::of(&err) == Some(ErrorCode::NotFound) => err,
+			Err(err) => return Err(err),
+		};
+		let mut current = PathBuf::from(&path);
+		while let Some(parent) = current.parent().map(Path::to_path_buf) {
+			let candidate = parent.to_string_lossy().to_string();
+			match self.list_dir(peer, candidate.clone()).await {
+				Ok(entries) => return Ok((candidate, entries)),
+				Err(err) if ErrorCode::of(&err) == Some(ErrorCode::NotFound) => current = parent,
+				Err(_) => break,
+			}
+		}
+		Err(first_err)
+	}
+
+	pub fn list_dir_nearest_blocking(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+	) -> Result<(String, Vec<DirEntry>)> {
+		block_on(self.list_dir_nearest(peer, path))
+	}
+
+	pub async fn list_cpus(&self, peer_id: PeerId) -> Result<Vec<CpuInfo>> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ListCpus { tx, peer_id })
+			.await
+			.map_err(|e| anyhow!("failed to send ListCpus command: {e}"))?;
+		let cpus = rx
+			.await
+			.map_err(|e| anyhow!("ListCpus response channel closed: {e}"))??;
+		// this node is already sampled on a timer by the swarm loop
+		let local = self.state.lock().unwrap().me == peer_id;
+		if !local && let Err(err) = record_cpu_samples(&self.config.db_path(), &peer_id, &cpus) {
+			log::warn!("failed to record cpu history for {}: {err}", peer_id);
+		}
+		Ok(cpus)
+	}
+
+	pub fn list_cpus_blocking(&self, peer_id: PeerId) -> Result<Vec<CpuInfo>> {
+		block_on(self.list_cpus(peer_id))
+	}
+
+	/// Mounted filesystems of `peer` with their size, free space and I/O totals.
+	pub async fn list_disks(&self, peer_id: PeerId) -> Result<Vec<DiskInfo>> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ListDisks { tx, peer_id })
+			.await
+			.map_err(|e| anyhow!("failed to send ListDisks command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ListDisks response channel closed: {e}"))?
+	}
+
+	pub fn list_disks_blocking(&self, peer_id: PeerId) -> Result<Vec<DiskInfo>> {
+		block_on(self.list_disks(peer_id))
+	}
+
+	/// Network interfaces of `peer` with their addresses and traffic counters.
+	pub async fn list_interfaces(&self, peer_id: PeerId) -> Result<Vec<InterfaceInfo>> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ListInterfaces { tx, peer_id })
+			.await
+			.map_err(|e| anyhow!("failed to send ListInterfaces command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ListInterfaces response channel closed: {e}"))?
+	}
+
+	pub fn list_interfaces_blocking(&self, peer_id: PeerId) -> Result<Vec<InterfaceInfo>> {
+		block_on(self.list_interfaces(peer_id))
+	}
+
+	/// CPU, memory, disk and network totals of `peer`, with its uptime and load, in one
+	/// request.
+	pub async fn system_overview(&self, peer_id: PeerId) -> Result<SystemOverview> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::SystemOverview { tx, peer_id })
+			.await
+			.map_err(|e| anyhow!("failed to send SystemOverview command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("SystemOverview response channel closed: {e}"))?
+	}
+
+	pub fn system_overview_blocking(&self, peer_id: PeerId) -> Result<SystemOverview> {
+		block_on(self.system_overview(peer_id))
+	}
+
+	/// Uptime and load average of `peer`. The load is `None` when the peer runs Windows.
+	pub async fn system_load(&self, peer_id: PeerId) -> Result<SystemLoad> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::SystemLoad { tx, peer_id })
+			.await
+			.map_err(|e| anyhow!("failed to send SystemLoad command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("SystemLoad response channel closed: {e}"))?
+	}
+
+	pub fn system_load_blocking(&self, peer_id: PeerId) -> Result<SystemLoad> {
+		block_on(self.system_load(peer_id))
+	}
+
+	/// CPU usage samples recorded for `name` on `peer` since `since`, oldest first.
+	///
+	/// This node is sampled every 30 seconds, other peers every time their CPUs are listed.
+	/// Samples are kept for 24 hours.
+	pub fn cpu_history(
+		&self,
+		peer: PeerId,
+		name: &str,
+		since: DateTime<Utc>,
+	) -> Result<Vec<CpuSample>> {
+		let conn = self.db()?;
+		fetch_cpu_history(&conn, &peer.to_bytes(), name, since)
+	}
+
+	/// Sensor temperatures recorded for `peer` since `since`, oldest first. Only this node
+	/// is sampled, every 30 seconds, and samples are kept for 24 hours.
+	pub fn temperature_history(
+		&self,
+		peer: PeerId,
+		since: DateTime<Utc>,
+	) -> Result<Vec<TemperatureSample>> {
+		let conn = self.db()?;
+		fetch_temperature_history(&conn, &peer.to_bytes(), since)
+	}
+
+	/// Files indexed by `scan_folder` whose hex hash or mime type contains `query`, limited
+	/// to `mime` when given and sorted by when they were last seen, newest first with
+	/// `sort_desc`. Also returns every indexed mime type, to offer as filters.
+	pub async fn search_files(
+		&self,
+		query: &str,
+		mime: Option<&str>,
+		sort_desc: bool,
+	) -> Result<(Vec<FileEntry>, Vec<String>)> {
+		let conn = self.db()?;
+		let args = ListArgs {
+			search_word: Some(query.to_string()),
+			mime_type: mime.map(str::to_string),
+			sort_desc,
+		};
+		let files = list_files(&conn, args)?;
+		let mime_types = get_mime_types(&conn)?;
+		Ok((files, mime_types))
+	}
+
+	/// Index the files under a local `path` into the database. Blocks until the scan is
+	/// done or `cancel` is set, reporting progress through `on_progress`.
+	///
+	/// The config's `scan_exclude` patterns are added to `options.exclude`. Failures are
+	/// `ScanError`s and can be downcast to tell IO from database errors, except a database
+	/// that cannot be opened, which fails before the scan starts.
+	pub fn scan_folder(
+		&self,
+		path: impl AsRef<Path>,
+		options: &ScanOptions,
+		on_progress: impl Fn(ScanProgress) + Sync,
+		cancel: &AtomicBool,
+	) -> Result<ScanResult> {
+		let me = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?
+			.me;
+		let mut options = options.clone();
+		options
+			.exclude
+			.extend(self.config.scan_exclude.iter().cloned());
+		Ok(scan(
+			&me.to_bytes(),
+			path,
+			self.db()?,
+			&options,
+			on_progress,
+			cancel,
+		)?)
+	}
+
+	/// Every known peer with its addresses and status, for inventories.
+	///
+	/// Connected peers are asked for their hostname; peers that do not answer within
+	/// `EXPORT_IDENTIFY_TIMEOUT` are exported without one.
+	pub async fn export_peers(&self) -> Result<Vec<PeerSummary>> {
+		let (me, mut summaries) = {
+			let state = self
+				.state
+				.lock()
+				.map_err(|_| anyhow!("state lock poisoned"))?;
+			(state.me, state.peer_summaries())
+		};
+		let hostnames = futures::future::join_all(summaries.iter().map(|summary| async {
+			let peer: PeerId = summary.peer_id.parse().ok()?;
+			if peer == me {
+				return sysinfo::System::host_name();
+			}
+			if summary.status != "connected" {
+				return None;
+			}
+			match tokio::time::timeout(EXPORT_IDENTIFY_TIMEOUT, self.identify(peer)).await {
+				Ok(Ok(info)) => info.hostname,
+				_ => None,
+			}
+		}))
+		.await;
+		for (summary, hostname) in summaries.iter_mut().zip(hostnames) {
+			summary.hostname = hostname;
+		}
+		Ok(summaries)
+	}
+
+	/// Local nickname of `peer`. Nicknames are never sent to other peers.
+	pub fn peer_name(&self, peer: PeerId) -> Option<String> {
+		let state = self.state.lock().ok()?;
+		state.peer_name(&peer).map(str::to_string)
+	}
+
+	/// Give `peer` a local nickname, or clear it with `None` or a blank name.
+	pub fn set_peer_name(&self, peer: PeerId, name: Option<&str>) -> Result<()> {
+		let name = name.map(str::trim).filter(|name| !name.is_empty());
+		let conn = self.db()?;
+		save_peer_name(&conn, &peer, name)?;
+		let mut state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		state.set_peer_name(peer, name.map(str::to_string));
+		Ok(())
+	}
+
+	/// Remember that the user opened `path` on `peer`, for `recent_files`. Only the
+	/// `RECENT_FILES_KEPT` most recent files are kept.
+	pub fn record_recent_file(&self, peer: PeerId, path: &str) -> Result<()> {
+		let conn = self.db()?;
+		save_recent_file(&conn, &peer, path, Utc::now(), RECENT_FILES_KEPT)
+	}
+
+	/// Files opened with `record_recent_file`, newest first, on `peer` or on every peer.
+	pub fn recent_files(&self, peer: Option<PeerId>) -> Result<Vec<RecentFile>> {
+		let conn = self.db()?;
+		load_recent_files(&conn, peer.as_ref())
+	}
+
+	pub fn list_granted_permissions(&self, peer: PeerId) -> Result<Vec<Permission>> {
+		let state = self
+			.state
+			.lock()
+			.map_err(|_| anyhow!("state lock poisoned"))?;
+		Ok(state.permissions_granted_to_peer(&peer))
+	}
+
+	pub async fn list_permissions(&self, peer: PeerId) -> Result<Vec<Permission>> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ListPermissions { peer, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send ListPermissions command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ListPermissions response channel closed: {e}"))?
+	}
+
+	pub fn list_permissions_blocking(&self, peer: PeerId) -> Result<Vec<Permission>> {
+		block_on(self.list_permissions(peer))
+	}
+
+	pub async fn read_file(
+		&self,
+		peer: libp2p::PeerId,
+		path: impl Into<String>,
+		offset: u64,
+		length: Option<u64>,
+	) -> Result<FileChunk> {
+		let path = path.into();
+		let _permit = self.read_limits.acquire(peer).await;
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ReadFile(ReadFileCmd {
+				peer_id: peer,
+				path,
+				offset,
+				length,
+				tx,
+			}))
+			.await
+			.map_err(|e| anyhow!("failed to send ReadFile command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ReadFile response channel closed: {e}"))?
+	}
+
+	/// Read a whole file on `peer` front to back, `chunk_size` bytes per request and one
+	/// request at a time, so only the current chunk is held however large the file is.
+	/// Peers cap every chunk at `MAX_FILE_CHUNK`. The stream ends after the chunk marked
+	/// `eof` or after the first error.
+	pub fn read_file_stream(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		chunk_size: u64,
+	) -> impl futures::Stream<Item = Result<FileChunk>> + '_ {
+		let path = path.into();
+		futures::stream::unfold(Some(0), move |next| {
+			let path = path.clone();
+			async move {
+				let offset = next?;
+				match self.read_file(peer, path, offset, Some(chunk_size)).await {
+					Ok(chunk) => {
+						let end = offset + chunk.data.len() as u64;
+						// an empty chunk before eof would ask for the same offset forever
+						let done = chunk.eof || chunk.data.is_empty();
+						Some((Ok(chunk), (!done).then_some(end)))
+					}
+					Err(err) => Some((Err(err), None)),
+				}
+			}
+		})
+	}
+
+	pub fn read_file_blocking(
+		&self,
+		peer: libp2p::PeerId,
+		path: impl Into<String>,
+		offset: u64,
+		length: Option<u64>,
+	) -> Result<FileChunk> {
+		block_on(self.read_file(peer, path, offset, length))
+	}
+
+	/// Read several `(path, offset, length)` ranges in one round trip.
+	///
+	/// Each read succeeds or fails on its own. The serving peer limits a batch to 256
+	/// files and 4 MiB in total; reads beyond the byte budget fail with
+	/// `ErrorCode::LimitExceeded` and can be retried in a new batch.
+	pub async fn read_files(
+		&self,
+		peer: PeerId,
+		requests: Vec<(String, u64, Option<u64>)>,
+	) -> Result<Vec<Result<FileChunk, ErrorCode>>> {
+		if requests.len() > MAX_BATCH_FILES {
+			bail!("too many files in batch (max {MAX_BATCH_FILES})");
+		}
+		let _permit = self.read_limits.acquire(peer).await;
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::ReadFiles { peer, requests, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send ReadFiles command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("ReadFiles response channel closed: {e}"))?
+	}
+
+	pub fn read_files_blocking(
+		&self,
+		peer: PeerId,
+		requests: Vec<(String, u64, Option<u64>)>,
+	) -> Result<Vec<Result<FileChunk, ErrorCode>>> {
+		block_on(self.read_files(peer, requests))
+	}
+
+	/// Fetch the first `bytes` of a file together with its sniffed mime type.
+	///
+	/// The serving peer caps `bytes` at 4 KiB, so this stays cheap enough for list previews.
+	pub async fn preview_file(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		bytes: u64,
+	) -> Result<FilePreview> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::PreviewFile {
+				peer,
+				path,
+				bytes,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send PreviewFile command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("PreviewFile response channel closed: {e}"))?
+	}
+
+	pub fn preview_file_blocking(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		bytes: u64,
+	) -> Result<FilePreview> {
+		block_on(self.preview_file(peer, path, bytes))
+	}
+
+	/// Recursively sum file sizes under `path` on `peer`.
+	///
+	/// The walk is bounded in time and entry count; `DirSize::partial` is set when it
+	/// stopped early or some entries could not be read.
+	pub async fn dir_size(&self, peer: PeerId, path: impl Into<String>) -> Result<DirSize> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::DirSize { peer, path, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send DirSize command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("DirSize response channel closed: {e}"))?
+	}
+
+	pub fn dir_size_blocking(&self, peer: PeerId, path: impl Into<String>) -> Result<DirSize> {
+		block_on(self.dir_size(peer, path))
+	}
+
+	/// One read of a growing file: the bytes appended since `offset`, or its last
+	/// `from_end_bytes` when `offset` is `None`. The returned chunk's `offset` is 0 again
+	/// after the file was truncated, and its data is empty when nothing was appended.
+	pub async fn read_tail(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		offset: Option<u64>,
+		from_end_bytes: u64,
+	) -> Result<FileChunk> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::TailFile {
+				peer,
+				path: path.into(),
+				offset,
+				from_end_bytes,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send TailFile command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("TailFile response channel closed: {e}"))?
+	}
+
+	pub fn read_tail_blocking(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		offset: Option<u64>,
+		from_end_bytes: u64,
+	) -> Result<FileChunk> {
+		block_on(self.read_tail(peer, path, offset, from_end_bytes))
+	}
+
+	/// Follow a file on `peer` like `tail -f`: yields its last `from_end_bytes`, then every
+	/// batch of appended bytes, polling every `TAIL_POLL_INTERVAL` while it is idle. The
+	/// stream ends after the first error.
+	pub fn tail_file(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		from_end_bytes: u64,
+	) -> impl futures::Stream<Item = Result<Vec<u8>>> + '_ {
+		let path = path.into();
+		futures::stream::unfold(Some(None), move |next| {
+			let path = path.clone();
+			async move {
+				let mut offset = next?;
+				loop {
+					match self
+						.read_tail(peer, path.clone(), offset, from_end_bytes)
+						.await
+					{
+						Ok(chunk) => {
+							let end = chunk.offset + chunk.data.len() as u64;
+							if !chunk.data.is_empty() {
+								return Some((Ok(chunk.data), Some(Some(end))));
+							}
+							offset = Some(end);
+							tokio::time::sleep(TAIL_POLL_INTERVAL).await;
+						}
+						Err(err) => return Some((Err(err), None)),
+					}
+				}
+			}
+		})
+	}
+
+	/// Free and total bytes of the filesystem holding `path` on `peer`.
+	///
+	/// `path` does not have to exist yet; its closest existing parent is used. The peer
+	/// answers only for paths inside a folder shared with this peer.
+	pub async fn space_for(&self, peer: PeerId, path: impl Into<String>) -> Result<DiskSpace> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::SpaceFor { peer, path, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send SpaceFor command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("SpaceFor response channel closed: {e}"))?
+	}
+
+	pub fn space_for_blocking(&self, peer: PeerId, path: impl Into<String>) -> Result<DiskSpace> {
+		block_on(self.space_for(peer, path))
+	}
+
+	/// Up to `limit` files `peer` has indexed under the directory `prefix`, skipping the
+	/// first `offset`, ordered by path. Only folders shared with this peer are included,
+	/// and only files the peer has scanned. Pages hold at most `MAX_CATALOG_PAGE` entries.
+	pub async fn browse_catalog(
+		&self,
+		peer: PeerId,
+		prefix: impl Into<String>,
+		offset: u64,
+		limit: u64,
+	) -> Result<CatalogPage> {
+		let prefix = prefix.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::BrowseCatalog {
+				peer,
+				prefix,
+				offset,
+				limit,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send BrowseCatalog command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("BrowseCatalog response channel closed: {e}"))?
+	}
+
+	/// Which of `hashes` `peer` has indexed in folders this peer may read.
+	///
+	/// At most `MAX_FIND_HASHES` hashes can be asked for at once. Only files the peer has
+	/// scanned are known to it.
+	pub async fn find_hashes(&self, peer: PeerId, hashes: Vec<FileHash>) -> Result<Vec<FileHash>> {
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::FindHashes { peer, hashes, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send FindHashes command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("FindHashes response channel closed: {e}"))?
+	}
+
+	pub fn find_hashes_blocking(
+		&self,
+		peer: PeerId,
+		hashes: Vec<FileHash>,
+	) -> Result<Vec<FileHash>> {
+		block_on(self.find_hashes(peer, hashes))
+	}
+
+	/// Write `data` at `offset` into `path` on `peer`.
+	///
+	/// The folder must be shared writable with this peer. This node may write to its own
+	/// files, except under a folder it shares read-only. With `WriteMode::Atomic` the
+	/// chunks are staged and the final ack carries the SHA-256 of the assembled file.
+	/// `times` are applied once the data is in place, so send them with the final chunk.
+	pub async fn write_file(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		offset: u64,
+		data: Vec<u8>,
+		mode: WriteMode,
+		times: Option<FileTimestamps>,
+	) -> Result<FileWriteAck> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::WriteFile {
+				peer,
+				path,
+				offset,
+				data,
+				mode,
+				times,
+				tx,
+			})
+			.await
+			.map_err(|e| anyhow!("failed to send WriteFile command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("WriteFile response channel closed: {e}"))?
+	}
+
+	pub fn write_file_blocking(
+		&self,
+		peer: PeerId,
+		path: impl Into<String>,
+		offset: u64,
+		data: Vec<u8>,
+		mode: WriteMode,
+		times: Option<FileTimestamps>,
+	) -> Result<FileWriteAck> {
+		block_on(self.write_file(peer, path, offset, data, mode, times))
+	}
+
+	/// Create `path` and its missing parents on `peer`, returning the canonical path.
+	pub async fn create_dir(&self, peer: PeerId, path: impl Into<String>) -> Result<String> {
+		let path = path.into();
+		let (tx, rx) = oneshot::channel();
+		self.cmd_tx
+			.send(Command::CreateDir { peer, path, tx })
+			.await
+			.map_err(|e| anyhow!("failed to send CreateDir command: {e}"))?;
+		rx.await
+			.map_err(|e| anyhow!("CreateDir response channel closed: {e}"))?
+	}
+
+	/// Copy `src_path` on `src_peer` to `dst_path` on `dst_peer` through this peer.
+	///
+	/// Chunks are read from the source while earlier ones are written to the destination,
+	/// and at most `RELAY_PIPELINE_DEPTH` chunks are held in memory. The destination is
+	/// written atomically, so a transfer that fails on either side leaves it untouched. The
+	/// SHA-256 of the relayed bytes is compared with the one the destination reports.
+	pub async fn relay_copy(
+		&self,
+		src_peer: PeerId,
+		src_path: impl Into<String>,
+		dst_peer: PeerId,
+		dst_path: impl Into<String>,
+		on_progress: impl Fn(RelayProgress),
+	) -> Result<RelayReport> {
+		let src_path = src_path.into();
+		let dst_path = dst_path.into();
+		let total_bytes = self.remote_file_size(src_peer, &src_path).await;
+		let (tx, rx) = tokio::sync::mpsc::channel::<FileChunk>(RELAY_PIPELINE_DEPTH);
+
+		// each side owns its end of the channel, so the other notices when it stops
+		let reader = async {
+			let tx = tx;
+			let mut offset = 0;
+			loop {
+				let chunk = self
+					.read_file(src_peer, src_path.clone(), offset, Some(RELAY_CHUNK_SIZE))
+					.await
+					.map_err(|err| {
+						anyhow!(
+							"reading {src_path} from {src_peer} failed after {offset} bytes: {err}"
+						)
+					})?;
+				offset += chunk.data.len() as u64;
+				let eof = chunk.eof || chunk.data.is_empty();
+				// a closed channel means the writer failed and reports why
+				if tx.send(chunk).await.is_err() || eof {
+					return Ok::<_, anyhow::Error>(());
+				}
+			}
+		};
+		let writer = async {
+			let mut rx = rx;
+			let mut hasher = Sha256Hasher::new();
+			let mut offset = 0;
+			while let Some(chunk) = rx.recv().await {
+				let last = chunk.eof || chunk.data.is_empty();
+				let len = chunk.data.len() as u64;
+				hasher.update(&chunk.data);
+				let ack = self
+					.write_file(
+						dst_peer,
+						dst_path.clone(),
+						offset,
+						chunk.data,
+						WriteMode::Atomic { last },
+						None,
+					)
+					.await
+					.map_err(|err| {
+						anyhow!(
+							"writing {dst_path} to {dst_peer} failed after {offset} bytes: {err}"
+						)
+					})?;
+				offset += len;
+				on_progress(RelayProgress {
+					bytes_copied: offset,
+					total_bytes,
+				});
+				if last {
+					let sha256 = hex(&hasher.finish());
+					if ack.sha256.as_ref().is_some_and(|remote| *remote != sha256) {
+						bail!("checksum mismatch after relaying to {dst_path}");
+					}
+					return Ok(RelayReport {
+						bytes_copied: offset,
+						sha256,
+					});
+				}
+			}
+			bail!("source closed before the end of {src_path}")
+		};
+		let (read, written) = futures::join!(reader, writer);
+		read?;
+		written
+	}
+
+	/// Size of a remote file from a listing of its folder, if the peer reports it.
+	async fn remote_file_size(&self, peer: PeerId, path: &str) -> Option<u64> {
+		let path = Path::new(path);
+		let name = path.file_name()?.to_string_lossy();
+		let entries = self
+			.list_dir(peer, path.parent()?.to_string_lossy())
+			.await
+			.ok()?;
+		entries
+			.into_iter()
+			.find(|entry| entry.name == name)
+			.map(|entry| entry.size)
+	}
+
+	/// Copy every file under the local `local_path` to `remote_path` on `peer`.
+	///
+	/// Files whose content the peer has already indexed (see `find_hashes`) are skipped.
+	/// Each file is written atomically and its checksum compared with the local hash. A
+	/// failed file is reported and the rest are still copied.
+	pub async fn replicate_folder(
+		&self,
+		peer: PeerId,
+		local_path: impl AsRef<Path>,
+		remote_path: impl Into<String>,
+	) -> Result<ReplicationReport> {
+		let local_root = fs::canonicalize(local_path.as_ref()).await?;
+		let remote_root = PathBuf::from(remote_path.into());
+		let root = local_root.clone();
+		let files: Vec<PathBuf> = tokio::task::spawn_blocking(move || {
+			walkdir::WalkDir::new(&root)
+				.sort_by_file_name()
+				.into_iter()
+				.filter_map(|entry| entry.ok())
+				.filter(|entry| entry.file_type().is_file())
+				.map(|entry| entry.into_path())
+				.collect()
+		})
+		.await?;
+		let mut hashes = Vec::with_capacity(files.len());
+		for file in &files {
+			hashes.push(file_hash(file).await);
+		}
+
+		let wanted: Vec<FileHash> = hashes
+			.iter()
+			.filter_map(|hash| hash.as_ref().ok())
+			.copied()
+			.collect();
+		let mut known = HashSet::new();
+		for batch in wanted.chunks(MAX_FIND_HASHES) {
+			match self.find_hashes(peer, batch.to_vec()).await {
+				Ok(hits) => known.extend(hits),
+				Err(err) => {
+					log::warn!("could not check existing files on {peer}, copying all: {err}");
+					known.clear();
+					break;
+				}
+			}
+		}
+
+		let mut needed = 0;
+		for (file, hash) in files.iter().zip(&hashes) {
+			if !matches!(hash, Ok(hash) if known.contains(hash)) {
+				needed += fs::metadata(file).await.map(|meta| meta.len()).unwrap_or(0);
+			}
+		}
+		// a peer that cannot answer is still tried; its writes fail with NoSpace if full
+		if let Ok(space) = self.space_for(peer, remote_root.to_string_lossy()).await
+			&& space.available < needed
+		{
+			return Err(PeerError {
+				code: ErrorCode::NoSpace,
+				message: format!(
+					"{} bytes to copy but only {} bytes free on {}",
+					needed, space.available, peer
+				),
+			}
+			.into());
+		}
+
+		let mut report = ReplicationReport::default();
+		let mut created = HashSet::new();
+		for (file, hash) in files.into_iter().zip(hashes) {
+			let relative = file
+				.strip_prefix(&local_root)
+				.unwrap_or(&file)
+				.to_path_buf();
+			let outcome = match hash {
+				Err(err) => ReplicationOutcome::Failed(format!("failed to hash: {err}")),
+				Ok(hash) if known.contains(&hash) => ReplicationOutcome::Skipped,
+				Ok(hash) => {
+					let remote = remote_root.join(&relative);
+					match self
+						.push_file(peer, &file, &remote, &hash, &mut created)
+						.await
+					{
+						Ok(bytes) => ReplicationOutcome::Copied { bytes },
+						Err(err) => ReplicationOutcome::Failed(format!("{err}")),
+					}
+				}
+			};
+			match &outcome {
+				ReplicationOutcome::Copied { bytes } => {
+					report.copied += 1;
+					report.bytes_copied += bytes;
+				}
+				ReplicationOutcome::Skipped => report.skipped += 1,
+				ReplicationOutcome::Failed(err) => {
+					log::warn!("failed to replicate {}: {err}", file.display());
+					report.failed += 1;
+				}
+			}
+			report.files.push(ReplicatedFile {
+				path: relative,
+				outcome,
+			});
+		}
+		Ok(report)
+	}
+
+	/// Upload one file for `replicate_folder`, creating its remote directory first.
+	async fn push_file(
+		&self,
+		peer: PeerId,
+		local: &Path,
+		remote: &Path,
+		hash: &FileHash,
+		created: &mut HashSet<PathBuf>,
+	) -> Result<u64> {
+		if let Some(parent) = remote.parent()
+			&& created.insert(parent.to_path_buf())
+		{
+			self.create_dir(peer, parent.to_string_lossy()).await?;
+		}
+		let mut file = fs::File::open(local).await?;
+		let meta = file.metadata().await?;
+		let len = meta.len();
+		let times = FileTimestamps::from_metadata(&meta);
+		let remote = remote.to_string_lossy().into_owned();
+		let mut offset = 0;
+		loop {
+			let mut chunk = vec![0u8; (len - offset).min(REPLICATE_CHUNK_SIZE) as usize];
+			file.read_exact(&mut chunk).await?;
+			let chunk_len = chunk.len() as u64;
+			let last = offset + chunk_len >= len;
+			let ack = self
+				.write_file(
+					peer,
+					remote.clone(),
+					offset,
+					chunk,
+					WriteMode::Atomic { last },
+					last.then_some(times),
+				)
+				.await?;
+			offset += chunk_len;
+			if last {
+				if ack.sha256.is_some_and(|sha256| sha256 != hex(hash)) {
+					bail!("checksum mismatch after copying to {}", remote);
+				}
+				return Ok(len);
+			}
+		}
+	}
+
+	/// Work out what `sync_folder` would do without copying anything.
+	///
+	/// Only the folder listings are read. Files with the same size on both sides are
+	/// marked `verify`, as their contents are only compared when the plan is applied.
+	pub async fn sync_plan(
+		&self,
+		peer: PeerId,
+		local_path: impl AsRef<Path>,
+		remote_path: impl Into<String>,
+		mode: SyncMode,
+	) -> Result<SyncPlan> {
+		let local_root = fs::canonicalize(local_path.as_ref()).await?;
+		let remote_root = PathBuf::from(remote_path.into());
+		let root = local_root.clone();
+		let local = tokio::task::spawn_blocking(move || local_sync_listing(&root)).await?;
+		let remote = self.remote_sync_listing(peer, &remote_root).await?;
+		let paths: BTreeSet<&PathBuf> = local.keys().chain(remote.keys()).collect();
+		let files = paths
+			.into_iter()
+			.map(|path| {
+				plan_file(
+					path,
+					local.get(path).copied(),
+					remote.get(path).copied(),
+					mode,
+				)
+			})
+			.collect();
+		Ok(SyncPlan {
+			peer,
+			local_root,
+			remote_root,
+			files,
+		})
+	}
+
+	/// Bring `local_path` and `remote_path` on `peer` in line.
+	///
+	/// Files are compared by size and modification time. When only the time differs, the
+	/// remote file is downloaded and hashed, so identical files are never copied. After a
+	/// copy the local modification time is set to the remote one, which keeps the next
+	/// sync from copying the file again. Deletions are not synced: a file missing on one
+	/// side is copied back in two-way mode and left alone in one-way mode.
+	pub async fn sync_folder(
+		&self,
+		peer: PeerId,
+		local_path: impl AsRef<Path>,
+		remote_path: impl Into<String>,
+		mode: SyncMode,
+	) -> Result<SyncReport> {
+		let plan = self.sync_plan(peer, local_path, remote_path, mode).await?;
+		Ok(self.apply_sync_plan(&plan).await)
+	}
+
+	/// Carry out a plan from `sync_plan`, for example after the user reviewed it.
+	pub async fn apply_sync_plan(&self, plan: &SyncPlan) -> SyncReport {
+		let peer = plan.peer;
+		let mut report = SyncReport::default();
+		let mut created = HashSet::new();
+		for planned in &plan.files {
+			let local_file = plan.local_root.join(&planned.path);
+			let remote_file = plan.remote_root.join(&planned.path);
+			let remote_modified = planned.remote.and_then(|remote| remote.modified);
+			let mut conflict = planned.conflict;
+			let result = async {
+				if planned.operation == SyncOperation::Skip {
+					return Ok(SyncAction::Unchanged);
+				}
+				let hash = match planned.local {
+					Some(_) => Some(file_hash(&local_file).await?),
+					None => None,
+				};
+				// equal sizes may still be the same content; the download is reused
+				// if the remote side wins
+				let mut temp = None;
+				if planned.verify {
+					let downloaded = self.download(peer, &remote_file, &local_file).await?;
+					if Some(file_hash(&downloaded).await?) == hash {
+						let _ = fs::remove_file(&downloaded).await;
+						if let Some(modified) = remote_modified {
+							set_mtime(&local_file, modified)?;
+						}
+						conflict = false;
+						return Ok(SyncAction::Unchanged);
+					}
+					temp = Some(downloaded);
+				}
+				match (planned.operation, hash) {
+					(SyncOperation::Upload, Some(hash)) => {
+						if let Some(temp) = temp {
+							let _ = fs::remove_file(&temp).await;
+						}
+						self.push_synced(peer, &local_file, &remote_file, &hash, &mut created)
+							.await?;
+						Ok(SyncAction::Pushed)
+					}
+					_ => {
+						let temp = match temp {
+							Some(temp) => temp,
+							None => self.download(peer, &remote_file, &local_file).await?,
+						};
+						finish_pull(&temp, &local_file, remote_modified).await?;
+						Ok(SyncAction::Pulled)
+					}
+				}
+			}
+			.await
+			.unwrap_or_else(|err: anyhow::Error| SyncAction::Failed(format!("{err}")));
+			if matches!(result, SyncAction::Failed(_)) {
+				// a failed hash or rename can leave the download behind
+				let _ = fs::remove_file(sync_temp_path(&local_file)).await;
+			}
+			match &result {
+				SyncAction::Pushed => report.pushed += 1,
+				SyncAction::Pulled => report.pulled += 1,
+				SyncAction::Unchanged => report.unchanged += 1,
+				SyncAction::Failed(err) => {
+					log::warn!("failed to sync {}: {err}", planned.path.display());
+					report.failed += 1;
+				}
+			}
+			if conflict {
+				report.conflicts += 1;
+			}
+			report.files.push(SyncedFile {
+				path: planned.path.clone(),
+				action: result,
+				conflict,
+			});
+		}
+		report
+	}
+
+	/// Every file under `root` on `peer`; a missing root is an empty folder.
+	async fn remote_sync_listing(&self, peer: PeerId, root: &Path) -> Result<SyncListing> {
+		let mut listing = SyncListing::new();
+		let mut pending = vec![PathBuf::new()];
+		while let Some(relative) = pending.pop() {
+			let dir = root.join(&relative);
+			let entries = match self.list_dir(peer, dir.to_string_lossy()).await {
+				Ok(entries) => entries,
+				Err(err)
+					if relative.as_os_str().is_empty()
+						&& ErrorCode::of(&err) == Some(ErrorCode::NotFound) =>
+				{
+					return Ok(listing);
+				}
+				Err(err) => return Err(err),
+			};
+			for entry in entries {
+				// symlinks are left alone, like the local walk does
+				if entry.is_symlink {
+					continue;
+				}
+				let path = relative.join(&entry.name);
+				if entry.is_dir {
+					pending.push(path);
+				} else {
+					listing.insert(
+						path,
+						SyncFileState {
+							size: entry.size,
+							modified: entry.modified_at,
+						},
+					);
+				}
+			}
+		}
+		Ok(listing)
+	}
+
+	/// Push a file and adopt the remote modification time locally.
+	async fn push_synced(
+		&self,
+		peer: PeerId,
+		local: &Path,
+		remote: &Path,
+		hash: &FileHash,
+		created: &mut HashSet<PathBuf>,
+	) -> Result<()> {
+		self.push_file(peer, local, remote, hash, created).await?;
+		let (Some(parent), Some(name)) = (remote.parent(), remote.file_name()) else {
+			return Ok(());
+		};
+		let entries = self.list_dir(peer, parent.to_string_lossy()).await?;
+		let modified = entries
+			.into_iter()
+			.find(|entry| entry.name == name.to_string_lossy())
+			.and_then(|entry| entry.modified_at);
+		if let Some(modified) = modified {
+			set_mtime(local, modified)?;
+		}
+		Ok(())
+	}
+
+	/// Download `remote` into a temp file next to `local`, removing it again on failure.
+	async fn download(&self, peer: PeerId, remote: &Path, local: &Path) -> Result<PathBuf> {
+		if let Some(parent) = local.parent() {
+			fs::create_dir_all(parent).await?;
+		}
+		let temp = sync_temp_path(local);
+		let result = async {
+			let mut file = fs::File::create(&temp).await?;
+			let remote = remote.to_string_lossy().into_owned();
+			let mut offset = 0;
+			loop {
+				let chunk = self
+					.read_file(peer, remote.clone(), offset, Some(REPLICATE_CHUNK_SIZE))
+					.await?;
+				file.write_all(&chunk.data).await?;
+				offset += chunk.data.len() as u64;
+				if chunk.eof || chunk.data.is_empty() {
+					break;
+				}
+			}
+			file.flush().await?;
+			Ok(())
+		}
+		.await;
+		if let Err(err) = result {
+			let _ = fs::remove_file(&temp).await;
+			return Err(err);
+		}
+		Ok(temp)
+	}
+
+	/// Hash each local file in `paths` and ask every connected peer whether it has a copy.
+	///
+	/// Peers are asked `FIND_BACKUPS_CONCURRENCY` at a time. A peer that fails or times out
+	/// is listed in `unknown` for every file rather than treated as not having it.
+	pub async fn find_backups(&self, paths: Vec<PathBuf>) -> Result<Vec<BackupLocations>> {
+		let mut hashes = Vec::with_capacity(paths.len());
+		for path in &paths {
+			let hash = file_hash(path)
+				.await
+				.map_err(|err| anyhow!("failed to hash {}: {err}", path.display()))?;
+			hashes.push(hash);
+		}
+		let peers: BTreeSet<PeerId> = {
+			let state = self
+				.state
+				.lock()
+				.map_err(|_| anyhow!("state lock poisoned"))?;
+			state
+				.connections
+				.iter()
+				.map(|connection| connection.peer_id)
+				.filter(|peer| *peer != state.me)
+				.collect()
+		};
+		let hashes = &hashes;
+		let answers: Vec<(PeerId, Option<HashSet<FileHash>>)> = futures::stream::iter(peers)
+			.map(|peer| async move {
+				let mut found = HashSet::new();
+				for batch in hashes.chunks(MAX_FIND_HASHES) {
+					let request = self.find_hashes(peer, batch.to_vec());
+					match tokio::time::timeout(FIND_HASHES_TIMEOUT, request).await {
+						Ok(Ok(hits)) => found.extend(hits),
+						Ok(Err(err)) => {
+							log::warn!("failed to query hashes on {peer}: {err}");
+							return (peer, None);
+						}
+						Err(_) => {
+							log::warn!("timed out querying hashes on {peer}");
+							return (peer, None);
+						}
+					}
+				}
+				(peer, Some(found))
+			})
+			.buffer_unordered(FIND_BACKUPS_CONCURRENCY)
+			.collect()
+			.await;
+		Ok(paths
+			.into_iter()
+			.zip(hashes)
+			.map(|(path, hash)| {
+				let mut locations = BackupLocations {
+					path,
+					found_on: Vec::new(),
+					unknown: Vec::new(),
+				};
+				for (peer, found) in &answers {
+					match found {
+						Some(found) if found.contains(hash) => locations.found_on.push(*peer),
+						Some(_) => {}
+						None => locations.unknown.push(*peer),
+					}
+				}
+				locations
+			})
+			.collect())
+	}
+
+	/// Wait for the peer until Ctrl+C (SIGINT) then perform a graceful shutdown.
+	pub async fn wait(self) {
+		// Wait for Ctrl+C
+		if let Err(e) = tokio::signal::ctrl_c().await {
+			log::error!("failed to listen for ctrl_c: {e}");
+		}
+		log::info!("interrupt received, shutting down");
+		self.shutdown().await;
+	}
+
+	/// Stop the background task and wait for it to finish.
+	pub async fn shutdown(mut self) {
+		if let Some(tx) = self.shutdown_tx.take() {
+			let _ = tx.send(());
+		}
+		// Await the background task
+		if let Err(e) = self.handle.await {
+			log::error!("task join error: {e}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::p2p::FileAccess;
+	use crate::state::MESSAGES_KEPT;
+
+	#[test]
+	fn lan_addresses_are_dialed_before_public_and_loopback() {
+		let addrs: Vec<Multiaddr> = [
+			"/ip4/127.0.0.1/tcp/8336",
+			"/ip4/203.0.113.9/tcp/8336",
+			"/ip4/192.168.1.20/tcp/8336",
+			"/ip6/fe80::1/tcp/8336",
+			"/ip4/10.0.0.7/tcp/8336",
+			"/ip4/192.168.1.20/tcp/8336",
+		]
+		.iter()
+		.map(|addr| addr.parse().unwrap())
+		.collect();
+		let ordered: Vec<String> = dial_order(addrs).iter().map(|a| a.to_string()).collect();
+		assert_eq!(
+			ordered,
+			vec![
+				"/ip4/192.168.1.20/tcp/8336",
+				"/ip4/10.0.0.7/tcp/8336",
+				"/ip4/203.0.113.9/tcp/8336",
+				"/ip4/127.0.0.1/tcp/8336",
+				"/ip6/fe80::1/tcp/8336",
+			]
+		);
+	}
+
+	use std::time::{SystemTime, UNIX_EPOCH};
+
+	fn temporary_dir(test: &str) -> PathBuf {
+		let unique = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_nanos();
+		let path = std::env::temp_dir().join(format!(
+			"puppypeer-test-{}-{}-{}",
+			test,
+			std::process::id(),
+			unique
+		));
+		std::fs::create_dir_all(&path).expect("create temp dir");
+		std::fs::canonicalize(path).expect("canonicalize temp dir")
+	}
+
+	/// A peer that keeps its identity and database in `root` and does not listen.
+	fn local_peer(root: &Path) -> PuppyPeer {
+		PuppyPeer::with_config(PuppyConfig {
+			keypair_path: Some(root.join("peer.key")),
+			db_path: Some(root.join("peer.db")),
+			listen_addrs: Vec::new(),
+			..PuppyConfig::default()
+		})
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn symlinked_directories_are_navigable_only_inside_shares() {
+		let root = temporary_dir("symlinks");
+		let shared = root.join("shared");
+		let outside = root.join("outside");
+		std::fs::create_dir_all(shared.join("real")).unwrap();
+		std::fs::create_dir_all(&outside).unwrap();
+		std::os::unix::fs::symlink(shared.join("real"), shared.join("inner")).unwrap();
+		std::os::unix::fs::symlink(&outside, shared.join("escape")).unwrap();
+
+		let entries = App::collect_dir_entries(&shared, |target| target.starts_with(&shared))
+			.await
+			.unwrap();
+		let find = |name: &str| entries.iter().find(|e| e.name == name).unwrap();
+
+		let real = find("real");
+		assert!(real.is_dir && !real.is_symlink && real.is_navigable());
+		let inner = find("inner");
+		assert!(inner.is_symlink && inner.target_is_dir && inner.is_navigable());
+		let escape = find("escape");
+		assert!(escape.is_symlink && !escape.target_is_dir && !escape.is_navigable());
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn reads_to_one_peer_stay_under_the_cap() {
+		let limits = ReadLimits::new(4);
+		let peer = PeerId::random();
+		let in_flight = std::sync::atomic::AtomicUsize::new(0);
+		let most = std::sync::atomic::AtomicUsize::new(0);
+		futures::future::join_all((0..20).map(|_| async {
+			let _permit = limits.acquire(peer).await;
+			let now = in_flight.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+			most.fetch_max(now, std::sync::atomic::Ordering::SeqCst);
+			tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+			in_flight.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+		}))
+		.await;
+		assert_eq!(most.into_inner(), 4);
+
+		// a busy peer does not hold up reads to another one
+		let _held: Vec<_> = futures::future::join_all((0..4).map(|_| limits.acquire(peer))).await;
+		let other = tokio::time::timeout(
+			std::time::Duration::from_secs(1),
+			limits.acquire(PeerId::random()),
+		)
+		.await;
+		assert!(other.is_ok());
+	}
+
+	#[tokio::test]
+	async fn listing_a_file_says_it_is_not_a_directory() {
+		let root = temporary_dir("list-file");
+		std::fs::create_dir_all(&root).unwrap();
+		let file = root.join("notes.txt");
+		std::fs::write(&file, b"hello").unwrap();
+
+		let err = App::collect_dir_entries(&file, |_| true).await.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NotADirectory));
+		assert!(err.to_string().contains("is a file, not a folder"));
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn recursive_listing_stops_at_the_depth_and_entry_cap() {
+		let root = temporary_dir("list-tree");
+		std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+		std::fs::write(root.join("a/one.txt"), b"1").unwrap();
+		std::fs::write(root.join("a/b/two.txt"), b"2").unwrap();
+		std::fs::write(root.join("top.txt"), b"3").unwrap();
+		let names = |tree: &DirTree| {
+			tree.entries
+				.iter()
+				.map(|entry| entry.name.clone())
+				.collect::<Vec<_>>()
+		};
+
+		let tree = App::collect_dir_tree(&root, 2, 100, |_| true)
+			.await
+			.unwrap();
+		assert_eq!(names(&tree), ["a", "a/b", "a/one.txt", "top.txt"]);
+		assert!(!tree.truncated);
+
+		let tree = App::collect_dir_tree(&root, 10, 3, |_| true).await.unwrap();
+		assert_eq!(names(&tree), ["a", "a/b", "a/b/c"]);
+		assert!(tree.truncated);
+
+		// a folder the requester may not search is listed but not entered
+		let hidden = root.join("a/b");
+		let tree = App::collect_dir_tree(&root, 10, 100, |target| target != hidden)
+			.await
+			.unwrap();
+		assert_eq!(names(&tree), ["a", "a/b", "a/one.txt", "top.txt"]);
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn find_hashes_only_reports_readable_locations() {
+		let root = temporary_dir("find-hashes");
+		let shared = root.join("shared");
+		std::fs::create_dir_all(&shared).unwrap();
+		std::fs::write(shared.join("a.txt"), b"backed up").unwrap();
+		std::fs::write(root.join("secret.txt"), b"private").unwrap();
+		let db_path = root.join("index.db");
+		let open = || {
+			let mut conn = rusqlite::Connection::open(&db_path).unwrap();
+			crate::db::run_migrations(&mut conn).unwrap();
+			conn
+		};
+		let node_id = [7u8; 16];
+		scan(
+			&node_id,
+			&root,
+			open(),
+			&ScanOptions::default(),
+			|_| {},
+			&AtomicBool::new(false),
+		)
+		.unwrap();
+
+		let hash = |data: &[u8]| crate::scan::sha256_hash(data).unwrap();
+		let found = readable_hashes(
+			&open(),
+			&node_id,
+			vec![hash(b"backed up"), hash(b"private"), hash(b"never seen")],
+			|path| path.starts_with(&shared),
+		)
+		.unwrap();
+		assert_eq!(found, vec![hash(b"backed up")]);
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[test]
+	fn sync_plan_follows_mode_and_policy() {
+		let at = |secs| SyncFileState {
+			size: 4,
+			modified: DateTime::from_timestamp(secs, 0),
+		};
+		let plan = |local, remote, mode| {
+			let file = plan_file(Path::new("a.txt"), local, remote, mode);
+			(file.operation, file.reason, file.conflict, file.verify)
+		};
+		let newest = SyncMode::TwoWay(ConflictPolicy::NewestWins);
+
+		assert_eq!(
+			plan(None, Some(at(1)), SyncMode::OneWay),
+			(SyncOperation::Skip, SyncReason::OneWay, false, false)
+		);
+		assert_eq!(
+			plan(None, Some(at(1)), newest),
+			(
+				SyncOperation::Download,
+				SyncReason::OnlyRemote,
+				false,
+				false
+			)
+		);
+		assert_eq!(
+			plan(Some(at(1)), Some(at(1)), newest),
+			(SyncOperation::Skip, SyncReason::Unchanged, false, false)
+		);
+		assert_eq!(
+			plan(Some(at(1)), Some(at(2)), newest),
+			(SyncOperation::Download, SyncReason::Newer, true, true)
+		);
+		assert_eq!(
+			plan(Some(at(1)), Some(at(2)), SyncMode::OneWay),
+			(SyncOperation::Upload, SyncReason::OneWay, true, true)
+		);
+		let bigger = SyncFileState { size: 5, ..at(0) };
+		assert_eq!(
+			plan(
+				Some(at(1)),
+				Some(bigger),
+				SyncMode::TwoWay(ConflictPolicy::PreferRemote)
+			),
+			(SyncOperation::Download, SyncReason::Preferred, true, false)
+		);
+	}
+
+	#[test]
+	fn local_sync_listing_skips_leftover_downloads() {
+		let root = temporary_dir("sync-temp");
+		std::fs::write(root.join("notes.txt"), b"notes").unwrap();
+		std::fs::write(sync_temp_path(&root.join("notes.txt")), b"no").unwrap();
+
+		let listing = local_sync_listing(&root);
+		assert_eq!(
+			listing.keys().collect::<Vec<_>>(),
+			vec![Path::new("notes.txt")]
+		);
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	/// A listening server and a client that has it as its bootstrap peer, once connected.
+	async fn bootstrapped_pair(root: &Path, idle_timeout_secs: u64) -> (PuppyPeer, PuppyPeer) {
+		let port = std::net::TcpListener::bind("127.0.0.1:0")
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.port();
+		let addr: Multiaddr = format!("/ip4/127.0.0.1/tcp/{port}").parse().unwrap();
+		let reconnect = crate::ReconnectPolicy {
+			idle_timeout_secs,
+			..Default::default()
+		};
+		let server = PuppyPeer::with_config(PuppyConfig {
+			keypair_path: Some(root.join("server.key")),
+			db_path: Some(root.join("server.db")),
+			listen_addrs: vec![addr.clone()],
+			reconnect: reconnect.clone(),
+			..PuppyConfig::default()
+		});
+		server.await_ready().await;
+		let server_id = server.state().lock().unwrap().me;
+		let client = PuppyPeer::with_config(PuppyConfig {
+			keypair_path: Some(root.join("client.key")),
+			db_path: Some(root.join("client.db")),
+			listen_addrs: Vec::new(),
+			bootstrap_peers: vec![addr.with(Protocol::P2p(server_id))],
+			reconnect,
+			..PuppyConfig::default()
+		});
+		for _ in 0..50 {
+			if !connections_to(&client, server_id).is_empty() {
+				return (server, client);
+			}
+			tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+		}
+		panic!("bootstrap peer never connected");
+	}
+
+	fn connections_to(peer: &PuppyPeer, target: PeerId) -> Vec<ConnectionId> {
+		peer.state()
+			.lock()
+			.unwrap()
+			.connections
+			.iter()
+			.filter(|connection| connection.peer_id == target)
+			.map(|connection| connection.connection_id)
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn idle_bootstrap_connection_outlives_the_idle_timeout() {
+		let root = temporary_dir("keep-alive");
+		let (server, client) = bootstrapped_pair(&root, 1).await;
+		let server_id = server.state().lock().unwrap().me;
+		let first = connections_to(&client, server_id)[0];
+
+		// without keep-alive the connection closes after a second and is redialed
+		tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+		assert!(connections_to(&client, server_id).contains(&first));
+
+		client.shutdown().await;
+		server.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn dir_size_is_answered_from_a_task() {
+		let root = temporary_dir("dir-size");
+		let folder = root.join("shared");
+		std::fs::create_dir_all(folder.join("nested")).unwrap();
+		std::fs::write(folder.join("a.txt"), b"12345").unwrap();
+		std::fs::write(folder.join("nested").join("b.txt"), b"123").unwrap();
+		let (server, client) = bootstrapped_pair(&root, 300).await;
+		let server_id = server.state().lock().unwrap().me;
+		let client_id = client.state().lock().unwrap().me;
+		let folder = folder
+			.canonicalize()
+			.unwrap()
+			.to_string_lossy()
+			.into_owned();
+		server
+			.grant_access(
+				server_id,
+				client_id,
+				vec![PermissionGrant::Files {
+					path: folder.clone(),
+					access: FileAccess::Read,
+				}],
+				false,
+			)
+			.await
+			.unwrap();
+
+		let size = client.dir_size(server_id, folder).await.unwrap();
+		assert_eq!((size.total_bytes, size.file_count), (8, 2));
+		assert!(!size.partial);
+		// refused walks are still answered in place
+		let refused = client
+			.dir_size(server_id, root.to_string_lossy())
+			.await
+			.unwrap_err();
+		assert!(format!("{refused:#}").contains("Access denied"));
+
+		client.shutdown().await;
+		server.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn disconnected_bootstrap_peer_is_not_redialed() {
+		let root = temporary_dir("bootstrap-disconnect");
+		let (server, client) = bootstrapped_pair(&root, 300).await;
+		let server_id = server.state().lock().unwrap().me;
+
+		client.disconnect(server_id).await.unwrap();
+		// the first redial would come after a second
+		tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+		assert!(connections_to(&client, server_id).is_empty());
+
+		client.shutdown().await;
+		server.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn instances_with_their_own_config_do_not_collide() {
+		let root = temporary_dir("two-instances");
+		let config = |name: &str| PuppyConfig {
+			keypair_path: Some(root.join(format!("{name}.key"))),
+			db_path: Some(root.join(format!("{name}.db"))),
+			listen_addrs: Vec::new(),
+			..PuppyConfig::default()
+		};
+		let me = |peer: &PuppyPeer| peer.state().lock().unwrap().me;
+		let a = PuppyPeer::with_config(config("a"));
+		let b = PuppyPeer::with_config(config("b"));
+		assert!(a.is_ready() && b.is_ready());
+		let (a_id, b_id) = (me(&a), me(&b));
+		assert_ne!(a_id, b_id);
+
+		a.record_recent_file(b_id, "/shared/notes.txt").unwrap();
+		assert_eq!(a.recent_files(None).unwrap().len(), 1);
+		assert!(b.recent_files(None).unwrap().is_empty());
+
+		// the identity is kept in the instance's own keypair file
+		a.shutdown().await;
+		let again = PuppyPeer::with_config(config("a"));
+		assert_eq!(me(&again), a_id);
+		again.shutdown().await;
+		b.shutdown().await;
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	#[tokio::test]
+	async fn messages_reach_the_inbox_and_subscribers() {
+		let root = temporary_dir("messages");
+		let peer = local_peer(&root);
+		let me = peer.state().lock().unwrap().me;
+		let mut events = peer.subscribe();
+
+		peer.send_message(me, "rebooting in 5").await.unwrap();
+		assert_eq!(
+			events.recv().await.unwrap(),
+			PeerEvent::Message {
+				peer_id: me.to_string(),
+				text: String::from("rebooting in 5"),
 			}
-			Command::ReadFile(req) => {
-				if self.state.lock().unwrap().me == req.peer_id {
-					let chunk = read_file(Path::new(&req.path), req.offset, req.length).await;
-					let _ = req.tx.send(chunk);
-					return;
-				}
-				let request_id = self.swarm.behaviour_mut().puppypeer.send_request(
-					&req.peer_id,
-					PeerReq::ReadFile {
-						path: req.path.clone(),
-						offset: req.offset,
-						length: req.length,
-					},
-				);
-				self.pending_requests
-					.insert(request_id, Pending::<FileChunk>::new(req.tx));
+		);
+		let long = "x".repeat(MAX_MESSAGE_BYTES + 1);
+		assert!(peer.send_message(me, long).await.is_err());
+		{
+			let state = peer.state();
+			let mut state = state.lock().unwrap();
+			assert_eq!(state.messages.len(), 1);
+			assert_eq!(state.messages[0].from, me);
+			for idx in 0..MESSAGES_KEPT {
+				state.push_message(PeerMessage {
+					from: me,
+					text: idx.to_string(),
+					received_at: Utc::now(),
+				});
 			}
+			assert_eq!(state.messages.len(), MESSAGES_KEPT);
+			assert_eq!(state.messages[0].text, "0");
 		}
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub async fn run(&mut self) {
-		tokio::select! {
-			event = self.swarm.select_next_some() => {
-				self.handle_swarm_event(event).await;
-			}
-			cmd = self.rx.recv() => {
-				if let Some(cmd) = cmd {
-					self.handle_cmd(cmd).await;
+	#[tokio::test]
+	async fn local_writes_need_a_writable_share() {
+		let root = temporary_dir("local-write");
+		let (readable, writable) = (root.join("readable"), root.join("writable"));
+		std::fs::create_dir_all(&readable).unwrap();
+		std::fs::create_dir_all(&writable).unwrap();
+		let peer = local_peer(&root);
+		peer.share_read_only_folder(&readable).unwrap();
+		peer.share_read_write_folder(&writable).unwrap();
+		let me = peer.state().lock().unwrap().me;
+		let write = |dir: &Path| {
+			let path = dir.join("notes.txt").to_string_lossy().into_owned();
+			let mode = WriteMode::Atomic { last: true };
+			peer.write_file(me, path, 0, b"edited".to_vec(), mode, None)
+		};
+
+		let ack = write(&writable).await.unwrap();
+		assert_eq!(ack.bytes_written, 6);
+		assert_eq!(
+			std::fs::read(writable.join("notes.txt")).unwrap(),
+			b"edited"
+		);
+		let err = write(&readable).await.unwrap_err();
+		assert!(err.to_string().contains("read-only"));
+		assert!(!readable.join("notes.txt").exists());
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
+	}
+
+	/// Retry `write` while the share's usage is still being measured.
+	async fn until_measured<F, Fut>(mut write: F) -> Result<FileWriteAck>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = Result<FileWriteAck>>,
+	{
+		let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+		loop {
+			match write().await {
+				Err(err)
+					if ErrorCode::of(&err) == Some(ErrorCode::Io)
+						&& std::time::Instant::now() < deadline =>
+				{
+					tokio::time::sleep(std::time::Duration::from_millis(20)).await;
 				}
+				result => return result,
 			}
 		}
 	}
-}
 
-pub struct PuppyPeer {
-	shutdown_tx: Option<oneshot::Sender<()>>,
-	handle: JoinHandle<()>,
-	state: Arc<Mutex<State>>,
-	cmd_tx: UnboundedSender<Command>,
-}
+	#[tokio::test]
+	async fn quota_writes_wait_for_the_share_to_be_measured() {
+		let root = temporary_dir("quota");
+		let share = root.join("share");
+		std::fs::create_dir_all(&share).unwrap();
+		std::fs::write(share.join("existing.txt"), b"1234").unwrap();
+		let peer = local_peer(&root);
+		peer.share_read_write_folder(&share).unwrap();
+		peer.set_share_quota(&share, Some(10)).unwrap();
+		let me = peer.state().lock().unwrap().me;
+		let write = |name: &str| {
+			let path = share.join(name).to_string_lossy().into_owned();
+			peer.write_file(me, path, 0, b"edited".to_vec(), WriteMode::InPlace, None)
+		};
 
-impl PuppyPeer {
-	pub fn new() -> Self {
-		let state = Arc::new(Mutex::new(State::default()));
-		// channel to request shutdown
-		let (shutdown_tx, shutdown_rx) = oneshot::channel();
-		let state_clone = state.clone();
-		let (mut app, cmd_tx) = App::new(state_clone);
-		let mut shutdown_rx = shutdown_rx;
-		let handle: JoinHandle<()> = tokio::spawn(async move {
-			loop {
-				tokio::select! {
-					_ = &mut shutdown_rx => {
-						log::info!("PuppyPeer shutting down");
-						break;
-					}
-					_ = app.run() => {}
-				}
-			}
-		});
+		// the first write starts the walk and is refused until it reports back
+		let err = write("a.txt").await.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::Io));
+		let ack = until_measured(|| write("a.txt")).await.unwrap();
+		assert_eq!(ack.bytes_written, 6);
+		// 4 existing and 6 written bytes fill the quota
+		let err = write("b.txt").await.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NoSpace));
+		assert!(!share.join("b.txt").exists());
 
-		PuppyPeer {
-			shutdown_tx: Some(shutdown_tx),
-			handle,
-			state,
-			cmd_tx,
-		}
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	fn register_shared_folder(&self, path: PathBuf, flags: u8) -> anyhow::Result<()> {
-		let mut state = self
-			.state
-			.lock()
-			.map_err(|_| anyhow!("state lock poisoned"))?;
-		state.add_shared_folder(FolderRule::new(path, flags));
-		Ok(())
+	#[tokio::test]
+	async fn catalog_pages_follow_offset_and_limit() {
+		let root = temporary_dir("catalog");
+		let files = root.join("files");
+		std::fs::create_dir_all(&files).unwrap();
+		for name in ["a.txt", "b.txt", "c.txt"] {
+			std::fs::write(files.join(name), name).unwrap();
+		}
+		let peer = local_peer(&root);
+		let cancel = AtomicBool::new(false);
+		peer.scan_folder(&files, &ScanOptions::default(), |_| {}, &cancel)
+			.unwrap();
+		let me = peer.state().lock().unwrap().me;
+		let prefix = files.to_string_lossy().into_owned();
+
+		let page = peer.browse_catalog(me, prefix.clone(), 1, 1).await.unwrap();
+		assert_eq!(page.entries.len(), 1);
+		assert!(page.entries[0].path.ends_with("b.txt"));
+		assert!(page.has_more);
+		let page = peer.browse_catalog(me, prefix.clone(), 2, 5).await.unwrap();
+		assert_eq!(page.entries.len(), 1);
+		assert!(!page.has_more);
+		let err = peer
+			.browse_catalog(me, prefix, 0, MAX_CATALOG_PAGE + 1)
+			.await
+			.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::LimitExceeded));
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn share_read_only_folder(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
-		let canonical = std::fs::canonicalize(path.as_ref())
-			.map_err(|err| anyhow!("failed to canonicalize path: {err}"))?;
-		self.register_shared_folder(canonical, FLAG_READ | FLAG_SEARCH)
+	#[tokio::test]
+	async fn atomic_overwrites_only_count_the_growth_against_the_quota() {
+		let root = temporary_dir("quota-overwrite");
+		let share = root.join("share");
+		std::fs::create_dir_all(&share).unwrap();
+		std::fs::write(share.join("notes.txt"), b"12345678").unwrap();
+		let peer = local_peer(&root);
+		peer.share_read_write_folder(&share).unwrap();
+		peer.set_share_quota(&share, Some(10)).unwrap();
+		let me = peer.state().lock().unwrap().me;
+		let write = |name: &str, data: &[u8]| {
+			let path = share.join(name).to_string_lossy().into_owned();
+			let mode = WriteMode::Atomic { last: true };
+			peer.write_file(me, path, 0, data.to_vec(), mode, None)
+		};
+
+		// replacing 8 bytes with 9 grows the share by one byte, not nine
+		until_measured(|| write("notes.txt", b"123456789"))
+			.await
+			.unwrap();
+		assert_eq!(
+			std::fs::read(share.join("notes.txt")).unwrap(),
+			b"123456789"
+		);
+		// so the cached usage is 9 and one more byte still fits
+		write("more.txt", b"x").await.unwrap();
+		let err = write("full.txt", b"y").await.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::NoSpace));
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn share_read_write_folder(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
-		let canonical = std::fs::canonicalize(path.as_ref())
-			.map_err(|err| anyhow!("failed to canonicalize path: {err}"))?;
-		self.register_shared_folder(canonical, FLAG_READ | FLAG_WRITE | FLAG_SEARCH)
+	#[tokio::test]
+	async fn written_file_times_round_trip() {
+		let root = temporary_dir("file-times");
+		let source = root.join("source.txt");
+		let copy = root.join("copy.txt");
+		std::fs::write(&source, b"same").unwrap();
+		std::fs::write(&copy, b"same").unwrap();
+		let modified = DateTime::from_timestamp(1_600_000_000, 0).unwrap();
+		set_mtime(&source, modified).unwrap();
+
+		let times = FileTimestamps::from_metadata(&std::fs::metadata(&source).unwrap());
+		set_file_times(&copy, times).await.unwrap();
+
+		let copied = FileTimestamps::from_metadata(&std::fs::metadata(&copy).unwrap());
+		assert_eq!(copied.modified, Some(modified));
+		assert_eq!(copied.accessed, times.accessed);
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn set_peer_permissions(
-		&self,
-		peer: PeerId,
-		permissions: Vec<Permission>,
-	) -> anyhow::Result<()> {
-		let mut state = self
-			.state
-			.lock()
-			.map_err(|_| anyhow!("state lock poisoned"))?;
-		state.set_peer_permissions(peer, permissions);
-		state.save_changes()
+	#[test]
+	fn dropped_read_is_retried_until_it_succeeds() {
+		let peer = PeerId::random();
+		let req = PeerReq::ReadFile {
+			path: String::from("/shared/a.txt"),
+			offset: 0,
+			length: None,
+		};
+		let (tx, mut rx) = oneshot::channel();
+		let mut in_flight = InFlight::new(peer, &req, Pending::<FileChunk>::new(tx));
+
+		let delay = in_flight.next_attempt(&OutboundFailure::ConnectionClosed, 2);
+		assert_eq!(delay, Some(RETRY_BACKOFF));
+		assert_eq!(in_flight.attempts, 2);
+		assert!(matches!(in_flight.retry, Some(PeerReq::ReadFile { .. })));
+		in_flight.pending.complete(PeerRes::FileChunk(FileChunk {
+			offset: 0,
+			data: b"hello".to_vec(),
+			eof: true,
+		}));
+		assert_eq!(rx.try_recv().unwrap().unwrap().data, b"hello");
+
+		let write = PeerReq::WriteFile {
+			path: String::from("/shared/a.txt"),
+			offset: 0,
+			data: Vec::new(),
+			mode: WriteMode::Atomic { last: true },
+			times: None,
+		};
+		let (tx, _rx) = oneshot::channel();
+		let mut in_flight = InFlight::new(peer, &write, Pending::<FileWriteAck>::new(tx));
+		assert_eq!(
+			in_flight.next_attempt(&OutboundFailure::ConnectionClosed, 2),
+			None
+		);
 	}
 
-	pub fn state(&self) -> Arc<Mutex<State>> {
-		self.state.clone()
+	#[tokio::test]
+	async fn read_files_checks_each_path_and_reports_codes() {
+		let root = temporary_dir("read-files");
+		let shared = root.join("shared");
+		std::fs::create_dir_all(&shared).unwrap();
+		std::fs::write(shared.join("a.txt"), b"hello").unwrap();
+		std::fs::write(root.join("secret.txt"), b"nope").unwrap();
+
+		let path = |p: &Path| p.to_string_lossy().to_string();
+		let results = read_files(
+			vec![
+				(path(&shared.join("a.txt")), 1, None),
+				(path(&root.join("secret.txt")), 0, None),
+				(path(&shared.join("missing.txt")), 0, None),
+				(path(&shared), 0, None),
+			],
+			|target| target.starts_with(&shared),
+		)
+		.await;
+
+		assert_eq!(results[0].as_ref().unwrap().data, b"ello");
+		assert_eq!(results[1].as_ref().unwrap_err(), &ErrorCode::AccessDenied);
+		assert_eq!(results[2].as_ref().unwrap_err(), &ErrorCode::NotFound);
+		assert_eq!(results[3].as_ref().unwrap_err(), &ErrorCode::IsADirectory);
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub async fn list_dir(&self, peer: PeerId, path: impl Into<String>) -> Result<Vec<DirEntry>> {
-		let path = path.into();
-		let (tx, rx) = oneshot::channel();
-		self.cmd_tx
-			.send(Command::ListDir { peer, path, tx })
-			.map_err(|e| anyhow!("failed to send ListDir command: {e}"))?;
-		rx.await
-			.map_err(|e| anyhow!("ListDir response channel closed: {e}"))?
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn created_files_get_the_share_mode() {
+		use std::os::unix::fs::PermissionsExt;
+		let root = temporary_dir("create-mode");
+		std::fs::create_dir_all(&root).unwrap();
+		let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+		let dest = root.join("upload.bin");
+		let mut writes = AtomicWrites::default();
+		writes
+			.write(PeerId::random(), &dest, 0, b"secret", true, Some(0o600))
+			.await
+			.unwrap();
+		assert_eq!(mode(&dest), 0o600);
+
+		let dir = root.join("private");
+		std::fs::create_dir(&dir).unwrap();
+		apply_create_mode(&dir, Some(0o700)).await.unwrap();
+		assert_eq!(mode(&dir), 0o700);
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn list_dir_blocking(
-		&self,
-		peer: PeerId,
-		path: impl Into<String>,
-	) -> Result<Vec<DirEntry>> {
-		block_on(self.list_dir(peer, path))
+	#[tokio::test]
+	async fn interrupted_atomic_write_leaves_destination_untouched() {
+		let root = temporary_dir("atomic-write");
+		let dest = root.join("upload.bin");
+		std::fs::write(&dest, b"original").unwrap();
+		let peer = PeerId::random();
+		let mut writes = AtomicWrites::default();
+
+		writes
+			.write(peer, &dest, 0, b"new ", false, None)
+			.await
+			.unwrap();
+		// The connection drops before the final chunk arrives
+		writes.abort_peer(peer).await;
+		assert_eq!(std::fs::read(&dest).unwrap(), b"original");
+		assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+
+		writes
+			.write(peer, &dest, 0, b"new ", false, None)
+			.await
+			.unwrap();
+		assert_eq!(std::fs::read(&dest).unwrap(), b"original");
+		let ack = writes
+			.write(peer, &dest, 4, b"data", true, None)
+			.await
+			.unwrap();
+		assert_eq!(std::fs::read(&dest).unwrap(), b"new data");
+		assert_eq!(ack.total_len, Some(8));
+		assert_eq!(
+			ack.sha256.as_deref(),
+			Some("d5b7f828235a92d3d280fa08f3ddb9e5b6947123b44091c92db7594aa1408614")
+		);
+		assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub async fn list_cpus(&self, peer_id: PeerId) -> Result<Vec<CpuInfo>> {
-		let (tx, rx) = oneshot::channel();
-		self.cmd_tx
-			.send(Command::ListCpus { tx, peer_id })
-			.map_err(|e| anyhow!("failed to send ListCpus command: {e}"))?;
-		rx.await
-			.map_err(|e| anyhow!("ListCpus response channel closed: {e}"))?
+	#[tokio::test]
+	async fn failed_atomic_rename_removes_the_temp_file() {
+		let root = temporary_dir("atomic-rename");
+		// a file cannot be renamed over a non-empty directory
+		let dest = root.join("taken");
+		std::fs::create_dir_all(&dest).unwrap();
+		std::fs::write(dest.join("inside.txt"), b"keep").unwrap();
+		let peer = PeerId::random();
+		let mut writes = AtomicWrites::default();
+
+		let err = writes
+			.write(peer, &dest, 0, b"data", true, None)
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains("rename failed"));
+		let names: Vec<_> = std::fs::read_dir(&root)
+			.unwrap()
+			.map(|entry| entry.unwrap().file_name())
+			.collect();
+		assert_eq!(names, ["taken"]);
+		assert_eq!(std::fs::read(dest.join("inside.txt")).unwrap(), b"keep");
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn list_cpus_blocking(&self, peer_id: PeerId) -> Result<Vec<CpuInfo>> {
-		block_on(self.list_cpus(peer_id))
+	#[tokio::test]
+	async fn space_for_missing_path_uses_existing_parent() {
+		let root = temporary_dir("space-for");
+		let canonical = std::fs::canonicalize(&root).unwrap();
+		let missing = root.join("not/yet/created.bin");
+
+		assert_eq!(existing_ancestor(&missing).await, Some(canonical.clone()));
+		if let Some(space) = disk_space(&canonical) {
+			assert!(space.available <= space.total);
+		}
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn list_granted_permissions(&self, peer: PeerId) -> Result<Vec<Permission>> {
-		let state = self
-			.state
-			.lock()
-			.map_err(|_| anyhow!("state lock poisoned"))?;
-		Ok(state.permissions_granted_to_peer(&peer))
+	#[test]
+	fn tail_restarts_after_truncation() {
+		assert_eq!(tail_start(100, None, 10), 90);
+		assert_eq!(tail_start(5, None, 10), 0);
+		assert_eq!(tail_start(100, Some(40), 10), 40);
+		assert_eq!(tail_start(100, Some(100), 10), 100);
+		// the file was rotated and is now shorter than what we already read
+		assert_eq!(tail_start(20, Some(40), 10), 0);
 	}
 
-	pub async fn list_permissions(&self, peer: PeerId) -> Result<Vec<Permission>> {
-		let (tx, rx) = oneshot::channel();
-		self.cmd_tx
-			.send(Command::ListPermissions { peer, tx })
-			.map_err(|e| anyhow!("failed to send ListPermissions command: {e}"))?;
-		rx.await
-			.map_err(|e| anyhow!("ListPermissions response channel closed: {e}"))?
+	#[test]
+	fn share_paths_explain_missing_paths_and_accept_files() {
+		let root = temporary_dir("share-path");
+		let file = root.join("notes.txt");
+		std::fs::write(&file, b"hi").unwrap();
+
+		let err = resolve_share_path(&root.join("missing")).unwrap_err();
+		assert!(err.to_string().contains("does not exist"), "{err}");
+		let (canonical, is_file) = resolve_share_path(&file).unwrap();
+		assert_eq!(canonical, std::fs::canonicalize(&file).unwrap());
+		assert!(is_file);
+		assert!(!resolve_share_path(&root).unwrap().1);
+
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn list_permissions_blocking(&self, peer: PeerId) -> Result<Vec<Permission>> {
-		block_on(self.list_permissions(peer))
+	#[tokio::test]
+	async fn owners_grant_access_by_merging_or_replacing() {
+		let root = temporary_dir("grant-access");
+		let peer = local_peer(&root);
+		let me = peer.state().lock().unwrap().me;
+		let target = PeerId::random();
+		let files = |path: &str, access| PermissionGrant::Files {
+			path: String::from(path),
+			access,
+		};
+
+		let granted = peer
+			.grant_access(me, target, vec![files("/srv/a", FileAccess::Read)], false)
+			.await
+			.unwrap();
+		assert_eq!(granted, [files("/srv/a", FileAccess::Read)]);
+		let granted = peer
+			.grant_access(
+				me,
+				target,
+				vec![
+					files("/srv/a", FileAccess::ReadWrite),
+					files("/srv/b", FileAccess::Read),
+				],
+				true,
+			)
+			.await
+			.unwrap();
+		assert_eq!(
+			granted,
+			[
+				files("/srv/a", FileAccess::ReadWrite),
+				files("/srv/b", FileAccess::Read)
+			]
+		);
+		let granted = peer
+			.grant_access(me, target, vec![PermissionGrant::Owner], false)
+			.await
+			.unwrap();
+		assert_eq!(granted, [PermissionGrant::Owner]);
+		assert!(peer.state().lock().unwrap().is_owner(&target));
+		let stored = load_peer_permissions(&open_db(&root.join("peer.db")).unwrap(), &me).unwrap();
+		let stored: Vec<_> = stored
+			.iter()
+			.flat_map(|(peer_id, rules)| rules.iter().map(move |rule| (*peer_id, rule)))
+			.map(|(peer_id, rule)| (peer_id, PermissionGrant::from_rule(rule.rule())))
+			.collect();
+		assert_eq!(stored, [(target, PermissionGrant::Owner)]);
+
+		let err = peer
+			.grant_access(me, target, vec![PermissionGrant::SystemInfo], true)
+			.await
+			.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::InvalidArgument));
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub async fn read_file(
-		&self,
-		peer: libp2p::PeerId,
-		path: impl Into<String>,
-		offset: u64,
-		length: Option<u64>,
-	) -> Result<FileChunk> {
-		let path = path.into();
-		let (tx, rx) = oneshot::channel();
-		self.cmd_tx
-			.send(Command::ReadFile(ReadFileCmd {
-				peer_id: peer,
-				path,
-				offset,
-				length,
-				tx,
-			}))
-			.map_err(|e| anyhow!("failed to send ReadFile command: {e}"))?;
-		rx.await
-			.map_err(|e| anyhow!("ReadFile response channel closed: {e}"))?
+	#[tokio::test]
+	async fn the_overview_sums_the_separate_listings() {
+		let root = temporary_dir("overview");
+		let peer = local_peer(&root);
+		let me = peer.state().lock().unwrap().me;
+
+		let overview = peer.system_overview(me).await.unwrap();
+		let cpus = peer.list_cpus(me).await.unwrap();
+		assert_eq!(overview.cpu_count, cpus.len());
+		assert!(overview.mem.used <= overview.mem.total);
+		let interfaces = peer.list_interfaces(me).await.unwrap();
+		assert_eq!(overview.net_summary.interfaces, interfaces.len());
+		assert_eq!(overview.load.is_none(), cfg!(windows));
+		let load = peer.system_load(me).await.unwrap();
+		assert!(load.uptime >= overview.uptime);
+		assert_eq!(load.load.is_none(), cfg!(windows));
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	pub fn read_file_blocking(
-		&self,
-		peer: libp2p::PeerId,
-		path: impl Into<String>,
-		offset: u64,
-		length: Option<u64>,
-	) -> Result<FileChunk> {
-		block_on(self.read_file(peer, path, offset, length))
+	#[tokio::test]
+	async fn file_streams_end_at_eof() {
+		let root = temporary_dir("read-stream");
+		std::fs::create_dir_all(&root).unwrap();
+		let file = root.join("data.bin");
+		std::fs::write(&file, b"0123456789").unwrap();
+		let peer = local_peer(&root);
+		let me = peer.state().lock().unwrap().me;
+
+		let chunks: Vec<FileChunk> = peer
+			.read_file_stream(me, file.to_string_lossy(), 4)
+			.map(Result::unwrap)
+			.collect()
+			.await;
+		let offsets: Vec<_> = chunks.iter().map(|chunk| chunk.offset).collect();
+		assert_eq!(offsets, [0, 4, 8]);
+		let data: Vec<u8> = chunks.iter().flat_map(|chunk| chunk.data.clone()).collect();
+		assert_eq!(data, b"0123456789");
+		assert!(chunks.last().unwrap().eof);
+
+		let missing = root.join("missing.bin");
+		let results: Vec<_> = peer
+			.read_file_stream(me, missing.to_string_lossy(), 4)
+			.collect()
+			.await;
+		assert!(results.len() == 1 && results[0].is_err());
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 
-	/// Wait for the peer until Ctrl+C (SIGINT) then perform a graceful shutdown.
-	pub async fn wait(mut self) {
-		// Wait for Ctrl+C
-		if let Err(e) = tokio::signal::ctrl_c().await {
-			log::error!("failed to listen for ctrl_c: {e}");
-		}
-		log::info!("interrupt received, shutting down");
-		if let Some(tx) = self.shutdown_tx.take() {
-			let _ = tx.send(());
-		}
-		// Await the background task
-		if let Err(e) = self.handle.await {
-			log::error!("task join error: {e}");
+	#[tokio::test]
+	async fn issued_tokens_sign_in_and_grant_their_permissions() {
+		let root = temporary_dir("tokens");
+		let docs = root.join("docs");
+		std::fs::create_dir_all(&docs).unwrap();
+		let peer = local_peer(&root);
+		let state = peer.state();
+		let me = state.lock().unwrap().me;
+		state
+			.lock()
+			.unwrap()
+			.create_user(
+				String::from("alice"),
+				String::from("secret"),
+				Vec::new(),
+				Vec::new(),
+			)
+			.unwrap();
+		let grant = PermissionGrant::Files {
+			path: docs.to_string_lossy().into_owned(),
+			access: FileAccess::Read,
+		};
+
+		let issued = peer
+			.create_token(me, "alice", None, Some(3600), vec![grant.clone()])
+			.await
+			.unwrap();
+		assert_eq!(issued.permissions, [grant]);
+		let session = peer
+			.authenticate(
+				me,
+				AuthMethod::Token {
+					token: issued.token.clone(),
+				},
+			)
+			.await
+			.unwrap();
+		assert_eq!(session.username, "alice");
+		assert_eq!(session.expires_at, issued.expires_at);
+		let err = peer
+			.authenticate(
+				me,
+				AuthMethod::Token {
+					token: String::from("guessed"),
+				},
+			)
+			.await
+			.unwrap_err();
+		assert_eq!(ErrorCode::of(&err), Some(ErrorCode::AccessDenied));
+
+		// the session's grants apply to the peer that opened it, while it lasts
+		let other = PeerId::random();
+		{
+			let mut state = state.lock().unwrap();
+			assert!(!state.has_fs_access(other, &docs, FLAG_READ));
+			state.sessions.insert(other, session.clone());
+			assert!(state.has_fs_access(other, &docs, FLAG_READ));
+			assert!(!state.has_fs_access(other, &docs, FLAG_WRITE));
+			assert!(!state.is_owner(&other));
+			state.sessions.insert(
+				other,
+				SessionInfo {
+					expires_at: Some(1),
+					..session
+				},
+			);
+			assert!(!state.has_fs_access(other, &docs, FLAG_READ));
 		}
+
+		peer.shutdown().await;
+		std::fs::remove_dir_all(root).unwrap();
 	}
 }
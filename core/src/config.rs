@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use libp2p::Multiaddr;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Directory holding puppypeer's per-user files (`~/.puppypeer`).
+pub fn puppy_home() -> PathBuf {
+	env::var_os("HOME")
+		.or_else(|| env::var_os("USERPROFILE"))
+		.map(PathBuf::from)
+		.unwrap_or_else(|| PathBuf::from("."))
+		.join(".puppypeer")
+}
+
+/// Keypair file used when neither the config nor `KEYPAIR` names one.
+pub const DEFAULT_KEYPAIR_FILE: &str = "peer_keypair.bin";
+/// Database file used when neither the config nor `DB` names one.
+pub const DEFAULT_DB_FILE: &str = "puppyapp.db";
+
+/// Profile picked with `select_profile`, set at most once per process.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Run this process as profile `name`: the config file, keypair and database default to
+/// `profile_dir(name)` instead of the shared locations. Call it before loading any config.
+pub fn select_profile(name: &str) -> Result<()> {
+	validate_profile_name(name)?;
+	let dir = profile_dir(name);
+	std::fs::create_dir_all(&dir)
+		.with_context(|| format!("failed to create profile dir {}", dir.display()))?;
+	PROFILE
+		.set(name.to_string())
+		.map_err(|_| anyhow::anyhow!("a profile is already selected"))
+}
+
+/// The profile picked with `select_profile`, if any.
+pub fn profile() -> Option<&'static str> {
+	PROFILE.get().map(String::as_str)
+}
+
+/// Directory holding the files of profile `name` (`~/.puppypeer/profiles/<name>`).
+pub fn profile_dir(name: &str) -> PathBuf {
+	puppy_home().join("profiles").join(name)
+}
+
+/// Profile names become a directory name, so only letters, digits, `-`, `_` and `.` are
+/// allowed, and the name may not start with a dot.
+fn validate_profile_name(name: &str) -> Result<()> {
+	if name.is_empty() {
+		anyhow::bail!("profile name is empty");
+	}
+	if name.starts_with('.') {
+		anyhow::bail!("profile name {name:?} may not start with a dot");
+	}
+	if let Some(c) = name
+		.chars()
+		.find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+	{
+		anyhow::bail!("profile name {name:?} may not contain {c:?}");
+	}
+	Ok(())
+}
+
+/// `file` inside the selected profile's directory, or as given without a profile.
+fn profile_file(file: &str) -> PathBuf {
+	match profile() {
+		Some(name) => profile_dir(name).join(file),
+		None => PathBuf::from(file),
+	}
+}
+
+/// Location of the config file, overridable with the `CONFIG` env var. With a profile
+/// selected it is the profile's `config.json`.
+pub fn config_path() -> PathBuf {
+	env::var_os("CONFIG")
+		.map(PathBuf::from)
+		.unwrap_or_else(|| match profile() {
+			Some(name) => profile_dir(name).join("config.json"),
+			None => puppy_home().join("config.json"),
+		})
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PuppyConfig {
+	/// Where downloaded and opened files are written.
+	pub download_dir: PathBuf,
+	/// Writes from peers are refused when they would leave less free disk space than this.
+	pub min_free_space: u64,
+	/// Release source for `puppypeer update`; GitHub when unset.
+	pub update_source: Option<String>,
+	/// Glob patterns skipped by every scan, in addition to the ones given per scan.
+	pub scan_exclude: Vec<String>,
+	/// How often a request that is safe to repeat is sent again after its connection dropped.
+	pub request_retries: u32,
+	/// `ReadFile` and `ReadFiles` requests in flight to one peer at a time; more wait.
+	pub max_reads_per_peer: usize,
+	/// Entries returned by one `ListDirRecursive`; the rest of the tree is left out.
+	pub max_tree_entries: usize,
+	/// Addresses dialed at startup and again whenever their connection drops.
+	pub bootstrap_peers: Vec<Multiaddr>,
+	/// How connections are reaped and `bootstrap_peers` are redialed.
+	pub reconnect: ReconnectPolicy,
+	/// List this node itself in the peers views and graphs.
+	pub show_local_peer: bool,
+	/// File holding this node's identity, `DEFAULT_KEYPAIR_FILE` (inside the profile's
+	/// directory when one is selected) when unset. Two nodes in
+	/// one process need different files, or they get the same peer id.
+	pub keypair_path: Option<PathBuf>,
+	/// Database with permissions, nicknames and the file index, `DEFAULT_DB_FILE` (inside the
+	/// profile's directory when one is selected) when unset.
+	pub db_path: Option<PathBuf>,
+	/// Addresses the swarm listens on. Empty means no listener: the node can dial out but
+	/// nobody can connect to it.
+	pub listen_addrs: Vec<Multiaddr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+	/// Redial `bootstrap_peers` after a failed dial or a dropped connection.
+	pub enabled: bool,
+	/// Wait before the first redial; doubles with every failed dial.
+	pub initial_backoff_ms: u64,
+	pub max_backoff_ms: u64,
+	/// Connections without open streams are closed after this long. Connections to
+	/// bootstrap peers and signed-in sessions are kept open and only closed when a ping fails.
+	pub idle_timeout_secs: u64,
+}
+
+impl Default for ReconnectPolicy {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			initial_backoff_ms: 1000,
+			max_backoff_ms: 60_000,
+			idle_timeout_secs: 300,
+		}
+	}
+}
+
+impl ReconnectPolicy {
+	/// Wait before redialing after `failures` failed dials in a row.
+	pub fn backoff(&self, failures: u32) -> Duration {
+		Duration::from_millis(self.initial_backoff_ms)
+			.saturating_mul(2u32.saturating_pow(failures))
+			.min(Duration::from_millis(self.max_backoff_ms))
+	}
+
+	pub fn idle_timeout(&self) -> Duration {
+		Duration::from_secs(self.idle_timeout_secs)
+	}
+}
+
+impl Default for PuppyConfig {
+	fn default() -> Self {
+		Self {
+			download_dir: puppy_home().join("downloads"),
+			min_free_space: 1024 * 1024 * 1024,
+			update_source: None,
+			scan_exclude: vec![
+				String::from("**/.git/**"),
+				String::from("**/node_modules/**"),
+			],
+			request_retries: 2,
+			max_reads_per_peer: 4,
+			max_tree_entries: 10_000,
+			bootstrap_peers: Vec::new(),
+			reconnect: ReconnectPolicy::default(),
+			show_local_peer: true,
+			keypair_path: None,
+			db_path: None,
+			listen_addrs: vec!["/ip4/0.0.0.0/tcp/0".parse().unwrap()],
+		}
+	}
+}
+
+impl PuppyConfig {
+	/// Load the config file, falling back to defaults when it is missing or invalid, and
+	/// apply `apply_env_overrides`.
+	pub fn load() -> Self {
+		let path = config_path();
+		let mut config = match Self::load_from(&path) {
+			Ok(Some(config)) => config,
+			Ok(None) => Self::default(),
+			Err(err) => {
+				log::warn!("failed to load config {}: {err:#}", path.display());
+				Self::default()
+			}
+		};
+		config.apply_env_overrides();
+		config
+	}
+
+	/// Let `KEYPAIR` and `DB` replace `keypair_path` and `db_path`. Only `load` calls this,
+	/// so a config built in code is never changed by the environment.
+	pub fn apply_env_overrides(&mut self) {
+		if let Some(path) = env::var_os("KEYPAIR") {
+			self.keypair_path = Some(PathBuf::from(path));
+		}
+		if let Some(path) = env::var_os("DB") {
+			self.db_path = Some(PathBuf::from(path));
+		}
+	}
+
+	pub fn keypair_path(&self) -> PathBuf {
+		self.keypair_path
+			.clone()
+			.unwrap_or_else(|| profile_file(DEFAULT_KEYPAIR_FILE))
+	}
+
+	pub fn db_path(&self) -> PathBuf {
+		self.db_path
+			.clone()
+			.unwrap_or_else(|| profile_file(DEFAULT_DB_FILE))
+	}
+
+	pub fn load_from(path: &Path) -> Result<Option<Self>> {
+		if !path.exists() {
+			return Ok(None);
+		}
+		let raw = std::fs::read_to_string(path)
+			.with_context(|| format!("failed to read {}", path.display()))?;
+		let config = serde_json::from_str(&raw)
+			.with_context(|| format!("failed to parse {}", path.display()))?;
+		Ok(Some(config))
+	}
+
+	pub fn save_to(&self, path: &Path) -> Result<()> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+		Ok(())
+	}
+
+	/// Return the download directory, creating it if needed.
+	pub fn ensure_download_dir(&self) -> Result<PathBuf> {
+		std::fs::create_dir_all(&self.download_dir).with_context(|| {
+			format!(
+				"failed to create download dir {}",
+				self.download_dir.display()
+			)
+		})?;
+		Ok(self.download_dir.clone())
+	}
+
+	/// Pick a path for `name` inside the download directory that does not exist yet.
+	///
+	/// Only the final component of `name` is used, and clashes get a ` (n)` suffix.
+	pub fn download_path(&self, name: &str) -> Result<PathBuf> {
+		let dir = self.ensure_download_dir()?;
+		Ok(unique_path(&dir, name))
+	}
+}
+
+/// Path for `name` inside `dir` that does not exist yet, appending ` (n)` on clashes.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+	let name = Path::new(name)
+		.file_name()
+		.and_then(|n| n.to_str())
+		.filter(|n| !n.is_empty())
+		.unwrap_or("download");
+	let candidate = dir.join(name);
+	if !candidate.exists() {
+		return candidate;
+	}
+	let path = Path::new(name);
+	let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+	let ext = path.extension().and_then(|s| s.to_str());
+	let mut n = 1;
+	loop {
+		let file_name = match ext {
+			Some(ext) => format!("{stem} ({n}).{ext}"),
+			None => format!("{stem} ({n})"),
+		};
+		let candidate = dir.join(file_name);
+		if !candidate.exists() {
+			return candidate;
+		}
+		n += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reconnect_backoff_doubles_up_to_the_cap() {
+		let policy = ReconnectPolicy::default();
+		assert_eq!(policy.backoff(0), Duration::from_secs(1));
+		assert_eq!(policy.backoff(2), Duration::from_secs(4));
+		assert_eq!(policy.backoff(10), Duration::from_secs(60));
+		assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(60));
+	}
+
+	#[test]
+	fn profile_names_must_be_plain_directory_names() {
+		for name in ["test", "node-2", "a_b.c"] {
+			assert!(validate_profile_name(name).is_ok(), "{name}");
+		}
+		for name in ["", ".", "..", ".hidden", "a/b", "a\\b", "x y"] {
+			assert!(validate_profile_name(name).is_err(), "{name}");
+		}
+	}
+}
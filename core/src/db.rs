@@ -1,19 +1,21 @@
-use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, bail};
+use anyhow::{Context, anyhow, bail};
 use chrono::DateTime;
 use chrono::Utc;
 use libp2p::PeerId;
 use rusqlite::Connection;
+use rusqlite::OpenFlags;
 use rusqlite::ToSql;
 use rusqlite::params;
 use serde::Serialize;
 use tokio::sync::Mutex;
 
+use crate::p2p::TokenInfo;
 use crate::scan::FileHash;
 use crate::scan::FileLocation;
 use crate::state::{FolderRule, Permission, Rule};
+use crate::types::{CatalogEntry, CatalogPage, RecentFile};
 
 pub type NodeID = [u8; 16];
 
@@ -138,8 +140,79 @@ const MIGRATIONS: &[Migration] = &[
 			create index if not exists idx_peer_permissions_src_target on peer_permissions(src_peer, target_peer);
 		",
 	},
+	Migration {
+		id: 20250305,
+		name: "cpu_history",
+		sql: r"
+			create table cpu_history (
+				node_id BLOB not null,
+				name text not null,
+				usage real not null,
+				frequency integer not null,
+				sampled_at timestamp not null
+			);
+			create index if not exists idx_cpu_history_node_name_time on cpu_history(node_id, name, sampled_at);
+		",
+	},
+	Migration {
+		id: 20250312,
+		name: "peer_names",
+		sql: r"
+			create table peer_names (
+				peer_id blob primary key,
+				name text not null,
+				modified_at timestamp not null
+			);
+		",
+	},
+	Migration {
+		id: 20250320,
+		name: "recent_files",
+		sql: r"
+			create table recent_files (
+				peer_id blob not null,
+				path text not null,
+				opened_at timestamp not null,
+				primary key (peer_id, path)
+			);
+			create index if not exists idx_recent_files_opened_at on recent_files(opened_at);
+		",
+	},
+	Migration {
+		id: 20250402,
+		name: "tokens",
+		sql: r"
+			create table tokens (
+				id text primary key,
+				token_hash text not null unique,
+				username text not null,
+				label text null,
+				permissions text not null,
+				issued_at integer not null,
+				issued_by text not null,
+				expires_at integer null,
+				revoked integer not null default 0
+			);
+		",
+	},
+	Migration {
+		id: 20250410,
+		name: "temperature_history",
+		sql: r"
+			create table temperature_history (
+				node_id BLOB not null,
+				label text not null,
+				temperature real not null,
+				sampled_at timestamp not null
+			);
+			create index if not exists idx_temperature_history_node_time on temperature_history(node_id, sampled_at);
+		",
+	},
 ];
 
+/// How long samples are kept in `cpu_history` and `temperature_history` before being pruned.
+const CPU_HISTORY_RETENTION_HOURS: i64 = 24;
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Node {
 	pub id: NodeID,
@@ -240,9 +313,15 @@ pub struct FileEntry {
 	pub latest_datetime: String,
 }
 
+/// Filters for `list_files`.
 #[derive(Debug, Default, Serialize)]
 pub struct ListArgs {
-	search_word: Option<String>,
+	/// Substring of the hex hash or of the mime type.
+	pub search_word: Option<String>,
+	/// Exact mime type.
+	pub mime_type: Option<String>,
+	/// Newest `latest_datetime` first instead of oldest first.
+	pub sort_desc: bool,
 }
 
 pub struct DB {
@@ -367,6 +446,136 @@ pub fn fetch_cpus(conn: &Connection, node_id: &[u8]) -> anyhow::Result<Vec<Cpu>>
 	Ok(cpus)
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuSample {
+	pub name: String,
+	pub usage: f32,
+	pub frequency: u64,
+	pub sampled_at: DateTime<Utc>,
+}
+
+/// Append CPU samples to `cpu_history` and prune rows older than the retention window.
+pub fn save_cpu_samples(
+	conn: &mut Connection,
+	node_id: &[u8],
+	samples: &[CpuSample],
+) -> anyhow::Result<()> {
+	let tx = conn.transaction()?;
+	{
+		let mut stmt = tx.prepare(
+			"INSERT INTO cpu_history (node_id, name, usage, frequency, sampled_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+		)?;
+		for sample in samples {
+			stmt.execute(params![
+				node_id,
+				&sample.name,
+				sample.usage,
+				sample.frequency as i64,
+				&sample.sampled_at
+			])?;
+		}
+	}
+	let cutoff = Utc::now() - chrono::Duration::hours(CPU_HISTORY_RETENTION_HOURS);
+	tx.execute(
+		"DELETE FROM cpu_history WHERE sampled_at < ?1",
+		params![&cutoff],
+	)?;
+	tx.commit()?;
+	Ok(())
+}
+
+/// Fetch the CPU samples of `name` on `node_id` taken at or after `since`, oldest first.
+pub fn fetch_cpu_history(
+	conn: &Connection,
+	node_id: &[u8],
+	name: &str,
+	since: DateTime<Utc>,
+) -> anyhow::Result<Vec<CpuSample>> {
+	let mut stmt = conn.prepare(
+		"SELECT name, usage, frequency, sampled_at FROM cpu_history
+         WHERE node_id = ?1 AND name = ?2 AND sampled_at >= ?3
+         ORDER BY sampled_at ASC",
+	)?;
+	let rows = stmt.query_map(params![node_id, name, &since], |row| {
+		Ok(CpuSample {
+			name: row.get(0)?,
+			usage: row.get(1)?,
+			frequency: row.get::<_, i64>(2)? as u64,
+			sampled_at: row.get(3)?,
+		})
+	})?;
+
+	let mut samples = Vec::new();
+	for s in rows {
+		samples.push(s?);
+	}
+	Ok(samples)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemperatureSample {
+	pub label: String,
+	/// Degrees Celsius.
+	pub temperature: f32,
+	pub sampled_at: DateTime<Utc>,
+}
+
+/// Append sensor readings to `temperature_history` and prune rows older than the retention
+/// window.
+pub fn save_temperature_samples(
+	conn: &mut Connection,
+	node_id: &[u8],
+	samples: &[TemperatureSample],
+) -> anyhow::Result<()> {
+	let tx = conn.transaction()?;
+	{
+		let mut stmt = tx.prepare(
+			"INSERT INTO temperature_history (node_id, label, temperature, sampled_at) VALUES (?1, ?2, ?3, ?4)",
+		)?;
+		for sample in samples {
+			stmt.execute(params![
+				node_id,
+				&sample.label,
+				sample.temperature,
+				&sample.sampled_at
+			])?;
+		}
+	}
+	let cutoff = Utc::now() - chrono::Duration::hours(CPU_HISTORY_RETENTION_HOURS);
+	tx.execute(
+		"DELETE FROM temperature_history WHERE sampled_at < ?1",
+		params![&cutoff],
+	)?;
+	tx.commit()?;
+	Ok(())
+}
+
+/// Fetch every sensor reading of `node_id` taken at or after `since`, oldest first.
+pub fn fetch_temperature_history(
+	conn: &Connection,
+	node_id: &[u8],
+	since: DateTime<Utc>,
+) -> anyhow::Result<Vec<TemperatureSample>> {
+	let mut stmt = conn.prepare(
+		"SELECT label, temperature, sampled_at FROM temperature_history
+         WHERE node_id = ?1 AND sampled_at >= ?2
+         ORDER BY sampled_at ASC",
+	)?;
+	let rows = stmt.query_map(params![node_id, &since], |row| {
+		Ok(TemperatureSample {
+			label: row.get(0)?,
+			temperature: row.get(1)?,
+			sampled_at: row.get(2)?,
+		})
+	})?;
+
+	let mut samples = Vec::new();
+	for s in rows {
+		samples.push(s?);
+	}
+	Ok(samples)
+}
+
 /// Remove CPU rows for `node_id` whose names are not in `current_names`.
 pub fn remove_stale_cpus(
 	conn: &Connection,
@@ -523,18 +732,33 @@ pub fn fetch_interfaces(conn: &Connection, node_id: &[u8]) -> anyhow::Result<Vec
 }
 
 pub fn list_files(conn: &Connection, args: ListArgs) -> anyhow::Result<Vec<FileEntry>> {
-	// Build SQL and params based on whether we have a search term
+	// Build SQL and params based on which filters are set
 	let mut sql = String::from(
 		"SELECT hash, size, mime_type, first_datetime, latest_datetime \
-		 FROM file_entries",
+		 FROM file_entries WHERE 1 = 1",
 	);
+	let pattern = args
+		.search_word
+		.as_deref()
+		.map(str::trim)
+		.filter(|word| !word.is_empty())
+		.map(|word| format!("%{}%", word.to_lowercase()));
 	let mut params: Vec<&dyn ToSql> = Vec::new();
-
-	// if let Some(ref term) = args.search_word.filter(|s| !s.is_empty()) {
-	// 	sql.push_str(" WHERE mime_type LIKE ?1");
-	// 	let pattern = format!("%{}%", term);
-	// 	params.push(&pattern);
-	// }
+	if let Some(pattern) = &pattern {
+		// hashes are blobs, so match against their lowercase hex form
+		sql.push_str(" AND (lower(hex(hash)) LIKE ? OR lower(mime_type) LIKE ?)");
+		params.push(pattern);
+		params.push(pattern);
+	}
+	if let Some(mime_type) = &args.mime_type {
+		sql.push_str(" AND mime_type = ?");
+		params.push(mime_type);
+	}
+	sql.push_str(if args.sort_desc {
+		" ORDER BY latest_datetime DESC"
+	} else {
+		" ORDER BY latest_datetime ASC"
+	});
 
 	let mut stmt = conn.prepare(&sql)?;
 	let rows = stmt.query_map(&params[..], |row| {
@@ -554,10 +778,11 @@ pub fn list_files(conn: &Connection, args: ListArgs) -> anyhow::Result<Vec<FileE
 	Ok(files)
 }
 
-pub async fn get_mime_types(conn: &Connection) -> anyhow::Result<Vec<String>> {
-	let mut stmt =
-		conn.prepare("SELECT DISTINCT mime_type FROM file_entries WHERE mime_type IS NOT NULL")?;
-	let rows = stmt.query_map((), |row| row.get::<_, String>(0)).unwrap();
+pub fn get_mime_types(conn: &Connection) -> anyhow::Result<Vec<String>> {
+	let mut stmt = conn.prepare(
+		"SELECT DISTINCT mime_type FROM file_entries WHERE mime_type IS NOT NULL ORDER BY mime_type",
+	)?;
+	let rows = stmt.query_map((), |row| row.get::<_, String>(0))?;
 
 	let mut mime_types = Vec::new();
 	for mime_type in rows {
@@ -592,9 +817,10 @@ pub fn get_file_location(
 	hash: &[u8],
 ) -> anyhow::Result<Option<FileLocation>> {
 	let mut stmt = conn.prepare(
-		"SELECT path, hash, size, timestamp, created_at, modified_at, accessed_at \
-		 FROM file_locations \
-		 WHERE node_id = ? AND hash = ?",
+		"SELECT l.path, l.hash, l.size, l.timestamp, l.created_at, l.modified_at, l.accessed_at, e.mime_type \
+		 FROM file_locations l \
+		 LEFT JOIN file_entries e ON e.hash = l.hash \
+		 WHERE l.node_id = ? AND l.hash = ?",
 	)?;
 	let mut rows = stmt.query_map(&[node_id, hash], |row| {
 		// get an optional Vec<u8> for the hash
@@ -607,8 +833,8 @@ pub fn get_file_location(
 			path: PathBuf::from(row.get::<_, String>(0)?),
 			hash,
 			size: row.get::<_, i64>(2)? as u64,
-			// file_locations does not store mime_type, set to None
-			mime_type: None,
+			// mime types are stored per hash in file_entries
+			mime_type: row.get(7)?,
 			timestamp: row.get(3)?,
 			created_at: row.get(4)?,
 			modified_at: row.get(5)?,
@@ -624,6 +850,94 @@ pub fn get_file_location(
 	}
 }
 
+/// Paths indexed on `node_id` whose content has `hash`.
+pub fn hash_locations(
+	conn: &Connection,
+	node_id: &[u8],
+	hash: &[u8],
+) -> anyhow::Result<Vec<PathBuf>> {
+	let mut stmt =
+		conn.prepare_cached("SELECT path FROM file_locations WHERE node_id = ?1 AND hash = ?2")?;
+	let rows = stmt.query_map([node_id, hash], |row| row.get::<_, String>(0))?;
+	let mut paths = Vec::new();
+	for path in rows {
+		paths.push(PathBuf::from(path?));
+	}
+	Ok(paths)
+}
+
+/// The page of files indexed on `node_id` at or below the directory `prefix` that starts
+/// `offset` entries in, ordered by path. With `readable`, only paths one of those rules
+/// covers are listed; `None` lists every path.
+pub fn catalog_page(
+	conn: &Connection,
+	node_id: &[u8],
+	prefix: &str,
+	readable: Option<&[FolderRule]>,
+	offset: u64,
+	limit: u64,
+) -> anyhow::Result<CatalogPage> {
+	// everything below `dir/` sorts between `dir/` and `dir0`, '0' being the byte after '/'
+	const BELOW: &str = "(l.path = ? OR (l.path >= ? AND l.path < ?))";
+	let below = |dir: &str| {
+		let dir = dir.trim_end_matches('/');
+		[dir.to_string(), format!("{}/", dir), format!("{}0", dir)]
+	};
+	let mut sql = format!(
+		"SELECT l.path, l.hash, l.size, e.mime_type, l.modified_at \
+		 FROM file_locations l LEFT JOIN file_entries e ON e.hash = l.hash \
+		 WHERE l.node_id = ? AND {BELOW}"
+	);
+	let mut paths = Vec::from(below(prefix));
+	if let Some(rules) = readable {
+		let mut covered = Vec::new();
+		for rule in rules {
+			let path = rule.path().to_string_lossy();
+			if rule.is_file() {
+				covered.push("l.path = ?");
+				paths.push(path.into_owned());
+			} else {
+				covered.push(BELOW);
+				paths.extend(below(&path));
+			}
+		}
+		if covered.is_empty() {
+			return Ok(CatalogPage {
+				entries: Vec::new(),
+				has_more: false,
+			});
+		}
+		sql.push_str(&format!(" AND ({})", covered.join(" OR ")));
+	}
+	sql.push_str(" ORDER BY l.path LIMIT ? OFFSET ?");
+	// one row past the page tells whether more follow
+	let fetch = limit.saturating_add(1).min(i64::MAX as u64) as i64;
+	let skip = offset.min(i64::MAX as u64) as i64;
+	let mut params: Vec<&dyn ToSql> = vec![&node_id];
+	params.extend(paths.iter().map(|path| path as &dyn ToSql));
+	params.push(&fetch);
+	params.push(&skip);
+
+	let mut stmt = conn.prepare(&sql)?;
+	let rows = stmt.query_map(&params[..], |row| {
+		let hash: Option<Vec<u8>> = row.get(1)?;
+		Ok(CatalogEntry {
+			path: row.get(0)?,
+			hash: hash.map(|hash| hash.iter().map(|b| format!("{:02x}", b)).collect()),
+			size: row.get::<_, i64>(2)? as u64,
+			mime_type: row.get(3)?,
+			modified_at: row.get(4)?,
+		})
+	})?;
+	let mut entries = Vec::new();
+	for entry in rows {
+		entries.push(entry?);
+	}
+	let has_more = entries.len() as u64 > limit;
+	entries.truncate(limit as usize);
+	Ok(CatalogPage { entries, has_more })
+}
+
 const RULE_TYPE_OWNER: i64 = 0;
 const RULE_TYPE_FOLDER: i64 = 1;
 
@@ -665,6 +979,20 @@ pub fn save_peer_permissions(
 	Ok(())
 }
 
+/// Hand the permissions `from` granted over to `to`, after this node changed identity.
+/// Returns the number of rules moved.
+pub fn move_granted_permissions(
+	conn: &Connection,
+	from: &PeerId,
+	to: &PeerId,
+) -> anyhow::Result<usize> {
+	let moved = conn.execute(
+		"UPDATE peer_permissions SET src_peer = ?2 WHERE src_peer = ?1",
+		params![&from.to_bytes(), &to.to_bytes()],
+	)?;
+	Ok(moved)
+}
+
 pub fn load_peer_permissions(
 	conn: &Connection,
 	src_peer: &PeerId,
@@ -701,6 +1029,129 @@ pub fn load_peer_permissions(
 	Ok(results)
 }
 
+/// Store an issued token. Only `token_hash` is kept, so the secret cannot be read back.
+pub fn save_token(conn: &Connection, token: &TokenInfo, token_hash: &str) -> anyhow::Result<()> {
+	conn.execute(
+		"INSERT INTO tokens (id, token_hash, username, label, permissions, issued_at, issued_by, expires_at, revoked)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+		params![
+			token.id,
+			token_hash,
+			token.username,
+			token.label,
+			serde_json::to_string(&token.permissions)?,
+			token.issued_at as i64,
+			token.issued_by,
+			token.expires_at.map(|at| at as i64),
+			token.revoked,
+		],
+	)?;
+	Ok(())
+}
+
+/// The token whose secret hashes to `token_hash`, revoked and expired ones included.
+pub fn find_token(conn: &Connection, token_hash: &str) -> anyhow::Result<Option<TokenInfo>> {
+	let mut stmt = conn.prepare(
+		"SELECT id, username, label, permissions, issued_at, issued_by, expires_at, revoked
+         FROM tokens WHERE token_hash = ?1",
+	)?;
+	let mut rows = stmt.query(params![token_hash])?;
+	let Some(row) = rows.next()? else {
+		return Ok(None);
+	};
+	let permissions: String = row.get(3)?;
+	let issued_at: i64 = row.get(4)?;
+	let expires_at: Option<i64> = row.get(6)?;
+	Ok(Some(TokenInfo {
+		id: row.get(0)?,
+		username: row.get(1)?,
+		label: row.get(2)?,
+		permissions: serde_json::from_str(&permissions)
+			.context("invalid token permissions in database")?,
+		expires_at: expires_at.map(|at| at as u64),
+		revoked: row.get(7)?,
+		issued_at: issued_at as u64,
+		issued_by: row.get(5)?,
+	}))
+}
+
+/// Store the local nickname of `peer`, or forget it when `name` is `None`.
+pub fn save_peer_name(conn: &Connection, peer: &PeerId, name: Option<&str>) -> anyhow::Result<()> {
+	match name {
+		Some(name) => conn.execute(
+			"INSERT INTO peer_names (peer_id, name, modified_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(peer_id) DO UPDATE SET
+                 name        = excluded.name,
+                 modified_at = excluded.modified_at",
+			params![peer.to_bytes(), name, Utc::now()],
+		)?,
+		None => conn.execute(
+			"DELETE FROM peer_names WHERE peer_id = ?1",
+			params![peer.to_bytes()],
+		)?,
+	};
+	Ok(())
+}
+
+pub fn load_peer_names(conn: &Connection) -> anyhow::Result<Vec<(PeerId, String)>> {
+	let mut stmt = conn.prepare("SELECT peer_id, name FROM peer_names")?;
+	let mut rows = stmt.query([])?;
+	let mut names = Vec::new();
+	while let Some(row) = rows.next()? {
+		let peer_bytes: Vec<u8> = row.get(0)?;
+		let peer = PeerId::from_bytes(&peer_bytes)
+			.map_err(|err| anyhow!("invalid peer id from database: {err}"))?;
+		names.push((peer, row.get(1)?));
+	}
+	Ok(names)
+}
+
+/// Remember that `path` on `peer` was opened at `opened_at`, keeping only the `keep` most
+/// recent files across all peers.
+pub fn save_recent_file(
+	conn: &Connection,
+	peer: &PeerId,
+	path: &str,
+	opened_at: DateTime<Utc>,
+	keep: usize,
+) -> anyhow::Result<()> {
+	conn.execute(
+		"INSERT INTO recent_files (peer_id, path, opened_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(peer_id, path) DO UPDATE SET opened_at = excluded.opened_at",
+		params![peer.to_bytes(), path, opened_at],
+	)?;
+	conn.execute(
+		"DELETE FROM recent_files WHERE rowid NOT IN
+         (SELECT rowid FROM recent_files ORDER BY opened_at DESC LIMIT ?1)",
+		params![keep as i64],
+	)?;
+	Ok(())
+}
+
+/// Recently opened files, newest first, on `peer` or on every peer.
+pub fn load_recent_files(
+	conn: &Connection,
+	peer: Option<&PeerId>,
+) -> anyhow::Result<Vec<RecentFile>> {
+	let mut stmt = conn.prepare(
+		"SELECT peer_id, path, opened_at FROM recent_files
+         WHERE ?1 IS NULL OR peer_id = ?1 ORDER BY opened_at DESC",
+	)?;
+	let mut rows = stmt.query(params![peer.map(|peer| peer.to_bytes())])?;
+	let mut files = Vec::new();
+	while let Some(row) = rows.next()? {
+		let peer_bytes: Vec<u8> = row.get(0)?;
+		let peer = PeerId::from_bytes(&peer_bytes)
+			.map_err(|err| anyhow!("invalid peer id from database: {err}"))?;
+		files.push(RecentFile {
+			peer,
+			path: row.get(1)?,
+			opened_at: row.get(2)?,
+		});
+	}
+	Ok(files)
+}
+
 /// Runs embedded database migrations.
 ///
 /// # Arguments
@@ -711,7 +1162,7 @@ pub fn load_peer_permissions(
 ///
 /// Returns an `anyhow::Error` if any database operation fails.
 pub fn run_migrations(conn: &mut Connection) -> anyhow::Result<()> {
-	log::info!("running migrations");
+	log::debug!("running migrations");
 	conn.execute(
 		"CREATE TABLE IF NOT EXISTS migrations (
             id INTEGER PRIMARY KEY,
@@ -721,6 +1172,18 @@ pub fn run_migrations(conn: &mut Connection) -> anyhow::Result<()> {
 		(),
 	)?;
 
+	let latest = latest_schema_version();
+	if let Some(version) = schema_version(conn)?
+		&& version > latest
+	{
+		bail!(
+			"database schema version {} is newer than version {} known to this build; \
+			 upgrade puppypeer instead of letting an older build change it",
+			version,
+			latest
+		);
+	}
+
 	let applied_migrations: Vec<u32> = {
 		let mut stmt = conn.prepare("SELECT id FROM migrations")?;
 		let m = stmt.query_map((), |row| row.get(0))?;
@@ -756,13 +1219,267 @@ pub fn run_migrations(conn: &mut Connection) -> anyhow::Result<()> {
 			log::info!("migration {} applied successfully.", migration.id);
 		}
 	} else {
-		log::info!("No new migrations to apply.");
+		log::debug!("No new migrations to apply.");
 	}
 
 	Ok(())
 }
 
-pub fn open_db() -> Connection {
-	let db_name = env::var("DB").unwrap_or_else(|_| String::from("puppyapp.db"));
-	Connection::open(db_name).unwrap()
+/// Newest schema version, the id of the last migration, this build knows.
+pub fn latest_schema_version() -> u32 {
+	MIGRATIONS
+		.iter()
+		.map(|migration| migration.id)
+		.max()
+		.unwrap_or(0)
+}
+
+/// Id of the newest migration applied to `conn`, or `None` for a database that was never
+/// migrated.
+pub fn schema_version(conn: &Connection) -> anyhow::Result<Option<u32>> {
+	let migrated: bool = conn.query_row(
+		"SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'migrations')",
+		(),
+		|row| row.get(0),
+	)?;
+	if !migrated {
+		return Ok(None);
+	}
+	Ok(conn.query_row("SELECT MAX(id) FROM migrations", (), |row| row.get(0))?)
+}
+
+/// Schema of the database file next to the schema this build expects.
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaStatus {
+	/// `None` when the file does not exist or was never migrated.
+	pub version: Option<u32>,
+	pub latest: u32,
+}
+
+/// Read the schema version of the database at `path` without creating or migrating it.
+pub fn schema_status(path: &Path) -> anyhow::Result<SchemaStatus> {
+	let latest = latest_schema_version();
+	if !path.exists() {
+		return Ok(SchemaStatus {
+			version: None,
+			latest,
+		});
+	}
+	let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+		.with_context(|| format!("cannot open database {}", path.display()))?;
+	Ok(SchemaStatus {
+		version: schema_version(&conn)?,
+		latest,
+	})
+}
+
+/// Open the database at `path`, usually `PuppyConfig::db_path`, and bring its schema up to
+/// date. Callers report the error and carry on without the database instead of panicking.
+pub fn open_db(path: &Path) -> anyhow::Result<Connection> {
+	let mut conn = Connection::open(path)
+		.with_context(|| format!("index unavailable: cannot open database {}", path.display()))?;
+	run_migrations(&mut conn).with_context(|| {
+		format!(
+			"index unavailable: cannot migrate database {}",
+			path.display()
+		)
+	})?;
+	Ok(conn)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn newer_schema_is_refused() {
+		let mut conn = Connection::open_in_memory().unwrap();
+		assert_eq!(schema_version(&conn).unwrap(), None);
+		run_migrations(&mut conn).unwrap();
+		let latest = latest_schema_version();
+		assert_eq!(schema_version(&conn).unwrap(), Some(latest));
+
+		conn.execute(
+			"INSERT INTO migrations (id, name) VALUES (?1, 'from_the_future')",
+			[latest + 1],
+		)
+		.unwrap();
+		let err = run_migrations(&mut conn).unwrap_err();
+		assert!(err.to_string().contains("newer than version"));
+	}
+
+	#[test]
+	fn cpu_and_temperature_history_keep_a_day() {
+		let mut conn = Connection::open_in_memory().unwrap();
+		run_migrations(&mut conn).unwrap();
+		let node = [7u8; 16];
+		let now = Utc::now();
+		let cpu = |usage, ago| CpuSample {
+			name: String::from("cpu0"),
+			usage,
+			frequency: 2_000,
+			sampled_at: now - chrono::Duration::minutes(ago),
+		};
+		let temperature = |temperature, ago| TemperatureSample {
+			label: String::from("Package id 0"),
+			temperature,
+			sampled_at: now - chrono::Duration::minutes(ago),
+		};
+		// a day and an hour ago, pruned by the next save
+		save_cpu_samples(&mut conn, &node, &[cpu(5.0, 25 * 60)]).unwrap();
+		save_temperature_samples(&mut conn, &node, &[temperature(40.0, 25 * 60)]).unwrap();
+		save_cpu_samples(&mut conn, &node, &[cpu(20.0, 1), cpu(10.0, 2)]).unwrap();
+		save_temperature_samples(&mut conn, &node, &[temperature(55.0, 1)]).unwrap();
+
+		let week_ago = now - chrono::Duration::days(7);
+		let usage: Vec<f32> = fetch_cpu_history(&conn, &node, "cpu0", week_ago)
+			.unwrap()
+			.iter()
+			.map(|sample| sample.usage)
+			.collect();
+		assert_eq!(usage, [10.0, 20.0]);
+		let recent = now - chrono::Duration::seconds(90);
+		assert_eq!(
+			fetch_cpu_history(&conn, &node, "cpu0", recent)
+				.unwrap()
+				.len(),
+			1
+		);
+		assert!(
+			fetch_cpu_history(&conn, &[8u8; 16], "cpu0", week_ago)
+				.unwrap()
+				.is_empty()
+		);
+		let temperatures = fetch_temperature_history(&conn, &node, week_ago).unwrap();
+		assert_eq!(temperatures.len(), 1);
+		assert_eq!(temperatures[0].temperature, 55.0);
+	}
+
+	#[test]
+	fn recent_files_keep_the_newest_per_path() {
+		let mut conn = Connection::open_in_memory().unwrap();
+		run_migrations(&mut conn).unwrap();
+		let a = PeerId::random();
+		let b = PeerId::random();
+		let at = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+		save_recent_file(&conn, &a, "/notes.txt", at(1), 3).unwrap();
+		save_recent_file(&conn, &b, "/notes.txt", at(2), 3).unwrap();
+		save_recent_file(&conn, &a, "/todo.txt", at(3), 3).unwrap();
+		// reopening moves the file to the top instead of listing it twice
+		save_recent_file(&conn, &a, "/notes.txt", at(4), 3).unwrap();
+		save_recent_file(&conn, &a, "/plan.txt", at(5), 3).unwrap();
+
+		let all: Vec<_> = load_recent_files(&conn, None)
+			.unwrap()
+			.into_iter()
+			.map(|file| (file.peer, file.path))
+			.collect();
+		assert_eq!(
+			all,
+			vec![
+				(a, String::from("/plan.txt")),
+				(a, String::from("/notes.txt")),
+				(a, String::from("/todo.txt")),
+			]
+		);
+		assert!(load_recent_files(&conn, Some(&b)).unwrap().is_empty());
+	}
+
+	#[test]
+	fn catalog_pages_stay_inside_the_prefix_and_readable_rules() {
+		let mut conn = Connection::open_in_memory().unwrap();
+		run_migrations(&mut conn).unwrap();
+		let node = [1u8; 4];
+		for path in [
+			"/srv/photos2/b.jpg",
+			"/srv/photos/sub/c.jpg",
+			"/srv/photos/a.jpg",
+			"/srv/photos",
+		] {
+			conn.execute(
+				"INSERT INTO file_locations (node_id, path, size, timestamp) VALUES (?1, ?2, 1, 0)",
+				params![&node[..], path],
+			)
+			.unwrap();
+		}
+		let paths = |prefix: &str, readable: Option<&[FolderRule]>, offset, limit| {
+			let page = catalog_page(&conn, &node, prefix, readable, offset, limit).unwrap();
+			let paths: Vec<String> = page.entries.into_iter().map(|entry| entry.path).collect();
+			(paths, page.has_more)
+		};
+		assert_eq!(
+			paths("/srv/photos/", None, 0, 10),
+			(
+				vec![
+					String::from("/srv/photos"),
+					String::from("/srv/photos/a.jpg"),
+					String::from("/srv/photos/sub/c.jpg")
+				],
+				false
+			)
+		);
+		assert_eq!(
+			paths("/srv", None, 1, 2),
+			(
+				vec![
+					String::from("/srv/photos/a.jpg"),
+					String::from("/srv/photos/sub/c.jpg")
+				],
+				true
+			)
+		);
+		let readable = [
+			FolderRule::new(PathBuf::from("/srv/photos/sub"), 0),
+			FolderRule::new(PathBuf::from("/srv/photos2/b.jpg"), 0).with_file(true),
+		];
+		assert_eq!(
+			paths("/", Some(&readable), 0, 10),
+			(
+				vec![
+					String::from("/srv/photos/sub/c.jpg"),
+					String::from("/srv/photos2/b.jpg")
+				],
+				false
+			)
+		);
+		assert_eq!(
+			paths("/srv/photos", Some(&readable[1..]), 0, 10),
+			(vec![], false)
+		);
+		assert_eq!(paths("/", Some(&[]), 0, 10), (vec![], false));
+	}
+
+	#[test]
+	fn file_search_filters_and_sorts_by_latest() {
+		let mut conn = Connection::open_in_memory().unwrap();
+		run_migrations(&mut conn).unwrap();
+		for (byte, mime, latest) in [
+			(0xabu8, "image/png", "2025-01-02"),
+			(0xcd, "text/plain", "2025-01-03"),
+			(0xef, "image/jpeg", "2025-01-01"),
+		] {
+			conn.execute(
+				"INSERT INTO file_entries (hash, size, mime_type, first_datetime, latest_datetime) \
+				 VALUES (?1, 1, ?2, ?3, ?3)",
+				params![&[byte; 32][..], mime, latest],
+			)
+			.unwrap();
+		}
+		let search = |word: &str, mime: Option<&str>, sort_desc| {
+			let args = ListArgs {
+				search_word: Some(word.to_string()),
+				mime_type: mime.map(str::to_string),
+				sort_desc,
+			};
+			list_files(&conn, args)
+				.unwrap()
+				.into_iter()
+				.map(|entry| entry.hash[0])
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(search("image", None, true), vec![0xab, 0xef]);
+		assert_eq!(search("image", None, false), vec![0xef, 0xab]);
+		assert_eq!(search("CDCD", None, true), vec![0xcd]);
+		assert_eq!(search("", Some("image/jpeg"), true), vec![0xef]);
+	}
 }
@@ -1,10 +1,24 @@
 mod app;
+mod config;
 mod db;
 pub mod p2p;
 pub mod scan;
 mod state;
 mod types;
-pub use state::{FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FolderRule, Permission, Rule, State};
-pub use types::FileChunk;
+pub use state::{
+	Direction, FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FolderRule, Permission, Route, Rule, State,
+};
+pub use types::{
+	BackupLocations, CatalogEntry, CatalogPage, ConflictPolicy, DirSize, DirTree, DiskSpace,
+	FileChunk, FilePreview, IdentityRotation, PeerEvent, PeerMessage, PeerSummary, PlannedFile,
+	RecentFile, RelayProgress, RelayReport, ReplicatedFile, ReplicationOutcome, ReplicationReport,
+	SyncAction, SyncFileState, SyncMode, SyncOperation, SyncPlan, SyncReason, SyncReport,
+	SyncedFile,
+};
 pub mod wait_group;
 pub use app::PuppyPeer;
+pub use config::{
+	PuppyConfig, ReconnectPolicy, config_path, profile, profile_dir, puppy_home, select_profile,
+	unique_path,
+};
+pub use db::{CpuSample, FileEntry, SchemaStatus, TemperatureSample, schema_status};
@@ -1,11 +1,18 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use futures::AsyncRead;
+use libp2p::core::{Endpoint, transport::PortUse, upgrade::ReadyUpgrade};
 use libp2p::identity::Keypair;
 use libp2p::multiaddr::Protocol;
 use libp2p::request_response::{
 	self, Config as RequestResponseConfig, Event as RequestResponseEvent,
 	Message as RequestResponseMessage, ProtocolSupport,
 };
+use libp2p::swarm::{
+	ConnectionDenied, ConnectionHandler, ConnectionHandlerEvent, ConnectionId, FromSwarm,
+	NotifyHandler, SubstreamProtocol, THandler, THandlerOutEvent, ToSwarm,
+	handler::{ConnectionEvent, FullyNegotiatedInbound, FullyNegotiatedOutbound},
+};
 use libp2p::{
 	Multiaddr, PeerId, StreamProtocol, Swarm, SwarmBuilder, identity, noise,
 	swarm::{NetworkBehaviour, SwarmEvent},
@@ -14,23 +21,42 @@ use libp2p::{
 use libp2p::{mdns, ping};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::net::{IpAddr, UdpSocket};
-use std::path::Path;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll, Waker};
 use std::time::{SystemTime, UNIX_EPOCH};
-use sysinfo::{Disks, Networks, System};
+use sysinfo::{Disks, Networks};
 use tokio::sync::Mutex;
 use tokio::time::{Duration, interval};
 use uuid::Uuid;
 
-use crate::types::FileChunk;
+use crate::scan::FileHash;
+use crate::state::{FLAG_READ, FLAG_SEARCH, FLAG_WRITE, FolderRule, Permission, Rule, User};
+use crate::types::{CatalogPage, DirSize, DirTree, FileChunk, FilePreview};
 use crate::wait_group::WaitGroupGuard;
 
 const PUPPYPEER_PROTOCOL: &str = "/puppypeer/0.0.1";
-const MAX_FILE_CHUNK: u64 = 4 * 1024 * 1024; // 4 MiB per transfer chunk
+/// Streams on this protocol carry no data; an open one keeps its connection from idling out.
+const KEEP_ALIVE_PROTOCOL: &str = "/puppypeer/keep-alive/0.0.1";
+pub const MAX_FILE_CHUNK: u64 = 4 * 1024 * 1024; // 4 MiB per transfer chunk
+pub(crate) const MAX_PREVIEW_BYTES: u64 = 4 * 1024; // upper bound for PreviewFile heads
+pub(crate) const MAX_BATCH_FILES: usize = 256; // ReadFiles request count limit
+pub(crate) const MAX_BATCH_BYTES: u64 = MAX_FILE_CHUNK; // ReadFiles total payload limit
+pub(crate) const DIR_SIZE_MAX_ENTRIES: u64 = 1_000_000; // DirSize walk budget
+pub(crate) const DIR_SIZE_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const MAX_FIND_HASHES: usize = 1024; // FindHashes request count limit
+pub const MAX_CATALOG_PAGE: u64 = 1000; // BrowseCatalog entries per page limit
+pub const MAX_MESSAGE_BYTES: usize = 4 * 1024; // Message text limit
+pub const SUBTREE_DEPTH: u32 = 16; // ListDirRecursive depth used by the file browsers
+pub(crate) const MAX_PATH_BYTES: usize = 4096; // longest path a request may name
+const MAX_FILE_OFFSET: u64 = i64::MAX as u64; // furthest position a file can be seeked to
+const MDNS_GROUP: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
 const OWNER_ROLE: &str = "owner";
 const VIEWER_ROLE: &str = "viewer";
 const DEFAULT_SESSION_TTL: u64 = 60 * 60; // 1 hour sessions for credential auth
@@ -40,6 +66,12 @@ pub enum PeerReq {
 	ListDir {
 		path: String,
 	},
+	/// Entries of `path` and its subdirectories down to `max_depth` levels, `1` being
+	/// `path`'s own entries. Symlinks are listed but never followed.
+	ListDirRecursive {
+		path: String,
+		max_depth: u32,
+	},
 	StatFile {
 		path: String,
 	},
@@ -48,14 +80,61 @@ pub enum PeerReq {
 		offset: u64,
 		length: Option<u64>,
 	},
+	ReadFiles {
+		requests: Vec<(String, u64, Option<u64>)>,
+	},
+	PreviewFile {
+		path: String,
+		bytes: u64,
+	},
+	/// Bytes appended to `path` since `offset`, or its last `from_end_bytes` when `offset`
+	/// is `None`. Answered with a `FileChunk` that starts over at 0 if the file shrank.
+	TailFile {
+		path: String,
+		offset: Option<u64>,
+		from_end_bytes: u64,
+	},
+	DirSize {
+		path: String,
+	},
+	/// Which of these SHA-256 hashes the peer has indexed in folders the requester may read.
+	FindHashes {
+		hashes: Vec<FileHash>,
+	},
+	/// One page of the files the peer has indexed under `prefix`, a directory such as `/`
+	/// or a share root, limited to folders the requester may read. Served from the index,
+	/// so it is fast on huge trees but only as fresh as the last scan.
+	BrowseCatalog {
+		prefix: String,
+		offset: u64,
+		limit: u64,
+	},
 	WriteFile {
 		path: String,
 		offset: u64,
 		data: Vec<u8>,
+		#[serde(default)]
+		mode: WriteMode,
+		/// Timestamps set once the data is in place: after every in-place chunk, or after
+		/// the final chunk of an atomic write.
+		#[serde(default)]
+		times: Option<FileTimestamps>,
+	},
+	/// Create a directory and its missing parents inside a writable share.
+	CreateDir {
+		path: String,
 	},
 	ListCpus,
 	ListDisks,
+	/// Free and total bytes of the filesystem holding `path`, which may not exist yet.
+	SpaceFor {
+		path: String,
+	},
 	ListInterfaces,
+	/// CPU, memory, disk and network totals in one round trip.
+	SystemOverview,
+	/// Uptime and load average, cheap enough to poll.
+	SystemLoad,
 	Authenticate {
 		method: AuthMethod,
 	},
@@ -71,8 +150,10 @@ pub enum PeerReq {
 		expires_in: Option<u64>,
 		permissions: Vec<PermissionGrant>,
 	},
+	/// Give the peer `peer_id` these permissions, on top of the ones it has when `merge`
+	/// is set, or instead of them. Only owners may send it.
 	GrantAccess {
-		username: String,
+		peer_id: String,
 		permissions: Vec<PermissionGrant>,
 		merge: bool,
 	},
@@ -87,17 +168,230 @@ pub enum PeerReq {
 		username: String,
 	},
 	ListPermissions,
+	Identify,
+	/// A short note for the operator of the peer, e.g. "rebooting in 5".
+	Message {
+		text: String,
+	},
+}
+
+impl PeerReq {
+	/// Whether sending the request a second time has the same effect as sending it once,
+	/// so it can be retried after a dropped connection.
+	///
+	/// In-place writes qualify since they land at a fixed offset. Atomic writes do not: a
+	/// repeated final chunk would find its staged file already renamed into place.
+	pub fn is_retryable(&self) -> bool {
+		match self {
+			PeerReq::ListDir { .. }
+			| PeerReq::ListDirRecursive { .. }
+			| PeerReq::StatFile { .. }
+			| PeerReq::ReadFile { .. }
+			| PeerReq::ReadFiles { .. }
+			| PeerReq::PreviewFile { .. }
+			| PeerReq::TailFile { .. }
+			| PeerReq::DirSize { .. }
+			| PeerReq::FindHashes { .. }
+			| PeerReq::BrowseCatalog { .. }
+			| PeerReq::CreateDir { .. }
+			| PeerReq::ListCpus
+			| PeerReq::ListDisks
+			| PeerReq::SpaceFor { .. }
+			| PeerReq::ListInterfaces
+			| PeerReq::SystemOverview
+			| PeerReq::SystemLoad
+			| PeerReq::ListUsers
+			| PeerReq::ListTokens { .. }
+			| PeerReq::ListPermissions
+			| PeerReq::Identify => true,
+			PeerReq::WriteFile { mode, .. } => *mode == WriteMode::InPlace,
+			// a retried note would show up twice in the inbox
+			PeerReq::Message { .. }
+			| PeerReq::Authenticate { .. }
+			| PeerReq::CreateUser { .. }
+			| PeerReq::CreateToken { .. }
+			| PeerReq::GrantAccess { .. }
+			| PeerReq::RevokeToken { .. }
+			| PeerReq::RevokeUser { .. } => false,
+		}
+	}
+
+	/// Name of the variant for log lines, without the payload a `Debug` dump would include.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			PeerReq::ListDir { .. } => "ListDir",
+			PeerReq::ListDirRecursive { .. } => "ListDirRecursive",
+			PeerReq::StatFile { .. } => "StatFile",
+			PeerReq::ReadFile { .. } => "ReadFile",
+			PeerReq::ReadFiles { .. } => "ReadFiles",
+			PeerReq::PreviewFile { .. } => "PreviewFile",
+			PeerReq::TailFile { .. } => "TailFile",
+			PeerReq::DirSize { .. } => "DirSize",
+			PeerReq::FindHashes { .. } => "FindHashes",
+			PeerReq::BrowseCatalog { .. } => "BrowseCatalog",
+			PeerReq::WriteFile { .. } => "WriteFile",
+			PeerReq::CreateDir { .. } => "CreateDir",
+			PeerReq::ListCpus => "ListCpus",
+			PeerReq::ListDisks => "ListDisks",
+			PeerReq::SpaceFor { .. } => "SpaceFor",
+			PeerReq::ListInterfaces => "ListInterfaces",
+			PeerReq::SystemOverview => "SystemOverview",
+			PeerReq::SystemLoad => "SystemLoad",
+			PeerReq::Authenticate { .. } => "Authenticate",
+			PeerReq::CreateUser { .. } => "CreateUser",
+			PeerReq::CreateToken { .. } => "CreateToken",
+			PeerReq::GrantAccess { .. } => "GrantAccess",
+			PeerReq::ListUsers => "ListUsers",
+			PeerReq::ListTokens { .. } => "ListTokens",
+			PeerReq::RevokeToken { .. } => "RevokeToken",
+			PeerReq::RevokeUser { .. } => "RevokeUser",
+			PeerReq::ListPermissions => "ListPermissions",
+			PeerReq::Identify => "Identify",
+			PeerReq::Message { .. } => "Message",
+		}
+	}
+
+	/// Paths on the serving peer the request reads or writes.
+	pub(crate) fn paths(&self) -> Vec<&String> {
+		match self {
+			PeerReq::ListDir { path }
+			| PeerReq::ListDirRecursive { path, .. }
+			| PeerReq::StatFile { path }
+			| PeerReq::ReadFile { path, .. }
+			| PeerReq::PreviewFile { path, .. }
+			| PeerReq::TailFile { path, .. }
+			| PeerReq::DirSize { path }
+			| PeerReq::WriteFile { path, .. }
+			| PeerReq::CreateDir { path }
+			| PeerReq::SpaceFor { path }
+			| PeerReq::BrowseCatalog { prefix: path, .. } => vec![path],
+			PeerReq::ReadFiles { requests } => requests.iter().map(|(path, _, _)| path).collect(),
+			_ => Vec::new(),
+		}
+	}
+
+	fn paths_mut(&mut self) -> Vec<&mut String> {
+		match self {
+			PeerReq::ListDir { path }
+			| PeerReq::ListDirRecursive { path, .. }
+			| PeerReq::StatFile { path }
+			| PeerReq::ReadFile { path, .. }
+			| PeerReq::PreviewFile { path, .. }
+			| PeerReq::TailFile { path, .. }
+			| PeerReq::DirSize { path }
+			| PeerReq::WriteFile { path, .. }
+			| PeerReq::CreateDir { path }
+			| PeerReq::SpaceFor { path }
+			| PeerReq::BrowseCatalog { prefix: path, .. } => vec![path],
+			PeerReq::ReadFiles { requests } => {
+				requests.iter_mut().map(|(path, _, _)| path).collect()
+			}
+			_ => Vec::new(),
+		}
+	}
+
+	/// Why the request is malformed, whatever is on disk: an empty, overlong or NUL
+	/// containing path, a `WriteFile` carrying more than `MAX_FILE_CHUNK`, or an offset no
+	/// file can reach. Checked before anything is resolved.
+	pub fn validate(&self) -> Result<(), String> {
+		for path in self.paths() {
+			if path.is_empty() {
+				return Err(String::from("Path must not be empty"));
+			}
+			if path.len() > MAX_PATH_BYTES {
+				return Err(format!("Path is longer than {} bytes", MAX_PATH_BYTES));
+			}
+			if path.contains('\0') {
+				return Err(String::from("Path must not contain NUL"));
+			}
+		}
+		let offsets: Vec<u64> = match self {
+			PeerReq::ReadFile { offset, .. }
+			| PeerReq::TailFile {
+				offset: Some(offset),
+				..
+			} => vec![*offset],
+			PeerReq::ReadFiles { requests } => {
+				requests.iter().map(|(_, offset, _)| *offset).collect()
+			}
+			PeerReq::WriteFile { offset, data, .. } => {
+				if data.len() as u64 > MAX_FILE_CHUNK {
+					return Err(format!(
+						"Write chunk is larger than {} bytes",
+						MAX_FILE_CHUNK
+					));
+				}
+				vec![offset.saturating_add(data.len() as u64)]
+			}
+			_ => Vec::new(),
+		};
+		if offsets.iter().any(|offset| *offset > MAX_FILE_OFFSET) {
+			return Err(String::from("Offset is out of range"));
+		}
+		Ok(())
+	}
+
+	/// The request with read lengths capped at `MAX_FILE_CHUNK`, so one answer holds at
+	/// most a chunk however much was asked for. Readers go on from the returned chunk.
+	pub fn with_clamped_lengths(mut self) -> Self {
+		let clamp = |length: &mut Option<u64>| {
+			*length = Some(length.unwrap_or(MAX_FILE_CHUNK).min(MAX_FILE_CHUNK));
+		};
+		match &mut self {
+			PeerReq::ReadFile { length, .. } => clamp(length),
+			PeerReq::ReadFiles { requests } => {
+				for (_, _, length) in requests.iter_mut() {
+					clamp(length);
+				}
+			}
+			_ => {}
+		}
+		self
+	}
+
+	/// Whether any path has a `..` component, with `\` counted as a separator too.
+	///
+	/// Such paths are refused before anything is resolved: a `..` in a name that does not
+	/// exist yet, such as a write target, is not resolved by canonicalizing its parent.
+	pub fn has_parent_dir(&self) -> bool {
+		self.paths()
+			.iter()
+			.any(|path| path.split(['/', '\\']).any(|part| part == ".."))
+	}
+
+	/// The request with every path passed through `normalize_request_path`, so paths from
+	/// Windows clients resolve the same as ones from Unix clients.
+	pub fn with_normalized_paths(mut self) -> Self {
+		for path in self.paths_mut() {
+			*path = normalize_request_path(path);
+		}
+		self
+	}
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PeerRes {
 	DirEntries(Vec<DirEntry>),
+	DirTree(DirTree),
 	FileStat(DirEntry),
 	FileChunk(FileChunk),
+	FileChunks(Vec<Result<FileChunk, ErrorCode>>),
+	FilePreview(FilePreview),
+	DirSize(DirSize),
+	HashesFound(Vec<FileHash>),
+	Catalog(CatalogPage),
 	WriteAck(FileWriteAck),
+	/// Canonical path of the directory made by `CreateDir`.
+	DirCreated(String),
 	Cpus(Vec<CpuInfo>),
 	Disks(Vec<DiskInfo>),
+	Space {
+		available: u64,
+		total: u64,
+	},
 	Interfaces(Vec<InterfaceInfo>),
+	Overview(SystemOverview),
+	Load(SystemLoad),
 	AuthSuccess {
 		session: SessionInfo,
 	},
@@ -120,20 +414,102 @@ pub enum PeerRes {
 	TokenRevoked {
 		token_id: String,
 	},
+	/// Everything the peer is granted after a `GrantAccess`.
 	AccessGranted {
-		username: String,
+		peer_id: String,
 		permissions: Vec<PermissionGrant>,
 	},
 	Users(Vec<UserSummary>),
 	Tokens(Vec<TokenInfo>),
 	Error(String),
+	Failure {
+		code: ErrorCode,
+		message: String,
+	},
 	Permissions(Vec<crate::state::Permission>),
+	Identity(BuildInfo),
+	MessageAck,
+}
+
+/// Machine readable reason for a failed operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+	NotFound,
+	AccessDenied,
+	IsADirectory,
+	NotADirectory,
+	InvalidArgument,
+	LimitExceeded,
+	NoSpace,
+	Io,
+}
+
+impl ErrorCode {
+	/// Code carried by an error returned from a `PuppyPeer` call, if it has one.
+	pub fn of(err: &anyhow::Error) -> Option<Self> {
+		if let Some(peer_err) = err.downcast_ref::<PeerError>() {
+			return Some(peer_err.code);
+		}
+		if let Some(code) = err.downcast_ref::<ErrorCode>() {
+			return Some(*code);
+		}
+		err.downcast_ref::<std::io::Error>().map(Self::from_io)
+	}
+
+	pub fn from_io(err: &std::io::Error) -> Self {
+		match err.kind() {
+			std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+			std::io::ErrorKind::PermissionDenied => ErrorCode::AccessDenied,
+			std::io::ErrorKind::InvalidInput => ErrorCode::InvalidArgument,
+			_ => ErrorCode::Io,
+		}
+	}
+}
+
+impl std::fmt::Display for ErrorCode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let text = match self {
+			ErrorCode::NotFound => "not found",
+			ErrorCode::AccessDenied => "access denied",
+			ErrorCode::IsADirectory => "is a directory",
+			ErrorCode::NotADirectory => "not a directory",
+			ErrorCode::InvalidArgument => "invalid argument",
+			ErrorCode::LimitExceeded => "limit exceeded",
+			ErrorCode::NoSpace => "not enough space",
+			ErrorCode::Io => "i/o error",
+		};
+		f.write_str(text)
+	}
 }
 
+impl std::error::Error for ErrorCode {}
+
+/// Error returned to callers when a peer answers with `PeerRes::Failure`.
+///
+/// Downcast an `anyhow::Error` to this type to inspect the code.
+#[derive(Debug, Clone)]
+pub struct PeerError {
+	pub code: ErrorCode,
+	pub message: String,
+}
+
+impl std::fmt::Display for PeerError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} ({})", self.message, self.code)
+	}
+}
+
+impl std::error::Error for PeerError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirEntry {
 	pub name: String,
 	pub is_dir: bool,
+	#[serde(default)]
+	pub is_symlink: bool,
+	/// Set when the entry is a symlink resolving to a directory inside the requester's shares.
+	#[serde(default)]
+	pub target_is_dir: bool,
 	pub extension: Option<String>,
 	pub mime: Option<String>,
 	pub size: u64,
@@ -142,9 +518,158 @@ pub struct DirEntry {
 	pub accessed_at: Option<DateTime<Utc>>,
 }
 
+impl DirEntry {
+	/// Whether the browser can descend into this entry.
+	pub fn is_navigable(&self) -> bool {
+		self.is_dir || self.target_is_dir
+	}
+
+	/// Dotfiles, which file browsers hide unless asked to show them. In a recursive
+	/// listing everything below a dot folder counts as hidden too.
+	pub fn is_hidden(&self) -> bool {
+		self.name.split('/').any(|part| part.starts_with('.'))
+	}
+}
+
+/// Compare file names the way people read them.
+///
+/// Case and accents are ignored and runs of digits compare by value, so `file2` sorts
+/// before `file10` and `Écran` next to `ecran`. Names that only differ in case, accents
+/// or leading zeros fall back to a plain comparison, keeping the order stable.
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+	natural_cmp_folded(&fold_name(a), &fold_name(b)).then_with(|| a.cmp(b))
+}
+
+/// Lowercase `name` and strip the accents that NFD splits off as combining marks.
+fn fold_name(name: &str) -> Vec<char> {
+	icu_normalizer::DecomposingNormalizerBorrowed::new_nfd()
+		.normalize_iter(name.chars())
+		.filter(|c| !('\u{0300}'..='\u{036f}').contains(c))
+		.flat_map(char::to_lowercase)
+		.collect()
+}
+
+fn natural_cmp_folded(a: &[char], b: &[char]) -> std::cmp::Ordering {
+	use std::cmp::Ordering;
+	let digits = |s: &[char], from: usize| {
+		let len = s[from..].iter().take_while(|c| c.is_ascii_digit()).count();
+		let run = &s[from..from + len];
+		let zeros = run.iter().take_while(|c| **c == '0').count();
+		(from + len, run[zeros..].to_vec())
+	};
+	let (mut i, mut j) = (0, 0);
+	while i < a.len() && j < b.len() {
+		let order = if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+			let (next_i, x) = digits(a, i);
+			let (next_j, y) = digits(b, j);
+			(i, j) = (next_i, next_j);
+			// without leading zeros, a longer run is a larger number
+			x.len().cmp(&y.len()).then_with(|| x.cmp(&y))
+		} else {
+			let order = a[i].cmp(&b[j]);
+			(i, j) = (i + 1, j + 1);
+			order
+		};
+		if order != Ordering::Equal {
+			return order;
+		}
+	}
+	(a.len() - i).cmp(&(b.len() - j))
+}
+
+/// How a `WriteFile` chunk is applied on the serving peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WriteMode {
+	/// Patch the destination directly; suited for sparse or random-access writes.
+	#[default]
+	InPlace,
+	/// Stage chunks in a temp file next to the destination and rename it into place
+	/// once the chunk with `last` set arrives, so readers never see a partial file.
+	Atomic { last: bool },
+}
+
+/// Timestamps to apply to a written file; `None` fields are left as they are.
+///
+/// `created` is only honoured on Windows and macOS, where the creation time can be set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTimestamps {
+	#[serde(default)]
+	pub created: Option<DateTime<Utc>>,
+	#[serde(default)]
+	pub modified: Option<DateTime<Utc>>,
+	#[serde(default)]
+	pub accessed: Option<DateTime<Utc>>,
+}
+
+impl FileTimestamps {
+	/// The timestamps of the file behind `meta`.
+	pub fn from_metadata(meta: &std::fs::Metadata) -> Self {
+		Self {
+			created: meta.created().ok().map(DateTime::from),
+			modified: meta.modified().ok().map(DateTime::from),
+			accessed: meta.accessed().ok().map(DateTime::from),
+		}
+	}
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileWriteAck {
 	pub bytes_written: u64,
+	/// Length of the destination (or staged) file after this chunk was applied.
+	#[serde(default)]
+	pub total_len: Option<u64>,
+	/// Hex SHA-256 of the assembled file, sent with the final chunk of an atomic write.
+	#[serde(default)]
+	pub sha256: Option<String>,
+}
+
+/// What a peer binary was built with, used to spot capability mismatches between peers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildInfo {
+	pub version: String,
+	pub features: Vec<String>,
+	pub transports: Vec<String>,
+	pub protocol_version: String,
+	/// Host the binary is running on, which is not part of the build itself.
+	#[serde(default)]
+	pub hostname: Option<String>,
+}
+
+impl BuildInfo {
+	pub fn current() -> Self {
+		let features = [
+			("sha2", cfg!(feature = "sha2")),
+			("ring", cfg!(feature = "ring")),
+			("rayon", cfg!(feature = "rayon")),
+		]
+		.into_iter()
+		.filter(|(_, enabled)| *enabled)
+		.map(|(name, _)| name.to_string())
+		.collect();
+		Self {
+			version: env!("CARGO_PKG_VERSION").to_string(),
+			features,
+			transports: ["tcp", "noise", "yamux", "mdns"]
+				.into_iter()
+				.map(String::from)
+				.collect(),
+			protocol_version: PUPPYPEER_PROTOCOL.to_string(),
+			hostname: sysinfo::System::host_name(),
+		}
+	}
+}
+
+impl std::fmt::Display for BuildInfo {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"core {} | protocol {} | features [{}] | transports [{}]",
+			self.version,
+			self.protocol_version,
+			self.features.join(", "),
+			self.transports.join(", ")
+		)
+	}
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -156,17 +681,19 @@ pub struct CpuInfo {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiskInfo {
-	name: String,
-	mount_path: String,
-	filesystem: String,
-	total_space: u64,
-	available_space: u64,
-	usage_percent: f32,
-	total_read_bytes: u64,
-	total_written_bytes: u64,
-	read_only: bool,
-	removable: bool,
-	kind: String,
+	pub name: String,
+	pub mount_path: String,
+	pub filesystem: String,
+	pub total_space: u64,
+	pub available_space: u64,
+	/// Share of `total_space` in use, 0-100.
+	pub usage_percent: f32,
+	pub total_read_bytes: u64,
+	pub total_written_bytes: u64,
+	pub read_only: bool,
+	pub removable: bool,
+	/// `HDD`, `SSD` or `Unknown(..)`.
+	pub kind: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -183,6 +710,106 @@ pub struct InterfaceInfo {
 	pub mtu: u64,
 }
 
+/// A peer's health at a glance, answered to `SystemOverview`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemOverview {
+	/// Mean usage over all CPUs, 0-100.
+	pub cpu_avg: f32,
+	pub cpu_count: usize,
+	pub mem: MemorySummary,
+	pub disks_summary: DisksSummary,
+	pub net_summary: NetSummary,
+	/// Seconds since the peer booted.
+	pub uptime: u64,
+	/// `None` where the OS has no load average, as on Windows.
+	pub load: Option<LoadAverage>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemorySummary {
+	pub total: u64,
+	pub used: u64,
+	pub swap_total: u64,
+	pub swap_used: u64,
+}
+
+/// Totals over every mounted filesystem.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisksSummary {
+	pub count: usize,
+	pub total_space: u64,
+	pub available_space: u64,
+}
+
+impl DisksSummary {
+	pub fn of(disks: &[DiskInfo]) -> Self {
+		Self {
+			count: disks.len(),
+			total_space: disks.iter().map(|disk| disk.total_space).sum(),
+			available_space: disks.iter().map(|disk| disk.available_space).sum(),
+		}
+	}
+}
+
+/// Traffic totals over every network interface since boot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetSummary {
+	pub interfaces: usize,
+	pub total_received: u64,
+	pub total_transmitted: u64,
+}
+
+impl NetSummary {
+	pub fn of(interfaces: &[InterfaceInfo]) -> Self {
+		Self {
+			interfaces: interfaces.len(),
+			total_received: interfaces.iter().map(|i| i.total_received).sum(),
+			total_transmitted: interfaces.iter().map(|i| i.total_transmitted).sum(),
+		}
+	}
+}
+
+/// Answer to `SystemLoad`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SystemLoad {
+	/// Seconds since the peer booted.
+	pub uptime: u64,
+	/// `None` where the OS has no load average, as on Windows.
+	pub load: Option<LoadAverage>,
+}
+
+impl SystemLoad {
+	pub fn current() -> Self {
+		Self {
+			uptime: sysinfo::System::uptime(),
+			load: LoadAverage::current(),
+		}
+	}
+}
+
+/// Run queue averages over the last 1, 5 and 15 minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoadAverage {
+	pub one: f64,
+	pub five: f64,
+	pub fifteen: f64,
+}
+
+impl LoadAverage {
+	/// The load average of this machine, `None` on Windows where sysinfo reports zeros.
+	pub fn current() -> Option<Self> {
+		if cfg!(windows) {
+			return None;
+		}
+		let load = sysinfo::System::load_average();
+		Some(Self {
+			one: load.one,
+			five: load.five,
+			fifteen: load.fifteen,
+		})
+	}
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AuthMethod {
 	Token { token: String },
@@ -221,6 +848,17 @@ pub struct UserSummary {
 	pub permissions: Vec<PermissionGrant>,
 }
 
+/// What `PuppyPeer::create_token` returns. `token` is the secret, shown once: the peer
+/// only keeps its hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedToken {
+	pub token: String,
+	pub token_id: String,
+	pub username: String,
+	pub permissions: Vec<PermissionGrant>,
+	pub expires_at: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenInfo {
 	pub id: String,
@@ -241,6 +879,7 @@ pub struct AgentBehaviour {
 	ping: ping::Behaviour,
 	pub puppypeer: PuppyPeerBehaviour,
 	pub mdns: mdns::tokio::Behaviour,
+	keep_alive: KeepAlive,
 }
 
 #[derive(Debug)]
@@ -268,6 +907,12 @@ impl From<mdns::Event> for AgentEvent {
 	}
 }
 
+impl From<Infallible> for AgentEvent {
+	fn from(event: Infallible) -> Self {
+		match event {}
+	}
+}
+
 impl AgentBehaviour {
 	fn new(local_peer_id: PeerId) -> Self {
 		let puppypeer_protocol = std::iter::once((
@@ -284,6 +929,213 @@ impl AgentBehaviour {
 			ping: ping::Behaviour::default(),
 			puppypeer,
 			mdns,
+			keep_alive: KeepAlive::default(),
+		}
+	}
+
+	/// Keep connections to `peer` open while idle, or stop doing so.
+	pub fn keep_alive(&mut self, peer: PeerId, keep: bool) {
+		self.keep_alive.set(peer, keep);
+	}
+}
+
+/// Keeps connections to wanted peers, such as bootstrap peers and signed-in sessions, from
+/// being closed by the idle timeout.
+///
+/// Each side's idle timer would close the connection on its own, so the side that wants
+/// it holds a `KEEP_ALIVE_PROTOCOL` stream open, which both ends count as activity. Dead
+/// connections are still closed when a ping fails.
+#[derive(Default)]
+pub struct KeepAlive {
+	peers: HashSet<PeerId>,
+	connections: HashMap<PeerId, HashSet<ConnectionId>>,
+	pending: VecDeque<ToSwarm<Infallible, bool>>,
+	waker: Option<Waker>,
+}
+
+impl KeepAlive {
+	/// Start or stop keeping the connections to `peer` open, including future ones.
+	fn set(&mut self, peer: PeerId, keep: bool) {
+		let changed = if keep {
+			self.peers.insert(peer)
+		} else {
+			self.peers.remove(&peer)
+		};
+		if !changed {
+			return;
+		}
+		for connection in self.connections.get(&peer).into_iter().flatten() {
+			self.pending.push_back(ToSwarm::NotifyHandler {
+				peer_id: peer,
+				handler: NotifyHandler::One(*connection),
+				event: keep,
+			});
+		}
+		if let Some(waker) = self.waker.take() {
+			waker.wake();
+		}
+	}
+
+	fn handler(&self, peer: &PeerId) -> KeepAliveHandler {
+		KeepAliveHandler {
+			wanted: self.peers.contains(peer),
+			..KeepAliveHandler::default()
+		}
+	}
+}
+
+impl NetworkBehaviour for KeepAlive {
+	type ConnectionHandler = KeepAliveHandler;
+	type ToSwarm = Infallible;
+
+	fn handle_established_inbound_connection(
+		&mut self,
+		_: ConnectionId,
+		peer: PeerId,
+		_: &Multiaddr,
+		_: &Multiaddr,
+	) -> Result<THandler<Self>, ConnectionDenied> {
+		Ok(self.handler(&peer))
+	}
+
+	fn handle_established_outbound_connection(
+		&mut self,
+		_: ConnectionId,
+		peer: PeerId,
+		_: &Multiaddr,
+		_: Endpoint,
+		_: PortUse,
+	) -> Result<THandler<Self>, ConnectionDenied> {
+		Ok(self.handler(&peer))
+	}
+
+	fn on_swarm_event(&mut self, event: FromSwarm) {
+		match event {
+			FromSwarm::ConnectionEstablished(established) => {
+				self.connections
+					.entry(established.peer_id)
+					.or_default()
+					.insert(established.connection_id);
+			}
+			FromSwarm::ConnectionClosed(closed) => {
+				if let Some(connections) = self.connections.get_mut(&closed.peer_id) {
+					connections.remove(&closed.connection_id);
+					if connections.is_empty() {
+						self.connections.remove(&closed.peer_id);
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn on_connection_handler_event(
+		&mut self,
+		_: PeerId,
+		_: ConnectionId,
+		event: THandlerOutEvent<Self>,
+	) {
+		match event {}
+	}
+
+	fn poll(&mut self, cx: &mut TaskContext<'_>) -> Poll<ToSwarm<Infallible, bool>> {
+		match self.pending.pop_front() {
+			Some(event) => Poll::Ready(event),
+			None => {
+				self.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+}
+
+/// Holds our keep-alive stream while the peer is wanted, and the peer's until it lets go.
+#[derive(Default)]
+pub struct KeepAliveHandler {
+	wanted: bool,
+	outbound: Option<libp2p::Stream>,
+	requested: bool,
+	// the peer does not speak the protocol, or opening the stream failed
+	refused: bool,
+	inbound: Option<libp2p::Stream>,
+}
+
+impl ConnectionHandler for KeepAliveHandler {
+	type FromBehaviour = bool;
+	type ToBehaviour = Infallible;
+	type InboundProtocol = ReadyUpgrade<StreamProtocol>;
+	type OutboundProtocol = ReadyUpgrade<StreamProtocol>;
+	type InboundOpenInfo = ();
+	type OutboundOpenInfo = ();
+
+	fn listen_protocol(&self) -> SubstreamProtocol<ReadyUpgrade<StreamProtocol>> {
+		SubstreamProtocol::new(
+			ReadyUpgrade::new(StreamProtocol::new(KEEP_ALIVE_PROTOCOL)),
+			(),
+		)
+	}
+
+	fn connection_keep_alive(&self) -> bool {
+		self.wanted
+	}
+
+	fn poll(
+		&mut self,
+		cx: &mut TaskContext<'_>,
+	) -> Poll<ConnectionHandlerEvent<ReadyUpgrade<StreamProtocol>, (), Infallible>> {
+		// nothing is ever sent, so a read only finishes when the peer closes its stream
+		if let Some(stream) = &mut self.inbound {
+			let mut buf = [0u8; 64];
+			let closed = loop {
+				match Pin::new(&mut *stream).poll_read(cx, &mut buf) {
+					Poll::Ready(Ok(0)) | Poll::Ready(Err(_)) => break true,
+					Poll::Ready(Ok(_)) => continue,
+					Poll::Pending => break false,
+				}
+			};
+			if closed {
+				self.inbound = None;
+			}
+		}
+		if self.wanted && self.outbound.is_none() && !self.requested && !self.refused {
+			self.requested = true;
+			return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+				protocol: self.listen_protocol(),
+			});
+		}
+		Poll::Pending
+	}
+
+	fn on_behaviour_event(&mut self, wanted: bool) {
+		self.wanted = wanted;
+		if !wanted {
+			self.outbound = None;
+		}
+	}
+
+	fn on_connection_event(
+		&mut self,
+		event: ConnectionEvent<ReadyUpgrade<StreamProtocol>, ReadyUpgrade<StreamProtocol>>,
+	) {
+		match event {
+			ConnectionEvent::FullyNegotiatedInbound(FullyNegotiatedInbound {
+				protocol: stream,
+				..
+			}) => self.inbound = Some(stream),
+			ConnectionEvent::FullyNegotiatedOutbound(FullyNegotiatedOutbound {
+				protocol: stream,
+				..
+			}) => {
+				self.requested = false;
+				if self.wanted {
+					self.outbound = Some(stream);
+				}
+			}
+			ConnectionEvent::DialUpgradeError(_) => {
+				self.requested = false;
+				self.refused = true;
+			}
+			_ => {}
 		}
 	}
 }
@@ -336,10 +1188,12 @@ fn write_file_range(path: &str, offset: u64, data: &[u8]) -> Result<FileWriteAck
 
 	Ok(FileWriteAck {
 		bytes_written: data.len() as u64,
+		total_len: Some(current_len.max(required_len)),
+		sha256: None,
 	})
 }
 
-fn collect_disk_info() -> Result<Vec<DiskInfo>, String> {
+pub(crate) fn collect_disk_info() -> Result<Vec<DiskInfo>, String> {
 	let disks = Disks::new_with_refreshed_list();
 	let infos = disks
 		.list()
@@ -372,6 +1226,25 @@ fn collect_disk_info() -> Result<Vec<DiskInfo>, String> {
 	Ok(infos)
 }
 
+pub(crate) fn collect_interface_info() -> Vec<InterfaceInfo> {
+	let networks = Networks::new_with_refreshed_list();
+	networks
+		.iter()
+		.map(|(name, data)| InterfaceInfo {
+			name: name.clone(),
+			mac: data.mac_address().to_string(),
+			ips: data.ip_networks().iter().map(|ip| ip.to_string()).collect(),
+			total_received: data.total_received(),
+			total_transmitted: data.total_transmitted(),
+			packets_received: data.total_packets_received(),
+			packets_transmitted: data.total_packets_transmitted(),
+			errors_on_received: data.total_errors_on_received(),
+			errors_on_transmitted: data.total_errors_on_transmitted(),
+			mtu: data.mtu(),
+		})
+		.collect()
+}
+
 #[derive(Debug, Clone)]
 struct UserRecord {
 	username: String,
@@ -401,6 +1274,41 @@ impl Capability {
 }
 
 impl PermissionGrant {
+	/// The filesystem rule this grant stands for, if any. `Viewer` reads everything, like
+	/// the `Files` grant on `/` the viewer role comes with.
+	pub fn fs_rule(&self) -> Option<Rule> {
+		let folder = |path: &str, flags| Rule::Folder(FolderRule::new(PathBuf::from(path), flags));
+		match self {
+			PermissionGrant::Owner => Some(Rule::Owner),
+			PermissionGrant::Viewer => Some(folder("/", FLAG_READ | FLAG_SEARCH)),
+			PermissionGrant::Files { path, access } => {
+				let write = match access {
+					FileAccess::Read => 0,
+					FileAccess::ReadWrite => FLAG_WRITE,
+				};
+				Some(folder(path, FLAG_READ | FLAG_SEARCH | write))
+			}
+			PermissionGrant::SystemInfo
+			| PermissionGrant::DiskInfo
+			| PermissionGrant::NetworkInfo => None,
+		}
+	}
+
+	/// The grant a stored rule stands for, the reverse of `fs_rule`.
+	pub fn from_rule(rule: &Rule) -> Self {
+		match rule {
+			Rule::Owner => PermissionGrant::Owner,
+			Rule::Folder(folder) => PermissionGrant::Files {
+				path: folder.path().to_string_lossy().into_owned(),
+				access: if folder.flags() & FLAG_WRITE != 0 {
+					FileAccess::ReadWrite
+				} else {
+					FileAccess::Read
+				},
+			},
+		}
+	}
+
 	fn allows(&self, capability: &Capability) -> bool {
 		match self {
 			PermissionGrant::Owner => true,
@@ -419,7 +1327,89 @@ impl PermissionGrant {
 	}
 }
 
-fn default_permissions_for_roles(roles: &HashSet<String>) -> HashSet<PermissionGrant> {
+/// Open a session for a token looked up by its hash, or say why it is refused. The
+/// session lasts as long as the token.
+pub(crate) fn session_for_token(token: Option<TokenInfo>, now: u64) -> Result<SessionInfo, String> {
+	let token = token.ok_or_else(|| String::from("Unknown token"))?;
+	if token.revoked {
+		return Err(String::from("Token was revoked"));
+	}
+	if token.expires_at.is_some_and(|expires_at| expires_at <= now) {
+		return Err(String::from("Token has expired"));
+	}
+	Ok(SessionInfo {
+		session_id: Uuid::new_v4().simple().to_string(),
+		username: token.username,
+		roles: Vec::new(),
+		permissions: token.permissions,
+		expires_at: token.expires_at,
+	})
+}
+
+/// The permissions a peer ends up with after `GrantAccess`: `existing` plus the rules for
+/// `grants` when `merge` is set, where a grant for a folder replaces the rule already
+/// there, or only the rules for `grants` otherwise. Grants with no filesystem rule, such
+/// as `SystemInfo`, cannot be stored for a peer and are refused.
+pub(crate) fn granted_permissions(
+	existing: Vec<Permission>,
+	grants: &[PermissionGrant],
+	merge: bool,
+) -> Result<Vec<Permission>, String> {
+	let mut permissions = if merge { existing } else { Vec::new() };
+	for grant in grants {
+		let rule = grant
+			.fs_rule()
+			.ok_or_else(|| format!("{grant:?} cannot be granted to a peer"))?;
+		permissions.retain(|permission| match (permission.rule(), &rule) {
+			(Rule::Owner, Rule::Owner) => false,
+			(Rule::Folder(old), Rule::Folder(new)) => old.path() != new.path(),
+			_ => true,
+		});
+		permissions.push(Permission::new(rule));
+	}
+	Ok(permissions)
+}
+
+/// Open a session for a password sign-in as `user`, looked up by name. Unknown users and
+/// wrong passwords get the same reason, so a failed sign-in does not tell which users
+/// exist. The session lasts `DEFAULT_SESSION_TTL`.
+pub(crate) fn session_for_credentials(
+	user: Option<&User>,
+	password: &str,
+	now: u64,
+) -> Result<SessionInfo, String> {
+	let verified = match user {
+		Some(user) => verify_password(&user.salt, password, &user.password_hash),
+		None => {
+			// hash anyway, so unknown users take as long to refuse as wrong passwords
+			verify_password("", password, "");
+			false
+		}
+	};
+	let user = user
+		.filter(|_| verified)
+		.ok_or_else(|| String::from("Invalid username or password"))?;
+	Ok(SessionInfo {
+		session_id: Uuid::new_v4().simple().to_string(),
+		username: user.name.clone(),
+		roles: user.roles.clone(),
+		permissions: user.permissions.clone(),
+		expires_at: Some(now.saturating_add(DEFAULT_SESSION_TTL)),
+	})
+}
+
+/// A new random token secret.
+pub(crate) fn generate_token() -> String {
+	format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// What the tokens table keeps instead of the secret. Tokens are random, so unlike
+/// passwords they need no salt.
+pub(crate) fn hash_token(token: &str) -> String {
+	hash_password("token", token)
+}
+
+pub(crate) fn default_permissions_for_roles(roles: &HashSet<String>) -> HashSet<PermissionGrant> {
 	let mut permissions = HashSet::new();
 	if roles.contains(OWNER_ROLE) {
 		permissions.insert(PermissionGrant::Owner);
@@ -438,18 +1428,18 @@ fn default_permissions_for_roles(roles: &HashSet<String>) -> HashSet<PermissionG
 	permissions
 }
 
-fn normalize_role(role: &str) -> String {
+pub(crate) fn normalize_role(role: &str) -> String {
 	role.trim().to_lowercase()
 }
 
-fn now_timestamp() -> u64 {
+pub(crate) fn now_timestamp() -> u64 {
 	SystemTime::now()
 		.duration_since(UNIX_EPOCH)
 		.map(|duration| duration.as_secs())
 		.unwrap_or(0)
 }
 
-fn hash_password(salt: &str, password: &str) -> String {
+pub(crate) fn hash_password(salt: &str, password: &str) -> String {
 	let mut hasher = Sha256::new();
 	hasher.update(salt.as_bytes());
 	hasher.update(password.as_bytes());
@@ -465,6 +1455,36 @@ fn verify_password(salt: &str, password: &str, expected_hash: &str) -> bool {
 	hash_password(salt, password) == expected_hash
 }
 
+/// Clean up a path sent by a peer before it touches the filesystem: `\` separators become
+/// `/`, and `.` and `..` components are collapsed. `..` never climbs above the root of an
+/// absolute path; leading `..` of a relative path are kept.
+pub fn normalize_request_path(path: &str) -> String {
+	let path = path.replace('\\', "/");
+	let absolute = path.starts_with('/');
+	let mut parts: Vec<&str> = Vec::new();
+	for part in path.split('/') {
+		match part {
+			"" | "." => {}
+			".." => match parts.last() {
+				Some(&"..") | None if !absolute => parts.push(".."),
+				Some(_) => {
+					parts.pop();
+				}
+				None => {}
+			},
+			part => parts.push(part),
+		}
+	}
+	let joined = parts.join("/");
+	if absolute {
+		format!("/{joined}")
+	} else if joined.is_empty() {
+		String::from(".")
+	} else {
+		joined
+	}
+}
+
 fn normalize_path(path: &str) -> String {
 	let trimmed = path.trim();
 	if trimmed.is_empty() {
@@ -517,6 +1537,32 @@ pub fn load_or_generate_keypair(path: &Path) -> Result<identity::Keypair> {
 	}
 }
 
+/// Replace the keypair at `path` with a new one, keeping the old file next to it as
+/// `<path>.bak-<unix seconds>`. Returns the old and new peer ids and the backup path.
+pub fn rotate_keypair(path: &Path) -> Result<(PeerId, PeerId, PathBuf)> {
+	let bytes = fs::read(path)
+		.with_context(|| format!("cannot read the identity at {}", path.display()))?;
+	let old = Keypair::from_protobuf_encoding(&bytes)?
+		.public()
+		.to_peer_id();
+	let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+	let mut backup = path.as_os_str().to_owned();
+	backup.push(format!(".bak-{stamp}"));
+	let backup = PathBuf::from(backup);
+	if backup.exists() {
+		anyhow::bail!("backup {} already exists; try again", backup.display());
+	}
+	fs::write(&backup, &bytes)?;
+	let key = Keypair::generate_ed25519();
+	// write next to the identity and rename, so a crash never leaves a half-written key
+	let mut staged = path.as_os_str().to_owned();
+	staged.push(".new");
+	let staged = PathBuf::from(staged);
+	fs::write(&staged, key.to_protobuf_encoding()?)?;
+	fs::rename(&staged, path)?;
+	Ok((old, key.public().to_peer_id(), backup))
+}
+
 fn libp2p_multiaddr(address: &Multiaddr, local_ip: IpAddr, peer_id: &PeerId) -> Multiaddr {
 	let mut reachable = Multiaddr::empty();
 	for protocol in address.iter() {
@@ -536,7 +1582,38 @@ fn libp2p_multiaddr(address: &Multiaddr, local_ip: IpAddr, peer_id: &PeerId) ->
 	reachable
 }
 
-pub fn build_swarm(id_keys: identity::Keypair, peer_id: PeerId) -> Result<Swarm<AgentBehaviour>> {
+/// The question the mDNS behaviour asks every cycle: who offers `_p2p._udp.local`.
+fn mdns_query() -> Vec<u8> {
+	let mut packet = Vec::with_capacity(33);
+	// id 0 as multicast queries use, standard query flags, one question and no records
+	for field in [0u16, 0, 1, 0, 0, 0] {
+		packet.extend_from_slice(&field.to_be_bytes());
+	}
+	for label in ["_p2p", "_udp", "local"] {
+		packet.push(label.len() as u8);
+		packet.extend_from_slice(label.as_bytes());
+	}
+	packet.push(0);
+	// type PTR, class IN
+	packet.extend_from_slice(&12u16.to_be_bytes());
+	packet.extend_from_slice(&1u16.to_be_bytes());
+	packet
+}
+
+/// Ask every peer on the local network to announce itself now. Peers multicast their
+/// answers, so the swarm's mDNS behaviour picks them up like those to its own queries.
+pub(crate) fn send_mdns_query() -> std::io::Result<()> {
+	let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+	socket.set_multicast_ttl_v4(255)?;
+	socket.send_to(&mdns_query(), MDNS_GROUP)?;
+	Ok(())
+}
+
+pub fn build_swarm(
+	id_keys: identity::Keypair,
+	peer_id: PeerId,
+	idle_timeout: Duration,
+) -> Result<Swarm<AgentBehaviour>> {
 	let swarm = SwarmBuilder::with_existing_identity(id_keys)
 		.with_tokio()
 		.with_tcp(
@@ -545,7 +1622,208 @@ pub fn build_swarm(id_keys: identity::Keypair, peer_id: PeerId) -> Result<Swarm<
 			yamux::Config::default,
 		)?
 		.with_behaviour(|_| AgentBehaviour::new(peer_id))?
-		.with_swarm_config(|cfg| cfg.with_idle_connection_timeout(Duration::from_secs(u64::MAX)))
+		.with_swarm_config(|cfg| cfg.with_idle_connection_timeout(idle_timeout))
 		.build();
 	Ok(swarm)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn request_paths_normalize_across_separators() {
+		assert_eq!(normalize_request_path(r"\home\me\docs"), "/home/me/docs");
+		assert_eq!(normalize_request_path("/home/me/docs/"), "/home/me/docs");
+		assert_eq!(
+			normalize_request_path(r"/home\me/./old\..\docs"),
+			"/home/me/docs"
+		);
+		assert_eq!(normalize_request_path(r"\..\etc"), "/etc");
+		assert_eq!(normalize_request_path("/"), "/");
+		assert_eq!(normalize_request_path(r"C:\Users\me"), "C:/Users/me");
+		assert_eq!(normalize_request_path(r"..\a\..\..\b"), "../../b");
+		assert_eq!(normalize_request_path(""), ".");
+
+		let req = PeerReq::ReadFiles {
+			requests: vec![(String::from(r"\share\a.txt"), 0, None)],
+		}
+		.with_normalized_paths();
+		let PeerReq::ReadFiles { requests } = req else {
+			panic!("request kind changed");
+		};
+		assert_eq!(requests[0].0, "/share/a.txt");
+	}
+
+	#[test]
+	fn mdns_query_asks_for_the_p2p_service() {
+		let packet = mdns_query();
+		assert_eq!(packet.len(), 33);
+		assert_eq!(&packet[4..6], &[0, 1]);
+		assert_eq!(
+			&packet[12..],
+			b"\x04_p2p\x04_udp\x05local\x00\x00\x0c\x00\x01"
+		);
+	}
+
+	#[test]
+	fn parent_dir_components_are_refused() {
+		let list = |path: &str| PeerReq::ListDir {
+			path: path.to_string(),
+		};
+		let write = |path: &str| PeerReq::WriteFile {
+			path: path.to_string(),
+			offset: 0,
+			data: b"owned".to_vec(),
+			mode: WriteMode::InPlace,
+			times: None,
+		};
+		assert!(list("../../etc/passwd").has_parent_dir());
+		assert!(list(r"C:\share\..\..\Windows").has_parent_dir());
+		// as the name of a file that does not exist yet
+		assert!(write("/srv/share/../../etc/passwd").has_parent_dir());
+		assert!(write("/srv/share/..").has_parent_dir());
+		let batch = PeerReq::ReadFiles {
+			requests: vec![
+				(String::from("/srv/share/a.txt"), 0, None),
+				(String::from("/srv/share/../../etc/passwd"), 0, None),
+			],
+		};
+		assert!(batch.has_parent_dir());
+
+		assert!(!list("/srv/share/..hidden/a..b").has_parent_dir());
+		assert!(!write("/srv/share/upload.bin").has_parent_dir());
+	}
+
+	#[test]
+	fn revoked_and_expired_tokens_are_refused() {
+		let token = |revoked, expires_at| TokenInfo {
+			id: String::from("t1"),
+			username: String::from("alice"),
+			label: None,
+			permissions: vec![PermissionGrant::Viewer],
+			expires_at,
+			revoked,
+			issued_at: 100,
+			issued_by: String::from("owner"),
+		};
+		assert_eq!(session_for_token(None, 200).unwrap_err(), "Unknown token");
+		assert_eq!(
+			session_for_token(Some(token(true, None)), 200).unwrap_err(),
+			"Token was revoked"
+		);
+		assert_eq!(
+			session_for_token(Some(token(false, Some(200))), 200).unwrap_err(),
+			"Token has expired"
+		);
+		let session = session_for_token(Some(token(false, Some(300))), 200).unwrap();
+		assert_eq!(session.username, "alice");
+		assert_eq!(session.permissions, [PermissionGrant::Viewer]);
+		assert_eq!(hash_token("abc"), hash_token("abc"));
+		assert_ne!(hash_token("abc"), "abc");
+	}
+
+	#[test]
+	fn password_sign_in_does_not_reveal_which_users_exist() {
+		let user = User::new(
+			String::from("bob"),
+			"hunter2",
+			vec![String::from(" Viewer ")],
+			Vec::new(),
+		);
+		assert_ne!(user.password_hash, "hunter2");
+		assert_eq!(user.roles, ["viewer"]);
+		let wrong_password = session_for_credentials(Some(&user), "hunter3", 100).unwrap_err();
+		let unknown_user = session_for_credentials(None, "hunter2", 100).unwrap_err();
+		assert_eq!(wrong_password, unknown_user);
+		let session = session_for_credentials(Some(&user), "hunter2", 100).unwrap();
+		assert_eq!(session.username, "bob");
+		assert!(session.permissions.contains(&PermissionGrant::Viewer));
+		assert_eq!(session.expires_at, Some(100 + DEFAULT_SESSION_TTL));
+	}
+
+	#[test]
+	fn malformed_requests_fail_validation() {
+		let list = |path: String| PeerReq::ListDir { path };
+		let write = |offset: u64, len: u64| PeerReq::WriteFile {
+			path: String::from("/srv/share/upload.bin"),
+			offset,
+			data: vec![0; len as usize],
+			mode: WriteMode::InPlace,
+			times: None,
+		};
+		let read = |offset: u64, length: Option<u64>| PeerReq::ReadFile {
+			path: String::from("/srv/share/big.iso"),
+			offset,
+			length,
+		};
+		assert!(list(String::new()).validate().is_err());
+		assert!(list("/a".repeat(MAX_PATH_BYTES)).validate().is_err());
+		assert!(list(String::from("/srv/share\0/etc")).validate().is_err());
+		assert!(write(0, MAX_FILE_CHUNK + 1).validate().is_err());
+		assert!(write(MAX_FILE_OFFSET, 1).validate().is_err());
+		assert!(read(u64::MAX, None).validate().is_err());
+
+		assert!(list(String::from("/srv/share")).validate().is_ok());
+		assert!(write(0, MAX_FILE_CHUNK).validate().is_ok());
+		let clamped = |length| match read(0, length).with_clamped_lengths() {
+			PeerReq::ReadFile { length, .. } => length,
+			_ => unreachable!(),
+		};
+		assert_eq!(clamped(None), Some(MAX_FILE_CHUNK));
+		assert_eq!(clamped(Some(u64::MAX)), Some(MAX_FILE_CHUNK));
+		assert_eq!(clamped(Some(10)), Some(10));
+	}
+
+	#[test]
+	fn natural_order_compares_numbers_and_ignores_accents() {
+		let mut names = vec![
+			"file10.txt",
+			"File2.txt",
+			"file1.txt",
+			"zebra",
+			"Écran",
+			"eclair",
+			"ecran",
+			"file02.txt",
+		];
+		names.sort_by(|a, b| natural_cmp(a, b));
+		assert_eq!(
+			names,
+			vec![
+				"eclair",
+				"ecran",
+				"Écran",
+				"file1.txt",
+				"File2.txt",
+				"file02.txt",
+				"file10.txt",
+				"zebra",
+			]
+		);
+	}
+
+	#[test]
+	fn rotated_keypair_keeps_a_backup_of_the_old_one() {
+		let dir = std::env::temp_dir().join(format!("puppypeer-rotate-{}", std::process::id()));
+		let path = dir.join("peer_keypair.bin");
+		let before = load_or_generate_keypair(&path)
+			.unwrap()
+			.public()
+			.to_peer_id();
+		let (old, new, backup) = rotate_keypair(&path).unwrap();
+		assert_eq!(old, before);
+		assert_ne!(new, old);
+		let reloaded = load_or_generate_keypair(&path)
+			.unwrap()
+			.public()
+			.to_peer_id();
+		assert_eq!(reloaded, new);
+		let restored = load_or_generate_keypair(&backup)
+			.unwrap()
+			.public()
+			.to_peer_id();
+		assert_eq!(restored, old);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -1,12 +1,15 @@
 use chrono::{DateTime, Utc};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
-use rusqlite::{Connection, ToSql};
+use rusqlite::{Connection, ToSql, params};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::canonicalize;
 use std::io::{self, Read, Seek};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use walkdir::WalkDir;
 
 pub type FileHash = [u8; 32];
@@ -35,28 +38,60 @@ impl PartialEq for FileLocation {
 	}
 }
 
+/// Incremental SHA-256 over whichever backend is enabled.
 #[cfg(feature = "ring")]
-fn sha256_hash<R: Read>(mut reader: R) -> io::Result<[u8; 32]> {
-	let mut context = ring::digest::Context::new(&ring::digest::SHA256);
-	let mut buffer = [0u8; 4096];
-	loop {
-		let count = reader.read(&mut buffer)?;
-		if count == 0 {
-			break;
-		}
-		context.update(&buffer[..count]);
+pub struct Sha256Hasher(ring::digest::Context);
+
+#[cfg(feature = "ring")]
+impl Sha256Hasher {
+	pub fn new() -> Self {
+		Self(ring::digest::Context::new(&ring::digest::SHA256))
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.0.update(data);
+	}
+
+	pub fn finish(self) -> [u8; 32] {
+		// Finalize the hash and copy it into a fixed-size array.
+		let digest: ring::digest::Digest = self.0.finish();
+		let mut hash = [0u8; 32];
+		hash.copy_from_slice(digest.as_ref());
+		hash
 	}
-	// Finalize the hash and copy it into a fixed-size array.
-	let digest: ring::digest::Digest = context.finish();
-	let mut hash = [0u8; 32];
-	hash.copy_from_slice(digest.as_ref());
-	Ok(hash)
 }
 
+/// Incremental SHA-256 over whichever backend is enabled.
 #[cfg(all(not(feature = "ring"), feature = "sha2"))]
-fn sha256_hash<R: Read>(mut reader: R) -> io::Result<[u8; 32]> {
-	use sha2::Digest;
-	let mut hasher = sha2::Sha256::new();
+pub struct Sha256Hasher(sha2::Sha256);
+
+#[cfg(all(not(feature = "ring"), feature = "sha2"))]
+impl Sha256Hasher {
+	pub fn new() -> Self {
+		use sha2::Digest;
+		Self(sha2::Sha256::new())
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		use sha2::Digest;
+		self.0.update(data);
+	}
+
+	pub fn finish(self) -> [u8; 32] {
+		use sha2::Digest;
+		self.0.finalize().into()
+	}
+}
+
+impl Default for Sha256Hasher {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// SHA-256 of everything `reader` yields, the hash the index and `FindHashes` use.
+pub fn sha256_hash<R: Read>(mut reader: R) -> io::Result<[u8; 32]> {
+	let mut hasher = Sha256Hasher::new();
 	let mut buffer = [0u8; 4096];
 	loop {
 		let count = reader.read(&mut buffer)?;
@@ -65,18 +100,77 @@ fn sha256_hash<R: Read>(mut reader: R) -> io::Result<[u8; 32]> {
 		}
 		hasher.update(&buffer[..count]);
 	}
-	Ok(hasher.finalize().into())
+	Ok(hasher.finish())
 }
 
 fn to_datetime(m: std::io::Result<std::time::SystemTime>) -> Option<chrono::DateTime<chrono::Utc>> {
 	m.ok().map(|t| chrono::DateTime::from(t))
 }
 
-fn handle_path<P: AsRef<Path>>(path: P) -> FileLocation {
-	let full_path = canonicalize(path.as_ref()).unwrap();
+/// Why a scan, or one file in it, failed.
+#[derive(Debug)]
+pub enum ScanError {
+	/// Reading `path` from disk failed. Only returned for the scanned folder itself; files
+	/// that cannot be read are collected in `ScanResult::errors`.
+	Io { path: PathBuf, source: io::Error },
+	/// The database rejected a query, for example because another writer holds the lock.
+	Db(rusqlite::Error),
+	/// An include or exclude glob does not parse.
+	Pattern(globset::Error),
+	/// Cancelled before any file was processed, so nothing was written.
+	Canceled,
+}
+
+impl std::fmt::Display for ScanError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ScanError::Io { path, source } => {
+				write!(f, "failed to read {}: {}", path.display(), source)
+			}
+			ScanError::Db(err) => write!(f, "database error: {}", err),
+			ScanError::Pattern(err) => write!(f, "invalid scan pattern: {}", err),
+			ScanError::Canceled => write!(f, "scan cancelled"),
+		}
+	}
+}
+
+impl std::error::Error for ScanError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ScanError::Io { source, .. } => Some(source),
+			ScanError::Db(err) => Some(err),
+			ScanError::Pattern(err) => Some(err),
+			ScanError::Canceled => None,
+		}
+	}
+}
+
+impl From<rusqlite::Error> for ScanError {
+	fn from(err: rusqlite::Error) -> Self {
+		ScanError::Db(err)
+	}
+}
+
+impl From<globset::Error> for ScanError {
+	fn from(err: globset::Error) -> Self {
+		ScanError::Pattern(err)
+	}
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> ScanError + '_ {
+	move |source| ScanError::Io {
+		path: path.to_path_buf(),
+		source,
+	}
+}
+
+fn handle_path<P: AsRef<Path>>(path: P) -> Result<FileLocation, ScanError> {
+	// walked paths are already absolute; symlinks are kept as walked so the file stays
+	// under the scanned folder
+	let full_path = path.as_ref().to_path_buf();
 	log::info!("processing {}", full_path.display());
-	let mut file = std::fs::File::open(path).unwrap();
-	let m = file.metadata().unwrap();
+	let mut file = std::fs::File::open(&full_path).map_err(io_error(&full_path))?;
+	let m = file.metadata().map_err(io_error(&full_path))?;
 	let created_at = to_datetime(m.created());
 	let modified_at = to_datetime(m.modified());
 	let accessed_at = to_datetime(m.accessed());
@@ -90,9 +184,10 @@ fn handle_path<P: AsRef<Path>>(path: P) -> FileLocation {
 		},
 		Err(_) => None,
 	};
-	file.seek(std::io::SeekFrom::Start(0)).unwrap();
-	let hash = sha256_hash(file).unwrap();
-	FileLocation {
+	file.seek(std::io::SeekFrom::Start(0))
+		.map_err(io_error(&full_path))?;
+	let hash = sha256_hash(file).map_err(io_error(&full_path))?;
+	Ok(FileLocation {
 		path: full_path,
 		hash: Some(hash),
 		size: m.len(),
@@ -101,44 +196,207 @@ fn handle_path<P: AsRef<Path>>(path: P) -> FileLocation {
 		created_at,
 		modified_at,
 		accessed_at,
-	}
+	})
 }
 
 const INSERT_FILE_LOCATION: &str = "INSERT INTO file_locations (node_id, path, hash, size, timestamp, created_at, modified_at, accessed_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
 const UPDATE_FILE_LOCATION: &str = "UPDATE file_locations SET hash = ?, size = ?, timestamp = ?, created_at = ?, modified_at = ?, accessed_at = ? WHERE node_id = ? and path = ?";
 const DELETE_FILE_LOCATION: &str = "DELETE FROM file_locations WHERE node_id = ? and path = ?";
-const UPSERT_FILE_ENTRY: &str = "INSERT INTO file_entries (hash, size, mime_type, first_datetime, latest_datetime) VALUES (?, ?, ?, ?, ?) ON CONFLICT(hash) DO UPDATE SET latest_datetime = excluded.latest_datetime";
+const UPSERT_FILE_ENTRY: &str = "INSERT INTO file_entries (hash, size, mime_type, first_datetime, latest_datetime) VALUES (?, ?, ?, ?, ?) ON CONFLICT(hash) DO UPDATE SET latest_datetime = excluded.latest_datetime, mime_type = COALESCE(excluded.mime_type, file_entries.mime_type)";
+
+/// Scanned files written per transaction, so readers are not blocked for the whole scan.
+const SCAN_BATCH_FILES: usize = 500;
+/// A batch is also committed once it has been open this long.
+const SCAN_BATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Which files under the scanned folder are indexed.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+	/// When not empty, only files matching one of these globs are indexed.
+	pub include: Vec<String>,
+	/// Files and directories matching these globs are skipped; directories are not entered.
+	pub exclude: Vec<String>,
+	/// Walk into symlinked directories and index symlinked files. Every target is visited
+	/// once, so symlink loops end and a file linked twice is indexed under its first path.
+	pub follow_symlinks: bool,
+}
+
+fn build_globset(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+	let mut builder = GlobSetBuilder::new();
+	for pattern in patterns {
+		builder.add(Glob::new(pattern)?);
+	}
+	builder.build()
+}
+
+/// `ScanOptions` compiled for one scan. Globs are matched against paths relative to the
+/// scanned folder.
+struct PathFilter {
+	root: PathBuf,
+	include: Option<GlobSet>,
+	exclude: GlobSet,
+}
+
+impl PathFilter {
+	fn new(root: &Path, options: &ScanOptions) -> Result<Self, globset::Error> {
+		let include = if options.include.is_empty() {
+			None
+		} else {
+			Some(build_globset(&options.include)?)
+		};
+		Ok(Self {
+			root: root.to_path_buf(),
+			include,
+			exclude: build_globset(&options.exclude)?,
+		})
+	}
+
+	fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+		path.strip_prefix(&self.root).unwrap_or(path)
+	}
+
+	/// Whether the walk should skip `path`. A directory is also tested with a trailing
+	/// separator so `**/node_modules/**` prunes `node_modules` itself.
+	fn skips(&self, path: &Path, is_dir: bool) -> bool {
+		let relative = self.relative(path);
+		if relative.as_os_str().is_empty() {
+			return false;
+		}
+		self.exclude.is_match(relative) || (is_dir && self.exclude.is_match(relative.join("")))
+	}
+
+	fn includes(&self, path: &Path) -> bool {
+		self.include
+			.as_ref()
+			.is_none_or(|include| include.is_match(self.relative(path)))
+	}
+}
+
+/// Minimum time between two progress reports while hashing.
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Running count reported by `scan` while it works through a folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanProgress {
+	pub files_scanned: u64,
+	pub total_files: u64,
+	pub current_path: PathBuf,
+}
 
 #[derive(Debug)]
 pub struct ScanResult {
 	pub updated_count: u64,
 	pub inserted_count: u64,
 	pub removed_count: u64,
+	/// Transactions committed while writing results.
+	pub batches_committed: u64,
+	/// The scan was stopped early; only the files hashed before that were written and
+	/// nothing was removed.
+	pub cancelled: bool,
+	/// Files that vanished or could not be read, as `ScanError::Io`. They were skipped and
+	/// their rows, if any, left as they were.
+	pub errors: Vec<ScanError>,
 	pub duration: std::time::Duration,
 }
 
+/// Write one scanned file, returning whether it was inserted (`Some(true)`), updated
+/// (`Some(false)`) or left alone.
+fn write_location(
+	conn: &Connection,
+	node_id: &[u8],
+	fl: &FileLocation,
+	prev: Option<&FileLocation>,
+) -> rusqlite::Result<Option<bool>> {
+	let change = match prev {
+		// completely identical (including timestamp) → skip
+		Some(prev) if fl == prev => None,
+		Some(_) => {
+			conn.prepare_cached(UPDATE_FILE_LOCATION)?.execute(params![
+				fl.hash,
+				fl.size,
+				fl.timestamp,
+				fl.created_at,
+				fl.modified_at,
+				fl.accessed_at,
+				node_id,
+				fl.path.to_string_lossy(),
+			])?;
+			Some(false)
+		}
+		None => {
+			conn.prepare_cached(INSERT_FILE_LOCATION)?.execute(params![
+				node_id,
+				fl.path.to_string_lossy(),
+				fl.hash,
+				fl.size,
+				fl.timestamp,
+				fl.created_at,
+				fl.modified_at,
+				fl.accessed_at,
+			])?;
+			Some(true)
+		}
+	};
+
+	let timestamps: Vec<_> = [fl.created_at, fl.modified_at, fl.accessed_at]
+		.iter()
+		.copied()
+		.flatten()
+		.collect();
+	let first_dt = timestamps.iter().min().copied();
+	let latest_dt = timestamps.iter().max().copied();
+	conn.prepare_cached(UPSERT_FILE_ENTRY)?.execute(params![
+		fl.hash,
+		fl.size,
+		fl.mime_type,
+		first_dt,
+		latest_dt,
+	])?;
+	Ok(change)
+}
+
+/// Scan `path` into the database, calling `on_progress` every `PROGRESS_INTERVAL` with
+/// the number of files processed so far. The folder is walked before hashing starts, so
+/// `total_files` is known from the first report.
+///
+/// Setting `cancel` stops the scan between files. The files processed so far are still
+/// committed and the result has `cancelled` set. A scan cancelled while still walking
+/// the folder fails with `ScanError::Canceled`.
+///
+/// Files that vanish or cannot be read while scanning are skipped and listed in
+/// `ScanResult::errors`; only the folder itself, the database, a pattern or cancelling
+/// fail the whole scan.
 pub fn scan<P: AsRef<Path>>(
 	node_id: &[u8],
 	path: P,
 	mut conn: Connection,
-) -> Result<ScanResult, String> {
+	options: &ScanOptions,
+	on_progress: impl Fn(ScanProgress) + Sync,
+	cancel: &AtomicBool,
+) -> Result<ScanResult, ScanError> {
 	let timer = std::time::Instant::now();
 	let mut updated_count = 0;
 	let mut inserted_count = 0;
 	let mut removed_count = 0;
+	let mut batches_committed = 0;
+	let mut errors = Vec::new();
+	let cancelled;
 	let path = path.as_ref().to_path_buf();
-	let absolute_path = canonicalize(&path).unwrap();
-	let tx = conn.transaction().unwrap();
+	let absolute_path = canonicalize(&path).map_err(io_error(&path))?;
+	// WAL lets readers (the GUI) keep querying while batches are written
+	if let Err(err) = conn.pragma_update(None, "journal_mode", "WAL") {
+		log::warn!("failed to enable WAL mode: {err}");
+	}
 
 	{
 		// load all existing file_locations into a map
-		let mut file_locations_stmt = tx
+		let mut file_locations_stmt = conn
 			.prepare(
-				"SELECT path, hash, size, timestamp, created_at, modified_at, accessed_at \
-			FROM file_locations \
-			WHERE path LIKE ?",
-			)
-			.map_err(|e| format!("error preparing statement: {:?}", e))?;
+				"SELECT l.path, l.hash, l.size, l.timestamp, l.created_at, l.modified_at, l.accessed_at, e.mime_type \
+			FROM file_locations l \
+			LEFT JOIN file_entries e ON e.hash = l.hash \
+			WHERE l.path LIKE ?",
+		)?;
 		let existing: HashMap<PathBuf, FileLocation> = file_locations_stmt
 			.query_map(
 				[&(absolute_path.to_string_lossy().to_string() + "%")],
@@ -147,39 +405,77 @@ pub fn scan<P: AsRef<Path>>(
 						path: PathBuf::from(row.get::<_, String>(0)?),
 						hash: row.get(1)?,
 						size: row.get(2)?,
-						mime_type: None, // we don’t need mime here
+						// kept per hash in file_entries; reused for unchanged files
+						mime_type: row.get(7)?,
 						timestamp: row.get(3)?,
 						created_at: row.get(4)?,
 						modified_at: row.get(5)?,
 						accessed_at: row.get(6)?,
 					})
 				},
-			)
-			.map_err(|e| format!("error querying file locations: {:?}", e))?
+			)?
 			.filter_map(Result::ok)
 			.map(|fl| (fl.path.clone(), fl))
 			.collect();
 
-		// scan disk
+		// scan disk, pruning excluded directories instead of walking and filtering them
+		let filter = PathFilter::new(&absolute_path, options)?;
+		let mut visited = HashSet::new();
 		let entries = WalkDir::new(&absolute_path)
+			.follow_links(options.follow_symlinks)
+			// stable order, so the same path wins for a target reachable through links
+			.sort_by_file_name()
 			.into_iter()
+			.filter_entry(|e| {
+				!cancel.load(Ordering::Relaxed)
+					&& !filter.skips(e.path(), e.file_type().is_dir())
+					&& (!options.follow_symlinks
+						|| canonicalize(e.path()).is_ok_and(|real| visited.insert(real)))
+			})
 			.filter_map(|e| e.ok())
-			.filter(|e| e.file_type().is_file())
+			.filter(|e| e.file_type().is_file() && filter.includes(e.path()))
 			.collect::<Vec<_>>();
+		if cancel.load(Ordering::Relaxed) {
+			return Err(ScanError::Canceled);
+		}
+		let total_files = entries.len() as u64;
+		on_progress(ScanProgress {
+			files_scanned: 0,
+			total_files,
+			current_path: absolute_path.clone(),
+		});
+		let files_scanned = AtomicU64::new(0);
+		let last_report = Mutex::new(std::time::Instant::now());
+		let report = |pbuf: &Path| {
+			let done = files_scanned.fetch_add(1, Ordering::Relaxed) + 1;
+			// try_lock keeps hashing threads from queueing up behind a slow callback
+			if let Ok(mut last) = last_report.try_lock()
+				&& last.elapsed() >= PROGRESS_INTERVAL
+			{
+				*last = std::time::Instant::now();
+				on_progress(ScanProgress {
+					files_scanned: done,
+					total_files,
+					current_path: pbuf.to_path_buf(),
+				});
+			}
+		};
 
 		#[cfg(feature = "rayon")]
-		let mapped = entries
-			.par_iter()
-			.map(|entry| (entry.path().to_path_buf(), entry.clone()));
+		let mapped = entries.par_iter().map(|entry| entry.path().to_path_buf());
 		#[cfg(not(feature = "rayon"))]
-		let mapped = entries
-			.iter()
-			.map(|entry| (entry.path().to_path_buf(), entry.clone()));
+		let mapped = entries.iter().map(|entry| entry.path().to_path_buf());
 
-		let mut scanned: HashMap<PathBuf, FileLocation> = mapped
-			.map(|(pbuf, entry)| {
+		let results: Vec<Result<(PathBuf, FileLocation), ScanError>> = mapped
+			.filter_map(|pbuf| {
+				if cancel.load(Ordering::Relaxed) {
+					return None;
+				}
 				// 1) quick metadata check
-				let meta = std::fs::metadata(&pbuf).unwrap();
+				let meta = match std::fs::metadata(&pbuf) {
+					Ok(meta) => meta,
+					Err(err) => return Some(Err(io_error(&pbuf)(err))),
+				};
 				let created_at = to_datetime(meta.created());
 				let modified_at = to_datetime(meta.modified());
 				let accessed_at = to_datetime(meta.accessed());
@@ -192,7 +488,8 @@ pub fn scan<P: AsRef<Path>>(
 						&& prev.accessed_at == accessed_at
 					{
 						// unchanged → reuse previous hash & mime; only update timestamp
-						return (
+						report(&pbuf);
+						return Some(Ok((
 							pbuf.clone(),
 							FileLocation {
 								path: pbuf.clone(),
@@ -204,95 +501,249 @@ pub fn scan<P: AsRef<Path>>(
 								modified_at,
 								accessed_at,
 							},
-						);
+						)));
 					}
 				}
 
 				// metadata changed (or new file) → do full read+hash
 				let fl = handle_path(&pbuf);
-				(pbuf.clone(), fl)
+				report(&pbuf);
+				Some(fl.map(|fl| (pbuf.clone(), fl)))
 			})
 			.collect();
+		// one unreadable file should not cost the rest of the index
+		let mut scanned: HashMap<PathBuf, FileLocation> = HashMap::new();
+		for result in results {
+			match result {
+				Ok((path, fl)) => {
+					scanned.insert(path, fl);
+				}
+				Err(err) => {
+					log::warn!("skipping {}", err);
+					errors.push(err);
+				}
+			}
+		}
+		cancelled = cancel.load(Ordering::Relaxed);
+		on_progress(ScanProgress {
+			files_scanned: scanned.len() as u64,
+			total_files,
+			current_path: absolute_path.clone(),
+		});
+
+		drop(file_locations_stmt);
+
+		// write results in batches, committing every SCAN_BATCH_FILES files or
+		// SCAN_BATCH_INTERVAL so each batch stays atomic without one long write lock
+		let mut tx = conn.transaction()?;
+		let mut pending = 0;
+		let mut opened_at = std::time::Instant::now();
 
-		// remove deleted files
-		let mut delete_stmt = tx.prepare(DELETE_FILE_LOCATION).unwrap();
+		// remove deleted files; a cancelled scan has not seen every file, so keep them all
+		let failed: HashSet<&Path> = errors
+			.iter()
+			.filter_map(|err| match err {
+				ScanError::Io { path, .. } => Some(path.as_path()),
+				_ => None,
+			})
+			.collect();
 		for old in existing.keys() {
-			if !scanned.contains_key(old) {
-				delete_stmt
-					.execute(&[&node_id as &dyn ToSql, &old.to_string_lossy() as &dyn ToSql])
-					.unwrap();
+			if !cancelled && !scanned.contains_key(old) && !failed.contains(old.as_path()) {
+				tx.prepare_cached(DELETE_FILE_LOCATION)?
+					.execute([&node_id as &dyn ToSql, &old.to_string_lossy() as &dyn ToSql])?;
 				removed_count += 1;
 			}
 		}
 
-		// insert or update each scanned file
-		let mut insert_stmt = tx.prepare(INSERT_FILE_LOCATION).unwrap();
-		let mut update_stmt = tx.prepare(UPDATE_FILE_LOCATION).unwrap();
+		// insert or update each scanned file and upsert its file_entries row
 		for (path, fl) in scanned.iter() {
-			if let Some(prev) = existing.get(path) {
-				if fl == prev {
-					// completely identical (including timestamp) → skip
-					continue;
-				}
-				// else: update hash/size/timestamps
-				update_stmt
-					.execute(&[
-						&fl.hash as &dyn ToSql,
-						&fl.size as &dyn ToSql,
-						&fl.timestamp as &dyn ToSql,
-						&fl.created_at as &dyn ToSql,
-						&fl.modified_at as &dyn ToSql,
-						&fl.accessed_at as &dyn ToSql,
-						&node_id as &dyn ToSql,
-						&fl.path.to_string_lossy() as &dyn ToSql,
-					])
-					.unwrap();
-				updated_count += 1;
-			} else {
-				// new file
-				insert_stmt
-					.execute(&[
-						&node_id as &dyn ToSql,
-						&fl.path.to_string_lossy() as &dyn ToSql,
-						&fl.hash as &dyn ToSql,
-						&fl.size as &dyn ToSql,
-						&fl.timestamp as &dyn ToSql,
-						&fl.created_at as &dyn ToSql,
-						&fl.modified_at as &dyn ToSql,
-						&fl.accessed_at as &dyn ToSql,
-					])
-					.unwrap();
-				inserted_count += 1;
+			match write_location(&tx, node_id, fl, existing.get(path))? {
+				Some(true) => inserted_count += 1,
+				Some(false) => updated_count += 1,
+				None => {}
+			}
+			pending += 1;
+			if pending >= SCAN_BATCH_FILES || opened_at.elapsed() >= SCAN_BATCH_INTERVAL {
+				tx.commit()?;
+				batches_committed += 1;
+				tx = conn.transaction()?;
+				pending = 0;
+				opened_at = std::time::Instant::now();
 			}
 		}
-
-		// upsert into file_entries as before…
-		let mut upsert_stmt = tx.prepare(UPSERT_FILE_ENTRY).unwrap();
-		for fl in scanned.values() {
-			let timestamps: Vec<_> = [fl.created_at, fl.modified_at, fl.accessed_at]
-				.iter()
-				.copied()
-				.flatten()
-				.collect();
-			let first_dt = timestamps.iter().min().copied();
-			let latest_dt = timestamps.iter().max().copied();
-			upsert_stmt
-				.execute(&[
-					&fl.hash as &dyn ToSql,
-					&fl.size as &dyn ToSql,
-					&fl.mime_type as &dyn ToSql,
-					&first_dt as &dyn ToSql,
-					&latest_dt as &dyn ToSql,
-				])
-				.unwrap();
-		}
+		tx.commit()?;
+		batches_committed += 1;
 	}
 
-	tx.commit().unwrap();
 	Ok(ScanResult {
 		updated_count,
 		inserted_count,
 		removed_count,
+		batches_committed,
+		cancelled,
+		errors,
 		duration: timer.elapsed(),
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::db::{get_file_location, run_migrations};
+
+	#[test]
+	fn rescan_keeps_mime_of_unchanged_files() {
+		let dir = std::env::temp_dir().join(format!("puppypeer-scan-mime-{}", std::process::id()));
+		let files = dir.join("files");
+		std::fs::create_dir_all(&files).unwrap();
+		let db_path = dir.join("test.db");
+		std::fs::write(files.join("notes.txt"), b"plain text notes").unwrap();
+		let open = || {
+			let mut conn = Connection::open(&db_path).unwrap();
+			run_migrations(&mut conn).unwrap();
+			conn
+		};
+		let node_id = [1u8; 16];
+
+		let keep_going = AtomicBool::new(false);
+		let options = ScanOptions::default();
+		scan(&node_id, &files, open(), &options, |_| {}, &keep_going).unwrap();
+		let reports = Mutex::new(Vec::new());
+		let second = scan(
+			&node_id,
+			&files,
+			open(),
+			&options,
+			|progress| reports.lock().unwrap().push(progress),
+			&keep_going,
+		)
+		.unwrap();
+		assert_eq!(second.inserted_count, 0);
+		assert!(second.batches_committed >= 1);
+		let reports = reports.into_inner().unwrap();
+		assert_eq!(reports.first().unwrap().files_scanned, 0);
+		let last = reports.last().unwrap();
+		assert_eq!(last.files_scanned, last.total_files);
+
+		// a scan cancelled before the first file keeps what is already indexed
+		let cancelled = scan(
+			&node_id,
+			&files,
+			open(),
+			&options,
+			|_| {},
+			&AtomicBool::new(true),
+		);
+		assert!(matches!(cancelled, Err(ScanError::Canceled)));
+
+		let conn = open();
+		let hash = sha256_hash(&b"plain text notes"[..]).unwrap();
+		let location = get_file_location(&conn, &node_id, &hash).unwrap().unwrap();
+		assert_eq!(location.mime_type.as_deref(), Some("text/plain"));
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn excluded_directory_is_not_descended_into() {
+		let dir =
+			std::env::temp_dir().join(format!("puppypeer-scan-exclude-{}", std::process::id()));
+		let files = dir.join("files");
+		let deps = files.join("node_modules").join("left-pad");
+		std::fs::create_dir_all(&deps).unwrap();
+		std::fs::write(files.join("keep.txt"), b"keep").unwrap();
+		std::fs::write(deps.join("index.js"), b"skip").unwrap();
+		let mut conn = Connection::open(dir.join("test.db")).unwrap();
+		run_migrations(&mut conn).unwrap();
+		let options = ScanOptions {
+			exclude: vec![String::from("**/node_modules/**")],
+			..Default::default()
+		};
+
+		let root = canonicalize(&files).unwrap();
+		let filter = PathFilter::new(&root, &options).unwrap();
+		assert!(filter.skips(&root.join("node_modules"), true));
+		assert!(!filter.skips(&root.join("keep.txt"), false));
+
+		let total = AtomicU64::new(0);
+		let result = scan(
+			&[2u8; 16],
+			&files,
+			conn,
+			&options,
+			|progress| total.store(progress.total_files, Ordering::Relaxed),
+			&AtomicBool::new(false),
+		)
+		.unwrap();
+		assert_eq!(result.inserted_count, 1);
+		assert_eq!(total.into_inner(), 1);
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn vanished_file_is_skipped_without_failing_the_scan() {
+		let dir =
+			std::env::temp_dir().join(format!("puppypeer-scan-vanished-{}", std::process::id()));
+		let files = dir.join("files");
+		std::fs::create_dir_all(&files).unwrap();
+		std::fs::write(files.join("stays.txt"), b"stays").unwrap();
+		std::fs::write(files.join("goes.txt"), b"goes").unwrap();
+		let mut conn = Connection::open(dir.join("test.db")).unwrap();
+		run_migrations(&mut conn).unwrap();
+
+		// the first report comes after the walk, before any file is read
+		let result = scan(
+			&[3u8; 16],
+			&files,
+			conn,
+			&ScanOptions::default(),
+			|progress| {
+				if progress.files_scanned == 0 {
+					let _ = std::fs::remove_file(files.join("goes.txt"));
+				}
+			},
+			&AtomicBool::new(false),
+		)
+		.unwrap();
+		assert_eq!(result.inserted_count, 1);
+		assert_eq!(result.errors.len(), 1);
+		assert!(matches!(
+			&result.errors[0],
+			ScanError::Io { path, .. } if path.ends_with("goes.txt")
+		));
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn symlink_loop_is_followed_once() {
+		let dir =
+			std::env::temp_dir().join(format!("puppypeer-scan-symlink-{}", std::process::id()));
+		let files = dir.join("files");
+		std::fs::create_dir_all(files.join("media")).unwrap();
+		std::fs::write(files.join("media").join("song.mp3"), b"la la").unwrap();
+		std::os::unix::fs::symlink(&files, files.join("media").join("loop")).unwrap();
+		std::os::unix::fs::symlink(files.join("media"), files.join("library")).unwrap();
+		let mut conn = Connection::open(dir.join("test.db")).unwrap();
+		run_migrations(&mut conn).unwrap();
+		let options = ScanOptions {
+			follow_symlinks: true,
+			..Default::default()
+		};
+
+		let result = scan(
+			&[3u8; 16],
+			&files,
+			conn,
+			&options,
+			|_| {},
+			&AtomicBool::new(false),
+		)
+		.unwrap();
+		assert_eq!(result.inserted_count, 1);
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+}
@@ -1,23 +1,98 @@
+use crate::p2p::{
+	PermissionGrant, SessionInfo, default_permissions_for_roles, hash_password, normalize_role,
+	now_timestamp,
+};
+use crate::types::{PeerMessage, PeerSummary};
 use anyhow::bail;
+use chrono::{DateTime, Utc};
+use libp2p::core::ConnectedPoint;
+use libp2p::multiaddr::Protocol;
 use libp2p::{Multiaddr, PeerId, swarm::ConnectionId};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 
 pub const FLAG_READ: u8 = 0x01;
 pub const FLAG_WRITE: u8 = 0x02;
 pub const FLAG_EXECUTE: u8 = 0x04;
 pub const FLAG_SEARCH: u8 = 0x08;
+/// Received notes kept in `State::messages`; older ones are dropped.
+pub const MESSAGES_KEPT: usize = 100;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct FolderRule {
 	path: PathBuf,
 	flags: u8,
+	#[serde(default)]
+	max_bytes: Option<u64>,
+	/// `path` is a single shared file rather than a folder.
+	#[serde(default)]
+	file: bool,
+	/// Unix mode given to files peers create here, instead of the process umask.
+	#[serde(default)]
+	file_mode: Option<u32>,
+	/// Unix mode given to directories peers create here.
+	#[serde(default)]
+	dir_mode: Option<u32>,
 }
 
 impl FolderRule {
 	pub fn new(path: PathBuf, flags: u8) -> Self {
-		Self { path, flags }
+		Self {
+			path,
+			flags,
+			max_bytes: None,
+			file: false,
+			file_mode: None,
+			dir_mode: None,
+		}
+	}
+
+	/// Mark the rule as sharing the single file at its path.
+	pub fn with_file(mut self, file: bool) -> Self {
+		self.file = file;
+		self
+	}
+
+	pub fn is_file(&self) -> bool {
+		self.file
+	}
+
+	/// Whether `path` falls under this rule: the exact path for a shared file, anything
+	/// below it for a folder.
+	pub fn covers(&self, path: &Path) -> bool {
+		if self.file {
+			path == self.path
+		} else {
+			path.starts_with(&self.path)
+		}
+	}
+
+	/// Limit the total bytes peers may store under this folder.
+	pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+		self.max_bytes = max_bytes;
+		self
+	}
+
+	pub fn max_bytes(&self) -> Option<u64> {
+		self.max_bytes
+	}
+
+	/// Set the Unix modes of files and directories peers create under this folder, e.g.
+	/// `0o600` and `0o700`. `None` leaves them to the process umask.
+	pub fn with_create_modes(mut self, file_mode: Option<u32>, dir_mode: Option<u32>) -> Self {
+		self.file_mode = file_mode;
+		self.dir_mode = dir_mode;
+		self
+	}
+
+	pub fn file_mode(&self) -> Option<u32> {
+		self.file_mode
+	}
+
+	pub fn dir_mode(&self) -> Option<u32> {
+		self.dir_mode
 	}
 
 	pub fn path(&self) -> &Path {
@@ -92,6 +167,14 @@ impl Permission {
 	pub fn expires_at(&self) -> Option<i64> {
 		self.expires_at
 	}
+
+	/// Whether this permission grants `access` to `path`.
+	pub fn allows(&self, path: &Path, access: u8) -> bool {
+		match &self.rule {
+			Rule::Owner => true,
+			Rule::Folder(rule) => rule.covers(path) && rule.allows(access),
+		}
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -122,12 +205,95 @@ pub struct Auth {
 pub struct Connection {
 	pub peer_id: PeerId,
 	pub connection_id: ConnectionId,
+	pub direction: Direction,
+	pub route: Route,
+	pub remote_addr: Multiaddr,
+}
+
+/// Which side opened a connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+	/// The peer dialed us.
+	Inbound,
+	/// We dialed the peer.
+	Outbound,
+}
+
+impl From<&ConnectedPoint> for Direction {
+	fn from(endpoint: &ConnectedPoint) -> Self {
+		if endpoint.is_dialer() {
+			Direction::Outbound
+		} else {
+			Direction::Inbound
+		}
+	}
+}
+
+impl std::fmt::Display for Direction {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Direction::Inbound => "inbound",
+			Direction::Outbound => "outbound",
+		})
+	}
+}
+
+/// Whether a connection reaches the peer directly or through a circuit relay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Route {
+	Direct,
+	/// Relayed, by the named relay when its id is part of the address.
+	Relayed {
+		relay: Option<PeerId>,
+	},
+}
+
+impl Route {
+	/// Route of a connection to `addr`. Relayed addresses look like
+	/// `/ip4/.../p2p/<relay>/p2p-circuit/p2p/<peer>`.
+	pub fn from_address(addr: &Multiaddr) -> Self {
+		let mut relay = None;
+		for protocol in addr.iter() {
+			match protocol {
+				Protocol::P2p(peer) => relay = Some(peer),
+				Protocol::P2pCircuit => return Route::Relayed { relay },
+				_ => {}
+			}
+		}
+		Route::Direct
+	}
+}
+
+impl From<&ConnectedPoint> for Route {
+	fn from(endpoint: &ConnectedPoint) -> Self {
+		Route::from_address(endpoint.get_remote_address())
+	}
+}
+
+impl std::fmt::Display for Route {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Route::Direct => f.write_str("direct"),
+			Route::Relayed { relay: Some(relay) } => write!(f, "relayed via {}", relay),
+			Route::Relayed { relay: None } => f.write_str("relayed"),
+		}
+	}
+}
+
+/// IP address `addr` starts with, if any.
+pub(crate) fn ip_of(addr: &Multiaddr) -> Option<IpAddr> {
+	match addr.iter().next()? {
+		Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+		Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+		_ => None,
+	}
 }
 
 #[derive(Clone, Debug)]
 pub struct DiscoveredPeer {
 	pub peer_id: PeerId,
-	pub multiaddr: Multiaddr,
+	/// Every address the peer was announced on, without repeats, oldest first.
+	pub addresses: Vec<Multiaddr>,
 }
 
 #[derive(Clone, Debug)]
@@ -139,7 +305,44 @@ pub struct Peer {
 #[derive(Clone, Debug)]
 pub struct User {
 	pub name: String,
-	pub passw: String,
+	/// Random salt hashed together with the password.
+	pub salt: String,
+	pub password_hash: String,
+	pub roles: Vec<String>,
+	/// What signing in with the password grants, including what `roles` imply.
+	pub permissions: Vec<PermissionGrant>,
+}
+
+impl User {
+	/// A user signing in with `password`, which is only kept salted and hashed.
+	pub fn new(
+		name: String,
+		password: &str,
+		roles: Vec<String>,
+		permissions: Vec<PermissionGrant>,
+	) -> Self {
+		let salt = uuid::Uuid::new_v4().simple().to_string();
+		let roles: HashSet<String> = roles
+			.iter()
+			.map(|role| normalize_role(role))
+			.filter(|role| !role.is_empty())
+			.collect();
+		let mut granted = permissions;
+		for permission in default_permissions_for_roles(&roles) {
+			if !granted.contains(&permission) {
+				granted.push(permission);
+			}
+		}
+		let mut roles: Vec<String> = roles.into_iter().collect();
+		roles.sort();
+		Self {
+			name,
+			password_hash: hash_password(&salt, password),
+			salt,
+			roles,
+			permissions: granted,
+		}
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -152,6 +355,19 @@ pub struct State {
 	pub peers: Vec<Peer>,
 	pub users: Vec<User>,
 	pub shared_folders: Vec<FolderRule>,
+	/// When each peer was last discovered, connected or disconnected.
+	pub last_seen: HashMap<PeerId, DateTime<Utc>>,
+	/// Set when another machine seems to share a peer id, ours or a peer's, which happens
+	/// when a keypair file is copied, e.g. with a cloned VM. Both UIs show it prominently.
+	pub identity_warning: Option<String>,
+	/// Notes from peers, oldest first, at most `MESSAGES_KEPT`. Not saved across restarts.
+	pub messages: VecDeque<PeerMessage>,
+	/// Answers that never left this node since startup, usually because the requester
+	/// disconnected first. Each one is logged with the peer and request kind.
+	pub failed_responses: u64,
+	/// Sessions peers opened with `PeerReq::Authenticate`. Their grants count towards
+	/// `effective_permissions` until they expire or the peer disconnects.
+	pub sessions: HashMap<PeerId, SessionInfo>,
 	dirty_permission_targets: HashSet<PeerId>,
 }
 
@@ -166,6 +382,11 @@ impl Default for State {
 			peers: Vec::new(),
 			users: Vec::new(),
 			shared_folders: Vec::new(),
+			last_seen: HashMap::new(),
+			identity_warning: None,
+			messages: VecDeque::new(),
+			failed_responses: 0,
+			sessions: HashMap::new(),
 			dirty_permission_targets: HashSet::new(),
 		}
 	}
@@ -174,27 +395,90 @@ impl Default for State {
 impl State {
 	pub fn authenticate(&mut self, peer_id: PeerId, method: AuthMethod) {}
 
+	/// Local nickname given to `peer_id`, if any.
+	pub fn peer_name(&self, peer_id: &PeerId) -> Option<&str> {
+		self.peers
+			.iter()
+			.find(|peer| peer.id == *peer_id)
+			.and_then(|peer| peer.name.as_deref())
+	}
+
+	pub fn set_peer_name(&mut self, peer_id: PeerId, name: Option<String>) {
+		match (self.peers.iter_mut().find(|peer| peer.id == peer_id), name) {
+			(Some(peer), Some(name)) => peer.name = Some(name),
+			(None, Some(name)) => self.peers.push(Peer {
+				id: peer_id,
+				name: Some(name),
+			}),
+			(_, None) => self.peers.retain(|peer| peer.id != peer_id),
+		}
+	}
+
+	pub fn push_message(&mut self, message: PeerMessage) {
+		self.messages.push_back(message);
+		while self.messages.len() > MESSAGES_KEPT {
+			self.messages.pop_front();
+		}
+	}
+
 	pub fn add_shared_folder(&mut self, rule: FolderRule) {
 		self.shared_folders.push(rule);
 	}
 
-	pub fn permissions_for_peer(&self, peer_id: &PeerId) -> Vec<Permission> {
-		let mut permissions: Vec<Permission> = self
-			.shared_folders
+	/// The most specific shared folder containing `path`.
+	pub fn shared_folder_for(&self, path: &Path) -> Option<&FolderRule> {
+		self.shared_folders
 			.iter()
-			.map(|rule| Permission {
-				rule: Rule::Folder(rule.clone()),
-				expires_at: None,
-			})
-			.collect();
+			.filter(|rule| rule.covers(path))
+			.max_by_key(|rule| rule.path().components().count())
+	}
+
+	/// Everything `peer_id` may do on this node: shared folders plus the rules of any
+	/// relationship with it. The local peer is an owner. `has_fs_access` is derived from
+	/// this list, so what is reported and what is enforced always agree.
+	pub fn effective_permissions(&self, peer_id: &PeerId) -> Vec<Permission> {
+		let mut permissions = Vec::new();
+		if *peer_id == self.me {
+			permissions.push(Permission::new(Rule::Owner));
+		}
+		permissions.extend(
+			self.shared_folders
+				.iter()
+				.map(|rule| Permission::new(Rule::Folder(rule.clone()))),
+		);
 		for relationship in &self.relationships {
 			if relationship.src == *peer_id || relationship.target == *peer_id {
 				permissions.extend(relationship.rules.iter().cloned());
 			}
 		}
+		if let Some(session) = self.live_session(peer_id) {
+			let expires_at = session.expires_at.map(|at| at as i64);
+			permissions.extend(
+				session
+					.permissions
+					.iter()
+					.filter_map(|grant| grant.fs_rule())
+					.map(|rule| Permission::with_expiration(rule, expires_at)),
+			);
+		}
 		permissions
 	}
 
+	/// The session `peer_id` authenticated, unless it has expired.
+	pub fn live_session(&self, peer_id: &PeerId) -> Option<&SessionInfo> {
+		let now = now_timestamp();
+		self.sessions
+			.get(peer_id)
+			.filter(|session| session.expires_at.is_none_or(|at| at > now))
+	}
+
+	/// Whether `peer_id` may administer this node: it is this node, or was made an owner.
+	pub fn is_owner(&self, peer_id: &PeerId) -> bool {
+		self.effective_permissions(peer_id)
+			.iter()
+			.any(|permission| matches!(permission.rule(), Rule::Owner))
+	}
+
 	pub fn permissions_granted_to_peer(&self, peer_id: &PeerId) -> Vec<Permission> {
 		self.relationships
 			.iter()
@@ -204,34 +488,23 @@ impl State {
 	}
 
 	pub fn has_fs_access(&self, src: PeerId, path: &Path, access: u8) -> bool {
-		if src == self.me {
-			return true;
-		}
-
-		for rule in &self.shared_folders {
-			if path.starts_with(rule.path()) && rule.allows(access) {
-				return true;
-			}
-		}
+		self.effective_permissions(&src)
+			.iter()
+			.any(|permission| permission.allows(path, access))
+	}
 
-		for rel in &self.relationships {
-			if rel.src == src || rel.target == src {
-				for rule in &rel.rules {
-					match &rule.rule {
-						Rule::Owner => {
-							return true;
-						}
-						Rule::Folder(folder_rule) => {
-							if path.starts_with(folder_rule.path()) && folder_rule.allows(access) {
-								return true;
-							}
-						}
-					}
-				}
+	/// The folder rules granting `src` the `access`, or `None` when it may access every
+	/// path. A path is accessible exactly when one of the rules covers it.
+	pub fn fs_access_rules(&self, src: PeerId, access: u8) -> Option<Vec<FolderRule>> {
+		let mut rules = Vec::new();
+		for permission in self.effective_permissions(&src) {
+			match permission.rule() {
+				Rule::Owner => return None,
+				Rule::Folder(rule) if rule.allows(access) => rules.push(rule.clone()),
+				Rule::Folder(_) => {}
 			}
 		}
-
-		false
+		Some(rules)
 	}
 
 	pub fn set_peer_permissions(&mut self, peer_id: PeerId, permissions: Vec<Permission>) {
@@ -269,12 +542,13 @@ impl State {
 		});
 	}
 
-	pub fn save_changes(&mut self) -> anyhow::Result<()> {
+	/// Write permissions changed since the last call to the database at `db_path`.
+	pub fn save_changes(&mut self, db_path: &Path) -> anyhow::Result<()> {
 		if self.dirty_permission_targets.is_empty() {
 			return Ok(());
 		}
 
-		let mut conn = crate::db::open_db();
+		let mut conn = crate::db::open_db(db_path)?;
 		let me = self.me;
 
 		for peer_id in self.dirty_permission_targets.drain() {
@@ -291,26 +565,274 @@ impl State {
 		Ok(())
 	}
 
+	/// Direction of every open connection to `peer_id`, in the order they were established.
+	pub fn connection_directions(&self, peer_id: PeerId) -> Vec<Direction> {
+		self.connections
+			.iter()
+			.filter(|connection| connection.peer_id == peer_id)
+			.map(|connection| connection.direction)
+			.collect()
+	}
+
+	/// Two addresses on different hosts that direct connections to `peer_id` come from.
+	/// One peer id on two hosts at once means they share a keypair.
+	pub fn conflicting_addresses(&self, peer_id: PeerId) -> Option<(Multiaddr, Multiaddr)> {
+		let direct: Vec<&Connection> = self
+			.connections
+			.iter()
+			.filter(|c| c.peer_id == peer_id && c.route == Route::Direct)
+			.collect();
+		for (idx, first) in direct.iter().enumerate() {
+			for second in &direct[idx + 1..] {
+				match (ip_of(&first.remote_addr), ip_of(&second.remote_addr)) {
+					(Some(a), Some(b)) if a != b => {
+						return Some((first.remote_addr.clone(), second.remote_addr.clone()));
+					}
+					_ => {}
+				}
+			}
+		}
+		None
+	}
+
+	/// Direction and route of every open connection to `peer_id`, oldest first. A peer
+	/// reached both ways, e.g. after hole punching, has a relayed and a direct entry.
+	pub fn connection_routes(&self, peer_id: PeerId) -> Vec<(Direction, Route)> {
+		self.connections
+			.iter()
+			.filter(|connection| connection.peer_id == peer_id)
+			.map(|connection| (connection.direction, connection.route))
+			.collect()
+	}
+
+	pub fn mark_seen(&mut self, peer_id: PeerId) {
+		self.last_seen.insert(peer_id, Utc::now());
+	}
+
+	/// Every peer we know about: discovered, connected, nicknamed and ourselves.
+	/// Hostnames are left empty since they have to be asked from the peers.
+	pub fn peer_summaries(&self) -> Vec<PeerSummary> {
+		fn row(rows: &mut BTreeMap<PeerId, PeerSummary>, peer_id: PeerId) -> &mut PeerSummary {
+			rows.entry(peer_id).or_insert_with(|| PeerSummary {
+				peer_id: peer_id.to_string(),
+				nickname: None,
+				hostname: None,
+				addresses: Vec::new(),
+				status: String::from("offline"),
+				last_seen: None,
+			})
+		}
+
+		let mut rows = BTreeMap::new();
+		for peer in &self.peers {
+			row(&mut rows, peer.id).nickname = peer.name.clone();
+		}
+		for discovered in &self.discovered_peers {
+			let summary = row(&mut rows, discovered.peer_id);
+			summary
+				.addresses
+				.extend(discovered.addresses.iter().map(|addr| addr.to_string()));
+			summary.status = String::from("discovered");
+		}
+		for connection in &self.connections {
+			row(&mut rows, connection.peer_id).status = String::from("connected");
+		}
+		row(&mut rows, self.me).status = String::from("local");
+		for (peer_id, summary) in rows.iter_mut() {
+			summary.last_seen = self.last_seen.get(peer_id).copied();
+		}
+		rows.into_values().collect()
+	}
+
+	/// Peers currently known from discovery, mDNS or otherwise.
+	pub fn discovered_peer_ids(&self) -> HashSet<PeerId> {
+		self.discovered_peers.iter().map(|p| p.peer_id).collect()
+	}
+
 	pub fn peer_discovered(&mut self, peer_id: PeerId, multiaddr: Multiaddr) {
-		if !self.discovered_peers.iter().any(|p| p.peer_id == peer_id) {
-			self.discovered_peers
-				.push(DiscoveredPeer { peer_id, multiaddr });
+		self.mark_seen(peer_id);
+		match self
+			.discovered_peers
+			.iter_mut()
+			.find(|p| p.peer_id == peer_id)
+		{
+			Some(peer) => {
+				if !peer.addresses.contains(&multiaddr) {
+					peer.addresses.push(multiaddr);
+				}
+			}
+			None => self.discovered_peers.push(DiscoveredPeer {
+				peer_id,
+				addresses: vec![multiaddr],
+			}),
 		}
 	}
 
+	/// Forget `multiaddr` for `peer_id`, and the peer itself once no address is left.
 	pub fn peer_expired(&mut self, peer_id: PeerId, multiaddr: Multiaddr) {
-		self.discovered_peers
-			.retain(|p| !(p.peer_id == peer_id && p.multiaddr == multiaddr));
+		for peer in self.discovered_peers.iter_mut() {
+			if peer.peer_id == peer_id {
+				peer.addresses.retain(|addr| *addr != multiaddr);
+			}
+		}
+		self.discovered_peers.retain(|p| !p.addresses.is_empty());
 	}
 
-	pub fn create_user(&mut self, username: String, password: String) -> anyhow::Result<()> {
+	pub fn create_user(
+		&mut self,
+		username: String,
+		password: String,
+		roles: Vec<String>,
+		permissions: Vec<PermissionGrant>,
+	) -> anyhow::Result<()> {
 		if self.users.iter().any(|u| u.name == username) {
 			bail!("User already exists");
 		}
-		self.users.push(User {
-			name: username,
-			passw: password,
-		});
+		self.users
+			.push(User::new(username, &password, roles, permissions));
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn one_peer_id_on_two_hosts_is_a_conflict() {
+		let mut state = State::default();
+		let peer = PeerId::random();
+		let connection = |id: usize, addr: &str| Connection {
+			peer_id: peer,
+			connection_id: ConnectionId::new_unchecked(id),
+			direction: Direction::Inbound,
+			route: Route::Direct,
+			remote_addr: addr.parse().unwrap(),
+		};
+		// inbound and outbound from the same host
+		state
+			.connections
+			.push(connection(1, "/ip4/192.168.1.20/tcp/51000"));
+		state
+			.connections
+			.push(connection(2, "/ip4/192.168.1.20/tcp/8336"));
+		assert_eq!(state.conflicting_addresses(peer), None);
+
+		state
+			.connections
+			.push(connection(3, "/ip4/192.168.1.31/tcp/8336"));
+		let (first, second) = state.conflicting_addresses(peer).unwrap();
+		assert_eq!(first.to_string(), "/ip4/192.168.1.20/tcp/51000");
+		assert_eq!(second.to_string(), "/ip4/192.168.1.31/tcp/8336");
+	}
+
+	#[test]
+	fn relayed_addresses_name_their_relay() {
+		let relay = PeerId::random();
+		let peer = PeerId::random();
+		let direct: Multiaddr = format!("/ip4/192.168.1.20/tcp/8336/p2p/{peer}")
+			.parse()
+			.unwrap();
+		let relayed: Multiaddr =
+			format!("/ip4/203.0.113.9/tcp/4001/p2p/{relay}/p2p-circuit/p2p/{peer}")
+				.parse()
+				.unwrap();
+		assert_eq!(Route::from_address(&direct), Route::Direct);
+		assert_eq!(
+			Route::from_address(&relayed),
+			Route::Relayed { relay: Some(relay) }
+		);
+		assert_eq!(
+			Route::from_address(&"/p2p-circuit".parse().unwrap()),
+			Route::Relayed { relay: None }
+		);
+	}
+
+	#[test]
+	fn discovered_addresses_are_kept_once_per_peer() {
+		let mut state = State::default();
+		let peer = PeerId::random();
+		let lan: Multiaddr = "/ip4/192.168.1.20/tcp/8336".parse().unwrap();
+		let wifi: Multiaddr = "/ip4/10.0.0.7/tcp/8336".parse().unwrap();
+		state.peer_discovered(peer, lan.clone());
+		state.peer_discovered(peer, lan.clone());
+		state.peer_discovered(peer, wifi.clone());
+
+		assert_eq!(state.discovered_peers.len(), 1);
+		assert_eq!(
+			state.discovered_peers[0].addresses,
+			vec![lan.clone(), wifi.clone()]
+		);
+
+		state.peer_expired(peer, lan);
+		assert_eq!(state.discovered_peers[0].addresses, vec![wifi.clone()]);
+		state.peer_expired(peer, wifi);
+		assert!(state.discovered_peers.is_empty());
+	}
+
+	#[test]
+	fn granted_folder_is_listed_and_enforced() {
+		let mut state = State::default();
+		let peer = PeerId::random();
+		let shared = PathBuf::from("/srv/photos");
+		state.set_peer_permissions(
+			peer,
+			vec![Permission::new(Rule::Folder(FolderRule::new(
+				shared.clone(),
+				FLAG_READ | FLAG_SEARCH,
+			)))],
+		);
+
+		let permissions = state.effective_permissions(&peer);
+		assert!(permissions.iter().any(|permission| matches!(
+			permission.rule(),
+			Rule::Folder(rule) if rule.path() == shared
+		)));
+		assert!(state.has_fs_access(peer, &shared.join("a.jpg"), FLAG_READ));
+		assert!(!state.has_fs_access(peer, &shared.join("a.jpg"), FLAG_WRITE));
+		assert!(!state.has_fs_access(peer, Path::new("/etc/passwd"), FLAG_READ));
+		assert!(!state.has_fs_access(PeerId::random(), &shared, FLAG_READ));
+
+		let readable = state.fs_access_rules(peer, FLAG_READ).unwrap();
+		assert_eq!(readable.len(), 1);
+		assert_eq!(readable[0].path(), shared);
+		assert!(state.fs_access_rules(peer, FLAG_WRITE).unwrap().is_empty());
+		assert!(state.fs_access_rules(state.me, FLAG_WRITE).is_none());
+	}
+
+	#[test]
+	fn shared_file_grants_only_that_file() {
+		let mut state = State::default();
+		let peer = PeerId::random();
+		let file = PathBuf::from("/a/file.txt");
+		state.add_shared_folder(FolderRule::new(file.clone(), FLAG_READ).with_file(true));
+
+		assert!(state.has_fs_access(peer, &file, FLAG_READ));
+		assert!(!state.has_fs_access(peer, Path::new("/a/file.txt.bak"), FLAG_READ));
+		assert!(!state.has_fs_access(peer, &file.join("child"), FLAG_READ));
+		assert!(!state.has_fs_access(peer, Path::new("/a"), FLAG_READ));
+	}
+
+	#[test]
+	fn peer_summaries_merge_discovery_connections_and_names() {
+		let mut state = State::default();
+		let peer = PeerId::random();
+		let addr: Multiaddr = "/ip4/192.168.1.20/tcp/4001".parse().unwrap();
+		state.peer_discovered(peer, addr.clone());
+		state.set_peer_name(peer, Some(String::from("nas")));
+		state.set_peer_name(PeerId::random(), Some(String::from("laptop")));
+
+		let summaries = state.peer_summaries();
+		assert_eq!(summaries.len(), 3);
+		let nas = summaries
+			.iter()
+			.find(|summary| summary.peer_id == peer.to_string())
+			.unwrap();
+		assert_eq!(nas.nickname.as_deref(), Some("nas"));
+		assert_eq!(nas.addresses, vec![addr.to_string()]);
+		assert_eq!(nas.status, "discovered");
+		assert!(nas.last_seen.is_some());
+		assert!(summaries.iter().any(|summary| summary.status == "offline"));
+		assert!(summaries.iter().any(|summary| summary.status == "local"));
+	}
+}
@@ -1,4 +1,9 @@
+use chrono::{DateTime, Utc};
+use libp2p::PeerId;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::p2p::DirEntry;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileChunk {
@@ -6,3 +11,285 @@ pub struct FileChunk {
 	pub data: Vec<u8>,
 	pub eof: bool,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePreview {
+	pub mime: Option<String>,
+	pub head: Vec<u8>,
+	pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirSize {
+	pub total_bytes: u64,
+	pub file_count: u64,
+	/// The walk stopped early (entry or time budget exhausted), so totals are a lower bound.
+	pub partial: bool,
+}
+
+/// Everything below a directory, from `PuppyPeer::list_dir_recursive`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirTree {
+	/// Parents come before their children. Each `name` is the path relative to the listed
+	/// directory, joined with `/`.
+	pub entries: Vec<DirEntry>,
+	/// The peer's entry cap was reached, so some entries are missing.
+	pub truncated: bool,
+}
+
+/// A file in a peer's index, from `PuppyPeer::browse_catalog`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+	pub path: String,
+	/// Hex SHA-256 of the content, unless the file could not be hashed.
+	pub hash: Option<String>,
+	pub size: u64,
+	pub mime_type: Option<String>,
+	pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// One page of a catalog, ordered by path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogPage {
+	pub entries: Vec<CatalogEntry>,
+	/// More entries follow; ask again with `offset` moved past these.
+	pub has_more: bool,
+}
+
+/// Result of `PuppyPeer::rotate_identity`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityRotation {
+	pub old_peer_id: String,
+	pub new_peer_id: String,
+	/// Copy of the replaced keypair file.
+	pub backup: PathBuf,
+	/// Permission rules this node granted, now stored under the new id.
+	pub migrated_grants: usize,
+}
+
+/// Free and total bytes of one filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskSpace {
+	pub available: u64,
+	pub total: u64,
+}
+
+/// A note received from a peer, kept in `State::messages`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerMessage {
+	pub from: PeerId,
+	pub text: String,
+	pub received_at: DateTime<Utc>,
+}
+
+/// A file opened on a peer, from `PuppyPeer::recent_files`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentFile {
+	pub peer: PeerId,
+	pub path: String,
+	pub opened_at: DateTime<Utc>,
+}
+
+/// A change to the peers this node knows, from `PuppyPeer::subscribe`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeerEvent {
+	/// mDNS found the peer at `address`.
+	Discovered { peer_id: String, address: String },
+	/// mDNS stopped seeing the peer at `address`.
+	Expired { peer_id: String, address: String },
+	/// First connection to the peer was established.
+	Connected { peer_id: String, address: String },
+	/// Last connection to the peer closed.
+	Disconnected { peer_id: String },
+	/// The peer sent a note with `PuppyPeer::send_message`.
+	Message { peer_id: String, text: String },
+}
+
+/// One row of `PuppyPeer::export_peers`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerSummary {
+	pub peer_id: String,
+	pub nickname: Option<String>,
+	pub hostname: Option<String>,
+	pub addresses: Vec<String>,
+	/// `local`, `connected`, `discovered` or `offline`.
+	pub status: String,
+	pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Where a local file is backed up, from `PuppyPeer::find_backups`.
+#[derive(Debug, Clone)]
+pub struct BackupLocations {
+	pub path: PathBuf,
+	/// Connected peers that have a file with the same content.
+	pub found_on: Vec<PeerId>,
+	/// Connected peers that did not answer, so the file may or may not be on them.
+	pub unknown: Vec<PeerId>,
+}
+
+/// What `PuppyPeer::replicate_folder` did with one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationOutcome {
+	Copied {
+		bytes: u64,
+	},
+	/// The peer already has a file with the same content.
+	Skipped,
+	Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicatedFile {
+	/// Path relative to the replicated folder.
+	pub path: PathBuf,
+	pub outcome: ReplicationOutcome,
+}
+
+/// Per-file results and totals of `PuppyPeer::replicate_folder`.
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationReport {
+	pub files: Vec<ReplicatedFile>,
+	pub copied: u64,
+	pub skipped: u64,
+	pub failed: u64,
+	pub bytes_copied: u64,
+}
+
+/// Bytes moved so far by `PuppyPeer::relay_copy`.
+#[derive(Debug, Clone, Copy)]
+pub struct RelayProgress {
+	pub bytes_copied: u64,
+	/// Size of the source file, when the source peer reported it.
+	pub total_bytes: Option<u64>,
+}
+
+/// A finished `PuppyPeer::relay_copy`.
+#[derive(Debug, Clone)]
+pub struct RelayReport {
+	pub bytes_copied: u64,
+	/// Hex SHA-256 of the copied file, confirmed by the destination peer.
+	pub sha256: String,
+}
+
+/// How a two-way sync picks a side when a file differs on both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+	/// The side with the later modification time wins; the local copy wins a tie.
+	#[default]
+	NewestWins,
+	PreferLocal,
+	PreferRemote,
+}
+
+/// Direction of `PuppyPeer::sync_folder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+	/// Make the remote folder match the local one.
+	OneWay,
+	/// Copy missing files both ways and settle differing files with the policy.
+	TwoWay(ConflictPolicy),
+}
+
+/// Size and modification time of a file on one side of a sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncFileState {
+	pub size: u64,
+	pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncOperation {
+	Upload,
+	Download,
+	Skip,
+}
+
+/// Why a file got its `SyncOperation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncReason {
+	OnlyLocal,
+	OnlyRemote,
+	/// Same size and modification time on both sides.
+	Unchanged,
+	/// One-way sync leaves remote-only files alone and overwrites differing ones.
+	OneWay,
+	/// The chosen side has the later modification time.
+	Newer,
+	/// The conflict policy prefers the chosen side.
+	Preferred,
+}
+
+impl std::fmt::Display for SyncReason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			SyncReason::OnlyLocal => "only exists locally",
+			SyncReason::OnlyRemote => "only exists on the peer",
+			SyncReason::Unchanged => "unchanged",
+			SyncReason::OneWay => "one-way sync",
+			SyncReason::Newer => "newer copy wins",
+			SyncReason::Preferred => "preferred by conflict policy",
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+	/// Path relative to the synced folders.
+	pub path: PathBuf,
+	pub operation: SyncOperation,
+	pub reason: SyncReason,
+	pub local: Option<SyncFileState>,
+	pub remote: Option<SyncFileState>,
+	/// The file exists on both sides and differs in size or modification time.
+	pub conflict: bool,
+	/// Both copies have the same size, so the contents are compared before copying and
+	/// the copy is skipped when they match.
+	pub verify: bool,
+}
+
+/// What `PuppyPeer::sync_folder` would do, from `PuppyPeer::sync_plan`.
+#[derive(Debug, Clone)]
+pub struct SyncPlan {
+	pub peer: PeerId,
+	pub local_root: PathBuf,
+	pub remote_root: PathBuf,
+	pub files: Vec<PlannedFile>,
+}
+
+impl SyncPlan {
+	pub fn count(&self, operation: SyncOperation) -> usize {
+		self.files
+			.iter()
+			.filter(|file| file.operation == operation)
+			.count()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+	Pushed,
+	Pulled,
+	Unchanged,
+	Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncedFile {
+	/// Path relative to the synced folders.
+	pub path: PathBuf,
+	pub action: SyncAction,
+	/// The file existed on both sides with different content.
+	pub conflict: bool,
+}
+
+/// Per-file results and totals of `PuppyPeer::sync_folder`.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+	pub files: Vec<SyncedFile>,
+	pub pushed: u64,
+	pub pulled: u64,
+	pub unchanged: u64,
+	pub conflicts: u64,
+	pub failed: u64,
+}
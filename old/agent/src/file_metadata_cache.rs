@@ -1,10 +1,15 @@
 use std::convert::TryInto;
 use sled::Db;
 use sled::IVec;
+use sled::Transactional;
 use sled::Tree;
+use sled::transaction::ConflictableTransactionError;
+use sled::transaction::ConflictableTransactionResult;
+use sled::transaction::TransactionalTree;
 
 use crate::types::FileMetadata;
 
+// File ids are stored little-endian in every tree.
 fn decode_file_id(file_id: IVec) -> Option<u64> {
     let bytes: [u8; 8] = match file_id.to_vec().try_into() {
         Ok(a) => a,
@@ -14,6 +19,32 @@ fn decode_file_id(file_id: IVec) -> Option<u64> {
     Some(u64::from_le_bytes(bytes))
 }
 
+/// Look up the file id of `file_path` inside a transaction, allocating one if it has none.
+fn file_id_for_path(paths: &TransactionalTree, file_path: &str) -> ConflictableTransactionResult<u64, bincode::Error> {
+    if let Some(file_id) = paths.get(file_path)?.and_then(decode_file_id) {
+        return Ok(file_id);
+    }
+
+    let file_id = paths.generate_id()?;
+    paths.insert(file_path, &file_id.to_le_bytes())?;
+
+    Ok(file_id)
+}
+
+fn read_metadata(metadatas: &TransactionalTree, file_id: u64) -> ConflictableTransactionResult<Option<FileMetadata>, bincode::Error> {
+    match metadatas.get(file_id.to_le_bytes())? {
+        Some(raw) => Ok(Some(bincode::deserialize(&raw).map_err(ConflictableTransactionError::Abort)?)),
+        None => Ok(None)
+    }
+}
+
+fn write_metadata(metadatas: &TransactionalTree, file_id: u64, file_metadata: &FileMetadata) -> ConflictableTransactionResult<(), bincode::Error> {
+    let raw = bincode::serialize(file_metadata).map_err(ConflictableTransactionError::Abort)?;
+    metadatas.insert(&file_id.to_le_bytes(), raw)?;
+
+    Ok(())
+}
+
 pub struct FileMetadataCache {
     db: Db,
     file_metadatas: Tree,
@@ -58,52 +89,41 @@ impl FileMetadataCache {
     }
 
     pub fn set_hash(&self, file_path: &str, file_hash: Vec<u8>) -> Result<(), failure::Error> {
-        let file_id = match self.get_file_id_with_path(file_path)? {
-            Some(r) => r,
-            None => {
-                let file_id = self.db.generate_id()?;
-
-                self.file_paths.insert(file_path, &file_id.to_le_bytes())?;
-
-                file_id
-            }
-        };
+        (&self.file_paths, &self.file_metadatas, &self.file_hashes).transaction(|(paths, metadatas, hashes)| {
+            let file_id = file_id_for_path(paths, file_path)?;
 
-        match self.get_file_metadata(file_id)? {
-            Some(file_metadata) => {
-                let mut file_metadata = file_metadata.clone();
+            if let Some(mut file_metadata) = read_metadata(metadatas, file_id)? {
                 file_metadata.file_hash = Some(file_hash.clone());
 
-                self.set_file_metadata_with_file_id(file_id, file_metadata)?;
+                write_metadata(metadatas, file_id, &file_metadata)?;
             }
-            None => {
 
+            if hashes.get(file_hash.as_slice())?.is_none() {
+                hashes.insert(file_hash.as_slice(), &file_id.to_le_bytes())?;
             }
-        };
 
-        match self.get_file_id_with_file_hash(&file_hash)? {
-            Some(_) => {},
-            None => {
-                self.file_hashes.insert(file_hash, &file_id.to_be_bytes())?;
-            }
-        };
+            Ok(())
+        })?;
 
         Ok(())
     }
 
+    /// Store metadata for `file_path`. When `file_metadata` carries no hash, one recorded
+    /// with `set_hash` is kept only while the file size is unchanged, since a resized file
+    /// has new content.
     pub fn set_file_metadata(&self, file_path: &str, file_metadata: FileMetadata) -> Result<(), failure::Error> {
-        let file_id = match self.get_file_id_with_path(file_path)? {
-            Some(r) => r,
-            None => {
-                let file_id = self.db.generate_id()?;
-
-                self.file_paths.insert(file_path, &file_id.to_le_bytes());
-
-                file_id
+        (&self.file_paths, &self.file_metadatas).transaction(|(paths, metadatas)| {
+            let file_id = file_id_for_path(paths, file_path)?;
+
+            let mut file_metadata = file_metadata.clone();
+            if file_metadata.file_hash.is_none() {
+                file_metadata.file_hash = read_metadata(metadatas, file_id)?
+                    .filter(|m| m.file_size == file_metadata.file_size)
+                    .and_then(|m| m.file_hash);
             }
-        };
 
-        self.set_file_metadata_with_file_id(file_id, file_metadata)?;
+            write_metadata(metadatas, file_id, &file_metadata)
+        })?;
 
         Ok(())
     }
@@ -132,12 +152,74 @@ impl FileMetadataCache {
             None => None
         })
     }
+}
 
-    fn set_file_metadata_with_file_id(&self, file_id: u64, file_metadata: FileMetadata) -> Result<(), failure::Error> {
-        let b = bincode::serialize(&file_metadata)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
 
-        self.file_metadatas.insert(file_id.to_le_bytes(), b)?;
-        
-        Ok(())
+    fn temporary_cache() -> FileMetadataCache {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+
+        FileMetadataCache::new(db)
+    }
+
+    #[test]
+    fn concurrent_updates_keep_hash_and_metadata() {
+        let cache = Arc::new(temporary_cache());
+        cache.set_file_metadata("/data/a.bin", FileMetadata { file_hash: None, file_size: 1 }).unwrap();
+
+        let handles: Vec<_> = (0..8u8).map(|i| {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                for _ in 0..25 {
+                    if i % 2 == 0 {
+                        cache.set_hash("/data/a.bin", vec![i; 32]).unwrap();
+                    } else {
+                        cache.set_file_metadata("/data/a.bin", FileMetadata { file_hash: None, file_size: 1 }).unwrap();
+                    }
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let file_metadata = cache.get_file_metadata_from_path("/data/a.bin").unwrap().unwrap();
+        let file_hash = file_metadata.file_hash.expect("hash lost by a concurrent metadata write");
+        assert!([0u8, 2, 4, 6].iter().any(|i| file_hash == vec![*i; 32]));
+        assert_eq!(cache.file_paths.len(), 1);
+        assert_eq!(cache.file_metadatas.len(), 1);
+        assert_eq!(cache.file_hashes.len(), 4);
+    }
+
+    #[test]
+    fn a_resized_file_drops_its_stale_hash() {
+        let cache = temporary_cache();
+        cache.set_file_metadata("/data/c.bin", FileMetadata { file_hash: None, file_size: 3 }).unwrap();
+        cache.set_hash("/data/c.bin", vec![9; 32]).unwrap();
+
+        cache.set_file_metadata("/data/c.bin", FileMetadata { file_hash: None, file_size: 3 }).unwrap();
+        let same_size = cache.get_file_metadata_from_path("/data/c.bin").unwrap().unwrap();
+        assert_eq!(same_size.file_hash, Some(vec![9; 32]));
+
+        cache.set_file_metadata("/data/c.bin", FileMetadata { file_hash: None, file_size: 4 }).unwrap();
+        let resized = cache.get_file_metadata_from_path("/data/c.bin").unwrap().unwrap();
+        assert_eq!(resized.file_hash, None);
+        assert_eq!(resized.file_size, 4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn hash_lookup_returns_the_path_file_id() {
+        let cache = temporary_cache();
+        cache.set_file_metadata("/data/b.bin", FileMetadata { file_hash: None, file_size: 3 }).unwrap();
+        cache.set_hash("/data/b.bin", vec![7; 32]).unwrap();
+
+        let by_path = cache.get_file_id_with_path("/data/b.bin").unwrap().unwrap();
+        let by_hash = cache.get_file_id_with_file_hash(&[7; 32]).unwrap().unwrap();
+        assert_eq!(by_path, by_hash);
+        assert_eq!(cache.remove_hash(&[7; 32]).unwrap(), Some(by_path));
+    }
+}